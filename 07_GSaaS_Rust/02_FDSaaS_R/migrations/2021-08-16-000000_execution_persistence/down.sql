@@ -0,0 +1,29 @@
+-- 
+-- (c) Incomplete Worlds 2021 
+-- Alberto Fernandez (ajfg)
+--  
+-- FDS as a Service main
+--  
+-- SQLite cannot drop columns. The table is created again
+-- 
+
+CREATE TABLE t_execution_record_old (
+  execution_id         INTEGER NOT NULL PRIMARY KEY,
+  user_id              CHARACTER(36) NOT NULL,
+  module_id            INTEGER NOT NULL,
+  module_instance_id   INTEGER NOT NULL,
+  start_time           DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  stop_time            DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  status               CHARACTER(40) NOT NULL,
+  complete_flag        BOOLEAN NOT NULL,
+  expiration_time      DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+INSERT INTO t_execution_record_old 
+  SELECT execution_id, user_id, module_id, module_instance_id, start_time, stop_time, 
+         status, complete_flag, expiration_time
+  FROM t_execution_record;
+
+DROP TABLE t_execution_record;
+
+ALTER TABLE t_execution_record_old RENAME TO t_execution_record;
@@ -0,0 +1,18 @@
+-- 
+-- (c) Incomplete Worlds 2021 
+-- Alberto Fernandez (ajfg)
+--  
+-- FDS as a Service main
+--  
+-- Execution records keep the request and the answer, so, they can be
+-- recovered after a restart
+-- 
+
+ALTER TABLE t_execution_record ADD COLUMN msg_id           VARCHAR(256) DEFAULT '' NOT NULL;
+-- InternalMessage sent to the module
+ALTER TABLE t_execution_record ADD COLUMN request          TEXT DEFAULT '' NOT NULL;
+-- RestResponse returned by the module
+ALTER TABLE t_execution_record ADD COLUMN answer           TEXT DEFAULT '' NOT NULL;
+ALTER TABLE t_execution_record ADD COLUMN progress         INTEGER DEFAULT 0 NOT NULL;
+ALTER TABLE t_execution_record ADD COLUMN progress_detail  TEXT DEFAULT '' NOT NULL;
+ALTER TABLE t_execution_record ADD COLUMN timeout          INTEGER DEFAULT 0 NOT NULL;
@@ -0,0 +1,38 @@
+-- 
+-- (c) Incomplete Worlds 2021 
+-- Alberto Fernandez (ajfg)
+--  
+-- FDS as a Service main
+--  
+-- SQLite cannot drop columns. The execution records table is created again
+-- 
+
+DROP TABLE IF EXISTS t_webhook_delivery;
+
+CREATE TABLE t_execution_record_old (
+  execution_id         INTEGER NOT NULL PRIMARY KEY,
+  user_id              CHARACTER(36) NOT NULL,
+  module_id            INTEGER NOT NULL,
+  module_instance_id   INTEGER NOT NULL,
+  start_time           DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  stop_time            DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  status               CHARACTER(40) NOT NULL,
+  complete_flag        BOOLEAN NOT NULL,
+  expiration_time      DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  msg_id               VARCHAR(256) DEFAULT '' NOT NULL,
+  request              TEXT DEFAULT '' NOT NULL,
+  answer               TEXT DEFAULT '' NOT NULL,
+  progress             INTEGER DEFAULT 0 NOT NULL,
+  progress_detail      TEXT DEFAULT '' NOT NULL,
+  timeout              INTEGER DEFAULT 0 NOT NULL
+);
+
+INSERT INTO t_execution_record_old 
+  SELECT execution_id, user_id, module_id, module_instance_id, start_time, stop_time, 
+         status, complete_flag, expiration_time, msg_id, request, answer, progress, 
+         progress_detail, timeout
+  FROM t_execution_record;
+
+DROP TABLE t_execution_record;
+
+ALTER TABLE t_execution_record_old RENAME TO t_execution_record;
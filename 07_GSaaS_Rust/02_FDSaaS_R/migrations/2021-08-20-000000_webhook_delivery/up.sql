@@ -0,0 +1,25 @@
+-- 
+-- (c) Incomplete Worlds 2021 
+-- Alberto Fernandez (ajfg)
+--  
+-- FDS as a Service main
+--  
+-- Webhook callbacks. The final response of an execution is sent to the callback URL
+-- 
+
+-- Empty = No callback
+ALTER TABLE t_execution_record ADD COLUMN callback_url  VARCHAR(1024) DEFAULT '' NOT NULL;
+
+-- It contains a record for each delivery attempt
+CREATE TABLE IF NOT EXISTS t_webhook_delivery (
+  id                   INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  execution_id         INTEGER NOT NULL,
+  callback_url         VARCHAR(1024) NOT NULL,
+  attempt              INTEGER NOT NULL,
+  delivery_time        DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  -- 0 = No answer
+  http_status          INTEGER NOT NULL,
+  -- Delivered, Retrying, Failed
+  status               CHARACTER(40) NOT NULL,
+  detail               TEXT NOT NULL
+);
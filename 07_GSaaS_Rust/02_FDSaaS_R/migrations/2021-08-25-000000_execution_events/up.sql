@@ -0,0 +1,11 @@
+-- 
+-- (c) Incomplete Worlds 2021 
+-- Alberto Fernandez (ajfg)
+--  
+-- FDS as a Service main
+--  
+-- Sequence of the last event of an execution. The sequence continues after a restart,
+-- so, the header Last-Event-ID of the streams is still valid
+-- 
+
+ALTER TABLE t_execution_record ADD COLUMN event_counter  INTEGER DEFAULT 0 NOT NULL;
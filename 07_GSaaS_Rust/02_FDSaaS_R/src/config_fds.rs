@@ -50,6 +50,47 @@ pub struct ConfigVariables {
     pub fds_nng_sub_address:        String,
 
     pub fds_nng_bus_address:        String,
+
+    // Executions interrupted by a restart are sent again to the modules. Otherwise, they fail
+    #[serde(default)]
+    pub requeue_executions:         bool,
+
+    // Seconds. Completed executions are kept this time
+    #[serde(default = "default_execution_retention_time")]
+    pub execution_retention_time:   u32,
+
+    // File containing the key for signing the webhook callbacks (HMAC-SHA256). It shall not be
+    // under version control. The variable FDSAAS_CALLBACK_SECRET_KEY takes precedence
+    #[serde(default)]
+    pub callback_secret_key_file:   String,
+
+    // Number of attempts for delivering a webhook callback
+    #[serde(default = "default_callback_max_attempts")]
+    pub callback_max_attempts:      u32,
+
+    // Seconds between the first two attempts. It is doubled after every attempt
+    #[serde(default = "default_callback_initial_backoff")]
+    pub callback_initial_backoff:   u64,
+
+    // The modules definition file is reloaded when it is modified
+    #[serde(default)]
+    pub watch_modules_definition:   bool,
+
+    // Ids of the users that can send administration messages; i.e. reload_modules
+    #[serde(default)]
+    pub admin_users:                Vec<String>,
+}
+
+fn default_execution_retention_time() -> u32 {
+    3600
+}
+
+fn default_callback_max_attempts() -> u32 {
+    5
+}
+
+fn default_callback_initial_backoff() -> u64 {
+    2
 }
 
 
@@ -70,8 +111,22 @@ impl ConfigVariables
             fds_nng_rep_address:        String::new(),
             fds_nng_sub_address:        String::new(),
             fds_nng_bus_address:        String::new(),
+            requeue_executions:         false,
+            execution_retention_time:   default_execution_retention_time(),
+            callback_secret_key_file:   String::new(),
+            callback_max_attempts:      default_callback_max_attempts(),
+            callback_initial_backoff:   default_callback_initial_backoff(),
+            watch_modules_definition:   false,
+            admin_users:                Vec::new(),
         }
     }
+
+    /**
+     * Check if a user can send administration messages. A message without user is never allowed
+     */
+    pub fn is_admin_user(&self, in_user_id: &str) -> bool {
+        in_user_id.is_empty() == false && self.admin_users.iter().any(|u| u == in_user_id)
+    }
 }
 
 impl ToString for ConfigVariables {
@@ -88,6 +143,13 @@ impl ToString for ConfigVariables {
 
         output_buffer.push_str( format!("FDS Bus Address: {}\n", self.fds_nng_bus_address).as_str() );
 
+        output_buffer.push_str( format!("Re-queue Executions: {}\n", self.requeue_executions).as_str() );
+        output_buffer.push_str( format!("Execution Retention Time: {}\n", self.execution_retention_time).as_str() );
+        output_buffer.push_str( format!("Callback Secret Key File: {}\n", self.callback_secret_key_file).as_str() );
+        output_buffer.push_str( format!("Callback Max Attempts: {}\n", self.callback_max_attempts).as_str() );
+        output_buffer.push_str( format!("Watch Modules Definition: {}\n", self.watch_modules_definition).as_str() );
+        output_buffer.push_str( format!("Admin Users: {:?}\n", self.admin_users).as_str() );
+
         return output_buffer;
     }
 }
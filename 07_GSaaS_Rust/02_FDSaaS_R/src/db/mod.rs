@@ -9,6 +9,7 @@
 
 pub mod schema;
 pub mod http_access;
+pub mod webhook_delivery;
 
 //#[macro_use]
 use diesel;
@@ -44,9 +45,25 @@ pub type DbPool = Pool<ConnectionManager<SqliteConnection>>;
 // }
 
 
-// pub fn run_migrations(conn: &SqliteConnection) {
-//   let _ = diesel_migrations::run_pending_migrations(&*conn);
-// }
+// SQL files of the 'migrations' folder. They are embedded in the executable
+embed_migrations!("migrations");
+
+/**
+ * Run the migrations that are not applied yet to the database
+ * The tables are created or updated before any access
+ */
+pub fn run_migrations(in_pool: &DbPool) -> Result<(), String>
+{
+    let tmp_conn = match in_pool.get() {
+        Ok(c) => c,
+        Err(e) => return Err( format!("Unable to get a database connection: {}", e) ),
+    };
+
+    match embedded_migrations::run(&tmp_conn) {
+        Ok(_)  => Ok(()),
+        Err(e) => Err( format!("Unable to run the database migrations: {}", e) ),
+    }
+}
 
 /**
  * Establish a connection with the SQLite database and create a pool of connections
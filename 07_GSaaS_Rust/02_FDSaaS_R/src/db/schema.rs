@@ -9,6 +9,14 @@ table! {
         status -> Text,
         complete_flag -> Bool,
         expiration_time -> Timestamp,
+        msg_id -> Text,
+        request -> Text,
+        answer -> Text,
+        progress -> Integer,
+        progress_detail -> Text,
+        timeout -> Integer,
+        callback_url -> Text,
+        event_counter -> Integer,
     }
 }
 
@@ -22,8 +30,22 @@ table! {
     }
 }
 
+table! {
+    t_webhook_delivery (id) {
+        id -> Integer,
+        execution_id -> Integer,
+        callback_url -> Text,
+        attempt -> Integer,
+        delivery_time -> Timestamp,
+        http_status -> Integer,
+        status -> Text,
+        detail -> Text,
+    }
+}
+
 
 allow_tables_to_appear_in_same_query!(
     t_execution_record,
     t_http_access,
+    t_webhook_delivery,
 );
@@ -0,0 +1,111 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * FDS as a Service main
+ *
+ * Functions to manage the log of webhook deliveries; CRUD
+ */
+use serde::{Deserialize, Serialize};
+
+//#[macro_use]
+use diesel;
+use diesel::prelude::*;
+
+use crate::schema::*;
+
+
+#[derive(Debug, Deserialize, Serialize, Queryable)]
+pub struct WebhookDelivery {
+     pub id:             i32,
+     pub execution_id:   i32,
+     pub callback_url:   String,
+     pub attempt:        i32,
+     pub delivery_time:  chrono::NaiveDateTime,
+     // HTTP status returned by the callback URL. 0 = No answer
+     pub http_status:    i32,
+     // Delivered, Retrying, Failed
+     pub status:         String,
+     pub detail:         String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Insertable)]
+#[table_name="t_webhook_delivery"]
+pub struct WebhookDeliveryDb {
+     //pub id:            i32,
+     pub execution_id:   i32,
+     pub callback_url:   String,
+     pub attempt:        i32,
+     pub delivery_time:  chrono::NaiveDateTime,
+     pub http_status:    i32,
+     pub status:         String,
+     pub detail:         String,
+}
+
+
+impl WebhookDelivery
+{
+    pub fn create(conn: &SqliteConnection, in_execution_id: u32, in_callback_url: &String, in_attempt: u32,
+                  in_http_status: u16, in_status: &str, in_detail: &String) -> Result<usize, diesel::result::Error>
+    {
+        // Create a new record
+        let new_delivery = WebhookDeliveryDb {
+            // Autoincrement
+            //id:              0,
+            execution_id:    in_execution_id as i32,
+            callback_url:    in_callback_url.clone(),
+            attempt:         in_attempt as i32,
+            delivery_time:   chrono::Utc::now().naive_utc(),
+            http_status:     in_http_status as i32,
+            status:          String::from(in_status),
+            detail:          in_detail.clone(),
+        };
+
+        // Insert into the table
+        diesel::insert_into(t_webhook_delivery::table).values(&new_delivery)
+                                                      .execute(conn)
+    }
+
+    /**
+     * Return the delivery attempts of an execution
+     */
+    pub fn by_execution_id(conn: &SqliteConnection, in_execution_id: u32) -> Result<Vec<Self>, diesel::result::Error>
+    {
+        t_webhook_delivery::table.filter( t_webhook_delivery::execution_id.eq(in_execution_id as i32) )
+                                 .order( t_webhook_delivery::id.asc() )
+                                 .load::<WebhookDelivery>(conn)
+    }
+
+    /**
+     * Deliveries that were not completed. Completed executions with a callback URL whose last
+     * attempt is being retried or that do not have any attempt
+     * Return: execution id, callback URL, final response and next attempt
+     */
+    pub fn pending(conn: &SqliteConnection) -> Result<Vec<(i32, String, String, i32)>, diesel::result::Error>
+    {
+        let tmp_executions = t_execution_record::table.filter( t_execution_record::complete_flag.eq(true) )
+                                                      .filter( t_execution_record::callback_url.ne("") )
+                                                      .select( (t_execution_record::execution_id,
+                                                                t_execution_record::callback_url,
+                                                                t_execution_record::answer) )
+                                                      .load::<(i32, String, String)>(conn)?;
+
+        let mut output_list = Vec::new();
+
+        for (tmp_execution_id, tmp_callback_url, tmp_answer) in tmp_executions {
+            let tmp_last = t_webhook_delivery::table.filter( t_webhook_delivery::execution_id.eq(tmp_execution_id) )
+                                                    .order( t_webhook_delivery::id.desc() )
+                                                    .first::<WebhookDelivery>(conn)
+                                                    .optional()?;
+
+            match tmp_last {
+                None => output_list.push( (tmp_execution_id, tmp_callback_url, tmp_answer, 1) ),
+                Some(d) if d.status == "Retrying" => output_list.push( (tmp_execution_id, tmp_callback_url, tmp_answer, d.attempt + 1) ),
+                // Delivered or Failed
+                Some(_) => (),
+            };
+        }
+
+        Ok(output_list)
+    }
+}
@@ -0,0 +1,120 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * FDS as a Service
+ * ExecutionEventStream
+ * Stream of the events of an execution; status transitions and progress reported by the module
+ * The events are sent as Server-Sent Events. The stream ends when the execution is completed
+ * A comment is sent periodically, so, proxies and clients do not close an idle connection
+ *
+ */
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use log::{debug, error};
+
+// Actix Web Server
+use actix_web::web::Bytes;
+use actix_web::rt::time::{interval_at, Instant, Interval};
+use futures::stream::Stream;
+
+use crate::fds_messages::*;
+use crate::tasks_manager::*;
+
+
+// Content type of the stream
+pub const SSE_CONTENT_TYPE : &str = "text/event-stream";
+
+// Seconds between keep-alive comments while there are no events
+pub const SSE_KEEP_ALIVE_PERIOD : u64 = 15;
+
+// Comment line. It is ignored by the clients
+const SSE_KEEP_ALIVE : &str = ": keep-alive\n\n";
+
+
+pub struct ExecutionEventStream {
+    execution_id:       u32,
+    // Sequence of the last event sent
+    last_sequence:      u32,
+    done_flag:          bool,
+    keep_alive:         Interval,
+}
+
+impl ExecutionEventStream {
+    /**
+     * The events after in_last_sequence are sent. 0 = All the events
+     */
+    pub fn new(in_execution_id: u32, in_last_sequence: u32) -> Self {
+        let tmp_period = Duration::from_secs(SSE_KEEP_ALIVE_PERIOD);
+
+        ExecutionEventStream {
+            execution_id:   in_execution_id,
+            last_sequence:  in_last_sequence,
+            done_flag:      false,
+            keep_alive:     interval_at(Instant::now() + tmp_period, tmp_period),
+        }
+    }
+}
+
+/**
+ * Format of an event:
+ *    id: SEQUENCE
+ *    event: status | progress
+ *    data: JSON
+ */
+fn format_event(in_event: &ExecutionEventStruct) -> String {
+    format!("id: {}\nevent: {}\ndata: {}\n\n",
+            in_event.sequence,
+            in_event.event,
+            serde_json::to_string(in_event).unwrap())
+}
+
+impl Stream for ExecutionEventStream {
+    type Item = Result<Bytes, actix_web::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done_flag == true {
+            return Poll::Ready(None);
+        }
+
+        // The waker is stored in the Tasks Manager, it will wake up the stream
+        // when a new event is added
+        let (tmp_events, complete_flag) = match TASK_MANAGER.write().unwrap().get_events(self.execution_id,
+                                                                                         self.last_sequence,
+                                                                                         cx.waker()) {
+            Ok(r) => r,
+            Err(e) => {
+                // The execution has been removed
+                error!("Error reading the events of task: {}", e);
+                return Poll::Ready(None);
+            },
+        };
+
+        if tmp_events.is_empty() == false {
+            let mut output_buffer = String::new();
+
+            for an_event in tmp_events.iter() {
+                output_buffer.push_str( format_event(an_event).as_str() );
+                self.last_sequence = an_event.sequence;
+            }
+
+            debug!("Execution: {} events sent up to: {}", self.execution_id, self.last_sequence);
+
+            return Poll::Ready( Some( Ok( Bytes::from(output_buffer) ) ) );
+        }
+
+        if complete_flag == true {
+            self.done_flag = true;
+            return Poll::Ready(None);
+        }
+
+        // The interval wakes up the stream when the next comment is due
+        if self.keep_alive.poll_tick(cx).is_ready() == true {
+            return Poll::Ready( Some( Ok( Bytes::from_static( SSE_KEEP_ALIVE.as_bytes() ) ) ) );
+        }
+
+        Poll::Pending
+    }
+}
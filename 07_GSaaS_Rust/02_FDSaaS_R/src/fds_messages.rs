@@ -35,6 +35,50 @@ pub struct GetVersionResponseStruct
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GetStatusResponseStruct {
     pub status:               String,
+    // Health of the modules
+    #[serde(default)]
+    pub modules:              Vec<ModuleHealthStruct>,
+}
+
+/**
+ * Result of reloading the modules definition file. Names of the modules
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReloadModulesResponseStruct {
+    pub added:                Vec<String>,
+    pub removed:              Vec<String>,
+    // The old instances are stopped once their executions are completed
+    pub restarted:            Vec<String>,
+    pub unchanged:            Vec<String>,
+}
+
+/**
+ * Health of a module. Status: Healthy, Degraded (some instances are not running), Down
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModuleHealthStruct {
+    pub module_id:            u32,
+    pub name:                 String,
+    pub status:               String,
+    pub instances:            Vec<InstanceHealthStruct>,
+}
+
+/**
+ * Health of an instance of a module. Status: Starting, Running, Erroneous, Stopped
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstanceHealthStruct {
+    pub module_instance_id:   u32,
+    pub status:               String,
+    // Process id. 0 = No process
+    pub pid:                  u32,
+    // UTC. Format: yyyy-mm-dd hh:mi:ss
+    pub start_time:           String,
+    // Last answer to get_status
+    pub last_status_time:     String,
+    // Consecutive restarts
+    pub restart_count:        u32,
+    pub running_executions:   u32,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -44,6 +88,52 @@ pub struct GetModuleStatusResponseStruct {
     pub status:               String,
 }
 
+/**
+ * Response to a message submitted asynchronously
+ * The module executes it in the background
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExecutionSubmittedStruct {
+    pub execution_id:         u32,
+    pub execution_status:     String,
+    // URL for querying the execution
+    pub location:             String,
+}
+
+/**
+ * Status of an execution
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExecutionStatusStruct {
+    pub execution_id:         u32,
+    pub module_id:            u32,
+    pub execution_status:     String,
+    // Percentage, 0 - 100. As reported by the module
+    pub progress:             u8,
+    pub progress_detail:      String,
+    // UTC. Format: yyyy-mm-dd hh:mi:ss
+    pub start_time:           String,
+    pub stop_time:            String,
+    pub complete_flag:        bool,
+}
+
+/**
+ * Event of an execution. Sent to the clients following the execution
+ * Type of event; "status" (a transition) or "progress" (reported by the module)
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExecutionEventStruct {
+    // Consecutive number of the event within the execution
+    pub sequence:             u32,
+    pub event:                String,
+    pub execution_id:         u32,
+    pub execution_status:     String,
+    pub progress:             u8,
+    pub progress_detail:      String,
+    // UTC. Format: yyyy-mm-dd hh:mi:ss
+    pub event_time:           String,
+}
+
 
 
 
@@ -11,7 +11,7 @@
 
 use std::{env};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::sync::mpsc;
 use std::process;
 use std::fs;
@@ -50,6 +50,9 @@ use nng::{Message, Protocol, Socket, Error};
 #[macro_use]
 extern crate diesel;
 
+#[macro_use]
+extern crate diesel_migrations;
+
 //#[macro_use]
 use lazy_static::lazy_static;
 
@@ -73,6 +76,14 @@ use tasks_manager::*;
 mod wait_for_task;
 use wait_for_task::*;
 
+// Stream. Events of an execution
+mod execution_events;
+use execution_events::*;
+
+// Callbacks. Final answer of the executions
+mod webhooks;
+use webhooks::*;
+
 // Common functions
 mod config_fds;
 use config_fds::*;
@@ -95,6 +106,18 @@ use db::http_access::*;
 // limit the maximum amount of data that server will accept
 const MAX_SIZE_JSON : usize =  262_144;
 
+// Period for checking the timeout of the executions
+const TIMEOUT_CHECK_PERIOD : Duration = Duration::from_secs(1);
+
+// Period for removing the expired execution records
+const CLEANUP_PERIOD : Duration = Duration::from_secs(60);
+
+// Period for checking the health of the modules
+const SUPERVISION_PERIOD : Duration = Duration::from_secs(5);
+
+// Period for checking if the modules definition file has been modified
+const DEFINITIONS_CHECK_PERIOD : Duration = Duration::from_secs(5);
+
 const FDSAAS_VERSION : &str = "0.1";
 
 
@@ -248,6 +271,20 @@ fn main_control_loop()
     //let mut modules_manager = ModuleManager::new();
     //info!("Module Manager created");
 
+    // Set the DB Pool and recover the executions of the previous run
+    // It shall be done before starting the modules, so, the queued executions are sent when they are ready
+    {
+        let tmp_global_data = GLOBAL_DATA.read().unwrap();
+        let mut tmp_task_manager = TASK_MANAGER.write().unwrap();
+
+        tmp_task_manager.set_db_pool( tmp_global_data.db_pool.clone() );
+        tmp_task_manager.set_retention_time( tmp_config_data.execution_retention_time );
+
+        if let Err(e) = tmp_task_manager.recover_tasks( tmp_config_data.requeue_executions ) {
+            error!("Unable to recover executions: {}. IGNORED", e);
+        }
+    }
+
     // Load Module definitions
     // Start executing the modules
     if let Err(_e) = ModuleManager::current().load_module_definitions() {
@@ -260,13 +297,10 @@ fn main_control_loop()
         info!("Module definitions correctly loaded");
     }
 
-    // Set the DB Pool
-    {
-        let tmp_global_data = GLOBAL_DATA.read().unwrap();
-        TASK_MANAGER.write().unwrap().set_db_pool( tmp_global_data.db_pool.clone() );
-    }
-    
     let mut done_flag = false;
+    let mut last_timeout_check = Instant::now();
+    let mut last_cleanup = Instant::now();
+    let mut last_definitions_check = Instant::now();
     
     while done_flag == false {
         {
@@ -278,6 +312,27 @@ fn main_control_loop()
             }
         }
 
+        // Cancel the executions without answer
+        // Stop the instances of the modules that are not needed
+        if last_timeout_check.elapsed() >= TIMEOUT_CHECK_PERIOD {
+            ModuleManager::current().cancel_expired_executions();
+            ModuleManager::current().stop_idle_instances();
+            ModuleManager::current().remove_drained_modules();
+            last_timeout_check = Instant::now();
+        }
+
+        // Reload the modules definition file when it is modified
+        if tmp_config_data.watch_modules_definition == true && last_definitions_check.elapsed() >= DEFINITIONS_CHECK_PERIOD {
+            ModuleManager::current().check_definitions_file();
+            last_definitions_check = Instant::now();
+        }
+
+        // Remove the expired executions
+        if last_cleanup.elapsed() >= CLEANUP_PERIOD {
+            TASK_MANAGER.write().unwrap().remove_expired_tasks();
+            last_cleanup = Instant::now();
+        }
+
         // Read SUB message
         // ------------------------------------------
         let input_msg = sub_control_socket.try_recv();
@@ -288,8 +343,8 @@ fn main_control_loop()
                 // End of the loop
                 break;
             } 
-        } else {
-            // Small sleep
+
+            // Nothing received. Small sleep
             thread::sleep(Duration::from_millis(50));
             continue;
         }
@@ -299,8 +354,14 @@ fn main_control_loop()
 
         debug!("Received MAIN message: {}", String::from_utf8( json_buffer.as_slice().to_vec()).unwrap() );
 
+        // Remove the topic
+        let mut json_slice = json_buffer.as_slice();
+        if json_slice.starts_with(main_topic) == true {
+            json_slice = &json_slice[main_topic.len()..];
+        }
+
         // Decode JSON
-        let json_message = serde_json::from_slice(json_buffer.as_slice());
+        let json_message = serde_json::from_slice(json_slice);
 
         let _request_message : RestRequest = match json_message {
             Ok(msg) => {
@@ -336,7 +397,21 @@ fn main_control_loop()
                 msg 
             },
             Err(_e) => {
-                debug!("   ****** SKIP RestRequest ");
+                // Answers and progress messages of the modules
+                let json_response : Result<InternalResponseMessage, serde_json::Error> = serde_json::from_slice(json_slice);
+
+                match json_response {
+                    Ok(r) => {
+                        debug!("   ****** InternalResponseMessage ");
+                        if let Err(e) = process_incoming_response(&r) {
+                            error!("ERROR: Processing module response: {}. IGNORED", e);
+                        }
+                    },
+                    Err(_e) => {
+                        debug!("   ****** SKIP RestRequest ");
+                    },
+                };
+
                 // Unused
                 RestRequest::new()
             },
@@ -399,6 +474,12 @@ fn process_incoming_response(in_msg: &InternalResponseMessage) -> Result<Interna
             return ModuleManager::current().module_is_ready(in_msg);
         },
 
+        // Periodic check of the modules. Sent by the supervisor loop
+        "supervise_modules" => {
+            ModuleManager::current().supervise_modules();
+            return Ok(in_msg.clone());
+        },
+
         /*
          * Process the answer from the module. Subscribe; Orb Propagation 
          */      
@@ -414,6 +495,18 @@ fn process_incoming_response(in_msg: &InternalResponseMessage) -> Result<Interna
             return ModuleManager::current().handle_module_answer(in_msg);
         },
 
+        // The module has aborted a cancelled execution. The instance is idle again
+        "cancel_execution_response" => {
+            return ModuleManager::current().handle_module_answer(in_msg);
+        },
+
+        /*
+         * Progress of an on-going execution
+         */
+        "progress" => {
+            return ModuleManager::current().handle_module_progress(in_msg);
+        },
+
         _ => { println!("Unknown message code: {}", in_msg.response.msg_code_id.as_str() );
                error!("Unknown message code: {}. IGNORED", in_msg.response.msg_code_id.as_str() );
              }
@@ -536,6 +629,24 @@ fn process_a_message(in_json_message: &mut InternalMessage) -> Result<InternalRe
             return ModuleManager::current().call_module(in_json_message);
         },
 
+        // === ASYNCHRONOUS EXECUTIONS ==========================
+        "execution_status" => {
+            return process_execution_status(in_json_message);
+        },
+
+        "execution_result" => {
+            return process_execution_result(in_json_message);
+        },
+
+        "cancel_execution" => {
+            return process_cancel_execution(in_json_message);
+        },
+
+        // === ADMINISTRATION ==========================
+        "reload_modules" => {
+            return process_reload_modules(in_json_message);
+        },
+
         _ => { println!("Unknown message code: {}", in_json_message.request.msg_code_id.as_str() );
                error!("Unknown message code: {}. IGNORED", in_json_message.request.msg_code_id.as_str() );
              }
@@ -545,6 +656,171 @@ fn process_a_message(in_json_message: &mut InternalMessage) -> Result<InternalRe
                               json!("Message Ignored"), 0 ) )
 }
 
+/**
+ * Read the execution id of the execution_status and execution_result messages
+ */
+fn get_execution_id(in_json_message: &InternalMessage) -> Result<u32, InternalResponseMessage> 
+{
+    match in_json_message.request.parameters["execution_id"].as_u64() {
+        Some(i) => Ok(i as u32),
+        None => {
+            let tmp_msg = format!("ERROR: Execution id not found. IGNORED");
+            error!("{}", tmp_msg.as_str() );
+
+            Err( InternalResponseMessage::new_error("error_response", in_json_message.request.msg_id.clone(), 
+                                                    tmp_msg.as_str(), 0) )
+        },
+    }
+}
+
+/**
+ * Return the status and progress of an execution
+ */
+fn process_execution_status(in_json_message: &InternalMessage) -> Result<InternalResponseMessage, String> 
+{
+    let tmp_execution_id = match get_execution_id(in_json_message) {
+        Ok(i) => i,
+        Err(e) => return Ok(e),
+    };
+    let tmp_msg_id = in_json_message.request.msg_id.clone();
+
+    let tmp_status = TASK_MANAGER.read().unwrap().get_execution_status(tmp_execution_id, &in_json_message.user_id);
+
+    match tmp_status {
+        Ok(s) => {
+            Ok( InternalResponseMessage::new_value("execution_status_response", tmp_msg_id, json!(s), tmp_execution_id) )
+        },
+        Err(e) => {
+            Ok( InternalResponseMessage::new_error_ext("error_response", tmp_msg_id, 
+                                                       StatusCode::NOT_FOUND.as_u16() as i32, e.as_str(), tmp_execution_id) )
+        },
+    }
+}
+
+/**
+ * Return the answer of the module once the execution is completed
+ * Until then, it returns the status with code 202
+ */
+fn process_execution_result(in_json_message: &InternalMessage) -> Result<InternalResponseMessage, String> 
+{
+    let tmp_execution_id = match get_execution_id(in_json_message) {
+        Ok(i) => i,
+        Err(e) => return Ok(e),
+    };
+    let tmp_msg_id = in_json_message.request.msg_id.clone();
+
+    let tmp_status = TASK_MANAGER.read().unwrap().get_execution_status(tmp_execution_id, &in_json_message.user_id);
+    let tmp_status = match tmp_status {
+        Ok(s) => s,
+        Err(e) => {
+            return Ok( InternalResponseMessage::new_error_ext("error_response", tmp_msg_id, 
+                                                              StatusCode::NOT_FOUND.as_u16() as i32, e.as_str(), tmp_execution_id) );
+        },
+    };
+
+    if tmp_status.complete_flag == false {
+        let mut resp_json_message = InternalResponseMessage::new_value("execution_result_response", tmp_msg_id, 
+                                                                       json!(tmp_status), tmp_execution_id);
+        resp_json_message.response.status = StatusCode::ACCEPTED.as_u16() as i32;
+
+        return Ok(resp_json_message);
+    }
+
+    // The answer is the RestResponse sent by the module
+    let tmp_answer = TASK_MANAGER.read().unwrap().get_answer(tmp_execution_id);
+    let tmp_answer = match tmp_answer {
+        Ok(a) => a,
+        Err(e) => {
+            return Ok( InternalResponseMessage::new_error_ext("error_response", tmp_msg_id, 
+                                                              StatusCode::NOT_FOUND.as_u16() as i32, e.as_str(), tmp_execution_id) );
+        },
+    };
+
+    match serde_json::from_str::<RestResponse>(tmp_answer.as_str()) {
+        Ok(r) => {
+            let mut resp_json_message = InternalResponseMessage::new();
+
+            resp_json_message.response     = r;
+            resp_json_message.execution_id = tmp_execution_id;
+
+            Ok(resp_json_message)
+        },
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to decode the answer of execution {}: {}", tmp_execution_id, e.to_string());
+            error!("{}", tmp_msg.as_str() );
+
+            Ok( InternalResponseMessage::new_error("error_response", tmp_msg_id, tmp_msg.as_str(), tmp_execution_id) )
+        },
+    }
+}
+
+/**
+ * Cancel an execution on user request
+ * A task waiting for the answer receives an error
+ */
+fn process_cancel_execution(in_json_message: &InternalMessage) -> Result<InternalResponseMessage, String> 
+{
+    let tmp_execution_id = match get_execution_id(in_json_message) {
+        Ok(i) => i,
+        Err(e) => return Ok(e),
+    };
+    let tmp_msg_id = in_json_message.request.msg_id.clone();
+
+    // Only the owner can cancel it
+    let tmp_status = TASK_MANAGER.read().unwrap().get_execution_status(tmp_execution_id, &in_json_message.user_id);
+    let tmp_status = match tmp_status {
+        Ok(s) => s,
+        Err(e) => {
+            return Ok( InternalResponseMessage::new_error_ext("error_response", tmp_msg_id, 
+                                                              StatusCode::NOT_FOUND.as_u16() as i32, e.as_str(), tmp_execution_id) );
+        },
+    };
+
+    if tmp_status.complete_flag == true {
+        let tmp_msg = format!("ERROR: Execution {} is already {}", tmp_execution_id, tmp_status.execution_status);
+        error!("{}", tmp_msg.as_str() );
+
+        return Ok( InternalResponseMessage::new_error_ext("error_response", tmp_msg_id, 
+                                                          StatusCode::CONFLICT.as_u16() as i32, tmp_msg.as_str(), tmp_execution_id) );
+    }
+
+    if let Err(e) = ModuleManager::current().cancel_execution(tmp_execution_id, StatusCode::GONE.as_u16(), 
+                                                             "Execution cancelled by the user") {
+        return Ok( InternalResponseMessage::new_error("error_response", tmp_msg_id, e.as_str(), tmp_execution_id) );
+    }
+
+    // Return the new status
+    process_execution_status(in_json_message)
+}
+
+/**
+ * Reload the modules definition file
+ * The in-flight executions of the removed and changed modules are completed before stopping them
+ * Only the users in "admin_users" of the configuration file can reload the modules
+ */
+fn process_reload_modules(in_json_message: &InternalMessage) -> Result<InternalResponseMessage, String> 
+{
+    let tmp_msg_id = in_json_message.request.msg_id.clone();
+
+    if CONFIG_VARIABLES.read().unwrap().is_admin_user(in_json_message.user_id.as_str()) == false {
+        let tmp_msg = format!("ERROR: User {} is not allowed to reload the modules", in_json_message.user_id);
+        error!("{}", tmp_msg.as_str() );
+
+        return Ok( InternalResponseMessage::new_error_ext("error_response", tmp_msg_id, 
+                                                          StatusCode::FORBIDDEN.as_u16() as i32, tmp_msg.as_str(), 0) );
+    }
+
+    match ModuleManager::current().reload_module_definitions() {
+        Ok(r) => {
+            Ok( InternalResponseMessage::new_value("reload_modules_response", tmp_msg_id, json!(r), 0) )
+        },
+        Err(e) => {
+            // The current modules are kept
+            Ok( InternalResponseMessage::new_error("error_response", tmp_msg_id, e.as_str(), 0) )
+        },
+    }
+}
+
 /**
  * Check the input parameters of the REST
  */
@@ -642,6 +918,77 @@ fn check_response_parameters(in_json_message: &InternalResponseMessage) -> Resul
 
     return Ok(false);
 }
+
+/**
+ * Supervisor of the modules
+ * Periodically, it asks the main control loop to check the health of the modules. The Module Manager
+ * can only be used from the main control loop. So, the request is published in its SUB socket
+ */
+fn supervisor_loop() 
+{
+    info!("**** Starting SUPERVISOR loop ");
+
+    let tmp_sub_address = CONFIG_VARIABLES.read().unwrap().fds_nng_sub_address.clone();
+
+    // Create PUB Socket
+    // ---------------------------------------------------
+    let pub_socket = Socket::new(Protocol::Pub0);
+    let pub_socket = match pub_socket  {
+        Ok(s) =>  { info!("NNG Socket PUB supervisor correctly created ");
+            s
+        },
+        Err(e) => {
+            error!("Unable to create supervisor PUB socket. Error: {}", e.to_string());
+            return;
+        },
+    };
+
+    // Give the main control loop time to start listening
+    thread::sleep(SUPERVISION_PERIOD);
+
+    // Start publishing messages
+    let unused_result = pub_socket.dial( tmp_sub_address.as_str() );
+    if let Err(e) = unused_result {
+        error!("Error when starting dialing into socket: {}", e );
+        return;
+    }
+    info!("Correctly connected to Main Control (SUB). Address: {}", tmp_sub_address);
+
+    let mut counter : u64 = 0;
+    let mut last_supervision = Instant::now();
+
+    loop {
+        {
+            let tmp_global_data = GLOBAL_DATA.read().unwrap();
+            if tmp_global_data.exit_flag == true {
+                debug!("SUPERVISOR loop exiting");
+                break;
+            }
+        }
+
+        if last_supervision.elapsed() >= SUPERVISION_PERIOD {
+            counter += 1;
+
+            let tmp_message = InternalResponseMessage::new_value("supervise_modules", format!("{}", counter), json!({}), 0);
+
+            // Format: main:JSON
+            let mut socket_m = nng::Message::from("main:".as_bytes());
+            socket_m.push_back(tmp_message.to_string().as_bytes());
+
+            if let Err(e) = pub_socket.send(socket_m) {
+                error!("ERROR: Sending supervision request. Error: {}", nng::Error::from(e) );
+            }
+
+            last_supervision = Instant::now();
+        }
+
+        // Small sleep
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    info!("**** Stopping SUPERVISOR loop ");
+}
+
 /**
  * It does receives messages via a New Nanomsg socket.
  * It opens 2 sockets; 
@@ -858,6 +1205,7 @@ fn nng_control_loop()
 /**
  * Return the status of the FDS module
  * Status can be; None, Running, Stopped
+ * It includes the health of the modules and their instances
  */
 async fn get_status() -> impl Responder 
 {
@@ -866,7 +1214,8 @@ async fn get_status() -> impl Responder
     let tmp_data = GLOBAL_DATA.read().unwrap();
     // let tmp_status = tmp_data.fds_status.to_string();
 
-    let tmp_status = GetStatusResponseStruct{ status : tmp_data.fds_status.to_string() };
+    let tmp_status = GetStatusResponseStruct{ status  : tmp_data.fds_status.to_string(),
+                                              modules : MODULES_HEALTH.read().unwrap().clone() };
 
     return HttpResponse::Ok().json( tmp_status );
 }
@@ -1050,16 +1399,11 @@ async fn forward_message_internal(in_socket: &Socket, in_payload: &String) -> Re
 }
 
 /**
- * Forward a message to the main control loop and wait for the answer
+ * Return the socket with the Main Control loop
+ * It is created the first time
  */
-async fn forward_message(in_payload: String, in_request: HttpRequest, in_db: web::Data<DbPool>) -> impl Responder 
+fn get_control_socket() -> Result<Socket, String> 
 {
-    debug!("HTTP FORWARD sending message: {}", in_payload );
-
-    // Record the HTTP access
-    record_access(&in_request, &in_db);
-
-    // Create a socket with the Main Control loop
     let tmp_control_socket;
 
     {
@@ -1068,10 +1412,10 @@ async fn forward_message(in_payload: String, in_request: HttpRequest, in_db: web
     }
 
     // Check if the socket already exists, if not create it
-    let tmp_control_socket = match tmp_control_socket {
+    match tmp_control_socket {
         Some(s) => {
             debug!("Socket to main already exist. Nothing to be done");
-            s
+            Ok(s)
             },
         None => { 
             // Create Socket, if it does not exist
@@ -1083,7 +1427,7 @@ async fn forward_message(in_payload: String, in_request: HttpRequest, in_db: web
                 },
                 Err(e) => {
                     error!("Unable to create Main Control REP socket. Error: {}", e.to_string());
-                    return HttpResponse::InternalServerError().body( e.to_string() );
+                    return Err( e.to_string() );
                 },
             };
 
@@ -1097,7 +1441,7 @@ async fn forward_message(in_payload: String, in_request: HttpRequest, in_db: web
             let unused_result = new_control_socket.dial( controller_address.as_str() );
             if let Err(e) = unused_result {
                 error!("Error when starting listening the socket: {}", e );
-                return  HttpResponse::InternalServerError().body( e.to_string() );
+                return Err( e.to_string() );
             }
             info!("Correctly connected to Main Control server. Address: {}", controller_address);
 
@@ -1116,7 +1460,26 @@ async fn forward_message(in_payload: String, in_request: HttpRequest, in_db: web
                 tmp_data.nng_socket = Some( new_control_socket.clone() );
             }
             
-            new_control_socket
+            Ok(new_control_socket)
+        },
+    }
+}
+
+/**
+ * Forward a message to the main control loop and wait for the answer
+ */
+async fn forward_message(in_payload: String, in_request: HttpRequest, in_db: web::Data<DbPool>) -> impl Responder 
+{
+    debug!("HTTP FORWARD sending message: {}", in_payload );
+
+    // Record the HTTP access
+    record_access(&in_request, &in_db);
+
+    // Create a socket with the Main Control loop
+    let tmp_control_socket = match get_control_socket() {
+        Ok(s) => s,
+        Err(e) => {
+            return HttpResponse::InternalServerError().body( e );
         },
     };
 
@@ -1128,6 +1491,11 @@ async fn forward_message(in_payload: String, in_request: HttpRequest, in_db: web
 
     match http_output {
         Ok(o) => {
+            // Cancelled executions return an error. I.e. 504 if the timeout expired
+            if let Ok(r) = serde_json::from_str::<RestResponse>( o.as_str() ) {
+                return build_http_response(&r);
+            }
+
             return HttpResponse::Ok()
                         .content_type("application/json")
                         .body( o );
@@ -1138,6 +1506,248 @@ async fn forward_message(in_payload: String, in_request: HttpRequest, in_db: web
     };
 }
 
+/**
+ * Forward a message to the Main Control loop and decode the InternalResponseMessage
+ * It does not wait for the module answer
+ */
+async fn forward_message_response(in_socket: &Socket, in_payload: &String) -> Result<InternalResponseMessage, String> 
+{
+    let internal_message : String = forward_message_internal(in_socket, in_payload).await?;
+
+    match serde_json::from_str::<InternalResponseMessage>( internal_message.as_str() ) {
+        Ok(m) => Ok(m),
+        Err(e) => {
+            let error_msg = format!("ERROR: Unable to decode Internal Response JSON message: {}", e.to_string());
+            error!("{}", error_msg);
+
+            Err( RestResponse::new_error_msg("error_response", String::from("-1"), error_msg).to_string() )
+        },
+    }
+}
+
+/**
+ * Build the HTTP response. The HTTP status is the status of the RestResponse
+ */
+fn build_http_response(in_response: &RestResponse) -> HttpResponse 
+{
+    let mut tmp_status = StatusCode::OK;
+
+    if in_response.status >= 100 && in_response.status < 600 {
+        if let Ok(s) = StatusCode::from_u16(in_response.status as u16) {
+            tmp_status = s;
+        }
+    }
+
+    HttpResponse::build(tmp_status)
+        .content_type("application/json")
+        .body( in_response.to_string() )
+}
+
+/**
+ * Submit a message to be executed asynchronously
+ * It does not wait for the answer of the module. It returns 202 and the location
+ * of the execution. Then, the caller polls the execution status and result
+ */
+async fn submit_message(in_payload: String, in_request: HttpRequest, in_db: web::Data<DbPool>) -> impl Responder 
+{
+    debug!("HTTP SUBMIT sending message: {}", in_payload );
+
+    // Record the HTTP access
+    record_access(&in_request, &in_db);
+
+    let tmp_control_socket = match get_control_socket() {
+        Ok(s) => s,
+        Err(e) => {
+            return HttpResponse::InternalServerError().body( e );
+        },
+    };
+
+    let json_message = match forward_message_response(&tmp_control_socket, &in_payload).await {
+        Ok(m) => m,
+        Err(e) => {
+            return HttpResponse::InternalServerError().body( e );
+        },
+    };
+
+    // Errors and messages not executed by a module. Return them as they are
+    if json_message.wait_flag == false {
+        return build_http_response(&json_message.response);
+    }
+
+    let tmp_location = format!("/fdsaas/api/execution/{}", json_message.execution_id);
+
+    // Queued (Idle) if all the instances of the module are busy. It could be already completed
+    let tmp_execution_status = match TASK_MANAGER.read().unwrap().get_status(json_message.execution_id) {
+        Ok(s) => s,
+        Err(e) => {
+            return HttpResponse::InternalServerError().body( e );
+        },
+    };
+
+    let tmp_submitted = ExecutionSubmittedStruct {
+        execution_id:     json_message.execution_id,
+        execution_status: tmp_execution_status,
+        location:         tmp_location.clone(),
+    };
+
+    let mut tmp_response = json_message.response.clone();
+    tmp_response.status = StatusCode::ACCEPTED.as_u16() as i32;
+    tmp_response.result = json!(tmp_submitted);
+
+    debug!("Execution submitted: {}", tmp_response.to_string());
+
+    HttpResponse::Accepted()
+        .header(actix_web::http::header::LOCATION, tmp_location)
+        .content_type("application/json")
+        .body( tmp_response.to_string() )
+}
+
+/**
+ * Forward a query about an execution to the Main Control loop
+ * The execution id of the URL is added to the message
+ */
+async fn forward_execution_query(in_execution_id: u32, in_msg_code_id: &str, in_payload: &String) -> HttpResponse 
+{
+    let mut tmp_request : RestRequest = match serde_json::from_str( in_payload.as_str() ) {
+        Ok(r) => r,
+        Err(e) => {
+            let error_msg = format!("ERROR: Unable to decode JSON message: {}", e.to_string());
+            error!("{}", error_msg);
+
+            return build_http_response( &RestResponse::new_error_msg("error_response", String::from("-1"), error_msg) );
+        },
+    };
+
+    tmp_request.msg_code_id                 = String::from(in_msg_code_id);
+    tmp_request.parameters["execution_id"]  = json!(in_execution_id);
+
+    let tmp_control_socket = match get_control_socket() {
+        Ok(s) => s,
+        Err(e) => {
+            return HttpResponse::InternalServerError().body( e );
+        },
+    };
+
+    match forward_message_response(&tmp_control_socket, &tmp_request.to_string()).await {
+        Ok(m) => build_http_response(&m.response),
+        Err(e) => HttpResponse::InternalServerError().body( e ),
+    }
+}
+
+/**
+ * Return the status and progress of an execution
+ */
+async fn get_execution_status(in_execution_id: web::Path<u32>, in_payload: String, in_request: HttpRequest, 
+                              in_db: web::Data<DbPool>) -> impl Responder 
+{
+    info!("   *** Get Execution Status: {}", in_execution_id);
+
+    // Record the HTTP access
+    record_access(&in_request, &in_db);
+
+    forward_execution_query(in_execution_id.into_inner(), "execution_status", &in_payload).await
+}
+
+/**
+ * Cancel an execution
+ */
+async fn cancel_execution(in_execution_id: web::Path<u32>, in_payload: String, in_request: HttpRequest, 
+                          in_db: web::Data<DbPool>) -> impl Responder 
+{
+    info!("   *** Cancel Execution: {}", in_execution_id);
+
+    // Record the HTTP access
+    record_access(&in_request, &in_db);
+
+    forward_execution_query(in_execution_id.into_inner(), "cancel_execution", &in_payload).await
+}
+
+/**
+ * Return the result of an execution. 202 while it is running
+ */
+async fn get_execution_result(in_execution_id: web::Path<u32>, in_payload: String, in_request: HttpRequest, 
+                              in_db: web::Data<DbPool>) -> impl Responder 
+{
+    info!("   *** Get Execution Result: {}", in_execution_id);
+
+    // Record the HTTP access
+    record_access(&in_request, &in_db);
+
+    forward_execution_query(in_execution_id.into_inner(), "execution_result", &in_payload).await
+}
+
+/**
+ * Authentication key of a stream of events. There is no body, so, it is read from the header "Authorization: Bearer KEY"
+ * It is not accepted in the query string; URLs end up in logs and browser histories
+ */
+fn events_authentication_key(in_request: &HttpRequest) -> Option<String>
+{
+    if let Some(h) = in_request.headers().get(actix_web::http::header::AUTHORIZATION) {
+        if let Ok(v) = h.to_str() {
+            if let Some(k) = v.trim().strip_prefix("Bearer ") {
+                if k.trim().is_empty() == false {
+                    return Some( String::from(k.trim()) );
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/**
+ * Stream the events of an execution (Server-Sent Events); status transitions and progress
+ * The stream ends when the execution is completed
+ * The header Last-Event-ID allows resuming the stream after a disconnection
+ */
+async fn get_execution_events(in_execution_id: web::Path<u32>, in_request: HttpRequest, 
+                              in_db: web::Data<DbPool>) -> impl Responder 
+{
+    info!("   *** Get Execution Events: {}", in_execution_id);
+
+    // Record the HTTP access
+    record_access(&in_request, &in_db);
+
+    let tmp_execution_id = in_execution_id.into_inner();
+    let tmp_msg_id       = format!("events_{}", tmp_execution_id);
+
+    let tmp_authentication_key = match events_authentication_key(&in_request) {
+        Some(k) => k,
+        None => {
+            let error_msg = String::from("ERROR: Authentication key not found");
+            error!("{}", error_msg);
+
+            return build_http_response( &RestResponse::new_error_id("error_response", tmp_msg_id, 401, error_msg) );
+        },
+    };
+
+    // Check the authentication and that the execution belongs to the user
+    let tmp_status_request = json!({
+        "version":             REST_JSON_VERSION,
+        "msg_code_id":         "execution_status",
+        "authentication_key":  tmp_authentication_key,
+        "msg_id":              tmp_msg_id,
+        "timestamp":           Utc::now().timestamp(),
+    });
+
+    let tmp_status_response = forward_execution_query(tmp_execution_id, "execution_status", &tmp_status_request.to_string()).await;
+    if tmp_status_response.status().is_success() == false {
+        return tmp_status_response;
+    }
+
+    let mut tmp_last_sequence : u32 = 0;
+    if let Some(h) = in_request.headers().get("Last-Event-ID") {
+        if let Ok(v) = h.to_str() {
+            tmp_last_sequence = v.trim().parse::<u32>().unwrap_or(0);
+        }
+    }
+
+    HttpResponse::Ok()
+        .content_type(SSE_CONTENT_TYPE)
+        .header(actix_web::http::header::CACHE_CONTROL, "no-cache")
+        .streaming( ExecutionEventStream::new(tmp_execution_id, tmp_last_sequence) )
+}
+
 /**
  * Return index.html
  */
@@ -1293,6 +1903,19 @@ async fn main() -> std::io::Result<()>
     //let conn_pool_copy = conn_pool.clone();
     let conn_pool_copy;
 
+    // Create or update the database tables before any access
+    {
+        let tmp_data = GLOBAL_DATA.read().unwrap();
+
+        if let Err(e) = db::run_migrations(&tmp_data.db_pool) {
+            println!("ERROR: {}", e);
+            error!("{}", e);
+            return Ok(());
+        }
+
+        info!("Database migrations applied");
+    }
+
     // Data shared between all threads
     {
         let mut tmp_data = GLOBAL_DATA.write().unwrap();
@@ -1344,6 +1967,7 @@ async fn main() -> std::io::Result<()>
                     .route("/api/{operation}/usage", web::get().to(api_usage))
                     .route("/api/version", web::get().to(get_version))
                     .route("/api/status", web::get().to(get_status))
+                    .route("/api/reload_modules", web::post().to(forward_message))
 
                     // PROPAGATE AN ORBIT
                     // ---------------------------------
@@ -1354,6 +1978,18 @@ async fn main() -> std::io::Result<()>
                     // ---------------------------------
                     .route("/api/run_script", web::get().to(forward_message))
 
+                    // ASYNCHRONOUS EXECUTIONS
+                    // It returns the execution id without waiting for the answer
+                    // ---------------------------------
+                    .route("/api/orb_propagation/submit", web::get().to(submit_message))
+                    .route("/api/orb_propagation_tle/submit", web::get().to(submit_message))
+                    .route("/api/run_script/submit", web::get().to(submit_message))
+
+                    .route("/api/execution/{execution_id}", web::get().to(get_execution_status))
+                    .route("/api/execution/{execution_id}/result", web::get().to(get_execution_result))
+                    .route("/api/execution/{execution_id}/cancel", web::post().to(cancel_execution))
+                    .route("/api/execution/{execution_id}/events", web::get().to(get_execution_events))
+
                     // /api/list - List all APIs
             )
             
@@ -1383,16 +2019,39 @@ async fn main() -> std::io::Result<()>
     });
 
     let srv = rx.recv().unwrap();
+
+    // Webhook callbacks. It shall be running before the executions are recovered
+    // Without a valid secret key, the requests with a callback URL are rejected
+    {
+        let tmp_config_data = CONFIG_VARIABLES.read().unwrap();
+        let tmp_global_data = GLOBAL_DATA.read().unwrap();
+
+        let tmp_result = load_secret_key(tmp_config_data.callback_secret_key_file.as_str())
+            .and_then(|k| start_webhook_loop(tmp_global_data.db_pool.clone(),
+                                             k,
+                                             tmp_config_data.callback_max_attempts,
+                                             tmp_config_data.callback_initial_backoff));
+
+        if let Err(e) = tmp_result {
+            warn!("Webhook callbacks are disabled: {}", e);
+        }
+    }
     
     // NNG (BUS, REQ, SUB) control loop
     let nng_thread = thread::spawn(move || {
         nng_control_loop();
     });
 
+    // Supervisor of the modules
+    let supervisor_thread = thread::spawn(move || {
+        supervisor_loop();
+    });
+
     // Main control loop thread
     main_control_loop();
 
     nng_thread.join().unwrap();
+    supervisor_thread.join().unwrap();
     
     // Small sleep
     thread::sleep(Duration::from_secs(1));
@@ -12,6 +12,7 @@
 //use std::cell::RefCell;
 use std::fs;
 use std::fs::File;
+use std::time::SystemTime;
 use std::process::{Child, Command, Stdio};
 //use std::rc::Rc;
 use std::result::Result;
@@ -46,14 +47,68 @@ use crate::fds_messages::*;
 use crate::tasks_manager::*;
 use TASK_MANAGER;
 
+// Callbacks
+use crate::webhooks::*;
+
 // Common functions
 use crate::config_fds::*;
 use CONFIG_VARIABLES;
 
+#[macro_use]
+use lazy_static::lazy_static;
+
 
 // Definition of types
 //-------------------------------------------------
 
+// Seconds. Maximum duration of an execution, if it is not defined for the module
+const DEFAULT_EXECUTION_TIMEOUT : u32 = 600;
+
+fn default_execution_timeout() -> u32 {
+    DEFAULT_EXECUTION_TIMEOUT
+}
+
+// Number of instances (processes) of a module, if it is not defined
+fn default_min_instances() -> u32 {
+    1
+}
+
+fn default_max_instances() -> u32 {
+    1
+}
+
+// Seconds. Instances above the minimum are stopped after being idle this time
+const DEFAULT_INSTANCE_IDLE_TIME : u32 = 300;
+
+fn default_instance_idle_time() -> u32 {
+    DEFAULT_INSTANCE_IDLE_TIME
+}
+
+// Offset of the first PUSH port with respect to the base port
+const FIRST_PUSH_PORT_OFFSET : u32 = 20;
+
+// Id of the first module. A random number
+const FIRST_MODULE_ID : u32 = 20;
+
+// Seconds. An idle instance that does not answer get_status within this time is restarted
+const HEALTH_TIMEOUT : i64 = 30;
+
+// Seconds. Maximum time for an instance to send the status "Ready" after being started
+const STARTUP_TIMEOUT : i64 = 60;
+
+// Seconds. Time before restarting a crashed instance. It is doubled after every consecutive restart
+const RESTART_BACKOFF_BASE : i64 = 2;
+const RESTART_BACKOFF_MAX : i64 = 300;
+
+// Seconds. The consecutive restarts are forgotten once the instance has been running this time
+const RESTART_RESET_TIME : i64 = 120;
+
+// Seconds. An instance that does not answer a cancelled execution within this time is restarted
+const CANCEL_ACK_TIMEOUT : i64 = 30;
+
+// Format of the times of the health report
+const HEALTH_TIME_FORMAT : &str = "%Y-%m-%d %H:%M:%S";
+
 #[derive(Serialize, Deserialize)]
 enum EnumModuleType {
     INTERNAL,
@@ -80,6 +135,17 @@ enum EnumModuleStatus {
     STOPPED,
 }
 
+impl EnumModuleStatus {
+    fn to_string(&self) -> String {
+        match *self {
+            EnumModuleStatus::IDLE       => String::from("Starting"),
+            EnumModuleStatus::RUNNING    => String::from("Running"),
+            EnumModuleStatus::ERRONEOUS  => String::from("Erroneous"),
+            EnumModuleStatus::STOPPED    => String::from("Stopped"),
+        }
+    }
+}
+
 // This is need for checking only some values
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 enum EnumExecutionStatus {
@@ -118,6 +184,17 @@ struct ModuleDefinition {
     working_directory: String,
     config_file:       String,
     arguments:         String,
+    // Seconds. The execution is cancelled if there is no answer. 0 = No timeout
+    #[serde(default = "default_execution_timeout")]
+    execution_timeout: u32,
+    // Instances started with the FDS. More are started on demand, up to max_instances
+    #[serde(default = "default_min_instances")]
+    min_instances:     u32,
+    #[serde(default = "default_max_instances")]
+    max_instances:     u32,
+    // Seconds. Instances above min_instances are stopped after being idle this time
+    #[serde(default = "default_instance_idle_time")]
+    instance_idle_time: u32,
     messages:          Vec<String>,
     input_variables:   Vec<VariableDefinition>,
     output_variables:  Vec<OutputVariableDefinition>,
@@ -132,8 +209,19 @@ pub struct Module {
 
     id:                 u32,
 
-    // TODO: Add a list of instances
-    //       Add functions for adding or removing instances from that list
+    instances:          Vec<ModuleInstance>,
+
+    // Removed or changed in the modules definition file. It does not receive new executions and it is 
+    // stopped once the running ones are completed
+    draining_flag:      bool,
+}
+
+/**
+ * A process executing a module. Each instance has its own PUSH port
+ * An instance executes one request at a time
+ */
+pub struct ModuleInstance {
+    // Unique between all modules. The module sends it in its messages
     instance_id:        u32,
     push_address:       String,
     push_port:          u32,
@@ -144,10 +232,41 @@ pub struct Module {
     // UTC time. Format;   yyyy-mm-ddThh:mi:ss.sss
     start_time:         DateTime<Utc>,
     stop_time:          DateTime<Utc>,
+    // Last time an execution was sent to the instance or was completed
+    last_used_time:     DateTime<Utc>,
+    // Last time the instance answered get_status
+    last_status_time:   DateTime<Utc>,
+    // Consecutive restarts after a crash. It sets the backoff before the next restart
+    restart_count:      u32,
+    // A crashed instance is restarted at this time
+    next_restart_time:  Option<DateTime<Utc>>,
+    // Execution cancelled while the instance was running it, and time of the cancellation
+    // The instance may still be running it. So, it is busy until the module answers
+    cancelled_execution: Option<(u32, DateTime<Utc>)>,
     // Process, after fork
     child_process:      Option<Child>,
 }
 
+impl ModuleInstance {
+    /**
+     * The instance cannot receive a new execution
+     */
+    fn is_busy(&self, in_running_count: u32) -> bool {
+        in_running_count > 0 || self.cancelled_execution.is_some()
+    }
+}
+
+impl Module {
+    /**
+     * Number of instances that are starting or running
+     */
+    fn active_instances(&self) -> u32 {
+        self.instances.iter()
+            .filter(|i| i.status == EnumModuleStatus::IDLE || i.status == EnumModuleStatus::RUNNING)
+            .count() as u32
+    }
+}
+
 //#[derive(Serialize, Deserialize, Debug)]
 #[derive(Debug)]
 pub struct ExecutionRecord {
@@ -181,6 +300,12 @@ pub struct InternalModuleData {
     pub list_running_modules: Vec<Module>,
     //pub list_executions:         Vec<ExecutionRecord>,
     //pub executions_counter:      u32,
+    // Instance ids are not reused. They are stored in the execution records
+    next_instance_id:         u32,
+    // Module ids are not reused either. A changed module receives a new id
+    next_module_id:           u32,
+    // Modification time of the modules definition file when it was loaded
+    definitions_time:         Option<SystemTime>,
 
     // Public
     // ---------------------
@@ -193,7 +318,24 @@ impl InternalModuleData {
             list_running_modules: Vec::new(),
             //list_executions:          Vec::new(),
             //executions_counter:       0,
+            next_instance_id:     1,
+            next_module_id:       FIRST_MODULE_ID,
+            definitions_time:     None,
+        }
+    }
+
+    /**
+     * Return the first PUSH port that is not used by any instance
+     */
+    fn allocate_push_port(&self, in_base_port: u32) -> u32 {
+        let mut output = in_base_port + FIRST_PUSH_PORT_OFFSET;
+
+        while self.list_running_modules.iter()
+                  .any(|m| m.instances.iter().any(|i| i.push_port == output)) == true {
+            output += 1;
         }
+
+        output
     }
 }
 
@@ -201,6 +343,13 @@ pub struct ModuleManager {
     data: RwLock<InternalModuleData>,
 }
 
+/**
+ * Health of the modules. It is updated by the supervisor and read by the HTTP thread (/api/status)
+ */
+lazy_static! {
+    pub static ref MODULES_HEALTH : Arc<RwLock<Vec<ModuleHealthStruct>>> = Arc::new( RwLock::new( Vec::new() ) );
+}
+
 thread_local! {
    pub static MODULE_MANAGER : Arc<ModuleManager> = Arc::new( ModuleManager::new() );
 //    //static TASK_MANAGER : RwLock< Arc<InternalTaskData> >  = RwLock::new( Arc::new( InternalTaskData::new() ) );
@@ -224,17 +373,47 @@ impl ModuleManager {
      */
     //pub async fn load_module_definitions(&self, in_config_variables: &ConfigVariables) -> std::result::Result<(), String>
     pub fn load_module_definitions(&self) -> std::result::Result<(), String> {
-        let tmp_config_data = CONFIG_VARIABLES.read().unwrap();
+        let (tmp_list, tmp_definitions_time) = self.read_module_definitions()?;
+
+        let mut tmp_data = self.data.write().unwrap();
+
+        tmp_data.definitions_time = tmp_definitions_time;
+
+        for a_definition in tmp_list {
+            self.add_module(&mut tmp_data, a_definition);
+        }
 
-        let module_file_name = tmp_config_data.modules_definition_file.clone();
+        Ok(())
+    }
+
+    /**
+     * Read and check the module definitions from the JSON config file
+     * Return the definitions and the modification time of the file
+     */
+    fn read_module_definitions(&self) -> Result<(Vec<ModuleDefinition>, Option<SystemTime>), String> {
+        let module_file_name = CONFIG_VARIABLES.read().unwrap().modules_definition_file.clone();
 
         // Open the file and return it. If an error, return the error
-        let module_file = File::open(&module_file_name).unwrap();
+        let module_file = match File::open(&module_file_name) {
+            Ok(f) => f,
+            Err(e) => {
+                let error_msg = format!("Unable to open modules definition file: {}. Error: {}", module_file_name, e);
+
+                error!("{}", error_msg);
+
+                return Err(error_msg);
+            }
+        };
         debug!(
             "Modules definition file read: {}",
             module_file_name.as_str()
         );
 
+        let tmp_definitions_time = match module_file.metadata() {
+            Ok(m) => m.modified().ok(),
+            Err(_e) => None,
+        };
+
         let the_definitions = serde_json::from_reader(module_file);
         let tmp_list: Vec<ModuleDefinition> = match the_definitions {
             Ok(l) => l,
@@ -248,75 +427,332 @@ impl ModuleManager {
             }
         };
 
-        // A random number
-        let mut next_id = 20;
+        // Do some checkings
+        for (i, a_definition) in tmp_list.iter().enumerate() {
+            if a_definition.binary_file.is_empty() == true {
+                let error_msg = format!("Binary file name of module: {} is empty", a_definition.name);
 
-        for a_definition in tmp_list {
-            let tmp_pull_port = tmp_config_data
-                .modules_base_pull_port
-                .trim()
-                .parse::<u32>()
-                .expect("Incorrect PULL base port number");
+                error!("{}", error_msg);
+
+                return Err(error_msg);
+            }
+
+            if a_definition.max_instances == 0 || 
+               a_definition.min_instances > a_definition.max_instances {
+                let error_msg = format!("Incorrect number of instances of module: {}. Min: {} Max: {}",
+                                        a_definition.name,
+                                        a_definition.min_instances,
+                                        a_definition.max_instances);
+
+                error!("{}", error_msg);
+
+                return Err(error_msg);
+            }
 
-            let mut new_module = Module {
-                definition: a_definition,
+            // The modules are identified by name when the file is reloaded
+            if tmp_list[..i].iter().any(|d| d.name == a_definition.name) == true {
+                let error_msg = format!("Module: {} is defined twice", a_definition.name);
 
-                id: next_id,
-                instance_id: 1,
+                error!("{}", error_msg);
 
-                push_address: tmp_config_data.modules_base_pull_address.clone(),
-                push_port: (tmp_pull_port + next_id),
+                return Err(error_msg);
+            }
+        }
 
-                push_socket: nng::Socket::new(nng::Protocol::Push0).unwrap(),
+        Ok( (tmp_list, tmp_definitions_time) )
+    }
 
-                sub_address: tmp_config_data.fds_nng_sub_address.clone(),
+    /**
+     * Add a module to the list and run the minimum number of instances
+     * Return the module id
+     */
+    fn add_module(&self, in_data: &mut InternalModuleData, in_definition: ModuleDefinition) -> u32 {
+        let new_module = Module {
+            definition: in_definition,
 
-                req_address: tmp_config_data.fds_nng_rep_address.clone(),
+            id: in_data.next_module_id,
 
-                status: EnumModuleStatus::IDLE,
+            instances: Vec::new(),
 
-                // UTC time. Format;   yyyy-mm-ddThh:mi:ss.sss
-                start_time: Utc::now(),
-                stop_time: Utc::now(),
+            draining_flag: false,
+        };
 
-                // Process
-                child_process: None,
-            };
+        // Increment counter
+        in_data.next_module_id += 1;
 
-            info!("   Loading module: {} - {}", new_module.definition.name, new_module.id);
+        info!("   Loading module: {} - {}", new_module.definition.name, new_module.id);
 
-            // Do some checkings
-            if new_module.definition.binary_file.is_empty() == true {
-                let error_msg = format!("Binary file name is empty");
+        let tmp_module_id = new_module.id;
 
-                error!("{}", error_msg);
+        in_data.list_running_modules.push(new_module);
 
-                return Err(error_msg);
-            }
+        let tmp_index = in_data.list_running_modules.len() - 1;
 
-            // Run the module
-            let _tmp_result = self.run_module(&mut new_module);
-            if let Err(e) = _tmp_result {
+        for _i in 0..in_data.list_running_modules[tmp_index].definition.min_instances {
+            if let Err(e) = self.new_instance(in_data, tmp_index) {
                 let error_msg = format!(
                     "Unable to execute module: {} error: {}",
-                    new_module.definition.binary_file.as_str(),
+                    in_data.list_running_modules[tmp_index].definition.binary_file.as_str(),
                     e
                 );
 
                 error!("{}", error_msg);
 
-                //If there is an error, the module will be added to the list anyway but in an erroneous state
+                //If there is an error, the module will be added to the list anyway but without that instance
+            }
+        }
+
+        tmp_module_id
+    }
+
+    /**
+     * Reload the modules definition file without stopping the FDS. The modules are compared by name:
+     * - Added modules are started
+     * - Removed modules are drained; they do not receive new executions and they are stopped once the 
+     *   running ones are completed
+     * - Changed modules are restarted; a new module is started with the new definition and the old one 
+     *   is drained
+     * The queued executions that no module can process are cancelled
+     */
+    pub fn reload_module_definitions(&self) -> Result<ReloadModulesResponseStruct, String> {
+        info!("Reloading module definitions");
+
+        let (tmp_list, tmp_definitions_time) = self.read_module_definitions()?;
+
+        let output;
+
+        {
+            let mut tmp_data = self.data.write().unwrap();
+
+            tmp_data.definitions_time = tmp_definitions_time;
+
+            output = {
+                let tmp_current : Vec<&ModuleDefinition> = tmp_data.list_running_modules.iter()
+                                                               .filter(|m| m.draining_flag == false)
+                                                               .map(|m| &m.definition)
+                                                               .collect();
+
+                diff_module_definitions(&tmp_current, &tmp_list)
+            };
+
+            // Removed and changed modules
+            for a_module in tmp_data.list_running_modules.iter_mut() {
+                if a_module.draining_flag == false && 
+                   (output.removed.contains(&a_module.definition.name) == true || 
+                    output.restarted.contains(&a_module.definition.name) == true) {
+                    info!("   Module: {} - {} removed or changed. Draining", a_module.definition.name, a_module.id);
+
+                    a_module.draining_flag = true;
+                }
+            }
+
+            // Added and changed modules
+            for a_definition in tmp_list {
+                if output.added.contains(&a_definition.name) == true || output.restarted.contains(&a_definition.name) == true {
+                    self.add_module(&mut tmp_data, a_definition);
+                }
+            }
+        }
+
+        self.cancel_orphan_executions();
+        self.remove_drained_modules();
+        self.update_health();
+
+        info!("Module definitions reloaded. Added: {:?} Removed: {:?} Restarted: {:?}", 
+              output.added, output.removed, output.restarted);
+
+        Ok(output)
+    }
+
+    /**
+     * Reload the modules definition file if it has been modified since it was loaded
+     */
+    pub fn check_definitions_file(&self) {
+        let module_file_name = CONFIG_VARIABLES.read().unwrap().modules_definition_file.clone();
+
+        let tmp_definitions_time = match fs::metadata(&module_file_name) {
+            Ok(m) => m.modified().ok(),
+            Err(_e) => return,
+        };
+
+        if tmp_definitions_time.is_none() == true || tmp_definitions_time == self.data.read().unwrap().definitions_time {
+            return;
+        }
+
+        info!("Modules definition file: {} modified", module_file_name);
+
+        if let Err(e) = self.reload_module_definitions() {
+            error!("Unable to reload module definitions: {}. The current modules are kept", e);
+
+            // Do not try again until the file is modified again
+            self.data.write().unwrap().definitions_time = tmp_definitions_time;
+        }
+    }
+
+    /**
+     * Cancel the queued executions whose message cannot be processed by any module. I.e. their module has been removed
+     */
+    fn cancel_orphan_executions(&self) {
+        let tmp_queued = TASK_MANAGER.read().unwrap().get_queued_tasks();
+
+        let mut tmp_orphans : Vec<u32> = Vec::new();
+
+        {
+            let tmp_data = self.data.read().unwrap();
+
+            for (tmp_execution_id, tmp_request) in tmp_queued.iter() {
+                let tmp_json_message : InternalMessage = match serde_json::from_str(tmp_request.as_str()) {
+                    Ok(m) => m,
+                    Err(_e) => continue,
+                };
+
+                if tmp_data.list_running_modules.iter()
+                       .any(|m| m.draining_flag == false && 
+                                m.definition.messages.contains(&tmp_json_message.request.msg_code_id) == true) == false {
+                    tmp_orphans.push(*tmp_execution_id);
+                }
+            }
+        }
+
+        for tmp_execution_id in tmp_orphans.iter() {
+            // 503 = Service Unavailable
+            if let Err(e) = self.cancel_execution(*tmp_execution_id, 503, "Execution cancelled. The module has been removed") {
+                error!("Unable to cancel execution: {}. Error: {}", tmp_execution_id, e);
+            }
+        }
+    }
+
+    /**
+     * Stop and remove the drained modules whose executions are completed
+     */
+    pub fn remove_drained_modules(&self) {
+        let mut tmp_data = self.data.write().unwrap();
+
+        let mut i = 0;
+        while i < tmp_data.list_running_modules.len() {
+            let a_module = &tmp_data.list_running_modules[i];
+
+            if a_module.draining_flag == false {
+                i += 1;
+                continue;
+            }
+
+            let tmp_busy_flag = {
+                let tmp_task_manager = TASK_MANAGER.read().unwrap();
+
+                a_module.instances.iter().any(|an_instance| 
+                    an_instance.is_busy(tmp_task_manager.get_running_count(a_module.id, an_instance.instance_id)))
+            };
+
+            if tmp_busy_flag == true {
+                i += 1;
+                continue;
             }
 
-            // Add module to the list
-            {
-                let m = ModuleManager::current();
-                let mut tmp_data = m.data.write().unwrap();
+            let mut tmp_module = tmp_data.list_running_modules.remove(i);
+
+            info!("Stopping drained Module: {} - {}", tmp_module.definition.name, tmp_module.id);
 
-                tmp_data.list_running_modules.push(new_module);
+            let tmp_module_name = tmp_module.definition.name.clone();
+
+            for an_instance in tmp_module.instances.iter_mut() {
+                self.kill_instance(&tmp_module_name, an_instance);
             }
-            // Increment counter
-            next_id += 1;
+        }
+    }
+
+    /**
+     * Add an instance to a module and start its process
+     * The instance is ready when it sends the status message "Ready"
+     * Return the instance id
+     */
+    fn new_instance(&self, in_data: &mut InternalModuleData, in_module_index: usize) -> Result<u32, String> {
+        let tmp_config_data = CONFIG_VARIABLES.read().unwrap();
+
+        let tmp_base_port = tmp_config_data
+            .modules_base_pull_port
+            .trim()
+            .parse::<u32>()
+            .expect("Incorrect PULL base port number");
+
+        let tmp_instance_id = in_data.next_instance_id;
+        in_data.next_instance_id += 1;
+
+        let mut new_instance = ModuleInstance {
+            instance_id: tmp_instance_id,
+
+            push_address: tmp_config_data.modules_base_pull_address.clone(),
+            push_port: in_data.allocate_push_port(tmp_base_port),
+
+            push_socket: nng::Socket::new(nng::Protocol::Push0).unwrap(),
+
+            sub_address: tmp_config_data.fds_nng_sub_address.clone(),
+
+            req_address: tmp_config_data.fds_nng_rep_address.clone(),
+
+            status: EnumModuleStatus::IDLE,
+
+            // UTC time. Format;   yyyy-mm-ddThh:mi:ss.sss
+            start_time: Utc::now(),
+            stop_time: Utc::now(),
+            last_used_time: Utc::now(),
+            last_status_time: Utc::now(),
+
+            restart_count: 0,
+            next_restart_time: None,
+            cancelled_execution: None,
+
+            // Process
+            child_process: None,
+        };
+
+        let tmp_module = &mut in_data.list_running_modules[in_module_index];
+
+        info!("   Starting instance: {} of module: {} - {}", tmp_instance_id, tmp_module.definition.name, tmp_module.id);
+
+        self.run_instance(&tmp_module.definition, &mut new_instance)?;
+
+        tmp_module.instances.push(new_instance);
+
+        Ok(tmp_instance_id)
+    }
+
+    /**
+     * Return the position of the instance that will execute the next request
+     * It shall be running and idle. If several are idle, the one that has been idle for longer
+     */
+    fn select_instance(&self, in_module: &Module) -> Option<usize> {
+        let tmp_task_manager = TASK_MANAGER.read().unwrap();
+
+        let tmp_candidates : Vec<(bool, DateTime<Utc>)> = in_module.instances.iter()
+            .map(|i| (i.status == EnumModuleStatus::RUNNING && 
+                      i.is_busy(tmp_task_manager.get_running_count(in_module.id, i.instance_id)) == false,
+                      i.last_used_time))
+            .collect();
+
+        select_idle_instance(&tmp_candidates)
+    }
+
+    /**
+     * Send a message to an instance of a module
+     */
+    fn send_to_instance(&self, in_module: &Module, in_instance: &ModuleInstance, in_json_message: &InternalMessage) -> Result<(), String> {
+        let tmp_message = Message::from(&serde_json::to_vec(in_json_message).unwrap());
+
+        // Send the message to the main control loop of the module
+        // Message is a JSON
+        debug!("Sending message: {}", in_json_message.to_string());
+
+        if let Err(e) = in_instance.push_socket.send(tmp_message) {
+            let error_msg = format!(
+                "Error when sending JSON message to Module: {} Instance: {} Error: {}",
+                in_module.definition.name,
+                in_instance.instance_id,
+                nng::Error::from(e)
+            );
+            error!("{}", error_msg);
+
+            return Err(error_msg);
         }
 
         Ok(())
@@ -326,185 +762,172 @@ impl ModuleManager {
      * Call a module
      * The module will receive a message and execute it. It will return a message either with the output
      * or a link to the file containing the answer
+     * The message is sent to an idle instance of the module. If all of them are busy, the execution 
+     * is queued and a new instance is started, if the maximum number of instances is not reached
      *
      * Return: A JSON object either describing the answer or a link to the resource containing the answer
      */
     pub fn call_module(&self, in_json_message: &mut InternalMessage) -> Result<InternalResponseMessage, String> {
         info!("Processing message JSON: {}", in_json_message.to_string());
 
-        let mut received_response: InternalResponseMessage = InternalResponseMessage::new();
         let tmp_msg_code_id = in_json_message.request.msg_code_id.clone();
         let tmp_msg_id = in_json_message.request.msg_id.clone();
 
         // Get user id
         let user_id = in_json_message.user_id.clone();
-        //let user_id : String;
-        // if in_json_message.request.parameters["user_id"].is_null() == false {
-        //     match in_json_message.request.parameters["user_id"].as_str() {
-        //     Some(u) => user_id = String::from(u),
-        //     None => {
-        //         let error_msg = String::from("user_id not found in the JSON message");
-        //         error!("{}", error_msg);
-
-        //         return Err( error_msg );
-        //     },
-        //     };
-        // } else {
-        //     let error_msg = String::from("user_id not found in the JSON message");
-        //     error!("{}", error_msg);
 
-        //     return Err( error_msg );
-        // }
-
-        // Look for the module that can execute the message
-        let mut module_found = false;
+        // Optional. The final answer will be sent to this URL
+        let tmp_callback_url = in_json_message.request.callback_url.clone().unwrap_or_default();
+        if tmp_callback_url.is_empty() == false {
+            if is_webhook_running() == false {
+                let error_msg = String::from("Callbacks are not available. The callback secret key is not configured");
+                error!("{}", error_msg);
 
-        let tmp_data = self.data.read().unwrap();
+                return Err(error_msg);
+            }
 
-        for (i, current_module) in tmp_data.list_running_modules.iter().enumerate() {
-            if current_module.definition.messages.contains(&tmp_msg_code_id) == true
-            {
-                // Check if module is running
-                if current_module.status != EnumModuleStatus::RUNNING {
-                    info!("Module is not running. It will be started");
+            if let Err(e) = check_callback_url(tmp_callback_url.as_str()) {
+                error!("{}", e);
 
-                    {
-                        let m = ModuleManager::current();
-                        let mut tmp_mut_data = m.data.write().unwrap();
+                return Err(e);
+            }
+        }
 
-                        let tmp_current_module = &mut tmp_mut_data.list_running_modules[i];
+        let mut tmp_data = self.data.write().unwrap();
 
-                        if let Err(e) = self.run_module(tmp_current_module) {
-                            error!("{}", e.to_string());
+        // Look for the module that can execute the message
+        let tmp_module_index = match tmp_data.list_running_modules.iter()
+                                         .position(|m| m.draining_flag == false && 
+                                                       m.definition.messages.contains(&tmp_msg_code_id) == true) {
+            Some(i) => i,
+            None => {
+                let error_msg = format!(
+                    "Error: Module to process message: {} not found",
+                    tmp_msg_code_id
+                );
+                error!("{}", error_msg);
 
-                            return Err(e.to_string());
-                        }
-                    }
-                }
-                debug!("Module: {} is running", current_module.definition.name);
-
-                // Add execution record
-                let task_id;
-                {
-                    task_id = TASK_MANAGER.write().unwrap().add_task(
-                        current_module.id,
-                        current_module.instance_id,
-                        user_id,
-                    );
-                }
+                return Err(error_msg);
+            }
+        };
 
-                if let Err(e) = task_id {
-                    let error_msg = format!("Error storing Task in the database: {}",e);
-                    error!("{}", error_msg);
+        let tmp_instance_index = self.select_instance(&tmp_data.list_running_modules[tmp_module_index]);
 
-                    return Err(error_msg);
-                }
+        let tmp_module = &tmp_data.list_running_modules[tmp_module_index];
 
-                // Mute into u32
-                let task_id: u32 = task_id.unwrap();
+        let tmp_instance_id = match tmp_instance_index {
+            Some(j) => tmp_module.instances[j].instance_id,
+            // Not assigned yet
+            None => 0,
+        };
 
-                // Add execution id to the JSON message
-                // let mut new_json_message: RestRequest = in_json_message.clone();
+        // Add execution record
+        let task_id = TASK_MANAGER.write().unwrap().add_task(
+            tmp_module.id,
+            tmp_instance_id,
+            user_id,
+            tmp_msg_id.clone(),
+            in_json_message.to_string(),
+            tmp_callback_url.clone(),
+            tmp_module.definition.execution_timeout,
+        );
 
-                // new_json_message.parameters["execution_id"] = json!(task_id);
-                in_json_message.execution_id = task_id;
+        if let Err(e) = task_id {
+            let error_msg = format!("Error storing Task in the database: {}",e);
+            error!("{}", error_msg);
 
+            return Err(error_msg);
+        }
 
-                // Send messages
-                let tmp_message = Message::from(&serde_json::to_vec(&in_json_message).unwrap());
+        // Mute into u32
+        let task_id: u32 = task_id.unwrap();
 
-                // Send the message to the main control loop of the module
-                // Message is a JSON
-                debug!("Sending message: {}", in_json_message.to_string());
+        // Add execution id to the JSON message
+        in_json_message.execution_id = task_id;
 
-                let status = current_module.push_socket.send(tmp_message);
-                if let Err(e) = status {
-                    let error_msg = format!(
-                        "Error when sending JSON message to Module: {} Error: {}",
-                        current_module.definition.name,
-                        nng::Error::from(e)
-                    );
-                    error!("{}", error_msg);
+        match tmp_instance_index {
+            Some(j) => {
+                debug!("Module: {} Instance: {} is idle", tmp_module.definition.name, tmp_instance_id);
 
-                    return Err(error_msg);
+                if let Err(e) = self.send_to_instance(tmp_module, &tmp_module.instances[j], in_json_message) {
+                    // The answer will not arrive
+                    let _ = TASK_MANAGER.write().unwrap().set_cancelled(task_id, 
+                                RestResponse::new_error_id("error_response", tmp_msg_id.clone(), 500, e.clone()).to_string());
+                    return Err(e);
                 }
 
-                // Create response
-                received_response = InternalResponseMessage::new_wait(tmp_msg_code_id.as_str(), 
-                                                                      tmp_msg_id, task_id);
+                tmp_data.list_running_modules[tmp_module_index].instances[j].last_used_time = Utc::now();
+            },
+            None => {
+                info!("All instances of Module: {} are busy. Execution: {} queued", tmp_module.definition.name, task_id);
 
-                // Return the received response
-                module_found = true;
-                break;
-            }
-        }
+                TASK_MANAGER.write().unwrap().set_queued(task_id)?;
 
-        if module_found == false {
-            let error_msg = format!(
-                "Error: Module to process message: {} not found",
-                tmp_msg_code_id
-            );
-            error!("{}", error_msg);
+                let tmp_module = &tmp_data.list_running_modules[tmp_module_index];
 
-            return Err(error_msg);
-        }
+                // Queued executions are sent when the new instance is ready
+                if tmp_module.active_instances() < tmp_module.definition.max_instances {
+                    if let Err(e) = self.new_instance(&mut tmp_data, tmp_module_index) {
+                        error!("Unable to start a new instance: {}", e);
+                    }
+                }
+            },
+        };
 
-        Ok(received_response)
+        // Create response
+        Ok( InternalResponseMessage::new_wait(tmp_msg_code_id.as_str(), tmp_msg_id, task_id) )
     }
 
     /**
-     * Spawn a process for executing an external module
-     * Stores the PID of the module
+     * Spawn a process for executing an instance of an external module
+     * Stores the PID of the instance
      * Set the start time of the process
-
-     TODO: Rename to add_instance
-     It shall start an instance
     */
-    fn run_module(&self, in_module: &mut Module) -> Result<u32, String> {
-        debug!("Running module: {}", in_module.definition.name);
+    fn run_instance(&self, in_definition: &ModuleDefinition, in_instance: &mut ModuleInstance) -> Result<u32, String> {
+        debug!("Running module: {} Instance: {}", in_definition.name, in_instance.instance_id);
 
         let mut command_line: String = String::from("");
 
-        if in_module.definition.binary_file_path.is_empty() == false {
-            command_line.push_str(in_module.definition.binary_file_path.as_str());
+        if in_definition.binary_file_path.is_empty() == false {
+            command_line.push_str(in_definition.binary_file_path.as_str());
             command_line.push_str("/");
         }
-        command_line.push_str(in_module.definition.binary_file.as_str());
+        command_line.push_str(in_definition.binary_file.as_str());
 
-        let tmp_pull_address: String = in_module.push_address.clone() + &String::from(":") + 
-                                       &in_module.push_port.to_string();
+        let tmp_pull_address: String = in_instance.push_address.clone() + &String::from(":") + 
+                                       &in_instance.push_port.to_string();
 
         let mut tmp_config_file: String = String::from("config.json");
 
-        if in_module.definition.config_file.is_empty() == false {
-            tmp_config_file = in_module.definition.config_file.clone();
+        if in_definition.config_file.is_empty() == false {
+            tmp_config_file = in_definition.config_file.clone();
         }
 
         // Command line:
         //   executable  config_file.json   instance_id   pull_address   sub_address   req_address
         let mut child_process = Command::new(command_line.as_str());
 
-        if in_module.definition.working_directory.is_empty() == false {
-            child_process.current_dir(in_module.definition.working_directory.clone());
+        if in_definition.working_directory.is_empty() == false {
+            child_process.current_dir(in_definition.working_directory.clone());
         }
 
-        if in_module.definition.arguments.is_empty() == false {
-            child_process.arg(in_module.definition.arguments.clone());
+        if in_definition.arguments.is_empty() == false {
+            child_process.arg(in_definition.arguments.clone());
         }
 
         let child_process = child_process
             .arg(tmp_config_file)
-            .arg(in_module.instance_id.to_string())
+            .arg(in_instance.instance_id.to_string())
             .arg(tmp_pull_address)
-            .arg(in_module.sub_address.clone())
-            .arg(in_module.req_address.clone())
+            .arg(in_instance.sub_address.clone())
+            .arg(in_instance.req_address.clone())
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
             //.stderr(Stdio::null())
             //.stdout( Stdio::piped() )
             .spawn();
 
-        info!("Executing Module: {} Cli: {} ", in_module.definition.name, command_line);
+        info!("Executing Module: {} Cli: {} ", in_definition.name, command_line);
 
         let tmp_result = match child_process {
             Ok(c) => c,
@@ -515,7 +938,7 @@ impl ModuleManager {
                 error!("{}", e);
 
                 // Set the status
-                in_module.status = EnumModuleStatus::ERRONEOUS;
+                in_instance.status = EnumModuleStatus::ERRONEOUS;
 
                 return Err(error_msg);
             }
@@ -523,18 +946,18 @@ impl ModuleManager {
 
         // Write the PID to a file
         let pid_data = format!("{}", tmp_result.id());
-        let pid_file_name = in_module.definition.name.clone() + &String::from(".pid");
+        let pid_file_name = format!("{}_{}.pid", in_definition.name, in_instance.instance_id);
         debug!("Module PID file: {}", pid_file_name);
         fs::write(pid_file_name, pid_data).expect("Unable to write PID module file");
 
         // Move the Child
-        in_module.child_process = Some(tmp_result);
+        in_instance.child_process = Some(tmp_result);
 
         // Set the start time as the current time
         //new_module.start_time = format!("{}", now.naive_utc());
         //new_module.stop_time  = format!("{}", now.naive_utc());
-        in_module.start_time = Utc::now();
-        in_module.stop_time = Utc::now();
+        in_instance.start_time = Utc::now();
+        in_instance.stop_time = Utc::now();
 
         // Small sleep 1 sec
         //thread::sleep(Duration::from_secs(1));
@@ -699,120 +1122,429 @@ impl ModuleManager {
         //let received_execution_id : u32 = response_json_message["execution_id"].as_u64().unwrap() as u32;
         //let received_answer : String = String::from( response_json_message["msg_buffer"].as_str().unwrap() );
 
-        {
-            TASK_MANAGER.write().unwrap()
-                .set_answer_completed(
-                    in_json_message.execution_id,
-                    in_json_message.response.to_string(),
-                )
-                .unwrap();
+        // I.e. an error_response of a module that could not decode the request
+        if in_json_message.execution_id == 0 {
+            let tmp_msg = format!("ERROR: Answer without execution id. Msg id: {}", in_json_message.response.msg_id);
+            error!("{}", tmp_msg.as_str());
+
+            return Err(tmp_msg);
+        }
+
+        // The execution could be unknown or already removed (expired)
+        let res = TASK_MANAGER.write().unwrap()
+                      .set_answer_completed(
+                          in_json_message.execution_id,
+                          in_json_message.response.to_string(),
+                      );
+
+        if let Err(e) = res {
+            let tmp_msg = format!("ERROR: Unable to store the answer of execution {}: {}", in_json_message.execution_id, e);
+            error!("{}", tmp_msg.as_str());
+
+            return Err(tmp_msg);
         }
 
+        // The instance can execute the next request
+        self.release_instance(in_json_message.execution_id);
+
         // Create response
         Ok(in_json_message.clone())
     }
 
     /**
-     * If we do not execute child.status(), the process will be a zombie
-     * wait
-        try_wait
-        wait_with_output
-
-        kill
+     * The instance that was executing a task is idle again. The queued executions are sent
      */
-    pub fn check_status_all_modules(&self) {
-        let m = ModuleManager::current();
-        let mut tmp_data = m.data.write().unwrap();
+    fn release_instance(&self, in_execution_id: u32) {
+        let (tmp_module_id, tmp_instance_id) = match TASK_MANAGER.read().unwrap().get_module_instance(in_execution_id) {
+            Ok(ids) => ids,
+            Err(_e) => return,
+        };
 
-        for a_module in tmp_data.list_running_modules.iter_mut() {
-            // Send a GetStatus message to all modules
-            // let get_status_message = GetStatusMessage {
-            //    header :  ApiMessage {
-            //       msg_code_id:        String::from("get_status"),
-            //       authentication_key: String::from(""),
-            //    },
-            //    user_id: String::from(""),
-            // };
-
-            //  let get_status_message = json!({
-            //     "msg_code_id" : "get_status",
-            //     "authentication_key" : "xyzzy",
-            //     "user_id": "magic_user",
-            //  });
-            let mut get_status_message: RestRequest = RestRequest::new();
+        {
+            let mut tmp_data = self.data.write().unwrap();
 
-            get_status_message.msg_code_id = String::from("get_status");
-            //get_status_message.user_id             = String::from("modules_manager");
+            for a_module in tmp_data.list_running_modules.iter_mut() {
+                if a_module.id != tmp_module_id {
+                    continue;
+                }
 
-            // Send the message
-            let json_message = Message::from(&serde_json::to_vec(&get_status_message).unwrap());
+                for an_instance in a_module.instances.iter_mut() {
+                    if an_instance.instance_id == tmp_instance_id {
+                        an_instance.last_used_time = Utc::now();
 
-            // Send the message to the main control loop
-            // Message is a JSON
-            debug!("sending message: {}", get_status_message.to_string());
+                        // The module has answered the cancelled execution
+                        if let Some((tmp_cancelled_id, _)) = an_instance.cancelled_execution {
+                            if tmp_cancelled_id == in_execution_id {
+                                info!("Cancelled execution: {} finished. Module: {} Instance: {}", in_execution_id, 
+                                      tmp_module_id, tmp_instance_id);
+                                an_instance.cancelled_execution = None;
+                            }
+                        }
+                    }
+                }
+            }
+        }
 
-            let status = a_module.push_socket.send(json_message);
-            if let Err(e) = status {
-                let error_msg = format!(
-                    "Error when sending JSON messae to Module: {} Error: {}",
-                    a_module.definition.name,
-                    nng::Error::from(e)
-                );
-                error!("{}", error_msg);
+        self.dispatch_queued_executions();
+    }
 
-                // We are going to assume, the module has crashed. Get exit status of module
-                a_module.status = EnumModuleStatus::ERRONEOUS;
+    /**
+     * Cancel an execution that is not completed
+     * The answer of the execution is an error with the code and the reason. The module is 
+     * notified, so, it can abort the execution
+     * The instance is busy until the module answers the cancelled execution. Otherwise, it is 
+     * restarted after CANCEL_ACK_TIMEOUT seconds
+     */
+    pub fn cancel_execution(&self, in_execution_id: u32, in_status_code: u16, in_reason: &str) -> Result<(), String> {
+        info!("Cancelling execution: {}. Reason: {}", in_execution_id, in_reason);
 
-                // Get exit status and kill the module
-                self.kill_module(a_module);
+        let tmp_msg_id = TASK_MANAGER.read().unwrap().get_msg_id(in_execution_id)?;
 
-                // TODO: Remove instance from the module
+        let tmp_answer = RestResponse::new_error_id("error_response", tmp_msg_id.clone(), 
+                                                    in_status_code as i32, String::from(in_reason));
 
-                for _i in 0..2u32 {
-                    self.run_module(a_module);
-                }
-            } else {
-                // Read response message
-                let response_message = a_module.push_socket.recv();
+        let (tmp_module_id, tmp_instance_id) = TASK_MANAGER.write().unwrap()
+                                                   .set_cancelled(in_execution_id, tmp_answer.to_string())?;
 
-                if let Err(e) = response_message {
-                    error!(
-                        "Error when receiving a message: {} from Module: {}",
-                        e, a_module.definition.name
-                    );
+        // Notify the module. A queued execution has not been sent to any instance
+        {
+            let mut tmp_data = self.data.write().unwrap();
+
+            for current_module in tmp_data.list_running_modules.iter_mut() {
+                if current_module.id != tmp_module_id {
                     continue;
                 }
 
-                // As u8[]
-                let json_buffer = response_message.unwrap();
+                for current_instance in current_module.instances.iter_mut() {
+                    if current_instance.instance_id != tmp_instance_id {
+                        continue;
+                    }
 
-                debug!(
-                    "Received message: {}",
-                    String::from_utf8(json_buffer.as_slice().to_vec()).unwrap()
-                );
+                    current_instance.cancelled_execution = Some( (in_execution_id, Utc::now()) );
+
+                    let mut cancel_message: RestRequest = RestRequest::new();
+
+                    cancel_message.msg_code_id = String::from("cancel_execution");
+                    cancel_message.msg_id      = tmp_msg_id.clone();
+                    cancel_message.parameters  = json!( { "reason": in_reason } );
+
+                    let cancel_message = InternalMessage::new(&cancel_message, String::new(), in_execution_id);
+
+                    // The execution is cancelled anyway
+                    if let Err(e) = self.send_to_instance(current_module, current_instance, &cancel_message) {
+                        warn!("Error when sending cancel message. {}. IGNORED", e);
+                    }
+                }
+            }
+        }
+
+        // The instance is not released yet
+        self.dispatch_queued_executions();
+
+        Ok(())
+    }
+
+    /**
+     * Cancel the executions whose timeout has expired
+     */
+    pub fn cancel_expired_executions(&self) {
+        let tmp_expired = TASK_MANAGER.read().unwrap().get_expired_tasks();
+
+        for (tmp_execution_id, tmp_timeout) in tmp_expired {
+            let tmp_reason = format!("Execution cancelled. No answer after {} seconds", tmp_timeout);
+
+            // 504 = Gateway Timeout
+            if let Err(e) = self.cancel_execution(tmp_execution_id, 504, tmp_reason.as_str()) {
+                error!("Unable to cancel execution: {}. Error: {}", tmp_execution_id, e);
+            }
+        }
+    }
+
+    /**
+     * Handle a progress message from a module
+     * It contains the percentage of the execution and an optional description
+     */
+    pub fn handle_module_progress(&self, in_json_message: &InternalResponseMessage) -> Result<InternalResponseMessage, String> {
+        debug!("Processing progress from module JSON: {}", in_json_message.to_string());
+
+        let tmp_progress = match in_json_message.response.result["progress"].as_u64() {
+            Some(p) => p.min(100) as u8,
+            None => {
+                let error_msg = format!("Progress message does not contain the progress field");
+                error!("{}", error_msg);
+                return Err(error_msg);
+            }
+        };
+
+        let tmp_detail = match in_json_message.response.result["detail"].as_str() {
+            Some(d) => String::from(d),
+            None => String::new(),
+        };
+
+        {
+            TASK_MANAGER.write().unwrap()
+                .set_progress(in_json_message.execution_id, tmp_progress, tmp_detail)?;
+        }
+
+        Ok(in_json_message.clone())
+    }
+
+    /**
+     * Supervise the instances of all modules. Called periodically
+     * - A process that has exited (Child::try_wait), an instance that does not become ready or an idle
+     *   instance that does not answer get_status is killed and restarted with backoff
+     * - The executions of a failed instance are set as Failed
+     * - The running instances receive a get_status message; the answer is processed by module_is_ready
+     * If we do not execute child.try_wait(), the process will be a zombie
+     */
+    pub fn supervise_modules(&self) {
+        let mut tmp_failed : Vec<(u32, u32, String)> = Vec::new();
+
+        {
+            let mut tmp_data = self.data.write().unwrap();
+            let current_time = Utc::now();
 
-                // Decode JSON
-                let json_message = serde_json::from_slice(json_buffer.as_slice());
-                let json_message: GetStatusResponseStruct = match json_message {
-                    Ok(msg) => msg,
-                    Err(e) => {
-                        let tmp_msg = format!(
-                            "ERROR: Unable to decode JSON message: {}. IGNORED",
-                            e.to_string()
-                        );
-                        error!("{}", tmp_msg.as_str());
+            for a_module in tmp_data.list_running_modules.iter_mut() {
+                let tmp_module_name = a_module.definition.name.clone();
+                let tmp_module_id = a_module.id;
+
+                for an_instance in a_module.instances.iter_mut() {
+                    match an_instance.status {
+                        EnumModuleStatus::STOPPED => continue,
+
+                        EnumModuleStatus::ERRONEOUS => {
+                            // A drained module is going to be stopped
+                            if a_module.draining_flag == false {
+                                self.restart_instance(&a_module.definition, an_instance);
+                            }
+                            continue;
+                        },
+
+                        _ => {},
+                    };
+
+                    let tmp_busy_flag = an_instance.is_busy(TASK_MANAGER.read().unwrap().get_running_count(tmp_module_id, an_instance.instance_id));
+
+                    if let Some(tmp_reason) = self.check_instance(an_instance, tmp_busy_flag) {
+                        error!("Module: {} Instance: {} has failed. {}", tmp_module_name, an_instance.instance_id, tmp_reason);
+
+                        self.kill_instance(&tmp_module_name, an_instance);
+
+                        an_instance.status = EnumModuleStatus::ERRONEOUS;
+                        self.schedule_restart(&tmp_module_name, an_instance);
+
+                        tmp_failed.push( (tmp_module_id, an_instance.instance_id, tmp_reason) );
                         continue;
                     }
-                };
 
-                if json_message.status != String::from("Running") {
-                    error!(
-                        "Module: {} is in an incorrect status: {}",
-                        a_module.definition.name, json_message.status
-                    );
+                    if an_instance.status != EnumModuleStatus::RUNNING {
+                        continue;
+                    }
+
+                    // It has been running long enough. A future crash starts the backoff again
+                    if an_instance.restart_count > 0 && 
+                       current_time - an_instance.start_time >= chrono::Duration::seconds(RESTART_RESET_TIME) {
+                        an_instance.restart_count = 0;
+                    }
+
+                    let mut get_status_message: RestRequest = RestRequest::new();
+
+                    get_status_message.msg_code_id = String::from("get_status");
+                    get_status_message.msg_id      = format!("{}", current_time.timestamp());
+
+                    let get_status_message = InternalMessage::new(&get_status_message, String::new(), 0);
+                    let json_message = Message::from(&serde_json::to_vec(&get_status_message).unwrap());
+
+                    // A broken socket is detected by the health timeout
+                    if let Err(e) = an_instance.push_socket.send(json_message) {
+                        warn!("Error when sending get_status to Module: {} Instance: {} Error: {}",
+                              tmp_module_name, an_instance.instance_id, nng::Error::from(e));
+                    }
                 }
             }
         }
+
+        for (tmp_module_id, tmp_instance_id, tmp_reason) in tmp_failed.iter() {
+            self.fail_instance_executions(*tmp_module_id, *tmp_instance_id, tmp_reason.as_str());
+        }
+
+        self.update_health();
+    }
+
+    /**
+     * Check if an instance is alive
+     * Return the reason of the failure. None = The instance is healthy
+     */
+    fn check_instance(&self, in_instance: &mut ModuleInstance, in_busy_flag: bool) -> Option<String> {
+        let current_time = Utc::now();
+
+        match &mut in_instance.child_process {
+            Some(c) => {
+                match c.try_wait() {
+                    Ok(Some(status)) => return Some( format!("Process exited with status: {}", status) ),
+                    Ok(None) => {},
+                    Err(e) => return Some( format!("Unable to obtain the process status: {}", e) ),
+                };
+            },
+            None => return Some( String::from("Process not found") ),
+        };
+
+        if in_instance.status == EnumModuleStatus::IDLE &&
+           current_time - in_instance.start_time >= chrono::Duration::seconds(STARTUP_TIMEOUT) {
+            return Some( format!("Status Ready not received after {} seconds", STARTUP_TIMEOUT) );
+        }
+
+        // The module did not abort the cancelled execution
+        if let Some((tmp_execution_id, tmp_cancel_time)) = in_instance.cancelled_execution {
+            if current_time - tmp_cancel_time >= chrono::Duration::seconds(CANCEL_ACK_TIMEOUT) {
+                return Some( format!("No answer to the cancelled execution: {} after {} seconds", tmp_execution_id, CANCEL_ACK_TIMEOUT) );
+            }
+        }
+
+        // A busy instance may not answer until the execution is completed. It is covered by the execution timeout
+        if in_instance.status == EnumModuleStatus::RUNNING && in_busy_flag == false &&
+           current_time - in_instance.last_status_time >= chrono::Duration::seconds(HEALTH_TIMEOUT) {
+            return Some( format!("No answer to get_status after {} seconds", HEALTH_TIMEOUT) );
+        }
+
+        None
+    }
+
+    /**
+     * Set the time of the next restart of a failed instance
+     */
+    fn schedule_restart(&self, in_module_name: &String, in_instance: &mut ModuleInstance) {
+        let tmp_backoff = restart_backoff(in_instance.restart_count);
+
+        in_instance.next_restart_time = Some( Utc::now() + chrono::Duration::seconds(tmp_backoff) );
+
+        warn!("Module: {} Instance: {} will be restarted in {} seconds", in_module_name, in_instance.instance_id, tmp_backoff);
+    }
+
+    /**
+     * Restart a failed instance once its backoff has expired
+     * The instance keeps its id and PUSH port. It is ready when it sends the status "Ready"
+     */
+    fn restart_instance(&self, in_definition: &ModuleDefinition, in_instance: &mut ModuleInstance) {
+        // I.e. the instance failed when it was connecting to the PUSH socket
+        if in_instance.next_restart_time.is_none() == true {
+            if in_instance.child_process.is_some() == true {
+                self.kill_instance(&in_definition.name, in_instance);
+                in_instance.status = EnumModuleStatus::ERRONEOUS;
+            }
+
+            self.schedule_restart(&in_definition.name, in_instance);
+            return;
+        }
+
+        if let Some(t) = in_instance.next_restart_time {
+            if Utc::now() < t {
+                return;
+            }
+        }
+
+        in_instance.restart_count += 1;
+        in_instance.next_restart_time = None;
+
+        info!("Restarting Module: {} Instance: {}. Restart: {}", in_definition.name, in_instance.instance_id, 
+              in_instance.restart_count);
+
+        // A new socket. It will be connected when the instance is ready
+        in_instance.push_socket = nng::Socket::new(nng::Protocol::Push0).unwrap();
+        in_instance.status = EnumModuleStatus::IDLE;
+
+        match self.run_instance(in_definition, in_instance) {
+            Ok(_) => {
+                in_instance.last_status_time = Utc::now();
+            },
+            Err(e) => {
+                error!("Unable to restart Module: {} Instance: {}. Error: {}", in_definition.name, in_instance.instance_id, e);
+
+                self.schedule_restart(&in_definition.name, in_instance);
+            },
+        };
+    }
+
+    /**
+     * Set as Failed the executions that were sent to a failed instance
+     * The answer is an error with the status 502 (Bad Gateway). The queued executions are sent to other instances
+     */
+    fn fail_instance_executions(&self, in_module_id: u32, in_instance_id: u32, in_reason: &str) {
+        let tmp_executions = TASK_MANAGER.read().unwrap().get_instance_tasks(in_module_id, in_instance_id);
+
+        for tmp_execution_id in tmp_executions.iter() {
+            let tmp_msg_id = match TASK_MANAGER.read().unwrap().get_msg_id(*tmp_execution_id) {
+                Ok(m) => m,
+                Err(_e) => continue,
+            };
+
+            let tmp_reason = format!("Execution failed. The module has crashed: {}", in_reason);
+
+            let tmp_answer = RestResponse::new_error_id("error_response", tmp_msg_id, 502, tmp_reason);
+
+            info!("Execution: {} failed. Module: {} Instance: {}", tmp_execution_id, in_module_id, in_instance_id);
+
+            if let Err(e) = TASK_MANAGER.write().unwrap().set_failed(*tmp_execution_id, tmp_answer.to_string()) {
+                error!("Unable to set execution: {} as failed. Error: {}", tmp_execution_id, e);
+            }
+        }
+
+        self.dispatch_queued_executions();
+    }
+
+    /**
+     * Update the health report of the modules
+     * Module status: Healthy (all the instances are running), Degraded, Down (no instance running), Draining
+     */
+    fn update_health(&self) {
+        let tmp_data = self.data.read().unwrap();
+        let tmp_task_manager = TASK_MANAGER.read().unwrap();
+
+        let mut tmp_health : Vec<ModuleHealthStruct> = Vec::new();
+
+        for a_module in tmp_data.list_running_modules.iter() {
+            let mut tmp_instances : Vec<InstanceHealthStruct> = Vec::new();
+
+            for an_instance in a_module.instances.iter() {
+                let tmp_pid = match &an_instance.child_process {
+                    Some(c) => c.id(),
+                    None => 0,
+                };
+
+                tmp_instances.push( InstanceHealthStruct {
+                    module_instance_id:  an_instance.instance_id,
+                    status:              an_instance.status.to_string(),
+                    pid:                 tmp_pid,
+                    start_time:          an_instance.start_time.format(HEALTH_TIME_FORMAT).to_string(),
+                    last_status_time:    an_instance.last_status_time.format(HEALTH_TIME_FORMAT).to_string(),
+                    restart_count:       an_instance.restart_count,
+                    running_executions:  tmp_task_manager.get_running_count(a_module.id, an_instance.instance_id),
+                });
+            }
+
+            let tmp_running = a_module.instances.iter()
+                                  .filter(|i| i.status == EnumModuleStatus::RUNNING)
+                                  .count();
+
+            let tmp_status = if a_module.draining_flag == true {
+                "Draining"
+            } else if tmp_running == 0 {
+                "Down"
+            } else if tmp_running < a_module.instances.len() {
+                "Degraded"
+            } else {
+                "Healthy"
+            };
+
+            tmp_health.push( ModuleHealthStruct {
+                module_id:   a_module.id,
+                name:        a_module.definition.name.clone(),
+                status:      String::from(tmp_status),
+                instances:   tmp_instances,
+            });
+        }
+
+        *MODULES_HEALTH.write().unwrap() = tmp_health;
     }
 
     /**
@@ -836,92 +1568,207 @@ impl ModuleManager {
             return Err(error_msg);
         }
         
+        let mut ready_flag = false;
+
         let module_status = in_json_message.response.result["status"].as_str().unwrap();
-        if module_status == "Ready" {
+        {
             let mut tmp_data = self.data.write().unwrap();
 
-            // Search for module and create PUSH socket
-            for (_i, current_module) in tmp_data.list_running_modules.iter_mut().enumerate() {
-                if current_module.instance_id == read_instance_id {
-                    // Try to connect to module 3 times
-                    let mut error_flag = false;
-                    let mut error_msg = String::new();
-
-                    let push_socket_address = current_module.push_address.clone() + &String::from(":") + 
-                                            &current_module.push_port.to_string();
-
-                    for i in 0..3 as u8 {
-                        // Give the module time to start
-                        thread::sleep(Duration::from_secs(1));
-
-                        info!("   Iteration: {}", i);
-                        error_flag = false;
-
-                        // Create the PUSH socket
-                        let tmp_push_socket = Socket::new(Protocol::Push0);
-                        current_module.push_socket = match tmp_push_socket {
-                            Ok(s) => {
-                                info!("PUSH Socket to {} correctly created ", current_module.definition.name);
-                                s
+            // Search for the instance and create PUSH socket
+            for a_module in tmp_data.list_running_modules.iter_mut() {
+                let tmp_module_name = a_module.definition.name.clone();
+
+                for current_instance in a_module.instances.iter_mut() {
+                    if current_instance.instance_id == read_instance_id {
+                        // The instance is alive
+                        current_instance.last_status_time = Utc::now();
+
+                        // Answer to the get_status of the supervisor. It is already connected
+                        if module_status != "Ready" || current_instance.status == EnumModuleStatus::RUNNING {
+                            if module_status != "Ready" {
+                                info!("Module with instance id: {} is not ready yet. Skipped", read_instance_id);
                             }
-                            Err(e) => {
-                                error_msg = format!("Unable to create PUSH Socket to {}. Error: {}",
-                                    current_module.definition.name,
-                                    e.to_string()
+                            break;
+                        }
+
+                        // Try to connect to module 3 times
+                        let mut error_flag = false;
+                        let mut error_msg = String::new();
+
+                        let push_socket_address = current_instance.push_address.clone() + &String::from(":") + 
+                                                &current_instance.push_port.to_string();
+
+                        for i in 0..3 as u8 {
+                            // Give the module time to start
+                            thread::sleep(Duration::from_secs(1));
+
+                            info!("   Iteration: {}", i);
+                            error_flag = false;
+
+                            // Create the PUSH socket
+                            let tmp_push_socket = Socket::new(Protocol::Push0);
+                            current_instance.push_socket = match tmp_push_socket {
+                                Ok(s) => {
+                                    info!("PUSH Socket to {} correctly created ", tmp_module_name);
+                                    s
+                                }
+                                Err(e) => {
+                                    error_msg = format!("Unable to create PUSH Socket to {}. Error: {}",
+                                        tmp_module_name,
+                                        e.to_string()
+                                    );
+                                    error!("{}", error_msg.as_str());
+
+                                    error_flag = true;
+
+                                    continue;
+                                }
+                            };
+
+                            // Start listening. It does need the module to be able to connect to. So, it must be up and running
+                            let unused_result = current_instance.push_socket.dial(push_socket_address.as_str());
+                            if let Err(e) = unused_result {
+                                let error_msg = format!(
+                                    "Error when connecting to PUSH socket using address: {}\n Error: {}",
+                                    push_socket_address, e
                                 );
-                                error!("{}", error_msg.as_str());
 
+                                error!("{}", error_msg);
                                 error_flag = true;
-
                                 continue;
+                            } else {
+                                // End of the loop
+                                break;
                             }
-                        };
-
-                        // Start listening. It does need the module to be able to connect to. So, it must be up and running
-                        let unused_result = current_module.push_socket.dial(push_socket_address.as_str());
-                        if let Err(e) = unused_result {
-                            let error_msg = format!(
-                                "Error when connecting to PUSH socket using address: {}\n Error: {}",
-                                push_socket_address, e
-                            );
-
-                            error!("{}", error_msg);
-                            error_flag = true;
-                            continue;
+                        }
+
+                        if error_flag == true {
+                            // Set the status
+                            current_instance.status = EnumModuleStatus::ERRONEOUS;
+
+                            // Kill the module
+                            if current_instance.child_process.is_some() {
+                                error!("Module: {} will be killed", tmp_module_name);
+
+                                // Get exit status and kill the module
+                                self.kill_instance(&tmp_module_name, current_instance);
+                            }
+
+                            return Err(error_msg);
                         } else {
-                            // End of the loop
-                            break;
+                            info!("Correctly connected to PUSH socket. Address: {}", push_socket_address);
+
+                            // Set the status
+                            current_instance.status = EnumModuleStatus::RUNNING;
+                            ready_flag = true;
                         }
+
+                        break;
                     }
+                }
+            }
+        }
 
-                    if error_flag == true {
-                        // Set the status
-                        current_module.status = EnumModuleStatus::ERRONEOUS;
+        // Send the queued executions. I.e. recovered at start-up
+        if ready_flag == true {
+            self.dispatch_queued_executions();
+        }
 
-                        // Kill the module
-                        if current_module.child_process.is_some() {
-                            error!("Module: {} will be killed", current_module.definition.name);
+        Ok(in_json_message.clone())
+    }
 
-                            // Get exit status and kill the module
-                            self.kill_module(current_module);
-                        }
+    /**
+     * Send the queued executions to the idle instances. The oldest executions first
+     * An execution remains queued until an instance of its module is idle
+     */
+    fn dispatch_queued_executions(&self) {
+        let tmp_queued = TASK_MANAGER.read().unwrap().get_queued_tasks();
 
-                        return Err(error_msg);
-                    } else {
-                        info!("Correctly connected to PUSH socket. Address: {}", push_socket_address);
+        if tmp_queued.is_empty() == true {
+            return;
+        }
 
-                        // Set the status
-                        current_module.status = EnumModuleStatus::RUNNING;
-                    }
+        let mut tmp_data = self.data.write().unwrap();
 
-                    break;
+        for (tmp_execution_id, tmp_request) in tmp_queued.iter() {
+            let mut tmp_json_message : InternalMessage = match serde_json::from_str(tmp_request.as_str()) {
+                Ok(m) => m,
+                Err(e) => {
+                    error!("Unable to decode the request of execution: {}. Error: {}", tmp_execution_id, e);
+                    continue;
                 }
+            };
+
+            let tmp_module_index = match tmp_data.list_running_modules.iter()
+                                             .position(|m| m.draining_flag == false && 
+                                                           m.definition.messages.contains(&tmp_json_message.request.msg_code_id) == true) {
+                Some(i) => i,
+                None => continue,
+            };
+
+            let tmp_instance_index = match self.select_instance(&tmp_data.list_running_modules[tmp_module_index]) {
+                Some(j) => j,
+                None => continue,
+            };
+
+            let current_module = &tmp_data.list_running_modules[tmp_module_index];
+            let current_instance = &current_module.instances[tmp_instance_index];
+
+            tmp_json_message.execution_id = *tmp_execution_id;
+
+            info!("Sending queued execution: {} to Module: {} Instance: {}", tmp_execution_id, 
+                  current_module.definition.name, current_instance.instance_id);
+
+            // It will be sent the next time an instance is idle
+            if let Err(_e) = self.send_to_instance(current_module, current_instance, &tmp_json_message) {
+                continue;
             }
-        } else {
-            info!("Module with instance id: {} is not ready yet. Skipped", read_instance_id);
+
+            if let Err(e) = TASK_MANAGER.write().unwrap().set_running(*tmp_execution_id, current_module.id,
+                                                                       current_instance.instance_id,
+                                                                       current_module.definition.execution_timeout) {
+                error!("{}", e);
+            }
+
+            tmp_data.list_running_modules[tmp_module_index].instances[tmp_instance_index].last_used_time = Utc::now();
         }
+    }
 
-        Ok(in_json_message.clone())
+    /**
+     * Stop the instances above the minimum that have been idle longer than the idle time of the module
+     */
+    pub fn stop_idle_instances(&self) {
+        let mut tmp_data = self.data.write().unwrap();
+        let current_time = Utc::now();
+
+        for a_module in tmp_data.list_running_modules.iter_mut() {
+            let tmp_module_name = a_module.definition.name.clone();
+            let tmp_idle_time = chrono::Duration::seconds(a_module.definition.instance_idle_time as i64);
+
+            while a_module.active_instances() > a_module.definition.min_instances {
+                let tmp_module_id = a_module.id;
+
+                let tmp_position = {
+                    let tmp_task_manager = TASK_MANAGER.read().unwrap();
+
+                    a_module.instances.iter().position(|i| 
+                        i.status == EnumModuleStatus::RUNNING && 
+                        current_time - i.last_used_time >= tmp_idle_time &&
+                        i.is_busy(tmp_task_manager.get_running_count(tmp_module_id, i.instance_id)) == false)
+                };
+
+                let tmp_position = match tmp_position {
+                    Some(p) => p,
+                    None => break,
+                };
+
+                let mut tmp_instance = a_module.instances.remove(tmp_position);
+
+                info!("Stopping idle instance: {} of Module: {}", tmp_instance.instance_id, tmp_module_name);
+
+                self.kill_instance(&tmp_module_name, &mut tmp_instance);
+            }
+        }
     }
 
     /**
@@ -930,11 +1777,17 @@ impl ModuleManager {
     pub fn kill_all_modules(&self) {
         info!("Stopping all modules");
 
-        let tmp_data = self.data.read().unwrap();
+        let mut tmp_data = self.data.write().unwrap();
 
-        for a_module in tmp_data.list_running_modules.iter() {
+        for a_module in tmp_data.list_running_modules.iter_mut() {
             info!("Stopping module: {}", a_module.definition.name);
 
+            let tmp_module_name = a_module.definition.name.clone();
+
+            for an_instance in a_module.instances.iter_mut() {
+                self.kill_instance(&tmp_module_name, an_instance);
+            }
+
             // Send Exit message to the module
             //  let exit_message = json!({
             //     "msg_code_id" : "exit",
@@ -1005,48 +1858,218 @@ impl ModuleManager {
     }
 
     /**
-     * Kill a module
+     * Kill an instance of a module
      * First, it tries to read the exit status. If not posible, it will kill it
      */
-    fn kill_module(&self, in_module: &mut Module) {
-        info!("Killing child process: {} of Module: {}", in_module.instance_id, in_module.definition.name);
+    fn kill_instance(&self, in_module_name: &String, in_instance: &mut ModuleInstance) {
+        info!("Killing child process: {} of Module: {}", in_instance.instance_id, in_module_name);
+
+        // Nothing is running after the process is killed
+        in_instance.cancelled_execution = None;
 
         // Not null
-        if let Some(c) = &mut in_module.child_process {
+        if let Some(c) = &mut in_instance.child_process {
             // Try to recover exit status of the process
             match c.try_wait() {
                 Ok(Some(status)) => {
                     info!("Module: {} Child: {} exited with status: {}",
-                        in_module.definition.name,
+                        in_module_name,
                         c.id(),
                         status);
                 }
                 Ok(None) => {
-                    info!("Module: {}  Child: {} has failed. It will be killed",
-                        in_module.definition.name,
+                    info!("Module: {}  Child: {} is running. It will be killed",
+                        in_module_name,
                         c.id());
 
-                    c.kill().unwrap();
+                    if let Err(e) = c.kill() {
+                        error!("Unable to kill Module: {} Child: {}. Error: {}", in_module_name, c.id(), e);
+                    }
+                    // Avoid a zombie process
+                    let _ = c.wait();
                 }
                 Err(e) => {
                     error!(
                         "Unable to obtain module status. Module: {}  Child: {} . It will be killed",
-                        in_module.definition.name,
+                        in_module_name,
                         c.id()
                     );
                     error!("{}", e);
-                    c.kill().unwrap();
+
+                    if let Err(e) = c.kill() {
+                        error!("Unable to kill Module: {} Child: {}. Error: {}", in_module_name, c.id(), e);
+                    }
                 }
             }
 
             // Remove the pid file
-            let pid_file_name = in_module.definition.name.clone() + &String::from(".pid");
+            let pid_file_name = format!("{}_{}.pid", in_module_name, in_instance.instance_id);
             debug!("Module PID file: {}", pid_file_name);
 
-            fs::remove_file(pid_file_name.as_str()).expect("Unable to remove module PID file");
+            if let Err(e) = fs::remove_file(pid_file_name.as_str()) {
+                warn!("Unable to remove module PID file: {}. Error: {}", pid_file_name, e);
+            }
 
-            in_module.status = EnumModuleStatus::STOPPED;
-            in_module.child_process = None;
+            in_instance.status = EnumModuleStatus::STOPPED;
+            in_instance.stop_time = Utc::now();
+            in_instance.child_process = None;
         }
     }
 }
+
+
+
+/**
+ * Index of the instance that shall receive the next execution; the available one (running and not busy)
+ * that has been idle for longer. None if all of them are busy
+ * in_instances: For each instance, whether it is available and the last time it was used
+ */
+fn select_idle_instance(in_instances: &[(bool, DateTime<Utc>)]) -> Option<usize> {
+    let mut output : Option<usize> = None;
+
+    for (i, (available_flag, last_used_time)) in in_instances.iter().enumerate() {
+        if *available_flag == false {
+            continue;
+        }
+
+        output = match output {
+            Some(j) if in_instances[j].1 <= *last_used_time => Some(j),
+            _ => Some(i),
+        };
+    }
+
+    output
+}
+
+/**
+ * Seconds before restarting a failed instance
+ * Backoff: RESTART_BACKOFF_BASE * 2 ^ consecutive restarts, up to RESTART_BACKOFF_MAX
+ */
+fn restart_backoff(in_restart_count: u32) -> i64 {
+    RESTART_BACKOFF_BASE
+        .saturating_mul( 1_i64 << in_restart_count.min(16) )
+        .min(RESTART_BACKOFF_MAX)
+}
+
+/**
+ * Compare the running modules with the modules definition file. The modules are compared by name
+ * in_current: Definitions of the running modules that are not draining
+ * in_new: Definitions read from the file
+ */
+fn diff_module_definitions(in_current: &[&ModuleDefinition], in_new: &[ModuleDefinition]) -> ReloadModulesResponseStruct {
+    let mut output = ReloadModulesResponseStruct {
+        added:      Vec::new(),
+        removed:    Vec::new(),
+        restarted:  Vec::new(),
+        unchanged:  Vec::new(),
+    };
+
+    for a_current in in_current.iter() {
+        if in_new.iter().any(|d| d.name == a_current.name) == false {
+            output.removed.push( a_current.name.clone() );
+        }
+    }
+
+    for a_definition in in_new.iter() {
+        match in_current.iter().find(|d| d.name == a_definition.name) {
+            Some(d) => {
+                if serde_json::to_value(d).unwrap() == serde_json::to_value(a_definition).unwrap() {
+                    output.unchanged.push( a_definition.name.clone() );
+                } else {
+                    output.restarted.push( a_definition.name.clone() );
+                }
+            },
+            None => output.added.push( a_definition.name.clone() ),
+        };
+    }
+
+    output
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn answer_for_an_unknown_or_expired_execution() {
+        let tmp_manager = ModuleManager::new();
+
+        // Unknown or already removed by remove_expired_tasks
+        let tmp_answer = InternalResponseMessage::new_value(String::from("run_script_response"), String::from("msg1"), json!("done"), u32::MAX);
+        assert!( tmp_manager.handle_module_answer(&tmp_answer).is_err() );
+
+        // Error of a module that could not decode the request
+        let tmp_answer = InternalResponseMessage::new_error(String::from("error_response"), String::from("msg2"), "Invalid request", 0);
+        assert!( tmp_manager.handle_module_answer(&tmp_answer).is_err() );
+    }
+
+    #[test]
+    fn instance_idle_for_longer_is_selected() {
+        let tmp_time = Utc.ymd(2021, 8, 2).and_hms(10, 15, 0);
+        let tmp_minute = chrono::Duration::minutes(1);
+
+        assert_eq!( select_idle_instance(&[]), None );
+
+        // All busy, failed or starting
+        assert_eq!( select_idle_instance(&[(false, tmp_time), (false, tmp_time - tmp_minute)]), None );
+
+        assert_eq!( select_idle_instance(&[(true, tmp_time), (true, tmp_time - tmp_minute), (true, tmp_time + tmp_minute)]), Some(1) );
+
+        // The oldest one is busy
+        assert_eq!( select_idle_instance(&[(true, tmp_time), (false, tmp_time - tmp_minute), (true, tmp_time + tmp_minute)]), Some(0) );
+
+        // Same time. The first one
+        assert_eq!( select_idle_instance(&[(false, tmp_time), (true, tmp_time), (true, tmp_time)]), Some(1) );
+    }
+
+    #[test]
+    fn restart_backoff_schedule() {
+        let tmp_schedule : Vec<i64> = (0..10).map(|c| restart_backoff(c)).collect();
+
+        assert_eq!( tmp_schedule, vec![2, 4, 8, 16, 32, 64, 128, 256, 300, 300] );
+
+        assert_eq!( restart_backoff(u32::MAX), RESTART_BACKOFF_MAX );
+    }
+
+    fn test_definition(in_name: &str, in_max_instances: u32) -> ModuleDefinition {
+        serde_json::from_value( json!({
+            "name":               in_name,
+            "description":        "Test module",
+            "module_type":        "EXTERNAL",
+            "binary_file":        "test_module",
+            "binary_file_path":   "",
+            "working_directory":  "",
+            "config_file":        "",
+            "arguments":          "",
+            "max_instances":      in_max_instances,
+            "messages":           [ "run_script" ],
+            "input_variables":    [],
+            "output_variables":   [],
+        }) ).unwrap()
+    }
+
+    #[test]
+    fn reload_differences() {
+        let tmp_current = vec![ test_definition("orb_propagator", 1), test_definition("tle_propagator", 1),
+                                test_definition("event_finder", 1) ];
+        let tmp_new     = vec![ test_definition("orb_propagator", 1), test_definition("tle_propagator", 4),
+                                test_definition("attitude", 1) ];
+
+        let tmp_current_refs : Vec<&ModuleDefinition> = tmp_current.iter().collect();
+
+        let tmp_diff = diff_module_definitions(&tmp_current_refs, &tmp_new);
+
+        assert_eq!( tmp_diff.added,     vec![String::from("attitude")] );
+        assert_eq!( tmp_diff.removed,   vec![String::from("event_finder")] );
+        assert_eq!( tmp_diff.restarted, vec![String::from("tle_propagator")] );
+        assert_eq!( tmp_diff.unchanged, vec![String::from("orb_propagator")] );
+
+        // Nothing is running
+        let tmp_diff = diff_module_definitions(&[], &tmp_new);
+
+        assert_eq!( tmp_diff.added.len(), 3 );
+        assert!( tmp_diff.removed.is_empty() && tmp_diff.restarted.is_empty() && tmp_diff.unchanged.is_empty() );
+    }
+}
@@ -10,6 +10,7 @@
 
 use std::{ops::Add, sync::{Arc, Mutex, RwLock}};
 use std::result::Result;
+use std::task::Waker;
 //use std::rc::{Rc};
 
 // Log 
@@ -21,7 +22,7 @@ use serde_json::{json, Value};
 
 // Date & Time
 // To be replaced by std::time
-use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, TimeZone, Utc};
 
 // Diesel
 #[macro_use]
@@ -31,13 +32,32 @@ use diesel::prelude::*;
 // Database
 use crate::schema::*;
 
+// Messages
+use common::common_messages::*;
+use crate::fds_messages::*;
+
 use crate::db::DbPool;
+use crate::webhooks::*;
 
 
 #[macro_use]
 use lazy_static::lazy_static;
 
 
+// Format of the times stored in the execution records
+const EXECUTION_TIME_FORMAT : &str = "%Y-%m-%d %H:%M:%S";
+
+// Seconds. Completed executions are kept 1 hour
+pub const DEFAULT_RETENTION_TIME : u32 = 3600;
+
+// Maximum number of events kept per execution. The oldest ones are discarded
+const MAX_EXECUTION_EVENTS : usize = 100;
+
+// Types of events of an execution
+pub const EVENT_STATUS : &str   = "status";
+pub const EVENT_PROGRESS : &str = "progress";
+
+
 // This is need for checking only some values
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 enum EnumExecutionStatus {
@@ -47,6 +67,7 @@ enum EnumExecutionStatus {
    COMPLETED,
    STOPPED,
    CANCELLED,
+   FAILED,
 }
 
 impl EnumExecutionStatus {
@@ -58,6 +79,7 @@ impl EnumExecutionStatus {
             EnumExecutionStatus::COMPLETED          => String::from("Completed"),
             EnumExecutionStatus::STOPPED            => String::from("Stopped"),
             EnumExecutionStatus::CANCELLED          => String::from("Cancelled"),
+            EnumExecutionStatus::FAILED             => String::from("Failed"),
         }
     }
 
@@ -69,6 +91,7 @@ impl EnumExecutionStatus {
             "Completed"          => EnumExecutionStatus::COMPLETED,
             "Stopped"            => EnumExecutionStatus::STOPPED,
             "Cancelled"          => EnumExecutionStatus::CANCELLED,
+            "Failed"             => EnumExecutionStatus::FAILED,
             _                    => EnumExecutionStatus::IDLE,
         }
     }
@@ -80,6 +103,12 @@ impl EnumExecutionStatus {
 pub struct ExecutionRecord {
    execution_id:          u32,
    user_id:               String,
+   // Identifier of the request message
+   msg_id:                String,
+   // InternalMessage sent to the module. It is used for re-queueing the execution
+   request:               String,
+   // The final answer is sent to this URL. Empty = No callback
+   callback_url:          String,
    module_id:             u32,
    module_instance_id:    u32,
    start_time:            String,
@@ -87,11 +116,105 @@ pub struct ExecutionRecord {
    status:                EnumExecutionStatus,
    answer:                String,
    complete_flag:         bool,
+   // Once completed, the record is removed after this time
+   expiration_time:       String,
+   // Percentage, 0 - 100. As reported by the module
+   progress:              u8,
+   progress_detail:       String,
+   // Seconds. The execution is cancelled if it is not completed by the deadline
+   // 0 = No timeout
+   timeout:               u32,
+   deadline:              Option<DateTime<Utc>>,
+   // Task waiting for the answer
+   waker:                 Option<Waker>,
+   // Status transitions and progress. They are not stored in the database
+   events:                Vec<ExecutionEventStruct>,
+   event_counter:         u32,
+   // Streams waiting for new events
+   event_wakers:          Vec<Waker>,
+}
+
+impl ExecutionRecord {
+    fn to_status(&self) -> ExecutionStatusStruct {
+        ExecutionStatusStruct {
+            execution_id:      self.execution_id,
+            module_id:         self.module_id,
+            execution_status:  self.status.to_string(),
+            progress:          self.progress,
+            progress_detail:   self.progress_detail.clone(),
+            start_time:        self.start_time.clone(),
+            stop_time:         self.stop_time.clone(),
+            complete_flag:     self.complete_flag,
+        }
+    }
+
+    /**
+     * Set the record as completed and wake up the task waiting for it
+     * The record will be kept in_retention_time seconds
+     */
+    fn complete(&mut self, in_status: EnumExecutionStatus, in_retention_time: u32) {
+        let current_time = Utc::now();
+
+        self.complete_flag   = true;
+        self.status          = in_status;
+        self.stop_time       = format!("{}", current_time.naive_utc().format(EXECUTION_TIME_FORMAT));
+        self.expiration_time = format!("{}", (current_time + Duration::seconds(in_retention_time as i64)).naive_utc().format(EXECUTION_TIME_FORMAT));
+        self.progress        = 100;
+
+        if let Some(w) = self.waker.take() {
+            w.wake();
+        }
+
+        self.add_event(EVENT_STATUS);
+    }
+
+    /**
+     * Send the final answer to the callback URL, if any
+     */
+    fn notify_callback(&self) {
+        if self.callback_url.is_empty() == true {
+            return;
+        }
+
+        queue_webhook( WebhookMessage {
+            execution_id:   self.execution_id,
+            callback_url:   self.callback_url.clone(),
+            payload:        self.answer.clone(),
+            first_attempt:  1,
+        });
+    }
+
+    /**
+     * Add an event with the current status and progress
+     * The streams following the execution are woken up
+     */
+    fn add_event(&mut self, in_event: &str) {
+        self.event_counter += 1;
+
+        self.events.push( ExecutionEventStruct {
+            sequence:          self.event_counter,
+            event:             String::from(in_event),
+            execution_id:      self.execution_id,
+            execution_status:  self.status.to_string(),
+            progress:          self.progress,
+            progress_detail:   self.progress_detail.clone(),
+            event_time:        format!("{}", Utc::now().naive_utc().format(EXECUTION_TIME_FORMAT)),
+        });
+
+        if self.events.len() > MAX_EXECUTION_EVENTS {
+            self.events.remove(0);
+        }
+
+        for w in self.event_wakers.drain(..) {
+            w.wake();
+        }
+    }
 }
 
 //#[derive(Serialize, Deserialize, Debug)]
-#[derive(Debug, Queryable, Insertable)]
+#[derive(Debug, Queryable, Insertable, AsChangeset)]
 #[table_name="t_execution_record"]
+#[primary_key(execution_id)]
 pub struct ExecutionRecordDB {
    execution_id:          i32,
    user_id:               String,
@@ -102,13 +225,21 @@ pub struct ExecutionRecordDB {
    status:                String,
    complete_flag:         bool,
    expiration_time:       String,
+   msg_id:                String,
+   request:               String,
+   answer:                String,
+   progress:              i32,
+   progress_detail:       String,
+   timeout:               i32,
+   callback_url:          String,
+   event_counter:         i32,
 }
 
 impl ExecutionRecordDB
 {
     pub fn new(in_record : &ExecutionRecord) -> Self
     {
-        let mut output = ExecutionRecordDB {
+        ExecutionRecordDB {
             execution_id :        in_record.execution_id as i32,
             user_id :             in_record.user_id.clone(),
             module_id :           in_record.module_id as i32,
@@ -117,17 +248,48 @@ impl ExecutionRecordDB
             stop_time :           in_record.stop_time.clone(),
             status :              in_record.status.to_string(),
             complete_flag :       in_record.complete_flag,
-            expiration_time :     in_record.start_time.clone(),
-        };
-
-        let mut tmp_start: NaiveDateTime = NaiveDateTime::parse_from_str(in_record.start_time.as_str(), "%Y-%m-%d %H:%M:%S").unwrap();
-
-        // Add +1 hour
-        tmp_start = tmp_start.add( FixedOffset::west(3600) );
-        
-        output.expiration_time     = tmp_start.to_string();
+            expiration_time :     in_record.expiration_time.clone(),
+            msg_id :              in_record.msg_id.clone(),
+            request :             in_record.request.clone(),
+            answer :              in_record.answer.clone(),
+            progress :            in_record.progress as i32,
+            progress_detail :     in_record.progress_detail.clone(),
+            timeout :             in_record.timeout as i32,
+            callback_url :        in_record.callback_url.clone(),
+            event_counter :       in_record.event_counter as i32,
+        }
+    }
 
-        output
+    /**
+    * Create the in-memory record. The execution does not have a deadline until it is
+    * sent to the module
+    */
+    fn to_record(&self) -> ExecutionRecord
+    {
+        ExecutionRecord {
+            execution_id:          self.execution_id as u32,
+            user_id:               self.user_id.clone(),
+            msg_id:                self.msg_id.clone(),
+            request:               self.request.clone(),
+            callback_url:          self.callback_url.clone(),
+            module_id:             self.module_id as u32,
+            module_instance_id:    self.module_instance_id as u32,
+            start_time:            self.start_time.clone(),
+            stop_time:             self.stop_time.clone(),
+            status:                EnumExecutionStatus::from_string(self.status.as_str()),
+            answer:                self.answer.clone(),
+            complete_flag:         self.complete_flag,
+            expiration_time:       self.expiration_time.clone(),
+            progress:              self.progress as u8,
+            progress_detail:       self.progress_detail.clone(),
+            timeout:               self.timeout as u32,
+            deadline:              None,
+            waker:                 None,
+            events:                Vec::new(),
+            // The sequence of the events continues after a restart
+            event_counter:         self.event_counter as u32,
+            event_wakers:          Vec::new(),
+        }
     }
 
     /**
@@ -146,12 +308,11 @@ impl ExecutionRecordDB
         }
     }
 
-    /*
-    pub fn list(conn: &SqliteConnection) -> Vec<Self> 
+    pub fn list(conn: &SqliteConnection) -> Result<Vec<Self>, diesel::result::Error>
     {
-        //user_dsl.load::<User>(conn).expect("Error loading users")
+        t_execution_record::table.order(t_execution_record::execution_id.asc())
+                                 .load::<ExecutionRecordDB>(conn)
     }
-    */
 
     pub fn by_id(conn: &SqliteConnection, in_id: i32) -> Option<Self> 
     {
@@ -164,6 +325,56 @@ impl ExecutionRecordDB
             Err(_e) => None,
         }
     }
+
+    /**
+    * Update all the fields of an execution record
+    */
+    pub fn update(&self, conn: &SqliteConnection) -> Result<usize, diesel::result::Error>
+    {
+        diesel::update( t_execution_record::table.find(self.execution_id) )
+            .set(self)
+            .execute(conn)
+    }
+
+    /**
+    * Update only the progress of an execution record and the sequence of its events.
+    * The rest of the record is not rewritten
+    */
+    pub fn update_progress(conn: &SqliteConnection, in_id: i32, in_progress: i32, in_detail: &String,
+                           in_event_counter: i32) -> Result<usize, diesel::result::Error>
+    {
+        diesel::update( t_execution_record::table.find(in_id) )
+            .set( (t_execution_record::progress.eq(in_progress),
+                   t_execution_record::progress_detail.eq(in_detail),
+                   t_execution_record::event_counter.eq(in_event_counter)) )
+            .execute(conn)
+    }
+
+    /**
+    * Delete the completed records whose expiration time has passed
+    */
+    pub fn delete_expired(conn: &SqliteConnection, in_time: &String) -> Result<usize, diesel::result::Error>
+    {
+        diesel::delete( t_execution_record::table.filter( t_execution_record::complete_flag.eq(true) )
+                                                 .filter( t_execution_record::expiration_time.le(in_time) ) )
+            .execute(conn)
+    }
+
+    /**
+    * Status of a record that is not in memory anymore
+    */
+    fn to_status(&self) -> ExecutionStatusStruct {
+        ExecutionStatusStruct {
+            execution_id:      self.execution_id as u32,
+            module_id:         self.module_id as u32,
+            execution_status:  self.status.clone(),
+            progress:          self.progress as u8,
+            progress_detail:   self.progress_detail.clone(),
+            start_time:        self.start_time.clone(),
+            stop_time:         self.stop_time.clone(),
+            complete_flag:     self.complete_flag,
+        }
+    }
 }
 
 
@@ -183,6 +394,8 @@ pub struct TaskListManager
     executions_counter:      u32,
     list_executions:         Vec<ExecutionRecord>,
     db_pool:                 Option<DbPool>,
+    // Seconds. Completed executions are kept this time
+    retention_time:          u32,
 }
 
 lazy_static! {
@@ -198,6 +411,7 @@ impl TaskListManager
             executions_counter:   0,
             list_executions:      Vec::new(),
             db_pool:              None,
+            retention_time:       DEFAULT_RETENTION_TIME,
         }
     }
 
@@ -206,12 +420,243 @@ impl TaskListManager
         self.db_pool = Some(in_pool);
     }
 
+    // Time the completed executions are kept
+    pub fn set_retention_time(&mut self, in_retention_time: u32) {
+        self.retention_time = in_retention_time;
+    }
+
+    /**
+     * Load the execution records stored in the database. Called at start-up
+     * The executions that were not completed have been interrupted; if in_requeue_flag is set 
+     * and the request is available, they are queued until their module is ready. Otherwise, they 
+     * are set as failed
+     * Return the number of records loaded
+     */
+    pub fn recover_tasks(&mut self, in_requeue_flag: bool) -> Result<u32, String>
+    {
+        info!("Recovering execution records");
+
+        if self.db_pool.is_none() {
+            return Ok(0);
+        }
+
+        // Do not load what has already expired
+        self.remove_expired_tasks();
+
+        let tmp_pool = self.db_pool.clone().unwrap();
+        let tmp_conn = match tmp_pool.get() {
+            Ok(c) => c,
+            Err(e) => {
+                let error_msg = format!("Error recovering task records: {}", e);
+                error!("{}", error_msg);
+                return Err(error_msg);
+            },
+        };
+
+        let tmp_list = match ExecutionRecordDB::list(&tmp_conn) {
+            Ok(l) => l,
+            Err(e) => {
+                let error_msg = format!("Error recovering task records: {}", e);
+                error!("{}", error_msg);
+                return Err(error_msg);
+            },
+        };
+
+        let mut number_records : u32 = 0;
+
+        for a_record_db in tmp_list {
+            let mut tmp_record = a_record_db.to_record();
+
+            // New identifiers shall not reuse the stored ones
+            if tmp_record.execution_id > self.executions_counter {
+                self.executions_counter = tmp_record.execution_id;
+            }
+
+            if tmp_record.complete_flag == false {
+                if in_requeue_flag == true && tmp_record.request.is_empty() == false {
+                    info!("Execution: {} re-queued", tmp_record.execution_id);
+
+                    tmp_record.status          = EnumExecutionStatus::IDLE;
+                    tmp_record.progress        = 0;
+                    tmp_record.progress_detail = String::new();
+                } else {
+                    warn!("Execution: {} was interrupted. Set as failed", tmp_record.execution_id);
+
+                    tmp_record.answer = RestResponse::new_error_id("error_response", tmp_record.msg_id.clone(), 500,
+                                                                   String::from("Execution interrupted by a restart of the FDS")).to_string();
+                    tmp_record.complete(EnumExecutionStatus::FAILED, self.retention_time);
+                    tmp_record.notify_callback();
+                }
+            }
+
+            // Initial event for the streams. Its sequence follows the last one sent before the restart
+            if tmp_record.events.is_empty() == true {
+                tmp_record.add_event(EVENT_STATUS);
+            }
+
+            if let Err(e) = ExecutionRecordDB::new(&tmp_record).update(&tmp_conn) {
+                error!("Error updating task record: {}", e);
+            }
+
+            self.list_executions.push(tmp_record);
+            number_records += 1;
+        }
+
+        info!("Execution records recovered: {}", number_records);
+
+        Ok(number_records)
+    }
+
+    /**
+     * Remove the completed executions whose expiration time has passed
+     * Both from memory and from the database
+     */
+    pub fn remove_expired_tasks(&mut self)
+    {
+        let tmp_current_time = format!("{}", Utc::now().naive_utc().format(EXECUTION_TIME_FORMAT));
+
+        self.list_executions.retain(|r| r.complete_flag == false || r.expiration_time > tmp_current_time);
+
+        if self.db_pool.is_some() {
+            let tmp_pool = self.db_pool.clone().unwrap();
+
+            match tmp_pool.get() {
+                Ok(c) => {
+                    match ExecutionRecordDB::delete_expired(&c, &tmp_current_time) {
+                        Ok(n) => {
+                            if n > 0 {
+                                info!("Expired execution records removed: {}", n);
+                            }
+                        },
+                        Err(e) => error!("Error removing expired task records: {}", e),
+                    };
+                },
+                Err(e) => error!("Error removing expired task records: {}", e),
+            };
+        }
+    }
+
+    /**
+     * Return the queued executions; id and request
+     */
+    pub fn get_queued_tasks(&self) -> Vec<(u32, String)>
+    {
+        let mut output : Vec<(u32, String)> = Vec::new();
+
+        for current_execution in self.list_executions.iter() {
+            if current_execution.complete_flag == false && current_execution.status == EnumExecutionStatus::IDLE {
+                output.push( (current_execution.execution_id, current_execution.request.clone()) );
+            }
+        }
+
+        output
+    }
+
+    /**
+     * Queue a task. All the instances of the module are busy
+     * It will be sent to the first instance that becomes idle. The timeout starts again at that moment
+     */
+    pub fn set_queued(&mut self, in_execution_id: u32) -> std::result::Result<String, String>
+    {
+        debug!("Set queued. Execution Id: {}", in_execution_id);
+
+        for current_execution in self.list_executions.iter_mut() {
+            if current_execution.execution_id == in_execution_id {
+                current_execution.status             = EnumExecutionStatus::IDLE;
+                current_execution.module_instance_id = 0;
+                // The time waiting for an instance is not counted
+                current_execution.deadline           = None;
+                current_execution.add_event(EVENT_STATUS);
+
+                self.update_db_status(in_execution_id);
+        
+                return Ok( String::from("") );
+            }
+        }
+
+        let tmp_error_msg = format!("Execution id not found: {}", in_execution_id);
+                
+        error!("{}", tmp_error_msg);
+        return Err( tmp_error_msg);
+    }
+
+    /**
+     * Number of tasks being executed by an instance of a module
+     */
+    pub fn get_running_count(&self, in_module_id: u32, in_instance_id: u32) -> u32
+    {
+        self.list_executions.iter()
+            .filter(|e| e.complete_flag == false && 
+                        e.status != EnumExecutionStatus::IDLE &&
+                        e.module_id == in_module_id &&
+                        e.module_instance_id == in_instance_id)
+            .count() as u32
+    }
+
+    /**
+     * Return the module id and instance id that execute a task
+     */
+    pub fn get_module_instance(&self, in_execution_id: u32) -> std::result::Result<(u32, u32), String>
+    {
+        for current_execution in self.list_executions.iter() {
+            if current_execution.execution_id == in_execution_id {
+                return Ok( (current_execution.module_id, current_execution.module_instance_id) );
+            }
+        }
+
+        let tmp_error_msg = format!("Execution id not found: {}", in_execution_id);
+                
+        error!("{}", tmp_error_msg);
+        return Err( tmp_error_msg);
+    }
+
+    /**
+     * A queued execution has been sent to a module
+     * The timeout starts now, if it was not running already (i.e. recovered executions)
+     */
+    pub fn set_running(&mut self, in_execution_id: u32, in_module_id : u32, in_instance_id: u32, 
+                       in_timeout: u32) -> std::result::Result<String, String>
+    {
+        debug!("Set running. Execution Id: {}", in_execution_id);
+
+        for current_execution in self.list_executions.iter_mut() {
+            if current_execution.execution_id == in_execution_id {
+                current_execution.status             = EnumExecutionStatus::RUNNING;
+                current_execution.module_id          = in_module_id;
+                current_execution.module_instance_id = in_instance_id;
+                current_execution.timeout            = in_timeout;
+
+                // The timeout starts when the request is sent to the module
+                current_execution.deadline = if in_timeout > 0 {
+                    Some( Utc::now() + Duration::seconds(in_timeout as i64) )
+                } else {
+                    None
+                };
+
+                current_execution.add_event(EVENT_STATUS);
+
+                self.update_db_status(in_execution_id);
+        
+                return Ok( String::from("") );
+            }
+        }
+
+        let tmp_error_msg = format!("Execution id not found: {}", in_execution_id);
+                
+        error!("{}", tmp_error_msg);
+        return Err( tmp_error_msg);
+    }
+
     /**
      * Create a new execution record, associate the async task
      * Add to the list of tasks
+     * The execution is cancelled after in_timeout seconds. 0 = No timeout
+     * The request is stored for re-queueing the execution after a restart
+     * The final answer is sent to in_callback_url. Empty = No callback
      * Return the identifier
      */
-    pub fn add_task(&mut self, in_module_id : u32, in_instance_id: u32, in_user_id: String) -> Result<u32, String>
+    pub fn add_task(&mut self, in_module_id : u32, in_instance_id: u32, in_user_id: String, in_msg_id: String,
+                    in_request: String, in_callback_url: String, in_timeout: u32) -> Result<u32, String>
     {
         debug!("Adding task to the list");
 
@@ -224,21 +669,41 @@ impl TaskListManager
 
         // Create execution record
         let current_time = Utc::now();
+
+        let mut tmp_deadline = None;
+        if in_timeout > 0 {
+            tmp_deadline = Some( current_time + Duration::seconds(in_timeout as i64) );
+        }
         
         // Create an execution record
-        let exec_record = ExecutionRecord {
+        let mut exec_record = ExecutionRecord {
             execution_id:          current_counter,
             user_id:               in_user_id,
+            msg_id:                in_msg_id,
+            request:               in_request,
+            callback_url:          in_callback_url,
             module_id:             in_module_id,
             module_instance_id:    in_instance_id,
-            start_time:            format!("{}", current_time.naive_utc()),
-            stop_time:             format!("{}", current_time.naive_utc()),
+            start_time:            format!("{}", current_time.naive_utc().format(EXECUTION_TIME_FORMAT)),
+            stop_time:             format!("{}", current_time.naive_utc().format(EXECUTION_TIME_FORMAT)),
             status:                EnumExecutionStatus::RUNNING,
             answer:                String::from(""),
             //wait_task:             Some(new_wait_task), 
             complete_flag:         false,
+            // Not removed while running
+            expiration_time:       format!("{}", current_time.naive_utc().format(EXECUTION_TIME_FORMAT)),
+            progress:              0,
+            progress_detail:       String::new(),
+            timeout:               in_timeout,
+            deadline:              tmp_deadline,
+            waker:                 None,
+            events:                Vec::new(),
+            event_counter:         0,
+            event_wakers:          Vec::new(),
         };
 
+        exec_record.add_event(EVENT_STATUS);
+
         let exec_record_db = ExecutionRecordDB::new(&exec_record);
         
         // Save execution record
@@ -334,7 +799,9 @@ impl TaskListManager
         
         for current_execution in self.list_executions.iter_mut() {
             if current_execution.execution_id == in_execution_id {
-                current_execution.complete_flag = true;
+                current_execution.complete(EnumExecutionStatus::COMPLETED, self.retention_time);
+
+                self.update_db_status(in_execution_id);
         
                 return Ok( String::from("") );
             }
@@ -359,8 +826,20 @@ impl TaskListManager
         for current_execution in self.list_executions.iter_mut() {
             debug!("current_execution.execution_id = {}   in_execution_id = {}", current_execution.execution_id,  in_execution_id);
             if current_execution.execution_id == in_execution_id {
+                // A late answer (cancelled, timed out) or a duplicated one is discarded. The callback is only notified once
+                if current_execution.complete_flag == true {
+                    warn!("Answer of completed execution: {}. Status: {}. IGNORED", in_execution_id, current_execution.status.to_string());
+                    return Ok( String::from("") );
+                }
+
                 current_execution.answer = in_answer;
-                current_execution.complete_flag = true;
+                current_execution.status = EnumExecutionStatus::RESPONSE_RECEIVED;
+                current_execution.add_event(EVENT_STATUS);
+
+                current_execution.complete(EnumExecutionStatus::COMPLETED, self.retention_time);
+                current_execution.notify_callback();
+
+                self.update_db_status(in_execution_id);
 
                 return Ok( String::from("") );
             }
@@ -372,6 +851,326 @@ impl TaskListManager
         return Err( tmp_error_msg);
     }
 
+    /**
+     * Cancel a task that is not completed. The answer shall describe the reason
+     * The waiting task is resumed
+     * Return the module id and instance id that execute the task
+     */
+    pub fn set_cancelled(&mut self, in_execution_id: u32, in_answer: String) -> std::result::Result<(u32, u32), String>
+    {
+        debug!("Set cancelled. Execution Id: {}, Answer: {}", in_execution_id, in_answer);
+        
+        for current_execution in self.list_executions.iter_mut() {
+            if current_execution.execution_id == in_execution_id {
+                if current_execution.complete_flag == true {
+                    let tmp_error_msg = format!("Execution id: {} is already completed", in_execution_id);
+                
+                    error!("{}", tmp_error_msg);
+                    return Err( tmp_error_msg);
+                }
+
+                current_execution.answer = in_answer;
+                current_execution.complete(EnumExecutionStatus::CANCELLED, self.retention_time);
+                current_execution.notify_callback();
+
+                let tmp_ids = (current_execution.module_id, current_execution.module_instance_id);
+
+                self.update_db_status(in_execution_id);
+
+                return Ok( tmp_ids );
+            }
+        }
+
+        let tmp_error_msg = format!("Execution id not found: {}", in_execution_id);
+                
+        error!("{}", tmp_error_msg);
+        return Err( tmp_error_msg);
+    }
+
+    /**
+     * Set a task as failed. I.e. the module executing it has crashed. The answer shall describe the reason
+     * The waiting task is resumed
+     */
+    pub fn set_failed(&mut self, in_execution_id: u32, in_answer: String) -> std::result::Result<String, String>
+    {
+        debug!("Set failed. Execution Id: {}, Answer: {}", in_execution_id, in_answer);
+        
+        for current_execution in self.list_executions.iter_mut() {
+            if current_execution.execution_id == in_execution_id {
+                if current_execution.complete_flag == true {
+                    let tmp_error_msg = format!("Execution id: {} is already completed", in_execution_id);
+                
+                    error!("{}", tmp_error_msg);
+                    return Err( tmp_error_msg);
+                }
+
+                current_execution.answer = in_answer;
+                current_execution.complete(EnumExecutionStatus::FAILED, self.retention_time);
+                current_execution.notify_callback();
+
+                self.update_db_status(in_execution_id);
+
+                return Ok( String::from("") );
+            }
+        }
+
+        let tmp_error_msg = format!("Execution id not found: {}", in_execution_id);
+                
+        error!("{}", tmp_error_msg);
+        return Err( tmp_error_msg);
+    }
+
+    /**
+     * Return the tasks being executed by an instance of a module
+     */
+    pub fn get_instance_tasks(&self, in_module_id: u32, in_instance_id: u32) -> Vec<u32>
+    {
+        self.list_executions.iter()
+            .filter(|e| e.complete_flag == false && 
+                        e.status != EnumExecutionStatus::IDLE &&
+                        e.module_id == in_module_id &&
+                        e.module_instance_id == in_instance_id)
+            .map(|e| e.execution_id)
+            .collect()
+    }
+
+    /**
+     * Return the tasks whose timeout has expired
+     * For each one: execution id and timeout in seconds
+     */
+    pub fn get_expired_tasks(&self) -> Vec<(u32, u32)>
+    {
+        let current_time = Utc::now();
+        let mut output : Vec<(u32, u32)> = Vec::new();
+
+        for current_execution in self.list_executions.iter() {
+            if current_execution.complete_flag == true {
+                continue;
+            }
+
+            if let Some(d) = current_execution.deadline {
+                if d <= current_time {
+                    output.push( (current_execution.execution_id, current_execution.timeout) );
+                }
+            }
+        }
+
+        output
+    }
+
+    /**
+     * Return the current status of the task. I.e. Idle (queued), Running
+     */
+    pub fn get_status(&self, in_execution_id: u32) -> std::result::Result<String, String>
+    {
+        for current_execution in self.list_executions.iter() {
+            if current_execution.execution_id == in_execution_id {
+                return Ok( current_execution.status.to_string() );
+            }
+        }
+
+        let tmp_error_msg = format!("Execution id not found: {}", in_execution_id);
+                
+        error!("{}", tmp_error_msg);
+        return Err( tmp_error_msg);
+    }
+
+    /**
+     * Return the msg id of the request that created the task
+     */
+    pub fn get_msg_id(&self, in_execution_id: u32) -> std::result::Result<String, String>
+    {
+        for current_execution in self.list_executions.iter() {
+            if current_execution.execution_id == in_execution_id {
+                return Ok( current_execution.msg_id.clone() );
+            }
+        }
+
+        let tmp_error_msg = format!("Execution id not found: {}", in_execution_id);
+                
+        error!("{}", tmp_error_msg);
+        return Err( tmp_error_msg);
+    }
+
+    /**
+     * Check if a task is complete. If not, the waker is stored
+     * It will be used to resume the waiting task once the task is completed or cancelled
+     */
+    pub fn check_complete(&mut self, in_execution_id: u32, in_waker: &Waker) -> std::result::Result<bool, String>
+    {
+        for current_execution in self.list_executions.iter_mut() {
+            if current_execution.execution_id == in_execution_id {
+                if current_execution.complete_flag == false {
+                    current_execution.waker = Some( in_waker.clone() );
+                }
+
+                return Ok( current_execution.complete_flag );
+            }
+        }
+
+        let tmp_error_msg = format!("Execution id not found: {}", in_execution_id);
+                
+        error!("{}", tmp_error_msg);
+        return Err( tmp_error_msg);
+    }
+
+    /**
+     * Return the events of a task after in_sequence and whether the task is complete
+     * If there are no new events, the waker is stored. It will be used to resume the stream 
+     * when a new event is added
+     */
+    pub fn get_events(&mut self, in_execution_id: u32, in_sequence: u32, 
+                      in_waker: &Waker) -> std::result::Result<(Vec<ExecutionEventStruct>, bool), String>
+    {
+        for current_execution in self.list_executions.iter_mut() {
+            if current_execution.execution_id == in_execution_id {
+                // A sequence that was never sent (i.e. the database was lost). All the events are sent again
+                let tmp_sequence = if in_sequence > current_execution.event_counter { 0 } else { in_sequence };
+
+                let tmp_events : Vec<ExecutionEventStruct> = current_execution.events.iter()
+                                                                .filter(|e| e.sequence > tmp_sequence)
+                                                                .cloned()
+                                                                .collect();
+
+                if tmp_events.is_empty() == true && current_execution.complete_flag == false {
+                    if current_execution.event_wakers.iter().any(|w| w.will_wake(in_waker)) == false {
+                        current_execution.event_wakers.push( in_waker.clone() );
+                    }
+                }
+
+                return Ok( (tmp_events, current_execution.complete_flag) );
+            }
+        }
+
+        let tmp_error_msg = format!("Execution id not found: {}", in_execution_id);
+                
+        error!("{}", tmp_error_msg);
+        return Err( tmp_error_msg);
+    }
+
+    /**
+     * Store the progress reported by the module executing the task
+     */
+    pub fn set_progress(&mut self, in_execution_id: u32, in_progress: u8, in_detail: String) -> std::result::Result<String, String>
+    {
+        debug!("Set progress. Execution Id: {}, Progress: {} {}", in_execution_id, in_progress, in_detail);
+        
+        for current_execution in self.list_executions.iter_mut() {
+            if current_execution.execution_id == in_execution_id {
+                // A completed task does not go back
+                if current_execution.complete_flag == true {
+                    return Ok( String::from("") );
+                }
+
+                current_execution.progress        = in_progress.min(100);
+                current_execution.progress_detail = in_detail;
+                current_execution.add_event(EVENT_PROGRESS);
+
+                let tmp_progress      = current_execution.progress as i32;
+                let tmp_detail        = current_execution.progress_detail.clone();
+                let tmp_event_counter = current_execution.event_counter as i32;
+
+                self.update_db_progress(in_execution_id, tmp_progress, &tmp_detail, tmp_event_counter);
+
+                return Ok( String::from("") );
+            }
+        }
+
+        let tmp_error_msg = format!("Execution id not found: {}", in_execution_id);
+                
+        error!("{}", tmp_error_msg);
+        return Err( tmp_error_msg);
+    }
+
+    /**
+     * Return the status and progress of a task
+     * The task shall belong to the user. If it is not in the list anymore, 
+     * the database record is used
+     */
+    pub fn get_execution_status(&self, in_execution_id: u32, in_user_id: &String) -> std::result::Result<ExecutionStatusStruct, String>
+    {
+        debug!("Get execution status. Execution Id: {}", in_execution_id);
+
+        let tmp_error_msg = format!("Execution id not found: {}", in_execution_id);
+        
+        for current_execution in self.list_executions.iter() {
+            if current_execution.execution_id == in_execution_id {
+                if current_execution.user_id != *in_user_id {
+                    error!("{}. It belongs to another user", tmp_error_msg);
+                    return Err( tmp_error_msg );
+                }
+
+                return Ok( current_execution.to_status() );
+            }
+        }
+
+        if self.db_pool.is_some() {
+            let tmp_pool = self.db_pool.clone().unwrap();
+
+            if let Ok(c) = tmp_pool.get() {
+                if let Some(r) = ExecutionRecordDB::by_id(&c, in_execution_id as i32) {
+                    if r.user_id == *in_user_id {
+                        return Ok( r.to_status() );
+                    }
+                }
+            }
+        }
+
+        error!("{}", tmp_error_msg);
+        return Err( tmp_error_msg);
+    }
+
+    /**
+     * Copy a task to its database record. Including the answer
+     */
+    fn update_db_status(&self, in_execution_id: u32)
+    {
+        if self.db_pool.is_none() {
+            return;
+        }
+
+        for current_execution in self.list_executions.iter() {
+            if current_execution.execution_id == in_execution_id {
+                let tmp_pool = self.db_pool.clone().unwrap();
+                let tmp_conn = match tmp_pool.get() {
+                    Ok(c) => c,
+                    Err(e) => {
+                        error!("Error updating task record: {}", e);
+                        return;
+                    },
+                };
+
+                if let Err(e) = ExecutionRecordDB::new(current_execution).update(&tmp_conn) {
+                    error!("Error updating task record: {}", e);
+                }
+                return;
+            }
+        }
+    }
+
+    /**
+     * Update only the progress columns of the database record of an execution
+     */
+    fn update_db_progress(&self, in_execution_id: u32, in_progress: i32, in_detail: &String, in_event_counter: i32)
+    {
+        if self.db_pool.is_none() {
+            return;
+        }
+
+        let tmp_pool = self.db_pool.clone().unwrap();
+        let tmp_conn = match tmp_pool.get() {
+            Ok(c) => c,
+            Err(e) => {
+                error!("Error updating task progress: {}", e);
+                return;
+            },
+        };
+
+        if let Err(e) = ExecutionRecordDB::update_progress(&tmp_conn, in_execution_id as i32, in_progress, in_detail, in_event_counter) {
+            error!("Error updating task progress: {}", e);
+        }
+    }
+
     /**
      * Check if a task is copmlete
      */
@@ -408,3 +1207,115 @@ impl TaskListManager
     {
     }
 }
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answer_of_an_expired_execution_is_rejected() {
+        let mut tmp_manager = TaskListManager::new();
+
+        // Removed as soon as it is completed
+        tmp_manager.set_retention_time(0);
+
+        let tmp_id = tmp_manager.add_task(20, 1, String::from("1"), String::from("msg1"), String::new(), String::new(), 0).unwrap();
+
+        assert!( tmp_manager.set_answer_completed(tmp_id, String::from("{}")).is_ok() );
+
+        tmp_manager.remove_expired_tasks();
+
+        assert!( tmp_manager.set_answer_completed(tmp_id, String::from("{}")).is_err() );
+        assert!( tmp_manager.set_answer_completed(tmp_id + 1, String::from("{}")).is_err() );
+    }
+
+    #[test]
+    fn answer_of_a_completed_execution_is_ignored() {
+        let mut tmp_manager = TaskListManager::new();
+
+        let tmp_id1 = tmp_manager.add_task(20, 1, String::from("1"), String::from("msg1"), String::new(), String::new(), 0).unwrap();
+        let tmp_id2 = tmp_manager.add_task(20, 1, String::from("1"), String::from("msg2"), String::new(), String::new(), 0).unwrap();
+
+        // Duplicated answer
+        assert!( tmp_manager.set_answer_completed(tmp_id1, String::from("first")).is_ok() );
+        assert!( tmp_manager.set_answer_completed(tmp_id1, String::from("second")).is_ok() );
+
+        assert_eq!( tmp_manager.get_answer(tmp_id1).unwrap(), String::from("first") );
+
+        // Late answer of a timed out execution
+        assert!( tmp_manager.set_failed(tmp_id2, String::from("timeout")).is_ok() );
+        assert!( tmp_manager.set_answer_completed(tmp_id2, String::from("late")).is_ok() );
+
+        assert_eq!( tmp_manager.get_answer(tmp_id2).unwrap(), String::from("timeout") );
+    }
+
+    #[test]
+    fn queued_executions_in_order_of_arrival() {
+        let mut tmp_manager = TaskListManager::new();
+
+        let tmp_id1 = tmp_manager.add_task(20, 0, String::from("1"), String::from("msg1"), String::from("request1"), String::new(), 0).unwrap();
+        let tmp_id2 = tmp_manager.add_task(20, 1, String::from("1"), String::from("msg2"), String::from("request2"), String::new(), 0).unwrap();
+        let tmp_id3 = tmp_manager.add_task(20, 0, String::from("1"), String::from("msg3"), String::from("request3"), String::new(), 0).unwrap();
+
+        assert!( tmp_manager.set_queued(tmp_id3).is_ok() );
+        assert!( tmp_manager.set_queued(tmp_id1).is_ok() );
+
+        assert_eq!( tmp_manager.get_queued_tasks(), vec![(tmp_id1, String::from("request1")), (tmp_id3, String::from("request3"))] );
+
+        // Sent to an instance
+        assert!( tmp_manager.set_running(tmp_id1, 20, 2, 0).is_ok() );
+
+        assert_eq!( tmp_manager.get_queued_tasks(), vec![(tmp_id3, String::from("request3"))] );
+        assert_eq!( tmp_manager.get_status(tmp_id1).unwrap(), String::from("Running") );
+        assert_eq!( tmp_manager.get_status(tmp_id3).unwrap(), String::from("Idle") );
+        assert_eq!( tmp_manager.get_running_count(20, 1), 1 );
+        assert_eq!( tmp_manager.get_running_count(20, 2), 1 );
+    }
+
+    #[test]
+    fn timeout_starts_when_the_execution_is_sent() {
+        let mut tmp_manager = TaskListManager::new();
+
+        let tmp_id = tmp_manager.add_task(20, 0, String::from("1"), String::from("msg1"), String::new(), String::new(), 60).unwrap();
+
+        // Waiting for an instance. The timeout is not running
+        assert!( tmp_manager.set_queued(tmp_id).is_ok() );
+        assert!( tmp_manager.list_executions[0].deadline.is_none() );
+        assert!( tmp_manager.get_expired_tasks().is_empty() );
+
+        assert!( tmp_manager.set_running(tmp_id, 20, 1, 60).is_ok() );
+        assert!( tmp_manager.get_expired_tasks().is_empty() );
+
+        let tmp_deadline = tmp_manager.list_executions[0].deadline.unwrap();
+        assert!( tmp_deadline > Utc::now() + Duration::seconds(55) );
+
+        // Expired once sent
+        tmp_manager.list_executions[0].deadline = Some( Utc::now() - Duration::seconds(1) );
+        assert_eq!( tmp_manager.get_expired_tasks(), vec![(tmp_id, 60)] );
+    }
+
+    #[test]
+    fn events_after_an_unknown_sequence() {
+        let mut tmp_manager = TaskListManager::new();
+        let tmp_waker       = futures::task::noop_waker();
+
+        let tmp_id = tmp_manager.add_task(20, 1, String::from("1"), String::from("msg1"), String::new(), String::new(), 0).unwrap();
+
+        assert!( tmp_manager.set_progress(tmp_id, 40, String::from("Propagating")).is_ok() );
+
+        let (tmp_events, complete_flag) = tmp_manager.get_events(tmp_id, 1, &tmp_waker).unwrap();
+        assert_eq!(tmp_events.len(), 1);
+        assert_eq!(tmp_events[0].sequence, 2);
+        assert_eq!(complete_flag, false);
+
+        // Nothing new
+        let (tmp_events, _) = tmp_manager.get_events(tmp_id, 2, &tmp_waker).unwrap();
+        assert!(tmp_events.is_empty());
+
+        // The sequence was never sent. All the events are sent again
+        let (tmp_events, _) = tmp_manager.get_events(tmp_id, 50, &tmp_waker).unwrap();
+        assert_eq!(tmp_events.len(), 2);
+    }
+}
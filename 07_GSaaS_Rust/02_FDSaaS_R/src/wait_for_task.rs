@@ -41,9 +41,8 @@ pub struct WaitForAnswerFuture {
 }
  
 
-// TODO: Add a timeout
-// Open point; what will it happen when the timeout expires????
-// I.e. Set task a complete and add an error 
+// Timeout. The Tasks Manager cancels the tasks whose timeout has expired. 
+// They are set as complete, the answer is an error and the waiting future is woken up
  
 impl Future for WaitForAnswerFuture {
     type Output = ();
@@ -54,15 +53,19 @@ impl Future for WaitForAnswerFuture {
 
         debug!("Poll WaitForAnswerFuture. Completed: {}", shared_state.completed);
 
-        let complete_flag = match TASK_MANAGER.read().unwrap().is_complete(shared_state.execution_id) {
+        // The waker is stored in the Tasks Manager, it will wake up the future 
+        // when the task is completed or cancelled
+        let complete_flag = match TASK_MANAGER.write().unwrap().check_complete(shared_state.execution_id, cx.waker()) {
             Ok(f) => f,
             Err(e) => {
+                // Otherwise, it would wait forever. The caller will get the error when reading the answer
                 error!("Error reading the state of task: {}", e);
-                false
+                true
             },
         };
 
         if complete_flag == true {
+            shared_state.completed = true;
             Poll::Ready(())
         } else {
             shared_state.waker = Some(cx.waker().clone());
@@ -0,0 +1,491 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * FDS as a Service
+ * Webhooks
+ * The final response of an execution is sent (HTTP POST) to the callback URL of the request.
+ * The body is signed with HMAC-SHA256. If the delivery fails, it is retried with exponential backoff.
+ * Every attempt is recorded in the database. The deliveries not completed are resumed at start-up
+ *
+ * The HTTP client is built without TLS, so, only http:// callbacks are accepted. The callback URL
+ * shall resolve to public addresses; private, loopback and link-local targets are rejected
+ */
+use std::env;
+use std::fs;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+// Log
+use log::{debug, error, info, warn};
+
+// Actix Web Client and runtime
+use actix_web::client::Client;
+use actix_web::http::Uri;
+use actix_web::rt::System;
+use actix_web::web;
+
+// Channel between the Tasks Manager and the delivery loop
+use futures::channel::mpsc::{channel, Sender};
+use futures::StreamExt;
+
+// HMAC
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+#[macro_use]
+use lazy_static::lazy_static;
+
+use crate::db::DbPool;
+use crate::db::webhook_delivery::*;
+
+
+// Header containing the signature of the body. Format: sha256=HEX
+pub const SIGNATURE_HEADER : &str   = "X-FDSaaS-Signature";
+pub const EXECUTION_ID_HEADER : &str = "X-FDSaaS-Execution-Id";
+
+// Seconds. Maximum time waiting for the answer of the callback URL
+const DELIVERY_TIMEOUT : u64 = 10;
+
+// Seconds. Maximum time between two attempts
+const MAX_BACKOFF : u64 = 3600;
+
+// Callbacks waiting to be delivered. If the queue is full, the callback is delivered at the next start-up
+const WEBHOOK_QUEUE_SIZE : usize = 1000;
+
+// Environment variable containing the key for signing the callbacks. It takes precedence over the key file
+pub const SECRET_KEY_VARIABLE : &str = "FDSAAS_CALLBACK_SECRET_KEY";
+
+// Minimum number of characters of the key for signing the callbacks
+pub const MIN_SECRET_KEY_LENGTH : usize = 16;
+
+
+/**
+ * Final response of an execution to be delivered
+ */
+#[derive(Debug, Clone)]
+pub struct WebhookMessage {
+    pub execution_id:    u32,
+    pub callback_url:    String,
+    // RestResponse
+    pub payload:         String,
+    // 1 = New delivery. Greater than 1 when a delivery is resumed at start-up
+    pub first_attempt:   u32,
+}
+
+/**
+ * Parameters of the delivery loop
+ */
+#[derive(Clone)]
+struct WebhookConfig {
+    db_pool:             DbPool,
+    secret_key:          String,
+    max_attempts:        u32,
+    // Seconds. It is doubled after every failed attempt
+    initial_backoff:     u64,
+}
+
+lazy_static! {
+    static ref WEBHOOK_SENDER : Mutex< Option< Sender<WebhookMessage> >> = Mutex::new( None );
+}
+
+/**
+ * Check if an address can be the target of a callback
+ * Private, loopback, link-local and the rest of non-routable addresses are rejected
+ */
+pub fn is_public_address(in_address: &IpAddr) -> bool {
+    match in_address {
+        IpAddr::V4(a) => {
+            let tmp_octets = a.octets();
+
+            a.is_loopback() == false && a.is_private() == false && a.is_link_local() == false &&
+            a.is_unspecified() == false && a.is_broadcast() == false && a.is_multicast() == false &&
+            // 0.0.0.0/8 and shared address space 100.64.0.0/10
+            tmp_octets[0] != 0 && (tmp_octets[0] == 100 && (tmp_octets[1] & 0xc0) == 64) == false
+        },
+        IpAddr::V6(a) => {
+            let tmp_segments = a.segments();
+
+            // IPv4-mapped addresses (::ffff:a.b.c.d)
+            if tmp_segments[0..5].iter().all(|s| *s == 0) == true && tmp_segments[5] == 0xffff {
+                let tmp_v4 = std::net::Ipv4Addr::new((tmp_segments[6] >> 8) as u8, tmp_segments[6] as u8,
+                                                     (tmp_segments[7] >> 8) as u8, tmp_segments[7] as u8);
+                return is_public_address( &IpAddr::V4(tmp_v4) );
+            }
+
+            a.is_loopback() == false && a.is_unspecified() == false && a.is_multicast() == false &&
+            // Unique local fc00::/7 and link-local fe80::/10
+            (tmp_segments[0] & 0xfe00) != 0xfc00 && (tmp_segments[0] & 0xffc0) != 0xfe80
+        },
+    }
+}
+
+/**
+ * Check that a callback URL can be used and return the addresses it resolves to
+ * Only http:// is accepted. All the addresses of the host shall be public
+ */
+pub fn check_callback_url(in_url: &str) -> Result<Vec<SocketAddr>, String> {
+    let tmp_uri = match in_url.parse::<Uri>() {
+        Ok(u) => u,
+        Err(e) => return Err( format!("Invalid callback URL: {}. {}", in_url, e) ),
+    };
+
+    match tmp_uri.scheme_str() {
+        Some("http") => (),
+        Some("https") => return Err( format!("Invalid callback URL: {}. HTTPS is not supported, use http://", in_url) ),
+        _ => return Err( format!("Invalid callback URL: {}. It shall start by http://", in_url) ),
+    };
+
+    let tmp_host = match tmp_uri.host() {
+        Some(h) if h.is_empty() == false => h.trim_start_matches('[').trim_end_matches(']'),
+        _ => return Err( format!("Invalid callback URL: {}. Host not found", in_url) ),
+    };
+
+    let tmp_addresses : Vec<SocketAddr> = match (tmp_host, tmp_uri.port_u16().unwrap_or(80)).to_socket_addrs() {
+        Ok(a) => a.collect(),
+        Err(e) => return Err( format!("Invalid callback URL: {}. Unable to resolve the host: {}", in_url, e) ),
+    };
+
+    if tmp_addresses.is_empty() == true {
+        return Err( format!("Invalid callback URL: {}. Unable to resolve the host", in_url) );
+    }
+
+    if let Some(a) = tmp_addresses.iter().find(|a| is_public_address(&a.ip()) == false) {
+        return Err( format!("Invalid callback URL: {}. The address {} is not allowed", in_url, a.ip()) );
+    }
+
+    Ok(tmp_addresses)
+}
+
+/**
+ * Check that a key can be used for signing the callbacks
+ */
+pub fn check_secret_key(in_secret_key: &str) -> Result<(), String> {
+    if in_secret_key.chars().count() < MIN_SECRET_KEY_LENGTH {
+        return Err( format!("The callback secret key shall have at least {} characters", MIN_SECRET_KEY_LENGTH) );
+    }
+
+    Ok(())
+}
+
+/**
+ * Read the key for signing the callbacks. Either from the environment variable FDSAAS_CALLBACK_SECRET_KEY
+ * or from the file in_file_name. The file shall not be under version control
+ */
+pub fn load_secret_key(in_file_name: &str) -> Result<String, String> {
+    let tmp_secret_key = match env::var(SECRET_KEY_VARIABLE) {
+        Ok(k) => k,
+        Err(_) => {
+            if in_file_name.is_empty() == true {
+                return Err( format!("Callback secret key not found. Set the variable {} or the key file", SECRET_KEY_VARIABLE) );
+            }
+
+            match fs::read_to_string(in_file_name) {
+                Ok(k) => k,
+                Err(e) => return Err( format!("Unable to read the callback secret key file {}: {}", in_file_name, e) ),
+            }
+        },
+    };
+
+    let tmp_secret_key = String::from( tmp_secret_key.trim() );
+
+    check_secret_key(tmp_secret_key.as_str())?;
+
+    Ok(tmp_secret_key)
+}
+
+/**
+ * Seconds to wait after a failed attempt. The initial backoff is doubled after every attempt
+ * in_attempt: Failed attempt. 1 = First one
+ */
+pub fn retry_backoff(in_initial_backoff: u64, in_attempt: u32) -> u64 {
+    let tmp_factor = 1u64.checked_shl(in_attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+
+    in_initial_backoff.saturating_mul(tmp_factor).min(MAX_BACKOFF)
+}
+
+/**
+ * Start the thread that delivers the webhooks
+ * The deliveries not completed before the last stop are resumed
+ * in_max_attempts: Number of attempts before the delivery is considered failed
+ * in_initial_backoff: Seconds between the first and second attempts
+ */
+pub fn start_webhook_loop(in_db_pool: DbPool, in_secret_key: String, in_max_attempts: u32, in_initial_backoff: u64) -> Result<(), String> {
+    check_secret_key(in_secret_key.as_str())?;
+
+    // Before the executions are recovered. They may add new callbacks
+    let tmp_pending_list = pending_webhooks(&in_db_pool)?;
+
+    if tmp_pending_list.is_empty() == false {
+        info!("Webhook deliveries resumed: {}", tmp_pending_list.len());
+    }
+
+    let (tx, mut rx) = channel::<WebhookMessage>(WEBHOOK_QUEUE_SIZE);
+
+    {
+        *WEBHOOK_SENDER.lock().unwrap() = Some(tx);
+    }
+
+    let tmp_config = WebhookConfig {
+        db_pool:          in_db_pool,
+        secret_key:       in_secret_key,
+        max_attempts:     in_max_attempts.max(1),
+        initial_backoff:  in_initial_backoff,
+    };
+
+    let _webhook_thread = thread::spawn(move || {
+        info!("**** Starting Webhook loop ");
+
+        System::new("webhooks").block_on(async move {
+            for a_message in tmp_pending_list {
+                actix_web::rt::spawn( deliver_webhook(tmp_config.clone(), a_message) );
+            }
+
+            // Each delivery is independent. A slow callback URL does not delay the others
+            while let Some(a_message) = rx.next().await {
+                actix_web::rt::spawn( deliver_webhook(tmp_config.clone(), a_message) );
+            }
+        });
+
+        info!("**** Stopping Webhook loop ");
+    });
+
+    Ok(())
+}
+
+/**
+ * Check if the callbacks can be delivered. I.e. the secret key is configured
+ */
+pub fn is_webhook_running() -> bool {
+    WEBHOOK_SENDER.lock().unwrap().is_some()
+}
+
+/**
+ * Queue the final response of an execution. It is sent in the background
+ * If the queue is full, it will be sent at the next start-up
+ */
+pub fn queue_webhook(in_message: WebhookMessage) {
+    debug!("Queueing webhook. Execution id: {} URL: {}", in_message.execution_id, in_message.callback_url);
+
+    match WEBHOOK_SENDER.lock().unwrap().as_mut() {
+        Some(s) => {
+            if let Err(e) = s.try_send(in_message) {
+                error!("Unable to queue webhook: {}. It will be delivered at the next start-up", e);
+            }
+        },
+        None => error!("Webhook loop is not running. Callback of execution: {} IGNORED", in_message.execution_id),
+    };
+}
+
+/**
+ * Deliveries that were not completed; completed executions with a callback URL and
+ * without attempts or whose last attempt is being retried
+ */
+fn pending_webhooks(in_db_pool: &DbPool) -> Result<Vec<WebhookMessage>, String> {
+    let tmp_conn = match in_db_pool.get() {
+        Ok(c) => c,
+        Err(e) => return Err( format!("Error reading pending webhooks: {}", e) ),
+    };
+
+    let tmp_list = match WebhookDelivery::pending(&tmp_conn) {
+        Ok(l) => l,
+        Err(e) => return Err( format!("Error reading pending webhooks: {}", e) ),
+    };
+
+    Ok( tmp_list.into_iter()
+                .map(|(tmp_execution_id, tmp_callback_url, tmp_answer, tmp_next_attempt)| WebhookMessage {
+                    execution_id:   tmp_execution_id as u32,
+                    callback_url:   tmp_callback_url,
+                    payload:        tmp_answer,
+                    first_attempt:  tmp_next_attempt as u32,
+                })
+                .collect() )
+}
+
+/**
+ * Signature of the body. HMAC-SHA256 in hexadecimal
+ */
+pub fn sign_payload(in_secret_key: &str, in_payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_varkey(in_secret_key.as_bytes()).expect("HMAC can take a key of any size");
+
+    mac.update(in_payload.as_bytes());
+
+    let mut output = String::from("sha256=");
+    for b in mac.finalize().into_bytes().iter() {
+        output.push_str( format!("{:02x}", b).as_str() );
+    }
+
+    output
+}
+
+/**
+ * Send the message. Retry until the callback URL answers with 2xx or the maximum number of attempts
+ */
+async fn deliver_webhook(in_config: WebhookConfig, in_message: WebhookMessage) {
+    let tmp_signature = sign_payload(in_config.secret_key.as_str(), in_message.payload.as_str());
+    let tmp_client = Client::default();
+
+    // Resumed after the maximum number of attempts
+    if in_message.first_attempt > in_config.max_attempts {
+        error!("Webhook delivery failed. Execution id: {} URL: {} Maximum number of attempts reached",
+               in_message.execution_id, in_message.callback_url);
+
+        record_delivery(&in_config, &in_message, in_message.first_attempt, 0, "Failed",
+                        &String::from("Maximum number of attempts reached"));
+        return;
+    }
+
+    for attempt in in_message.first_attempt.max(1)..=in_config.max_attempts {
+        debug!("Delivering webhook. Execution id: {} Attempt: {}", in_message.execution_id, attempt);
+
+        let (tmp_http_status, tmp_detail) = send_webhook(&tmp_client, &in_message, tmp_signature.as_str()).await;
+
+        if tmp_http_status >= 200 && tmp_http_status < 300 {
+            info!("Webhook delivered. Execution id: {} URL: {}", in_message.execution_id, in_message.callback_url);
+
+            record_delivery(&in_config, &in_message, attempt, tmp_http_status, "Delivered", &tmp_detail);
+            return;
+        }
+
+        if attempt == in_config.max_attempts {
+            error!("Webhook delivery failed. Execution id: {} URL: {} HTTP status: {} {}",
+                   in_message.execution_id, in_message.callback_url, tmp_http_status, tmp_detail);
+
+            record_delivery(&in_config, &in_message, attempt, tmp_http_status, "Failed", &tmp_detail);
+            return;
+        }
+
+        let tmp_backoff = retry_backoff(in_config.initial_backoff, attempt);
+
+        warn!("Webhook delivery error. Execution id: {} URL: {} HTTP status: {} {}. Retrying in {} seconds",
+              in_message.execution_id, in_message.callback_url, tmp_http_status, tmp_detail, tmp_backoff);
+
+        record_delivery(&in_config, &in_message, attempt, tmp_http_status, "Retrying", &tmp_detail);
+
+        actix_web::rt::time::delay_for( Duration::from_secs(tmp_backoff) ).await;
+    }
+}
+
+/**
+ * Send the message once. Return the HTTP status (0 = No answer) and the error detail
+ * The host is resolved and checked again before every attempt. The request is sent to the
+ * checked address, so, a change of the DNS records cannot redirect it to an internal address
+ */
+async fn send_webhook(in_client: &Client, in_message: &WebhookMessage, in_signature: &str) -> (u16, String) {
+    let tmp_url = in_message.callback_url.clone();
+
+    let tmp_addresses = match web::block(move || check_callback_url(tmp_url.as_str())).await {
+        Ok(a) => a,
+        Err(e) => return (0, e.to_string()),
+    };
+
+    // The URL was parsed by check_callback_url
+    let tmp_uri = in_message.callback_url.parse::<Uri>().unwrap();
+
+    let tmp_host = match tmp_uri.port_u16() {
+        Some(p) => format!("{}:{}", tmp_uri.host().unwrap_or_default(), p),
+        None    => String::from( tmp_uri.host().unwrap_or_default() ),
+    };
+
+    let tmp_target = format!("http://{}{}", tmp_addresses[0],
+                             tmp_uri.path_and_query().map(|p| p.as_str()).unwrap_or("/"));
+
+    let tmp_result = in_client.post(tmp_target.as_str())
+                              .timeout( Duration::from_secs(DELIVERY_TIMEOUT) )
+                              .header("Host", tmp_host.as_str())
+                              .header("Content-Type", "application/json")
+                              .header(SIGNATURE_HEADER, in_signature)
+                              .header(EXECUTION_ID_HEADER, in_message.execution_id.to_string())
+                              .send_body(in_message.payload.clone())
+                              .await;
+
+    match tmp_result {
+        Ok(r) => (r.status().as_u16(), String::new()),
+        Err(e) => (0, e.to_string()),
+    }
+}
+
+/**
+ * Add the attempt to the delivery log
+ */
+fn record_delivery(in_config: &WebhookConfig, in_message: &WebhookMessage, in_attempt: u32, in_http_status: u16,
+                   in_status: &str, in_detail: &String) {
+    let tmp_conn = match in_config.db_pool.get() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Error recording webhook delivery: {}", e);
+            return;
+        },
+    };
+
+    if let Err(e) = WebhookDelivery::create(&tmp_conn, in_message.execution_id, &in_message.callback_url, in_attempt,
+                                            in_http_status, in_status, in_detail) {
+        error!("Error recording webhook delivery: {}", e);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_of_the_payload() {
+        // RFC 4231, test case 2
+        assert_eq!(sign_payload("Jefe", "what do ya want for nothing?"),
+                   "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    }
+
+    #[test]
+    fn backoff_schedule() {
+        let tmp_schedule : Vec<u64> = (1..=5).map(|a| retry_backoff(2, a)).collect();
+
+        assert_eq!(tmp_schedule, vec![2, 4, 8, 16, 32]);
+
+        assert_eq!(retry_backoff(0, 3), 0);
+        assert_eq!(retry_backoff(2, 20), MAX_BACKOFF);
+        assert_eq!(retry_backoff(2, 200), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn callback_targets() {
+        assert!( check_callback_url("http://93.184.216.34/fds/callback").is_ok() );
+        assert!( check_callback_url("http://93.184.216.34:8080/fds/callback?id=1").is_ok() );
+
+        assert!( check_callback_url("https://93.184.216.34/fds/callback").is_err() );
+        assert!( check_callback_url("ftp://93.184.216.34/fds/callback").is_err() );
+        assert!( check_callback_url("93.184.216.34/fds/callback").is_err() );
+
+        assert!( check_callback_url("http://127.0.0.1:11005/fdsaas/api/reload_modules").is_err() );
+        assert!( check_callback_url("http://10.1.2.3/").is_err() );
+        assert!( check_callback_url("http://192.168.1.1/").is_err() );
+        assert!( check_callback_url("http://169.254.169.254/latest/meta-data").is_err() );
+        assert!( check_callback_url("http://[::1]/").is_err() );
+        assert!( check_callback_url("http://[::ffff:127.0.0.1]/").is_err() );
+    }
+
+    #[test]
+    fn public_addresses() {
+        let tmp_public  = ["93.184.216.34", "8.8.8.8", "100.128.0.1", "2606:2800:220:1::1"];
+        let tmp_private = ["0.1.2.3", "10.0.0.1", "100.64.0.1", "127.0.0.1", "169.254.169.254", "172.16.0.1",
+                           "192.168.0.1", "224.0.0.1", "255.255.255.255",
+                           "::", "::1", "fc00::1", "fd12::1", "fe80::1", "ff02::1", "::ffff:10.0.0.1"];
+
+        for a in tmp_public.iter() {
+            assert!( is_public_address( &a.parse().unwrap() ), "{}", a );
+        }
+
+        for a in tmp_private.iter() {
+            assert!( is_public_address( &a.parse().unwrap() ) == false, "{}", a );
+        }
+    }
+
+    #[test]
+    fn secret_keys() {
+        assert!( check_secret_key("").is_err() );
+        assert!( check_secret_key("0123456789").is_err() );
+        assert!( check_secret_key("0123456789abcdef").is_ok() );
+    }
+}
@@ -11,6 +11,7 @@ import unittest
 import json
 import requests
 import hashlib
+import time
 
 from datetime import timezone 
 import datetime 
@@ -116,6 +117,112 @@ class TestRunScript(unittest.TestCase):
 
         #     print("   Ok")
 
+    def test_run_submit(self):
+        global user_id, authentication_key
+
+        print("Test Run Script Asynchronous ......")
+        api_url = API_SERVER_IP + "fdsaas/api/run_script/submit"
+        
+        with open('run_script.json') as f:
+             in_json = json.load(f)
+
+        dt = datetime.datetime.now() 
+        
+        utc_time = dt.replace(tzinfo = timezone.utc) 
+        utc_timestamp = utc_time.timestamp() 
+
+        in_json["timestamp"] = int(utc_timestamp)
+        in_json["authentication_key"] = authentication_key
+
+        resp = requests.get(api_url,
+                            headers={"content-type": "application/json"},
+                            json=in_json)
+       
+        self.assertEqual(resp.status_code, 202)
+
+        print(resp.text)
+        resp_json = json.loads( resp.text )
+        self.assertEqual(resp.headers["Location"], resp_json["location"])
+
+        # Poll the result
+        with open('execution_status.json') as f:
+             status_json = json.load(f)
+
+        status_json["msg_code_id"] = "execution_result"
+        status_json["timestamp"] = int(utc_timestamp)
+        status_json["authentication_key"] = authentication_key
+
+        api_url = API_SERVER_IP + resp_json["location"].lstrip("/") + "/result"
+
+        for i in range(60):
+            resp = requests.get(api_url,
+                                headers={"content-type": "application/json"},
+                                json=status_json)
+
+            if resp.status_code != 202:
+                break
+
+            time.sleep(1)
+
+        self.assertEqual(resp.status_code, 200)
+
+        print(resp.text)
+
+    def test_run_events(self):
+        global user_id, authentication_key
+
+        print("Test Run Script Events ......")
+        api_url = API_SERVER_IP + "fdsaas/api/run_script/submit"
+        
+        with open('run_script.json') as f:
+             in_json = json.load(f)
+
+        dt = datetime.datetime.now() 
+        
+        utc_time = dt.replace(tzinfo = timezone.utc) 
+        utc_timestamp = utc_time.timestamp() 
+
+        in_json["timestamp"] = int(utc_timestamp)
+        in_json["authentication_key"] = authentication_key
+
+        resp = requests.get(api_url,
+                            headers={"content-type": "application/json"},
+                            json=in_json)
+       
+        self.assertEqual(resp.status_code, 202)
+
+        resp_json = json.loads( resp.text )
+
+        # Follow the execution until it is completed
+        with open('execution_status.json') as f:
+             status_json = json.load(f)
+
+        status_json["timestamp"] = int(utc_timestamp)
+        status_json["authentication_key"] = authentication_key
+
+        api_url = API_SERVER_IP + resp_json["location"].lstrip("/") + "/events"
+
+        resp = requests.get(api_url,
+                            headers={"content-type": "application/json"},
+                            json=status_json,
+                            stream=True,
+                            timeout=120)
+
+        self.assertEqual(resp.status_code, 200)
+        self.assertTrue(resp.headers["content-type"].startswith("text/event-stream"))
+
+        list_status = []
+        for line in resp.iter_lines(decode_unicode=True):
+            if line.startswith("data: "):
+                event_json = json.loads( line[len("data: "):] )
+                print(event_json)
+
+                if event_json["event"] == "status":
+                    list_status.append(event_json["execution_status"])
+
+        self.assertEqual(list_status[0], "Running")
+        self.assertEqual(list_status[-1], "Completed")
+
     def test_run_usage(self):
         global user_id, authentication_key
 
@@ -0,0 +1,12 @@
+-- 
+-- (c) Incomplete Worlds 2021
+-- Alberto Fernandez (ajfg)
+--  
+-- FDS as a Service main
+--  
+-- Ground stations and antennas. Used by the pass prediction of the Orbit Propagation module
+--
+
+DROP TABLE IF EXISTS t_antenna;
+
+DROP TABLE IF EXISTS t_ground_station;
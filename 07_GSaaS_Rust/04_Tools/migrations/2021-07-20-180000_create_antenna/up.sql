@@ -0,0 +1,33 @@
+-- 
+-- (c) Incomplete Worlds 2021
+-- Alberto Fernandez (ajfg)
+--  
+-- FDS as a Service main
+--  
+-- Ground stations and antennas. Used by the pass prediction of the Orbit Propagation module
+-- 
+
+CREATE TABLE IF NOT EXISTS t_ground_station (
+  id                   TEXT(36) NOT NULL PRIMARY KEY,
+  name                 TEXT(40) NOT NULL,
+  owner                TEXT(40) NOT NULL,
+  -- Format:  YYYY-MM-DDTHH:MM:SS
+  created              TEXT NOT NULL
+);
+
+
+CREATE TABLE IF NOT EXISTS t_antenna (
+  id                   TEXT(36) NOT NULL PRIMARY KEY,
+  name                 TEXT(40) NOT NULL,
+  station_id           TEXT(36) NOT NULL,
+  -- WGS84 degrees
+  latitude             REAL NOT NULL,
+  -- WGS84 degrees
+  longitude            REAL NOT NULL,
+  -- Meters
+  altitude             REAL NOT NULL,
+  -- Format:  YYYY-MM-DDTHH:MM:SS
+  created              TEXT NOT NULL,
+
+  FOREIGN KEY(station_id) REFERENCES t_ground_station(id)
+);
@@ -100,6 +100,9 @@ pub struct RestRequest {
     // Current timestamp in Unix time. Seconds since 1/Jan/1970
     pub timestamp:              Value,
     
+    // Optional. URL where the final response is sent (HTTP POST) once the execution is completed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback_url:           Option<String>,
     
     #[serde(flatten)]
     pub parameters:             Value,
@@ -121,6 +124,7 @@ impl RestRequest {
             msg_id:                 String::new(),
             //timestamp:              json!( Utc::now().timestamp() ),
             timestamp:              json!( SystemTime::now().elapsed().unwrap() ),
+            callback_url:           None,
             parameters:             Value::Null,
         }
     }
@@ -0,0 +1,13 @@
+-- 
+-- (c) Incomplete Worlds 2021
+-- Alberto Fernandez (ajfg)
+--  
+-- GS as a Service
+-- Orbit Propagation - SGP4 - TLE
+--  
+-- Ephemeris store. Every propagation run of a satellite is stored as a new version
+--
+
+DROP TABLE IF EXISTS t_ephemeris;
+
+DROP TABLE IF EXISTS t_ephemeris_run;
@@ -0,0 +1,48 @@
+-- 
+-- (c) Incomplete Worlds 2021
+-- Alberto Fernandez (ajfg)
+--  
+-- GS as a Service
+-- Orbit Propagation - SGP4 - TLE
+--  
+-- Ephemeris store. Every propagation run of a satellite is stored as a new version
+-- 
+
+CREATE TABLE IF NOT EXISTS t_ephemeris_run (
+  id                   TEXT(36) NOT NULL PRIMARY KEY,
+  mission_id           TEXT(10) NOT NULL,
+  satellite_id         TEXT(10) NOT NULL,
+  -- 1, 2, 3... per mission and satellite
+  version              INTEGER NOT NULL,
+  reference_frame      TEXT(10) NOT NULL,
+  -- Format:  YYYY-MM-DDTHH:MM:SS.ffffffZ
+  start_time           TEXT NOT NULL,
+  -- Format:  YYYY-MM-DDTHH:MM:SS.ffffffZ
+  stop_time            TEXT NOT NULL,
+  -- Seconds
+  step_size            INTEGER NOT NULL,
+  norad_id             INTEGER,
+  number_points        INTEGER NOT NULL,
+  -- Format:  YYYY-MM-DDTHH:MM:SS
+  created              TEXT NOT NULL,
+
+  UNIQUE(mission_id, satellite_id, version)
+);
+
+
+CREATE TABLE IF NOT EXISTS t_ephemeris (
+  run_id               TEXT(36) NOT NULL,
+  -- Format:  YYYY-MM-DDTHH:MM:SS.ffffffZ
+  epoch                TEXT NOT NULL,
+  -- km
+  x                    REAL NOT NULL,
+  y                    REAL NOT NULL,
+  z                    REAL NOT NULL,
+  -- km/s
+  vx                   REAL NOT NULL,
+  vy                   REAL NOT NULL,
+  vz                   REAL NOT NULL,
+
+  PRIMARY KEY(run_id, epoch),
+  FOREIGN KEY(run_id) REFERENCES t_ephemeris_run(id)
+);
@@ -0,0 +1,13 @@
+-- 
+-- (c) Incomplete Worlds 2021
+-- Alberto Fernandez (ajfg)
+--  
+-- GS as a Service
+-- Orbit Propagation - SGP4 - TLE
+--  
+-- TLE catalog. History of TLEs per NORAD id and link with the satellites of the Tools module
+--
+
+DROP TABLE IF EXISTS t_satellite_tle;
+
+DROP TABLE IF EXISTS t_tle;
@@ -0,0 +1,32 @@
+-- 
+-- (c) Incomplete Worlds 2021
+-- Alberto Fernandez (ajfg)
+--  
+-- GS as a Service
+-- Orbit Propagation - SGP4 - TLE
+--  
+-- TLE catalog. History of TLEs per NORAD id and link with the satellites of the Tools module
+-- 
+
+CREATE TABLE IF NOT EXISTS t_tle (
+  id                   TEXT(36) NOT NULL PRIMARY KEY,
+  norad_id             INTEGER NOT NULL,
+  name                 TEXT(40),
+  -- Format:  YYYY-MM-DDTHH:MM:SS.ffffffZ
+  epoch                TEXT NOT NULL,
+  line1                TEXT(69) NOT NULL,
+  line2                TEXT(69) NOT NULL,
+  -- Format:  YYYY-MM-DDTHH:MM:SS
+  created              TEXT NOT NULL,
+
+  UNIQUE(norad_id, epoch)
+);
+
+
+-- Satellite of the Tools database (t_satellite) and its NORAD id
+CREATE TABLE IF NOT EXISTS t_satellite_tle (
+  satellite_id         TEXT(36) NOT NULL PRIMARY KEY,
+  norad_id             INTEGER NOT NULL,
+  -- Format:  YYYY-MM-DDTHH:MM:SS
+  created              TEXT NOT NULL
+);
@@ -10,7 +10,7 @@
 use chrono::NaiveDateTime;
 // Serialize/Deserialize; YAML, JSON
 use serde::{Deserialize, Serialize};
-// use serde_json;
+use serde_json::Value;
 
 //use std::collections::HashMap;
 
@@ -59,10 +59,38 @@ use serde::{Deserialize, Serialize};
      pub epoch_format:        String,
      
      pub ephemeris:           Vec<SatelliteStateVector>,
+
+     // GeoJSON Feature. Split at the antimeridian
+     #[serde(skip_serializing_if = "Option::is_none")]
+     pub ground_track:        Option<Value>,
+
+     // Orbital events. I.e. eclipses
+     #[serde(skip_serializing_if = "Option::is_none")]
+     pub events:              Option<Vec<OrbitEventStruct>>,
+
+     // Version of the stored ephemeris. Only if add_to_database is true
+     #[serde(skip_serializing_if = "Option::is_none")]
+     pub ephemeris_version:   Option<i32>,
+
+     // I.e. propagation window far from the epoch of the orbital elements
+     pub warnings:            Vec<String>,
  }
 
 
 
+/**
+ * Orbital event. I.e. PENUMBRA_ENTRY, UMBRA_ENTRY, UMBRA_EXIT, PENUMBRA_EXIT
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrbitEventStruct {
+    pub time:                 String,
+    pub event:                String,
+
+    // Seconds since the matching entry. Only exit events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration:             Option<f64>,
+}
+
 // #[derive(Serialize, Deserialize, Debug)]
 // pub struct Vector3Struct {
 //     pub x:       f64,
@@ -70,31 +98,63 @@ use serde::{Deserialize, Serialize};
 //     pub z:       f64,
 // }
 
-#[derive(Serialize, Deserialize, Debug)]
+/**
+ * Orbital elements. Either a TLE or an OMM shall be provided
+ * If both are provided, the TLE is used
+ */
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct InputTleStruct {
-    pub tle: TleStruct,
-    // TODO
-   // pub OMM: OmmStruct,
+    pub tle: Option<TleStruct>,
+
+    // CCSDS OMM. It can be a JSON object (CelesTrak / Space-Track keywords)
+    // or a string containing a KVN or XML document
+    #[serde(rename = "OMM", alias = "omm")]
+    pub omm: Option<Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TleStruct {
     pub name:    Option<String>,
     pub line1:   String,
     pub line2:   String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct OmmStruct {
-    pub object_name:   String,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OutputTleStruct {
 
     pub reference_frame:      String,
     pub interpolation_order:  u16,
     pub output_format:        String,
+
+    // Only used by the CSV output format
+    pub csv:                  Option<CsvOutputStruct>,
+
+    // Only used by the JSON output format
+    // Add latitude, longitude and altitude (WGS-84) to every ephemeris. Default: false
+    pub geodetic:             Option<bool>,
+    // Add the position and velocity to every ephemeris. Default: true
+    pub cartesian:            Option<bool>,
+    // Add the ground track as a GeoJSON Feature. Default: false
+    pub ground_track:         Option<bool>,
+    // Add the umbra and penumbra entry and exit times to the list of events. Default: false
+    pub eclipse_events:       Option<bool>,
+}
+
+/**
+ * Options of the CSV output format
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CsvOutputStruct {
+    // List of columns. Allowed values; time, x, y, z, vx, vy, vz
+    // Default: all of them
+    pub columns:              Option<Vec<String>>,
+    // Distance units. Allowed values; km, m. Velocity will be km/s or m/s
+    // Default: km
+    pub units:                Option<String>,
+    // Column separator. Default: ","
+    pub separator:            Option<String>,
+    // Add a first line with the name of the columns. Default: true
+    pub header:               Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -102,6 +162,546 @@ pub struct SatelliteStateVector {
     pub time:                  String,
     pub position:              [f64; 3],
     pub velocity:              [f64; 3],
+
+    // WGS-84. Degrees, degrees and km
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latitude:              Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub longitude:             Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub altitude:              Option<f64>,
+}
+
+
+
+
+// =======================================================
+// Ephemeris store
+// =======================================================
+
+/**
+ * Read a stored ephemeris between two epochs
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EphemerisQueryStruct {
+
+    pub mission_id:            String,
+    pub satellite_id:          String,
+
+    // Last version if not provided
+    pub version:               Option<i32>,
+
+    // RFC 3339. Whole ephemeris if not provided
+    pub start_time:            Option<String>,
+    pub stop_time:             Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EphemerisQueryResponseStruct {
+
+    pub mission_id:            String,
+    pub satellite_id:          String,
+
+    pub version:               i32,
+    pub reference_frame:       String,
+    pub norad_id:              Option<i64>,
+    pub created:               String,
+
+    pub ephemeris:             Vec<SatelliteStateVector>,
+}
+
+/**
+ * List the stored versions of the ephemeris of a satellite
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EphemerisVersionsStruct {
+
+    pub mission_id:            String,
+    pub satellite_id:          String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EphemerisVersionsResponseStruct {
+
+    pub mission_id:            String,
+    pub satellite_id:          String,
+
+    pub versions:              Vec<EphemerisVersionStruct>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EphemerisVersionStruct {
+    pub version:               i32,
+    pub reference_frame:       String,
+    pub start_time:            String,
+    pub stop_time:             String,
+    pub step_size:             i32,
+    pub number_points:         i32,
+    pub norad_id:              Option<i64>,
+    pub created:               String,
+}
+
+
+// =======================================================
+// Ephemeris interpolation
+// =======================================================
+
+/**
+ * Interpolate an ephemeris at a list of epochs
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EphemerisInterpolationStruct {
+
+    // LAGRANGE or HERMITE. Default: LAGRANGE, or the one of the OEM
+    pub method:                Option<String>,
+    // Degree of the polynomial. Default: 7, or the one of the OEM
+    pub order:                 Option<u16>,
+
+    // RFC 3339
+    pub epochs:                Vec<String>,
+
+    pub input:                 InterpolationInputStruct,
+}
+
+/**
+ * Ephemeris to interpolate. Only one of the three fields shall be used
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InterpolationInputStruct {
+    // Ephemeris stored by the propagation (add_to_database)
+    pub stored:                Option<StoredEphemerisStruct>,
+
+    // List of state vectors, sorted by time
+    pub ephemeris:             Option<Vec<SatelliteStateVector>>,
+    // Reference frame of the list of state vectors. Only informative
+    pub reference_frame:       Option<String>,
+
+    // CCSDS OEM in KVN format
+    #[serde(rename = "OEM", alias = "oem")]
+    pub oem:                   Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StoredEphemerisStruct {
+    pub mission_id:            String,
+    pub satellite_id:          String,
+
+    // Last version if not provided
+    pub version:               Option<i32>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EphemerisInterpolationResponseStruct {
+
+    pub method:                String,
+    pub order:                 u16,
+
+    pub reference_frame:       Option<String>,
+
+    pub ephemeris:             Vec<SatelliteStateVector>,
+}
+
+
+// =======================================================
+// Batch propagation
+// =======================================================
+
+/**
+ * Propagate a list of satellites using the SGP4 / TLE propagator
+ * All of them share the same propagation window and output options
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrbPropagationTleBatchStruct {
+
+    pub mission_id:            String,
+
+    pub epoch_format:          String,
 
+    // 2020-05-15T11:30:00.000"
+    pub start_time:            String,
+    pub stop_time:             String,
+
+    pub step_size:             u16,
+
+    pub input:                 InputTleBatchStruct,
+
+    pub output:                OutputTleStruct,
+}
+
+/**
+ * List of orbital elements. Any combination of the three fields can be used
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InputTleBatchStruct {
+    pub tle:                   Option<Vec<TleStruct>>,
+
+    #[serde(rename = "OMM", alias = "omm")]
+    pub omm:                   Option<Vec<Value>>,
+
+    // Text of a TLE catalog in 2-line or 3-line format
+    pub catalog:               Option<String>,
+}
+
+/**
+ * Result of every object. A failed object does not fail the whole batch
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrbPropagationTleBatchResponseStruct {
+
+    pub mission_id:            String,
+
+    pub reference_frame:       String,
+    pub epoch_format:          String,
+
+    pub number_objects:        usize,
+    pub number_errors:         usize,
+
+    pub objects:               Vec<BatchObjectResultStruct>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BatchObjectResultStruct {
+    // Location of the object in the request. I.e. "tle[2]", "OMM[0]", "catalog line 7"
+    pub source:                String,
+
+    pub object_name:           Option<String>,
+    pub norad_id:              Option<u64>,
+
+    // OK or ERROR
+    pub status:                String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error:                 Option<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings:              Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ephemeris:             Option<Vec<SatelliteStateVector>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ground_track:          Option<Value>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events:                Option<Vec<OrbitEventStruct>>,
+}
+
+
+// =======================================================
+// Pass prediction
+// =======================================================
+
+/**
+ * Predict the passes of a satellite over an antenna
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PassPredictionStruct {
+
+    pub mission_id:            String,
+    pub satellite_id:          String,
+
+    // Antenna of the Tools module (t_antenna)
+    pub antenna_id:            String,
+
+    // 2020-05-15T11:30:00.000Z"
+    pub start_time:            String,
+    pub stop_time:             String,
+
+    // Step of the azimuth, elevation and range track. Seconds
+    pub step_size:             u16,
+
+    // Minimum elevation. Degrees. Default: 0.0
+    pub elevation_mask:        Option<f64>,
+
+    pub input:                 InputTleStruct,
+}
+
+/**
+ * List of passes over the antenna
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PassPredictionResponseStruct {
+
+    pub mission_id:            String,
+    pub satellite_id:          String,
+    pub antenna_id:            String,
+
+    pub elevation_mask:        f64,
+
+    pub passes:                Vec<PassStruct>,
+
+    // I.e. prediction window far from the epoch of the orbital elements
+    pub warnings:              Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PassStruct {
+    pub aos:                   String,
+    pub los:                   String,
+    pub tca:                   String,
+
+    // Seconds
+    pub duration:              f64,
+
+    // Degrees
+    pub aos_azimuth:           f64,
+    pub los_azimuth:           f64,
+    pub max_elevation:         f64,
+
+    pub track:                 Vec<TrackPointStruct>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrackPointStruct {
+    pub time:                  String,
+    // Degrees
+    pub azimuth:               f64,
+    // Degrees
+    pub elevation:             f64,
+    // km
+    pub range:                 f64,
+}
+
+/**
+ * Predict the range rate and the Doppler shift of a downlink carrier over the passes of a satellite
+ * The fields of the pass prediction and the carrier frequency
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DopplerPredictionStruct {
+    #[serde(flatten)]
+    pub pass:                  PassPredictionStruct,
+
+    // Downlink carrier frequency. Hz
+    pub frequency:             f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DopplerPredictionResponseStruct {
+
+    pub mission_id:            String,
+    pub satellite_id:          String,
+    pub antenna_id:            String,
+
+    pub elevation_mask:        f64,
+    // Hz
+    pub frequency:             f64,
+
+    pub passes:                Vec<DopplerPassStruct>,
+
+    // I.e. prediction window far from the epoch of the orbital elements
+    pub warnings:              Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DopplerPassStruct {
+    pub aos:                   String,
+    pub los:                   String,
+    pub tca:                   String,
+
+    // Seconds
+    pub duration:              f64,
+    // Degrees
+    pub max_elevation:         f64,
+
+    // Hz
+    pub min_frequency:         f64,
+    pub max_frequency:         f64,
+
+    pub track:                 Vec<DopplerPointStruct>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DopplerPointStruct {
+    pub time:                  String,
+    // Degrees
+    pub azimuth:               f64,
+    // Degrees
+    pub elevation:             f64,
+    // km
+    pub range:                 f64,
+    // km/s. Positive while the satellite moves away
+    pub range_rate:            f64,
+    // Received frequency. Hz
+    pub frequency:             f64,
+    // Received minus transmitted frequency. Hz
+    pub doppler_shift:         f64,
+}
+
+
+// =======================================================
+// TLE catalog
+// =======================================================
+
+/**
+ * Import a TLE catalog in 2-line or 3-line format
+ * Every TLE is stored with its NORAD id and epoch. Already stored TLEs are ignored
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TleCatalogImportStruct {
+    pub catalog:               String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TleCatalogImportResponseStruct {
+    pub number_tles:           usize,
+    pub number_inserted:       usize,
+    pub number_duplicates:     usize,
+    pub number_errors:         usize,
+
+    pub errors:                Vec<TleCatalogErrorStruct>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TleCatalogErrorStruct {
+    // Line of the catalog. Starting at 1
+    pub line_number:           usize,
+    pub error:                 String,
+}
+
+/**
+ * Link a satellite of the Tools module (t_satellite) with a NORAD id
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TleSatelliteLinkStruct {
+    pub satellite_id:          String,
+    pub norad_id:              u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TleSatelliteLinkResponseStruct {
+    pub mission_id:            String,
+    pub satellite_id:          String,
+    pub norad_id:              u32,
+}
+
+/**
+ * List the stored TLEs of a satellite or of a NORAD id
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TleHistoryStruct {
+    // Either the satellite or the NORAD id shall be provided
+    pub satellite_id:          Option<String>,
+    pub norad_id:              Option<u32>,
+
+    // 2020-05-15T11:30:00.000Z". Optional
+    pub start_time:            Option<String>,
+    pub stop_time:             Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TleHistoryResponseStruct {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub satellite_id:          Option<String>,
+    pub norad_id:              u32,
+
+    pub tles:                  Vec<TleHistoryEntryStruct>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TleHistoryEntryStruct {
+    pub epoch:                 String,
+    pub name:                  Option<String>,
+    pub line1:                 String,
+    pub line2:                 String,
+}
+
+
+// =======================================================
+// Conjunction screening
+// =======================================================
+
+/**
+ * Search the close approaches between a primary object and a list of secondary objects
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConjunctionScreeningStruct {
+
+    pub mission_id:            String,
+
+    // 2020-05-15T11:30:00.000Z"
+    pub start_time:            String,
+    pub stop_time:             String,
+
+    // Step of the coarse search. Seconds. Default: 60
+    pub step_size:             Option<u16>,
+
+    // Maximum miss distance. km
+    pub threshold:             f64,
+
+    pub primary:               ConjunctionObjectStruct,
+    pub secondaries:           Vec<ConjunctionObjectStruct>,
+
+    // Add a CDM to every conjunction. Default: false
+    pub cdm:                   Option<bool>,
+}
+
+/**
+ * Orbital elements of an object. Either a TLE, an OMM or a satellite linked to the TLE catalog
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConjunctionObjectStruct {
+    #[serde(default)]
+    pub satellite_id:          String,
+
+    #[serde(default)]
+    pub input:                 InputTleStruct,
+}
+
+/**
+ * List of conjunctions. Sorted by time of closest approach
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConjunctionScreeningResponseStruct {
+
+    pub mission_id:            String,
+
+    pub start_time:            String,
+    pub stop_time:             String,
+    pub step_size:             u16,
+    pub threshold:             f64,
+
+    pub number_secondaries:    usize,
+    pub number_errors:         usize,
+
+    pub conjunctions:          Vec<ConjunctionEventStruct>,
+
+    // Secondary objects that could not be screened
+    pub errors:                Vec<ConjunctionErrorStruct>,
+
+    // I.e. screening window far from the epoch of the orbital elements
+    pub warnings:              Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConjunctionEventStruct {
+    // Position in the list of secondaries. Starting at 0
+    pub secondary_index:       usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secondary_satellite_id: Option<String>,
+
+    pub primary_norad_id:      u64,
+    pub secondary_norad_id:    u64,
+    pub secondary_name:        Option<String>,
+
+    pub tca:                   String,
+    // km
+    pub miss_distance:         f64,
+    // km/s
+    pub relative_speed:        f64,
+
+    // Secondary minus primary. Radial, in-track and cross-track components. km
+    pub relative_position_rtn: [f64; 3],
+    // km/s
+    pub relative_velocity_rtn: [f64; 3],
+
+    // CCSDS CDM keywords. Only if cdm is true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cdm:                   Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConjunctionErrorStruct {
+    // Position in the list of secondaries. Starting at 0
+    pub secondary_index:       usize,
+    pub error:                 String,
+}
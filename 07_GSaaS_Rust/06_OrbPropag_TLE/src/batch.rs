@@ -0,0 +1,326 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Batch propagation. The objects are propagated in parallel by a pool of worker threads
+ * A failed object is reported in its own result and does not stop the rest of the batch
+ */
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// Log
+use log::{debug, error};
+
+// Date & Time
+use chrono::{DateTime, Utc};
+
+// Serialize/Deserialize; YAML, JSON
+use serde_json::json;
+
+use crate::api_messages::*;
+use crate::eop::*;
+use crate::omm::*;
+use crate::tle::*;
+use crate::ephemeris_builder::*;
+
+
+// Maximum number of objects of a batch request
+pub const MAX_BATCH_OBJECTS : usize = 1000;
+// Maximum number of state vectors of a batch request; all the objects
+pub const MAX_BATCH_SAMPLES : u64 = 1_000_000;
+
+
+/**
+ * One object of the batch. The elements could not be built if the input is wrong
+ */
+pub struct BatchObject {
+    pub source:         String,
+    pub elements:       Result<sgp4::Elements, String>,
+}
+
+
+/**
+ * Build the list of objects from the TLEs, the OMMs and the catalog of the request
+ */
+pub fn batch_objects(in_input: &InputTleBatchStruct) -> Vec<BatchObject>
+{
+    let mut output : Vec<BatchObject> = Vec::new();
+
+    if let Some(ref tle_list) = in_input.tle {
+        for (i, current_tle) in tle_list.iter().enumerate() {
+            output.push( BatchObject {
+                source:     format!("tle[{}]", i),
+                elements:   elements_from_tle(current_tle),
+            });
+        }
+    }
+
+    if let Some(ref omm_list) = in_input.omm {
+        for (i, current_omm) in omm_list.iter().enumerate() {
+            output.push( BatchObject {
+                source:     format!("OMM[{}]", i),
+                elements:   elements_from_omm(current_omm),
+            });
+        }
+    }
+
+    if let Some(ref catalog) = in_input.catalog {
+        for current_entry in parse_tle_catalog(catalog.as_str()).into_iter() {
+            let tmp_elements = match current_entry.tle {
+                Ok(tle) => elements_from_tle(&tle),
+                Err(e)  => Err(e),
+            };
+
+            output.push( BatchObject {
+                source:     format!("catalog line {}", current_entry.line_number),
+                elements:   tmp_elements,
+            });
+        }
+    }
+
+    output
+}
+
+/**
+ * Number of state vectors generated by a batch request; objects x steps
+ */
+pub fn batch_samples(in_number_objects: usize, in_start: &DateTime<Utc>, in_stop: &DateTime<Utc>, in_step_size: u16) -> u64
+{
+    // Same samples as sample_times; the stop time is always included
+    let tmp_duration = in_stop.signed_duration_since(*in_start).num_seconds().max(0) as u64;
+    let tmp_step_size = in_step_size.max(1) as u64;
+    let tmp_steps = (tmp_duration + tmp_step_size - 1) / tmp_step_size + 1;
+
+    (in_number_objects as u64).saturating_mul(tmp_steps)
+}
+
+/**
+ * Propagate one object of the batch
+ */
+fn propagate_object(in_object: BatchObject, in_mission_id: &String, in_start: &DateTime<Utc>, in_stop: &DateTime<Utc>,
+    in_step_size: u16, in_output: &OutputTleStruct, in_eop: &EopData, in_max_age_days: f64) -> BatchObjectResultStruct
+{
+    let mut output = BatchObjectResultStruct {
+        source:         in_object.source,
+        object_name:    None,
+        norad_id:       None,
+        status:         String::from("OK"),
+        error:          None,
+        warnings:       Vec::new(),
+        ephemeris:      None,
+        ground_track:   None,
+        events:         None,
+    };
+
+    let elements = match in_object.elements {
+        Ok(e) => e,
+        Err(e) => {
+            error!("Batch object {}: {}", output.source, e);
+
+            output.status = String::from("ERROR");
+            output.error = Some(e);
+            return output;
+        }
+    };
+
+    output.object_name = elements.object_name.clone();
+    output.norad_id = Some(elements.norad_id);
+    output.warnings = epoch_warnings(&elements, in_start, in_stop, in_max_age_days);
+
+    let tmp_properties = json!({
+        "mission_id":       in_mission_id,
+        "object_name":      elements.object_name,
+        "norad_id":         elements.norad_id,
+        "start_time":       in_start.to_rfc3339(),
+        "stop_time":        in_stop.to_rfc3339(),
+    });
+
+    match build_ephemeris(&elements, in_start, in_stop, in_step_size, in_output, in_eop, tmp_properties) {
+        Ok(products) => {
+            output.ephemeris = Some(products.ephemeris);
+            output.ground_track = products.ground_track;
+            output.events = products.events;
+        },
+        Err(e) => {
+            error!("Batch object {}: {}", output.source, e);
+
+            output.status = String::from("ERROR");
+            output.error = Some(e);
+        }
+    };
+
+    output
+}
+
+/**
+ * Propagate all the objects using a pool of worker threads
+ * The results are returned in the same order as the input objects
+ */
+pub fn propagate_batch(in_objects: Vec<BatchObject>, in_mission_id: String, in_start: DateTime<Utc>, in_stop: DateTime<Utc>,
+    in_step_size: u16, in_output: Arc<OutputTleStruct>, in_eop: Arc<EopData>, in_number_workers: usize,
+    in_max_age_days: f64) -> Vec<BatchObjectResultStruct>
+{
+    let tmp_number_objects = in_objects.len();
+    let tmp_number_workers = in_number_workers.max(1).min(tmp_number_objects.max(1));
+
+    debug!("Batch propagation of {} objects using {} workers", tmp_number_objects, tmp_number_workers);
+
+    // The objects are moved to the workers. Kept to report an object whose worker panicked
+    let tmp_sources : Vec<String> = in_objects.iter().map(|o| o.source.clone()).collect();
+
+    // Pending objects, in reverse order. Workers take them from the end
+    let tmp_queue : Arc<Mutex<Vec<(usize, BatchObject)>>> = Arc::new( Mutex::new( in_objects.into_iter().enumerate().rev().collect() ) );
+    let tmp_results : Arc<Mutex<Vec<Option<BatchObjectResultStruct>>>> = Arc::new( Mutex::new( (0..tmp_number_objects).map(|_| None).collect() ) );
+    let tmp_mission_id = Arc::new(in_mission_id);
+
+    let mut tmp_workers = Vec::with_capacity(tmp_number_workers);
+
+    for _ in 0..tmp_number_workers {
+        let tmp_queue = tmp_queue.clone();
+        let tmp_results = tmp_results.clone();
+        let tmp_output = in_output.clone();
+        let tmp_eop = in_eop.clone();
+        let tmp_mission_id = tmp_mission_id.clone();
+
+        tmp_workers.push( thread::spawn(move || {
+            loop {
+                let tmp_next = tmp_queue.lock().unwrap().pop();
+
+                let (tmp_index, tmp_object) = match tmp_next {
+                    Some(o) => o,
+                    None    => break,
+                };
+
+                let tmp_result = propagate_object(tmp_object, &tmp_mission_id, &in_start, &in_stop, in_step_size, &tmp_output, &tmp_eop,
+                    in_max_age_days);
+
+                tmp_results.lock().unwrap()[tmp_index] = Some(tmp_result);
+            }
+        }));
+    }
+
+    for current_worker in tmp_workers.into_iter() {
+        if current_worker.join().is_err() == true {
+            error!("Batch propagation worker panicked");
+        }
+    }
+
+    // A worker could only panic while propagating one object
+    let tmp_results = match Arc::try_unwrap(tmp_results) {
+        Ok(r) => r.into_inner().unwrap_or_else(|e| e.into_inner()),
+        Err(r) => r.lock().unwrap_or_else(|e| e.into_inner()).drain(..).collect(),
+    };
+
+    tmp_results.into_iter().enumerate().map(|(i, r)| {
+        r.unwrap_or_else(|| BatchObjectResultStruct {
+            source:         tmp_sources[i].clone(),
+            object_name:    None,
+            norad_id:       None,
+            status:         String::from("ERROR"),
+            error:          Some( format!("ERROR: Internal error while propagating the object {} (index {})", tmp_sources[i], i) ),
+            warnings:       Vec::new(),
+            ephemeris:      None,
+            ground_track:   None,
+            events:         None,
+        })
+    }).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    const ISS_LINE1 : &str = "1 25544U 98067A   20194.88612269 -.00002218  00000-0 -31515-4 0  9992";
+    const ISS_LINE2 : &str = "2 25544  51.6461 221.2784 0001413  89.1723 280.4612 15.49507896236008";
+
+    fn tle(in_name: &str, in_line1: &str) -> TleStruct
+    {
+        TleStruct {
+            name:   Some(String::from(in_name)),
+            line1:  String::from(in_line1),
+            line2:  String::from(ISS_LINE2),
+        }
+    }
+
+    fn output() -> Arc<OutputTleStruct>
+    {
+        Arc::new( serde_json::from_value( json!({
+            "reference_frame": "TEME", "interpolation_order": 7, "output_format": "json"
+        })).unwrap() )
+    }
+
+    #[test]
+    fn objects_keep_the_order_of_the_request() {
+        // The checksum of the second TLE is wrong
+        let tmp_input = InputTleBatchStruct {
+            tle:        Some(vec![ tle("A", ISS_LINE1), tle("B", &ISS_LINE1.replace("9992", "9993")) ]),
+            omm:        Some(vec![ json!({ "OBJECT_NAME": "C" }) ]),
+            catalog:    Some( format!("D\n{}\n{}\n", ISS_LINE1, ISS_LINE2) ),
+        };
+
+        let objects = batch_objects(&tmp_input);
+        let tmp_sources : Vec<&str> = objects.iter().map(|o| o.source.as_str()).collect();
+
+        assert_eq!(tmp_sources, vec!["tle[0]", "tle[1]", "OMM[0]", "catalog line 2"]);
+        assert!(objects[0].elements.is_ok());
+        assert!(objects[1].elements.is_err());
+        assert!(objects[2].elements.is_err());
+        assert!(objects[3].elements.is_ok());
+    }
+
+    #[test]
+    fn failed_objects_do_not_stop_the_batch() {
+        let tmp_start = Utc.ymd(2020, 7, 13).and_hms(0, 0, 0);
+        let tmp_stop = Utc.ymd(2020, 7, 13).and_hms(0, 10, 0);
+
+        let mut objects : Vec<BatchObject> = Vec::new();
+
+        for i in 0..6 {
+            objects.push( BatchObject {
+                source:     format!("tle[{}]", i),
+                elements:   if i % 2 == 0 {
+                                elements_from_tle( &tle("ISS", ISS_LINE1) )
+                            } else {
+                                Err( format!("ERROR: wrong object {}", i) )
+                            },
+            });
+        }
+
+        let results = propagate_batch(objects, String::from("mis1"), tmp_start, tmp_stop, 60, output(),
+            Arc::new(EopData::new()), 3, 1000.0);
+
+        assert_eq!(results.len(), 6);
+
+        for (i, current_result) in results.iter().enumerate() {
+            assert_eq!(current_result.source, format!("tle[{}]", i));
+
+            if i % 2 == 0 {
+                assert_eq!(current_result.status, "OK");
+                assert_eq!(current_result.norad_id, Some(25544));
+                assert_eq!(current_result.ephemeris.as_ref().unwrap().len(), 11);
+            } else {
+                assert_eq!(current_result.status, "ERROR");
+                assert_eq!(current_result.error, Some( format!("ERROR: wrong object {}", i) ));
+                assert!(current_result.ephemeris.is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn samples_of_a_batch() {
+        let tmp_start = Utc.ymd(2020, 7, 13).and_hms(0, 0, 0);
+        let tmp_stop = Utc.ymd(2020, 7, 14).and_hms(0, 0, 0);
+
+        assert_eq!(batch_samples(10, &tmp_start, &tmp_stop, 60), 10 * 1441);
+        assert_eq!(batch_samples(1, &tmp_start, &tmp_start, 60), 1);
+        // Not a multiple of the step size
+        assert_eq!(batch_samples(2, &tmp_start, &(tmp_start + chrono::Duration::seconds(95)), 10), 2 * 11);
+        assert!(batch_samples(MAX_BATCH_OBJECTS, &tmp_start, &tmp_stop, 1) > MAX_BATCH_SAMPLES);
+    }
+}
@@ -31,6 +31,21 @@ pub struct ConfigVariables {
     pub config_log_filename:                      String,
    
     pub orb_propagation_tle_http_address:         String,
+
+    // Earth Orientation Parameters. CelesTrak CSV format
+    pub eop_filename:                             String,
+
+    // SQLite database of the Tools module. Ground stations and antennas
+    pub tools_database_filename:                  String,
+
+    // SQLite database of this module. Stored ephemeris
+    pub ephemeris_database_filename:              String,
+
+    // Number of threads used by the batch propagation
+    pub batch_number_workers:                     usize,
+
+    // A warning is returned if the propagation window is farther from the epoch of the orbital elements. Days
+    pub tle_max_age_days:                         f64,
 }
 
 
@@ -43,6 +58,11 @@ impl ConfigVariables
             version:                    String::new(),
             config_log_filename:        String::new(),
             orb_propagation_tle_http_address:   String::new(),
+            eop_filename:               String::new(),
+            tools_database_filename:    String::new(),
+            ephemeris_database_filename: String::new(),
+            batch_number_workers:       1,
+            tle_max_age_days:           7.0,
         }
     }
 }
@@ -54,6 +74,11 @@ impl ToString for ConfigVariables {
         output_buffer.push_str( format!("Version: {}\n", self.version).as_str() );
         output_buffer.push_str( format!("Config log file name: {}\n", self.config_log_filename).as_str() );
         output_buffer.push_str( format!("Orbit Propagation TLE HTTP Address: {}\n", self.orb_propagation_tle_http_address).as_str() );
+        output_buffer.push_str( format!("EOP file name: {}\n", self.eop_filename).as_str() );
+        output_buffer.push_str( format!("Tools database file name: {}\n", self.tools_database_filename).as_str() );
+        output_buffer.push_str( format!("Ephemeris database file name: {}\n", self.ephemeris_database_filename).as_str() );
+        output_buffer.push_str( format!("Batch number of workers: {}\n", self.batch_number_workers).as_str() );
+        output_buffer.push_str( format!("TLE maximum age (days): {}\n", self.tle_max_age_days).as_str() );
 
         return output_buffer;
     }
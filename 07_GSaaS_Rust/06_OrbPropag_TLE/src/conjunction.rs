@@ -0,0 +1,366 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Conjunction screening. Close approaches between a primary object and a list of secondaries.
+ * The range rate is sampled with a coarse step and every minimum of the distance is refined
+ * by bisection (range rate equal to zero)
+ */
+
+// Log
+use log::{debug};
+
+// Date & Time
+use chrono::{DateTime, Utc, Duration};
+
+// Serialize/Deserialize; YAML, JSON
+use serde_json::{json, Value};
+
+use crate::propagation::*;
+use crate::frames::*;
+use crate::eop::*;
+
+
+// Default step of the coarse search. Seconds
+pub const DEFAULT_SCREENING_STEP : u16 = 60;
+
+// Maximum step of the coarse search. Seconds
+// Two minima of the distance are separated by a fraction of the orbital period
+pub const MAX_SCREENING_STEP : u16 = 300;
+
+// Maximum duration of the screening window. Days
+pub const MAX_SCREENING_DAYS : i64 = 7;
+
+// Maximum number of secondary objects of a request
+pub const MAX_CONJUNCTION_SECONDARIES : usize = 1000;
+
+// Accuracy of the time of closest approach. Seconds
+const TCA_TIME_TOLERANCE : f64 = 0.001;
+
+
+/**
+ * Close approach between the primary and a secondary object
+ * Relative vectors are secondary minus primary
+ */
+#[derive(Debug, Clone)]
+pub struct ConjunctionEvent {
+    // Time of closest approach
+    pub tca:                    DateTime<Utc>,
+    // km
+    pub miss_distance:          f64,
+    // km/s
+    pub relative_speed:         f64,
+
+    // Radial, transverse (in-track) and normal (cross-track) components. Local frame of the primary
+    // km
+    pub relative_position_rtn:  [f64; 3],
+    // km/s
+    pub relative_velocity_rtn:  [f64; 3],
+
+    // State vectors at TCA. TEME
+    pub primary:                EphemerisPoint,
+    pub secondary:              EphemerisPoint,
+}
+
+
+fn dot(a: &[f64; 3], b: &[f64; 3]) -> f64
+{
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn norm(a: &[f64; 3]) -> f64
+{
+    dot(a, a).sqrt()
+}
+
+fn difference(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3]
+{
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn unit(a: &[f64; 3]) -> [f64; 3]
+{
+    let tmp_norm = norm(a);
+
+    [a[0] / tmp_norm, a[1] / tmp_norm, a[2] / tmp_norm]
+}
+
+/**
+ * Project a vector on the radial, transverse and normal directions of a state vector
+ */
+pub fn to_rtn(in_point: &EphemerisPoint, in_vector: &[f64; 3]) -> [f64; 3]
+{
+    let tmp_radial = unit(&in_point.position);
+    let tmp_normal = unit( &cross(&in_point.position, &in_point.velocity) );
+    let tmp_transverse = cross(&tmp_normal, &tmp_radial);
+
+    [dot(&tmp_radial, in_vector), dot(&tmp_transverse, in_vector), dot(&tmp_normal, in_vector)]
+}
+
+
+/**
+ * Propagate one object at any time of the screening window
+ * Times are seconds since the start of the window
+ */
+struct ObjectPropagator<'a> {
+    elements:       &'a sgp4::Elements,
+    constants:      sgp4::Constants,
+    start:          DateTime<Utc>,
+}
+
+impl<'a> ObjectPropagator<'a> {
+    fn new(in_elements: &'a sgp4::Elements, in_start: &DateTime<Utc>) -> Result<Self, String>
+    {
+        Ok( ObjectPropagator {
+            elements:       in_elements,
+            constants:      sgp4::Constants::from_elements(in_elements)
+                                .map_err(|e| format!("ERROR: Unable to initialize SGP4: {}", e.to_string()) )?,
+            start:          *in_start,
+        })
+    }
+
+    fn time_at(&self, in_seconds: f64) -> DateTime<Utc>
+    {
+        self.start + Duration::microseconds( (in_seconds * 1.0e6).round() as i64 )
+    }
+
+    fn state_at(&self, in_seconds: f64) -> Result<EphemerisPoint, String>
+    {
+        propagate_at(self.elements, &self.constants, &self.time_at(in_seconds))
+    }
+}
+
+/**
+ * Derivative of the distance between two objects. km/s
+ * Negative while they are approaching
+ */
+fn range_rate(in_primary: &EphemerisPoint, in_secondary: &EphemerisPoint) -> f64
+{
+    let tmp_position = difference(&in_secondary.position, &in_primary.position);
+    let tmp_velocity = difference(&in_secondary.velocity, &in_primary.velocity);
+
+    dot(&tmp_position, &tmp_velocity) / norm(&tmp_position).max(1.0e-9)
+}
+
+
+/**
+ * Screening of a primary object over a time window
+ * The coarse ephemeris of the primary is computed once and shared by all the secondaries
+ */
+pub struct ConjunctionScreening<'a> {
+    primary:            ObjectPropagator<'a>,
+    // Seconds since start
+    times:              Vec<f64>,
+    primary_samples:    Vec<EphemerisPoint>,
+    // km
+    threshold:          f64,
+}
+
+impl<'a> ConjunctionScreening<'a> {
+    /**
+     * Propagate the primary object every step size seconds. Threshold in km
+     */
+    pub fn new(in_primary: &'a sgp4::Elements, in_start: &DateTime<Utc>, in_stop: &DateTime<Utc>, in_step_size: u16,
+        in_threshold: f64) -> Result<Self, String>
+    {
+        if in_threshold <= 0.0 {
+            return Err( format!("ERROR: Invalid screening threshold: {}. It shall be greater than 0 km", in_threshold) );
+        }
+
+        let tmp_times : Vec<f64> = sample_times(in_start, in_stop, in_step_size)?.iter()
+            .map(|t| t.signed_duration_since(*in_start).num_milliseconds() as f64 / 1000.0)
+            .collect();
+
+        let tmp_primary = ObjectPropagator::new(in_primary, in_start)?;
+
+        let mut tmp_samples : Vec<EphemerisPoint> = Vec::with_capacity(tmp_times.len());
+
+        for current_time in tmp_times.iter() {
+            tmp_samples.push( tmp_primary.state_at(*current_time)? );
+        }
+
+        Ok( ConjunctionScreening {
+            primary:            tmp_primary,
+            times:              tmp_times,
+            primary_samples:    tmp_samples,
+            threshold:          in_threshold,
+        })
+    }
+
+    /**
+     * Find the time where the range rate is zero (bisection)
+     * The range rate is negative at the lower bound and positive at the upper bound
+     */
+    fn find_tca(&self, in_secondary: &ObjectPropagator, in_lower: f64, in_upper: f64) -> Result<f64, String>
+    {
+        let mut tmp_lower = in_lower;
+        let mut tmp_upper = in_upper;
+
+        while tmp_upper - tmp_lower > TCA_TIME_TOLERANCE {
+            let tmp_middle = 0.5 * (tmp_lower + tmp_upper);
+
+            let tmp_rate = range_rate(&self.primary.state_at(tmp_middle)?, &in_secondary.state_at(tmp_middle)?);
+
+            if tmp_rate < 0.0 {
+                tmp_lower = tmp_middle;
+            } else {
+                tmp_upper = tmp_middle;
+            }
+        }
+
+        Ok( 0.5 * (tmp_lower + tmp_upper) )
+    }
+
+    /**
+     * Return the close approaches with a secondary object closer than the threshold. Sorted by time
+     * Minima at the start or stop time of the window are not reported
+     */
+    pub fn screen(&self, in_secondary: &sgp4::Elements) -> Result<Vec<ConjunctionEvent>, String>
+    {
+        let tmp_secondary = ObjectPropagator::new(in_secondary, &self.primary.start)?;
+
+        let mut output : Vec<ConjunctionEvent> = Vec::new();
+        let mut tmp_previous_rate : Option<f64> = None;
+
+        for (i, current_time) in self.times.iter().enumerate() {
+            let tmp_rate = range_rate(&self.primary_samples[i], &tmp_secondary.state_at(*current_time)?);
+
+            // From approaching to moving away. There is a minimum of the distance in between
+            if let Some(previous_rate) = tmp_previous_rate {
+                if previous_rate < 0.0 && tmp_rate >= 0.0 {
+                    let tmp_tca = self.find_tca(&tmp_secondary, self.times[i - 1], *current_time)?;
+
+                    let tmp_primary_state = self.primary.state_at(tmp_tca)?;
+                    let tmp_secondary_state = tmp_secondary.state_at(tmp_tca)?;
+
+                    let tmp_position = difference(&tmp_secondary_state.position, &tmp_primary_state.position);
+                    let tmp_velocity = difference(&tmp_secondary_state.velocity, &tmp_primary_state.velocity);
+
+                    if norm(&tmp_position) <= self.threshold {
+                        output.push( ConjunctionEvent {
+                            tca:                    tmp_primary_state.time,
+                            miss_distance:          norm(&tmp_position),
+                            relative_speed:         norm(&tmp_velocity),
+                            relative_position_rtn:  to_rtn(&tmp_primary_state, &tmp_position),
+                            relative_velocity_rtn:  to_rtn(&tmp_primary_state, &tmp_velocity),
+                            primary:                tmp_primary_state,
+                            secondary:              tmp_secondary_state,
+                        });
+                    }
+                }
+            }
+
+            tmp_previous_rate = Some(tmp_rate);
+        }
+
+        debug!("Found {} conjunctions with object {}", output.len(), in_secondary.norad_id);
+
+        Ok(output)
+    }
+}
+
+
+/**
+ * Conjunction Data Message (CCSDS 508.0-B-1) keywords in JSON format
+ * Covariance is not available from TLEs, so it is not included. State vectors in EME2000
+ */
+pub fn conjunction_cdm(in_event: &ConjunctionEvent, in_message_id: &str, in_primary: &sgp4::Elements,
+    in_secondary: &sgp4::Elements, in_eop_data: &EopData) -> Value
+{
+    let tmp_object = |in_name: &str, in_elements: &sgp4::Elements, in_point: &EphemerisPoint| -> Value {
+        let tmp_state = convert_from_teme(in_point, &EnumReferenceFrame::EME2000, in_eop_data);
+
+        json!({
+            "OBJECT":                   in_name,
+            "OBJECT_DESIGNATOR":        format!("{:05}", in_elements.norad_id),
+            "CATALOG_NAME":             "SATCAT",
+            "OBJECT_NAME":              in_elements.object_name.clone().unwrap_or(String::from("UNKNOWN")),
+            "INTERNATIONAL_DESIGNATOR": in_elements.international_designator.clone().unwrap_or(String::from("UNKNOWN")),
+            "EPHEMERIS_NAME":           "NONE",
+            "COVARIANCE_METHOD":        "DEFAULT",
+            "MANEUVERABLE":             "N/A",
+            "REF_FRAME":                "EME2000",
+            "X":                        tmp_state.position[0],
+            "Y":                        tmp_state.position[1],
+            "Z":                        tmp_state.position[2],
+            "X_DOT":                    tmp_state.velocity[0],
+            "Y_DOT":                    tmp_state.velocity[1],
+            "Z_DOT":                    tmp_state.velocity[2],
+        })
+    };
+
+    // CDM distances are in meters
+    json!({
+        "CCSDS_CDM_VERS":               "1.0",
+        "CREATION_DATE":                Utc::now().format("%Y-%m-%dT%H:%M:%S%.3f").to_string(),
+        "ORIGINATOR":                   "INCOMPLETE WORLDS GSAAS",
+        "MESSAGE_ID":                   in_message_id,
+        "TCA":                          in_event.tca.format("%Y-%m-%dT%H:%M:%S%.3f").to_string(),
+        "MISS_DISTANCE":                in_event.miss_distance * 1000.0,
+        "RELATIVE_SPEED":               in_event.relative_speed * 1000.0,
+        "RELATIVE_POSITION_R":          in_event.relative_position_rtn[0] * 1000.0,
+        "RELATIVE_POSITION_T":          in_event.relative_position_rtn[1] * 1000.0,
+        "RELATIVE_POSITION_N":          in_event.relative_position_rtn[2] * 1000.0,
+        "RELATIVE_VELOCITY_R":          in_event.relative_velocity_rtn[0] * 1000.0,
+        "RELATIVE_VELOCITY_T":          in_event.relative_velocity_rtn[1] * 1000.0,
+        "RELATIVE_VELOCITY_N":          in_event.relative_velocity_rtn[2] * 1000.0,
+        "OBJECT1":                      tmp_object("OBJECT1", in_primary, &in_event.primary),
+        "OBJECT2":                      tmp_object("OBJECT2", in_secondary, &in_event.secondary),
+    })
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn close_approaches_at_the_nodes() {
+        let primary = sgp4::Elements::from_tle(
+            Some(String::from("ISS (ZARYA)")),
+            "1 25544U 98067A   20194.88612269 -.00002218  00000-0 -31515-4 0  9992".as_bytes(),
+            "2 25544  51.6461 221.2784 0001413  89.1723 280.4612 15.49507896236008".as_bytes(),
+        ).unwrap();
+
+        // Same orbit with a different inclination. Both objects cross the equator at the same place and time
+        let mut secondary = primary.clone();
+        secondary.inclination += 0.1;
+
+        let tmp_start = elements_epoch(&primary) + Duration::minutes(10);
+        let tmp_stop = tmp_start + Duration::hours(3);
+
+        let screening = ConjunctionScreening::new(&primary, &tmp_start, &tmp_stop, 60, 5.0).unwrap();
+        let events = screening.screen(&secondary).unwrap();
+
+        // Two nodes per orbit. Around two orbits
+        assert!( events.len() >= 3 );
+
+        for (i, current_event) in events.iter().enumerate() {
+            assert!( current_event.miss_distance < 5.0 );
+            assert!( (norm(&current_event.relative_position_rtn) - current_event.miss_distance).abs() < 1.0e-6 );
+
+            // It is a minimum of the distance
+            for current_offset in [-1.0, 1.0].iter() {
+                let tmp_seconds = current_event.tca.signed_duration_since(tmp_start).num_microseconds().unwrap() as f64 / 1.0e6 + current_offset;
+                let tmp_primary = screening.primary.state_at(tmp_seconds).unwrap();
+                let tmp_secondary = ObjectPropagator::new(&secondary, &tmp_start).unwrap().state_at(tmp_seconds).unwrap();
+
+                assert!( norm(&difference(&tmp_secondary.position, &tmp_primary.position)) > current_event.miss_distance );
+            }
+
+            // Half an orbit between two nodes
+            if i > 0 {
+                let tmp_gap = current_event.tca.signed_duration_since(events[i - 1].tca).num_seconds();
+                assert!( (tmp_gap - 2788).abs() < 120, "Gap between conjunctions {}", tmp_gap );
+            }
+        }
+
+        // Far away objects are not reported
+        let screening = ConjunctionScreening::new(&primary, &tmp_start, &tmp_stop, 60, 0.001).unwrap();
+        assert!( screening.screen(&secondary).unwrap().is_empty() );
+    }
+}
@@ -0,0 +1,54 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Read only access to the antennas of the Tools module
+ */
+
+// JSON serialization
+use serde::{Deserialize, Serialize};
+
+// Time
+use chrono::NaiveDateTime;
+
+// Diesel
+use diesel::prelude::*;
+
+// Database
+use crate::db::schema::*;
+
+
+#[derive(Debug, Deserialize, Serialize, Queryable)]
+pub struct AntennaDb
+{
+    pub id:             String,
+    pub name:           String,
+    pub station_id:     String,
+    // WGS84 degrees
+    pub latitude:       f64,
+    // WGS84 degrees
+    pub longitude:      f64,
+    // Meters
+    pub altitude:       f64,
+    // Format:  YYYY-MM-DDTHH:MM:SS
+    pub created:        NaiveDateTime,
+}
+
+
+impl AntennaDb 
+{
+    /**
+     * Return None if the antenna does not exist. Any other error is returned as is
+     */
+    pub fn by_id(conn: &SqliteConnection, in_id: &String) -> Result<Option<Self>, diesel::result::Error> 
+    {
+        if in_id.is_empty() == true {
+            return Ok(None);
+        }
+
+        t_antenna::table.find(in_id).first::<AntennaDb>(conn).optional()
+    }
+}
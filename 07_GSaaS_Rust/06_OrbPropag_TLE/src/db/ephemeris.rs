@@ -0,0 +1,269 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Ephemeris store. Every propagation run of a satellite is stored as a new version,
+ * so downstream services can read the ephemeris without propagating again
+ */
+
+// JSON serialization
+use serde::{Deserialize, Serialize};
+
+// Time
+use chrono::{DateTime, Utc};
+
+// Diesel
+use diesel::prelude::*;
+
+// UUID
+use uuid::Uuid;
+
+// Database
+use crate::db::schema::*;
+
+use crate::api_messages::*;
+
+
+/**
+ * Propagation run. One version of the ephemeris of a satellite
+ */
+#[derive(Debug, Deserialize, Serialize, Queryable, Insertable)]
+#[table_name="t_ephemeris_run"]
+pub struct EphemerisRunDb
+{
+    pub id:               String,
+    pub mission_id:       String,
+    pub satellite_id:     String,
+    pub version:          i32,
+    pub reference_frame:  String,
+    // Format:  YYYY-MM-DDTHH:MM:SS.ffffffZ
+    pub start_time:       String,
+    // Format:  YYYY-MM-DDTHH:MM:SS.ffffffZ
+    pub stop_time:        String,
+    // Seconds
+    pub step_size:        i32,
+    pub norad_id:         Option<i64>,
+    pub number_points:    i32,
+    // Format:  YYYY-MM-DDTHH:MM:SS
+    pub created:          String,
+}
+
+/**
+ * One state vector of a propagation run
+ */
+#[derive(Debug, Queryable, Insertable)]
+#[table_name="t_ephemeris"]
+pub struct EphemerisDb
+{
+    pub run_id:     String,
+    // Format:  YYYY-MM-DDTHH:MM:SS.ffffffZ
+    pub epoch:      String,
+    // km
+    pub x:          f64,
+    pub y:          f64,
+    pub z:          f64,
+    // km/s
+    pub vx:         f64,
+    pub vy:         f64,
+    pub vz:         f64,
+}
+
+
+/**
+ * Epochs are stored with a fixed format, so they can be compared as text
+ */
+pub fn epoch_to_db(in_epoch: &DateTime<Utc>) -> String
+{
+    in_epoch.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string()
+}
+
+
+impl EphemerisRunDb
+{
+    /**
+     * Insert a new propagation run and its ephemeris
+     * The version is the next one of the mission and satellite
+     */
+    pub fn insert_db(conn: &SqliteConnection, in_mission_id: &String, in_satellite_id: &String,
+        in_reference_frame: &String, in_start: &DateTime<Utc>, in_stop: &DateTime<Utc>, in_step_size: u16,
+        in_norad_id: Option<u64>, in_ephemeris: &[SatelliteStateVector]) -> Result<Self, String>
+    {
+        let tmp_run_id = Uuid::new_v4().to_hyphenated().to_string();
+
+        let mut tmp_points : Vec<EphemerisDb> = Vec::with_capacity(in_ephemeris.len());
+
+        for current_point in in_ephemeris.iter() {
+            let tmp_epoch = DateTime::parse_from_rfc3339(current_point.time.as_str())
+                .map_err(|e| format!("ERROR: Invalid ephemeris time: {}. {}", current_point.time, e.to_string()) )?;
+
+            tmp_points.push( EphemerisDb {
+                run_id:     tmp_run_id.clone(),
+                epoch:      epoch_to_db( &tmp_epoch.with_timezone(&Utc) ),
+                x:          current_point.position[0],
+                y:          current_point.position[1],
+                z:          current_point.position[2],
+                vx:         current_point.velocity[0],
+                vy:         current_point.velocity[1],
+                vz:         current_point.velocity[2],
+            });
+        }
+
+        // The lock is taken before reading the last version. Concurrent runs get consecutive versions
+        conn.immediate_transaction::<_, diesel::result::Error, _>(|| {
+            let tmp_last_version = t_ephemeris_run::table
+                .filter( t_ephemeris_run::mission_id.eq(in_mission_id) )
+                .filter( t_ephemeris_run::satellite_id.eq(in_satellite_id) )
+                .select( diesel::dsl::max(t_ephemeris_run::version) )
+                .first::<Option<i32>>(conn)?;
+
+            let new_run = EphemerisRunDb {
+                id:               tmp_run_id.clone(),
+                mission_id:       in_mission_id.clone(),
+                satellite_id:     in_satellite_id.clone(),
+                version:          tmp_last_version.unwrap_or(0) + 1,
+                reference_frame:  in_reference_frame.clone(),
+                start_time:       epoch_to_db(in_start),
+                stop_time:        epoch_to_db(in_stop),
+                step_size:        in_step_size as i32,
+                norad_id:         in_norad_id.map(|n| n as i64),
+                number_points:    tmp_points.len() as i32,
+                created:          Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+            };
+
+            diesel::insert_into(t_ephemeris_run::table).values(&new_run).execute(conn)?;
+            diesel::insert_into(t_ephemeris::table).values(&tmp_points).execute(conn)?;
+
+            Ok(new_run)
+        })
+        .map_err(|e| format!("ERROR: Unable to store the ephemeris: {}", e.to_string()) )
+    }
+
+    /**
+     * Return a version of the ephemeris of a satellite. The last one if no version is provided
+     */
+    pub fn by_version(conn: &SqliteConnection, in_mission_id: &String, in_satellite_id: &String,
+        in_version: Option<i32>) -> Option<Self>
+    {
+        let query = t_ephemeris_run::table
+            .filter( t_ephemeris_run::mission_id.eq(in_mission_id) )
+            .filter( t_ephemeris_run::satellite_id.eq(in_satellite_id) )
+            .into_boxed();
+
+        let query = match in_version {
+            Some(v) => query.filter( t_ephemeris_run::version.eq(v) ),
+            None    => query.order( t_ephemeris_run::version.desc() ),
+        };
+
+        match query.first::<EphemerisRunDb>(conn) {
+            Ok(m) => Some(m),
+            Err(_e) => None,
+        }
+    }
+
+    /**
+     * List all the versions of the ephemeris of a satellite. Oldest first
+     */
+    pub fn list(conn: &SqliteConnection, in_mission_id: &String, in_satellite_id: &String) -> Result<Vec<Self>, diesel::result::Error>
+    {
+        t_ephemeris_run::table
+            .filter( t_ephemeris_run::mission_id.eq(in_mission_id) )
+            .filter( t_ephemeris_run::satellite_id.eq(in_satellite_id) )
+            .order( t_ephemeris_run::version.asc() )
+            .load::<EphemerisRunDb>(conn)
+    }
+
+    /**
+     * Read the ephemeris of this run between two epochs (both included)
+     */
+    pub fn ephemeris(&self, conn: &SqliteConnection, in_start: Option<&DateTime<Utc>>,
+        in_stop: Option<&DateTime<Utc>>) -> Result<Vec<SatelliteStateVector>, diesel::result::Error>
+    {
+        let mut query = t_ephemeris::table
+            .filter( t_ephemeris::run_id.eq(&self.id) )
+            .into_boxed();
+
+        if let Some(t) = in_start {
+            query = query.filter( t_ephemeris::epoch.ge( epoch_to_db(t) ) );
+        }
+
+        if let Some(t) = in_stop {
+            query = query.filter( t_ephemeris::epoch.le( epoch_to_db(t) ) );
+        }
+
+        let tmp_points = query.order( t_ephemeris::epoch.asc() ).load::<EphemerisDb>(conn)?;
+
+        Ok( tmp_points.into_iter().map(|p| SatelliteStateVector {
+            time:         p.epoch,
+            position:     [p.x, p.y, p.z],
+            velocity:     [p.vx, p.vy, p.vz],
+            latitude:     None,
+            longitude:    None,
+            altitude:     None,
+        }).collect() )
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    use crate::db::create_module_tables;
+
+    fn state_vector(in_time: &DateTime<Utc>, in_x: f64) -> SatelliteStateVector
+    {
+        SatelliteStateVector {
+            time:         in_time.to_rfc3339(),
+            position:     [in_x, 0.0, 0.0],
+            velocity:     [0.0, 7.5, 0.0],
+            latitude:     None,
+            longitude:    None,
+            altitude:     None,
+        }
+    }
+
+    #[test]
+    fn runs_are_versioned_and_queried_by_time() {
+        let conn = SqliteConnection::establish(":memory:").unwrap();
+        create_module_tables(&conn).unwrap();
+
+        let tmp_mission = String::from("mis1");
+        let tmp_satellite = String::from("sat1");
+        let tmp_frame = String::from("EME2000");
+
+        let tmp_start = Utc.ymd(2020, 7, 13).and_hms(0, 0, 0);
+        let tmp_ephemeris : Vec<SatelliteStateVector> = (0..5)
+            .map(|i| state_vector(&(tmp_start + chrono::Duration::seconds(60 * i)), 7000.0 + i as f64))
+            .collect();
+        let tmp_stop = tmp_start + chrono::Duration::seconds(240);
+
+        let first = EphemerisRunDb::insert_db(&conn, &tmp_mission, &tmp_satellite, &tmp_frame, &tmp_start, &tmp_stop, 60,
+            Some(25544), &tmp_ephemeris).unwrap();
+        let second = EphemerisRunDb::insert_db(&conn, &tmp_mission, &tmp_satellite, &tmp_frame, &tmp_start, &tmp_stop, 60,
+            Some(25544), &tmp_ephemeris[..3]).unwrap();
+
+        assert_eq!(first.version, 1);
+        assert_eq!(second.version, 2);
+        assert_eq!(EphemerisRunDb::list(&conn, &tmp_mission, &tmp_satellite).unwrap().len(), 2);
+
+        let last = EphemerisRunDb::by_version(&conn, &tmp_mission, &tmp_satellite, None).unwrap();
+        assert_eq!(last.version, 2);
+        assert_eq!(last.number_points, 3);
+
+        let tmp_from = tmp_start + chrono::Duration::seconds(60);
+        let tmp_to = tmp_start + chrono::Duration::seconds(180);
+        let points = first.ephemeris(&conn, Some(&tmp_from), Some(&tmp_to)).unwrap();
+
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].position[0], 7001.0);
+        assert_eq!(points[2].position[0], 7003.0);
+
+        assert!( EphemerisRunDb::by_version(&conn, &tmp_mission, &String::from("sat2"), None).is_none() );
+    }
+}
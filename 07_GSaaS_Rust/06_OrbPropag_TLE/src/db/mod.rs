@@ -0,0 +1,72 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * DB Module definition
+ */
+
+// List of 'modules' = files that compose the 'db' crate = package/lib
+pub mod antenna;
+pub mod ephemeris;
+pub mod satellite;
+pub mod tle;
+pub mod schema;
+
+
+use std::path::Path;
+
+use diesel::sqlite::SqliteConnection;
+use diesel::connection::SimpleConnection;
+
+use diesel::r2d2::ConnectionManager;
+use r2d2::Pool;
+
+pub type DbPool = Pool<ConnectionManager<SqliteConnection>>;
+
+/**
+ * Pool of connections of the database of this module (ephemeris and TLE catalog)
+ * It is a different type so both pools can be shared with the HTTP handlers
+ */
+#[derive(Clone)]
+pub struct EphemerisDbPool(pub DbPool);
+
+
+
+// Tables of the database of this module. Migrations are applied in order
+const MODULE_MIGRATIONS : [&str; 2] = [
+    include_str!("../../migrations/2021-07-25-100000_create_ephemeris/up.sql"),
+    include_str!("../../migrations/2021-07-28-090000_create_tle/up.sql"),
+];
+
+
+/**
+ * Create the tables of the database of this module (ephemeris and TLE catalog) if they do not exist
+ */
+pub fn create_module_tables(conn: &SqliteConnection) -> Result<(), diesel::result::Error>
+{
+    for current_migration in MODULE_MIGRATIONS.iter() {
+        conn.batch_execute(current_migration)?;
+    }
+
+    Ok(())
+}
+
+/**
+ * Establish a connection with a SQLite database and create a pool of connections
+ * The database shall exist. SQLite would create an empty one otherwise
+ */
+pub fn establish_connection(in_database_url: &String) -> Result<DbPool, String> 
+{
+    if Path::new(in_database_url.as_str()).exists() == false {
+        return Err( format!("Database file not found: {}", in_database_url) );
+    }
+
+    let manager = ConnectionManager::<SqliteConnection>::new(in_database_url.as_str());
+
+    r2d2::Pool::builder()
+        .build(manager)
+        .map_err(|e| format!("Failed to create DB pool: {}", e.to_string()) )
+}
@@ -0,0 +1,43 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Read only access to the satellites of the Tools module
+ */
+
+// JSON serialization
+use serde::{Deserialize, Serialize};
+
+// Diesel
+use diesel::prelude::*;
+
+// Database
+use crate::db::schema::*;
+
+
+#[derive(Debug, Deserialize, Serialize, Queryable)]
+pub struct SatelliteDb
+{
+    pub id:             String,
+    pub mission_id:     String,
+    pub name:           String,
+}
+
+
+impl SatelliteDb 
+{
+    pub fn by_id(conn: &SqliteConnection, in_id: &String) -> Option<Self> 
+    {
+        if in_id.is_empty() == true {
+            return None;
+        }
+
+        match t_satellite::table.find(in_id).first::<SatelliteDb>(conn) {
+            Ok(m) => Some(m),
+            Err(_e) => None,
+        }
+    }
+}
@@ -0,0 +1,102 @@
+// (c) Incomplete Worlds 2021
+// Alberto Fernandez (ajfg)
+//
+// GS as a Service
+// Orbit Propagation - SGP4 - TLE
+//
+// Tables of the Tools database used by this module. See 04_Tools/src/db/schema.rs
+// Tables of the ephemeris database. See migrations
+// NORAD ids are 32 bits unsigned. They are read as BigInt; INTEGER columns of SQLite have 64 bits
+
+table! {
+    t_ground_station (id) {
+        id -> Text,
+        name -> Text,
+        owner -> Text,
+        created -> Timestamp,
+    }
+}
+
+table! {
+    t_antenna (id) {
+        id -> Text,
+        name -> Text,
+        station_id -> Text,
+        latitude -> Double,
+        longitude -> Double,
+        altitude -> Double,
+        created -> Timestamp,
+    }
+}
+
+table! {
+    t_satellite (id) {
+        id -> Text,
+        mission_id -> Text,
+        name -> Text,
+    }
+}
+
+table! {
+    t_ephemeris_run (id) {
+        id -> Text,
+        mission_id -> Text,
+        satellite_id -> Text,
+        version -> Integer,
+        reference_frame -> Text,
+        start_time -> Text,
+        stop_time -> Text,
+        step_size -> Integer,
+        norad_id -> Nullable<BigInt>,
+        number_points -> Integer,
+        created -> Text,
+    }
+}
+
+table! {
+    t_ephemeris (run_id, epoch) {
+        run_id -> Text,
+        epoch -> Text,
+        x -> Double,
+        y -> Double,
+        z -> Double,
+        vx -> Double,
+        vy -> Double,
+        vz -> Double,
+    }
+}
+
+table! {
+    t_tle (id) {
+        id -> Text,
+        norad_id -> BigInt,
+        name -> Nullable<Text>,
+        epoch -> Text,
+        line1 -> Text,
+        line2 -> Text,
+        created -> Text,
+    }
+}
+
+table! {
+    t_satellite_tle (satellite_id) {
+        satellite_id -> Text,
+        norad_id -> BigInt,
+        created -> Text,
+    }
+}
+
+
+joinable!(t_antenna   -> t_ground_station (station_id));
+joinable!(t_ephemeris -> t_ephemeris_run (run_id));
+
+
+allow_tables_to_appear_in_same_query!(
+    t_ground_station,
+    t_antenna,
+    t_satellite,
+    t_ephemeris_run,
+    t_ephemeris,
+    t_tle,
+    t_satellite_tle,
+);
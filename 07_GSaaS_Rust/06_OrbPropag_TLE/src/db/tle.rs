@@ -0,0 +1,269 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * TLE catalog. Every TLE is stored per NORAD id and epoch
+ * The satellites of the Tools module are linked to a NORAD id
+ */
+
+// JSON serialization
+use serde::{Deserialize, Serialize};
+
+// Time
+use chrono::{DateTime, Utc};
+
+// Diesel
+use diesel::prelude::*;
+
+// UUID
+use uuid::Uuid;
+
+// Database
+use crate::db::schema::*;
+use crate::db::ephemeris::epoch_to_db;
+
+use crate::api_messages::*;
+use crate::tle::elements_from_tle;
+
+
+#[derive(Debug, Deserialize, Serialize, Queryable, Insertable)]
+#[table_name="t_tle"]
+pub struct TleDb
+{
+    pub id:             String,
+    pub norad_id:       i64,
+    pub name:           Option<String>,
+    // Format:  YYYY-MM-DDTHH:MM:SS.ffffffZ
+    pub epoch:          String,
+    pub line1:          String,
+    pub line2:          String,
+    // Format:  YYYY-MM-DDTHH:MM:SS
+    pub created:        String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Queryable, Insertable)]
+#[table_name="t_satellite_tle"]
+pub struct SatelliteTleDb
+{
+    pub satellite_id:   String,
+    pub norad_id:       i64,
+    // Format:  YYYY-MM-DDTHH:MM:SS
+    pub created:        String,
+}
+
+
+impl TleDb
+{
+    /**
+     * Create a new record. The NORAD id and the epoch are read from the elements
+     */
+    pub fn new(in_tle: &TleStruct, in_elements: &sgp4::Elements) -> Self
+    {
+        TleDb {
+            id:             Uuid::new_v4().to_hyphenated().to_string(),
+            norad_id:       in_elements.norad_id as i64,
+            name:           in_tle.name.clone(),
+            epoch:          epoch_to_db( &DateTime::<Utc>::from_utc(in_elements.datetime, Utc) ),
+            line1:          in_tle.line1.clone(),
+            line2:          in_tle.line2.clone(),
+            created:        Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        }
+    }
+
+    /**
+     * Insert a list of TLEs in one transaction
+     * A TLE with the same NORAD id and epoch of a stored one is ignored. Return the number of inserted TLEs
+     */
+    pub fn insert_db(conn: &SqliteConnection, in_tles: &[TleDb]) -> Result<usize, diesel::result::Error>
+    {
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            let mut output = 0;
+
+            for current_tle in in_tles.iter() {
+                output += diesel::insert_or_ignore_into(t_tle::table).values(current_tle).execute(conn)?;
+            }
+
+            Ok(output)
+        })
+    }
+
+    /**
+     * Return the TLE whose epoch is the closest one to the given time
+     */
+    pub fn closest(conn: &SqliteConnection, in_norad_id: i64, in_time: &DateTime<Utc>) -> Option<Self>
+    {
+        let tmp_time = epoch_to_db(in_time);
+
+        let tmp_before = t_tle::table
+            .filter( t_tle::norad_id.eq(in_norad_id) )
+            .filter( t_tle::epoch.le(&tmp_time) )
+            .order( t_tle::epoch.desc() )
+            .first::<TleDb>(conn)
+            .ok();
+
+        let tmp_after = t_tle::table
+            .filter( t_tle::norad_id.eq(in_norad_id) )
+            .filter( t_tle::epoch.gt(&tmp_time) )
+            .order( t_tle::epoch.asc() )
+            .first::<TleDb>(conn)
+            .ok();
+
+        let tmp_distance = |in_tle: &TleDb| -> i64 {
+            match DateTime::parse_from_rfc3339(in_tle.epoch.as_str()) {
+                Ok(t) => in_time.signed_duration_since(t.with_timezone(&Utc)).num_milliseconds().abs(),
+                Err(_) => i64::MAX,
+            }
+        };
+
+        match (tmp_before, tmp_after) {
+            (Some(b), Some(a)) => if tmp_distance(&b) <= tmp_distance(&a) { Some(b) } else { Some(a) },
+            (Some(b), None)    => Some(b),
+            (None, a)          => a,
+        }
+    }
+
+    /**
+     * Return the TLE of the catalog of a satellite whose epoch is the closest one to the given time
+     */
+    pub fn by_satellite(conn: &SqliteConnection, in_satellite_id: &String, in_time: &DateTime<Utc>) -> Result<Self, String>
+    {
+        let tmp_link = SatelliteTleDb::by_satellite(conn, in_satellite_id)
+            .ok_or( format!("ERROR: Satellite not linked to the TLE catalog: {}", in_satellite_id) )?;
+
+        TleDb::closest(conn, tmp_link.norad_id, in_time)
+            .ok_or( format!("ERROR: No TLE in the catalog. Satellite: {}  NORAD Id: {}", in_satellite_id, tmp_link.norad_id) )
+    }
+
+    /**
+     * List the TLEs of a NORAD id between two epochs (both included). Oldest first
+     */
+    pub fn history(conn: &SqliteConnection, in_norad_id: i64, in_start: Option<&DateTime<Utc>>,
+        in_stop: Option<&DateTime<Utc>>) -> Result<Vec<Self>, diesel::result::Error>
+    {
+        let mut query = t_tle::table
+            .filter( t_tle::norad_id.eq(in_norad_id) )
+            .into_boxed();
+
+        if let Some(t) = in_start {
+            query = query.filter( t_tle::epoch.ge( epoch_to_db(t) ) );
+        }
+
+        if let Some(t) = in_stop {
+            query = query.filter( t_tle::epoch.le( epoch_to_db(t) ) );
+        }
+
+        query.order( t_tle::epoch.asc() ).load::<TleDb>(conn)
+    }
+
+    /**
+     * SGP4 elements of the stored TLE
+     */
+    pub fn elements(&self) -> Result<sgp4::Elements, String>
+    {
+        let tmp_tle = TleStruct { name: self.name.clone(), line1: self.line1.clone(), line2: self.line2.clone() };
+
+        elements_from_tle(&tmp_tle)
+            .map_err(|e| format!("ERROR: Invalid TLE in the catalog. NORAD Id: {}. {}", self.norad_id, e) )
+    }
+}
+
+impl SatelliteTleDb
+{
+    /**
+     * Link a satellite with a NORAD id. A previous link of the satellite is replaced
+     */
+    pub fn insert_db(conn: &SqliteConnection, in_satellite_id: &String, in_norad_id: i64) -> Result<Self, diesel::result::Error>
+    {
+        let new_link = SatelliteTleDb {
+            satellite_id:   in_satellite_id.clone(),
+            norad_id:       in_norad_id,
+            created:        Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        };
+
+        diesel::replace_into(t_satellite_tle::table).values(&new_link).execute(conn)?;
+
+        Ok(new_link)
+    }
+
+    pub fn by_satellite(conn: &SqliteConnection, in_satellite_id: &String) -> Option<Self>
+    {
+        if in_satellite_id.is_empty() == true {
+            return None;
+        }
+
+        match t_satellite_tle::table.find(in_satellite_id).first::<SatelliteTleDb>(conn) {
+            Ok(m) => Some(m),
+            Err(_e) => None,
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    use crate::db::create_module_tables;
+
+    // Same object. Epochs 2006-06-25 and 2006-06-26
+    const TLE_06251_A : (&str, &str) = (
+        "1 06251U 62025E   06176.82412014  .00008885  00000-0  12808-3 0  3985",
+        "2 06251  58.0579  54.0425 0030035 139.1568 221.1854 15.56387291  6774");
+    const TLE_06251_B : (&str, &str) = (
+        "1 06251U 62025E   06177.82412014  .00008885  00000-0  12808-3 0  3985",
+        "2 06251  58.0579  54.0425 0030035 139.1568 221.1854 15.56387291  6774");
+
+    fn tle_db(in_lines: (&str, &str)) -> TleDb
+    {
+        let tmp_tle = TleStruct { name: None, line1: String::from(in_lines.0), line2: String::from(in_lines.1) };
+        let tmp_elements = sgp4::Elements::from_tle(None, in_lines.0.as_bytes(), in_lines.1.as_bytes()).unwrap();
+
+        TleDb::new(&tmp_tle, &tmp_elements)
+    }
+
+    #[test]
+    fn closest_tle_of_a_linked_satellite() {
+        let conn = SqliteConnection::establish(":memory:").unwrap();
+        create_module_tables(&conn).unwrap();
+
+        let tmp_tles = vec![ tle_db(TLE_06251_A), tle_db(TLE_06251_B), tle_db(TLE_06251_A) ];
+
+        // The last one is a duplicate
+        assert_eq!( TleDb::insert_db(&conn, &tmp_tles).unwrap(), 2 );
+
+        let tmp_satellite = String::from("sat1");
+        assert!( TleDb::by_satellite(&conn, &tmp_satellite, &Utc.ymd(2006, 6, 26).and_hms(0, 0, 0)).is_err() );
+
+        SatelliteTleDb::insert_db(&conn, &tmp_satellite, 6251).unwrap();
+
+        let tmp_tle = TleDb::by_satellite(&conn, &tmp_satellite, &Utc.ymd(2006, 6, 26).and_hms(0, 0, 0)).unwrap();
+        assert!( tmp_tle.epoch.starts_with("2006-06-25") );
+
+        let tmp_tle = TleDb::by_satellite(&conn, &tmp_satellite, &Utc.ymd(2006, 6, 26).and_hms(12, 0, 0)).unwrap();
+        assert!( tmp_tle.epoch.starts_with("2006-06-26") );
+
+        // Before the first epoch
+        let tmp_tle = TleDb::by_satellite(&conn, &tmp_satellite, &Utc.ymd(2006, 1, 1).and_hms(0, 0, 0)).unwrap();
+        assert!( tmp_tle.epoch.starts_with("2006-06-25") );
+
+        let tmp_history = TleDb::history(&conn, 6251, Some(&Utc.ymd(2006, 6, 26).and_hms(0, 0, 0)), None).unwrap();
+        assert_eq!( tmp_history.len(), 1 );
+    }
+
+    #[test]
+    fn link_with_a_large_norad_id() {
+        let conn = SqliteConnection::establish(":memory:").unwrap();
+        create_module_tables(&conn).unwrap();
+
+        let tmp_satellite = String::from("sat1");
+        SatelliteTleDb::insert_db(&conn, &tmp_satellite, u32::MAX as i64).unwrap();
+
+        assert_eq!( SatelliteTleDb::by_satellite(&conn, &tmp_satellite).unwrap().norad_id, u32::MAX as i64 );
+    }
+}
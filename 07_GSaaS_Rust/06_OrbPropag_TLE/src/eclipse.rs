@@ -0,0 +1,348 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Eclipse computation. Analytical Sun ephemeris and conical Earth shadow model (umbra and penumbra)
+ * See Vallado, "Fundamentals of Astrodynamics and Applications", Algorithms 29 and 34
+ * and Montenbruck & Gill, "Satellite Orbits", section 3.4.2
+ */
+
+// Log
+use log::{debug};
+
+// Date & Time
+use chrono::{DateTime, Utc, Duration};
+
+use crate::propagation::*;
+use crate::frames::*;
+use crate::geodetic::WGS84_EQUATORIAL_RADIUS;
+
+
+// Astronomical unit. km
+pub const ASTRONOMICAL_UNIT : f64 = 149597870.7;
+// Radius of the Sun. km
+pub const SUN_RADIUS : f64 = 696000.0;
+
+// Step used for searching the shadow boundaries. Seconds
+// The shortest eclipse of a LEO satellite lasts several minutes
+const ECLIPSE_SEARCH_STEP : f64 = 30.0;
+
+// Accuracy of the entry and exit times. Seconds
+const ECLIPSE_TIME_TOLERANCE : f64 = 0.01;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnumEclipseEvent {
+    PENUMBRA_ENTRY,
+    PENUMBRA_EXIT,
+    UMBRA_ENTRY,
+    UMBRA_EXIT,
+}
+
+impl EnumEclipseEvent {
+    pub fn to_string(&self) -> String {
+        match *self {
+            EnumEclipseEvent::PENUMBRA_ENTRY   => String::from("PENUMBRA_ENTRY"),
+            EnumEclipseEvent::PENUMBRA_EXIT    => String::from("PENUMBRA_EXIT"),
+            EnumEclipseEvent::UMBRA_ENTRY      => String::from("UMBRA_ENTRY"),
+            EnumEclipseEvent::UMBRA_EXIT       => String::from("UMBRA_EXIT"),
+        }
+    }
+}
+
+/**
+ * Entry or exit of the Earth shadow
+ * Exit events include the duration since the matching entry, if it is inside the window
+ */
+#[derive(Debug, Clone)]
+pub struct EclipseEvent {
+    pub time:           DateTime<Utc>,
+    pub event:          EnumEclipseEvent,
+    // Seconds
+    pub duration:       Option<f64>,
+}
+
+
+/**
+ * Position of the Sun. km
+ * Low precision analytical ephemeris (0.01 degrees), mean equator and equinox of date.
+ * The difference with TEME is a few arc seconds, negligible for the shadow computation
+ */
+pub fn sun_position(in_time: &DateTime<Utc>) -> [f64; 3]
+{
+    // Julian centuries since J2000. UTC is used instead of UT1 and TDB
+    let tmp_t = (julian_date(in_time) - JD_J2000) / 36525.0;
+
+    let tmp_mean_longitude = 280.460 + 36000.771 * tmp_t;
+    let tmp_mean_anomaly = (357.5291092 + 35999.05034 * tmp_t) * DEG_TO_RAD;
+
+    let tmp_ecliptic_longitude = (tmp_mean_longitude + 1.914666471 * tmp_mean_anomaly.sin()
+                                  + 0.019994643 * (2.0 * tmp_mean_anomaly).sin()) * DEG_TO_RAD;
+
+    let tmp_distance = (1.000140612 - 0.016708617 * tmp_mean_anomaly.cos()
+                        - 0.000139589 * (2.0 * tmp_mean_anomaly).cos()) * ASTRONOMICAL_UNIT;
+
+    let tmp_obliquity = (23.439291 - 0.0130042 * tmp_t) * DEG_TO_RAD;
+
+    [
+        tmp_distance * tmp_ecliptic_longitude.cos(),
+        tmp_distance * tmp_obliquity.cos() * tmp_ecliptic_longitude.sin(),
+        tmp_distance * tmp_obliquity.sin() * tmp_ecliptic_longitude.sin(),
+    ]
+}
+
+fn norm(v: &[f64; 3]) -> f64
+{
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+/**
+ * Conical shadow geometry seen from the satellite. Radians
+ * Return (apparent radius of the Sun, apparent radius of the Earth, angle between the centres)
+ * An annular eclipse (the Earth fully inside the solar disk) is reported as penumbra
+ */
+fn shadow_angles(in_satellite: &[f64; 3], in_sun: &[f64; 3]) -> (f64, f64, f64)
+{
+    let tmp_to_sun = [in_sun[0] - in_satellite[0], in_sun[1] - in_satellite[1], in_sun[2] - in_satellite[2]];
+
+    let tmp_sun_distance = norm(&tmp_to_sun);
+    let tmp_earth_distance = norm(in_satellite);
+
+    let tmp_sun_radius = (SUN_RADIUS / tmp_sun_distance).asin();
+    let tmp_earth_radius = (WGS84_EQUATORIAL_RADIUS / tmp_earth_distance).min(1.0).asin();
+
+    let tmp_cos = -(in_satellite[0] * tmp_to_sun[0] + in_satellite[1] * tmp_to_sun[1] + in_satellite[2] * tmp_to_sun[2])
+                  / (tmp_earth_distance * tmp_sun_distance);
+
+    (tmp_sun_radius, tmp_earth_radius, tmp_cos.max(-1.0).min(1.0).acos())
+}
+
+/**
+ * Shadow function of the penumbra. Negative when the satellite is in penumbra or umbra
+ */
+fn penumbra_function(in_satellite: &[f64; 3], in_sun: &[f64; 3]) -> f64
+{
+    let (a, b, c) = shadow_angles(in_satellite, in_sun);
+    c - (a + b)
+}
+
+/**
+ * Shadow function of the umbra. Negative when the satellite is in umbra
+ */
+fn umbra_function(in_satellite: &[f64; 3], in_sun: &[f64; 3]) -> f64
+{
+    let (a, b, c) = shadow_angles(in_satellite, in_sun);
+    c - (b - a)
+}
+
+
+/**
+ * Compute the shadow functions at any time of the search window
+ * Times are seconds since the start of the window
+ */
+struct EclipsePredictor<'a> {
+    elements:       &'a sgp4::Elements,
+    constants:      sgp4::Constants,
+    start:          DateTime<Utc>,
+}
+
+impl<'a> EclipsePredictor<'a> {
+    fn time_at(&self, in_seconds: f64) -> DateTime<Utc>
+    {
+        self.start + Duration::microseconds( (in_seconds * 1.0e6).round() as i64 )
+    }
+
+    /**
+     * Return the penumbra and umbra functions
+     */
+    fn shadow_at(&self, in_seconds: f64) -> Result<(f64, f64), String>
+    {
+        let tmp_time = self.time_at(in_seconds);
+        let tmp_point = propagate_at(self.elements, &self.constants, &tmp_time)?;
+        let tmp_sun = sun_position(&tmp_time);
+
+        Ok( (penumbra_function(&tmp_point.position, &tmp_sun), umbra_function(&tmp_point.position, &tmp_sun)) )
+    }
+
+    /**
+     * Find the time where one of the shadow functions changes its sign (bisection)
+     */
+    fn find_boundary(&self, in_lower: f64, in_upper: f64, in_umbra: bool) -> Result<f64, String>
+    {
+        let select = |v: (f64, f64)| -> f64 { if in_umbra == true { v.1 } else { v.0 } };
+
+        let mut tmp_lower = in_lower;
+        let mut tmp_upper = in_upper;
+        let tmp_lower_sign = select(self.shadow_at(tmp_lower)?) < 0.0;
+
+        while tmp_upper - tmp_lower > ECLIPSE_TIME_TOLERANCE {
+            let tmp_middle = 0.5 * (tmp_lower + tmp_upper);
+
+            if (select(self.shadow_at(tmp_middle)?) < 0.0) == tmp_lower_sign {
+                tmp_lower = tmp_middle;
+            } else {
+                tmp_upper = tmp_middle;
+            }
+        }
+
+        Ok( 0.5 * (tmp_lower + tmp_upper) )
+    }
+}
+
+
+/**
+ * Compute the umbra and penumbra entry and exit times between start and stop time
+ * Events are sorted by time
+ */
+pub fn eclipse_events(in_elements: &sgp4::Elements, in_start: &DateTime<Utc>, in_stop: &DateTime<Utc>) -> Result<Vec<EclipseEvent>, String>
+{
+    if in_stop < in_start {
+        return Err( String::from("ERROR: Stop time is smaller than the start time") );
+    }
+
+    let predictor = EclipsePredictor {
+        elements:       in_elements,
+        constants:      sgp4::Constants::from_elements(in_elements)
+                            .map_err(|e| format!("ERROR: Unable to initialize SGP4: {}", e.to_string()) )?,
+        start:          *in_start,
+    };
+
+    let tmp_duration = in_stop.signed_duration_since(*in_start).num_milliseconds() as f64 / 1000.0;
+
+    let mut output : Vec<EclipseEvent> = Vec::new();
+
+    let mut tmp_previous_time = 0.0;
+    let mut tmp_previous = predictor.shadow_at(0.0)?;
+    let mut current_time = 0.0;
+
+    while current_time < tmp_duration {
+        current_time = (current_time + ECLIPSE_SEARCH_STEP).min(tmp_duration);
+
+        let tmp_current = predictor.shadow_at(current_time)?;
+
+        // Penumbra boundary. Entry before the umbra entry, exit after the umbra exit
+        if (tmp_previous.0 < 0.0) != (tmp_current.0 < 0.0) {
+            let tmp_time = predictor.find_boundary(tmp_previous_time, current_time, false)?;
+
+            output.push( EclipseEvent {
+                time:       predictor.time_at(tmp_time),
+                event:      if tmp_current.0 < 0.0 { EnumEclipseEvent::PENUMBRA_ENTRY } else { EnumEclipseEvent::PENUMBRA_EXIT },
+                duration:   None,
+            });
+        }
+
+        if (tmp_previous.1 < 0.0) != (tmp_current.1 < 0.0) {
+            let tmp_time = predictor.find_boundary(tmp_previous_time, current_time, true)?;
+
+            output.push( EclipseEvent {
+                time:       predictor.time_at(tmp_time),
+                event:      if tmp_current.1 < 0.0 { EnumEclipseEvent::UMBRA_ENTRY } else { EnumEclipseEvent::UMBRA_EXIT },
+                duration:   None,
+            });
+        }
+
+        tmp_previous_time = current_time;
+        tmp_previous = tmp_current;
+    }
+
+    output.sort_by(|a, b| a.time.cmp(&b.time));
+
+    // Duration of every eclipse
+    let mut tmp_penumbra_entry : Option<DateTime<Utc>> = None;
+    let mut tmp_umbra_entry : Option<DateTime<Utc>> = None;
+
+    for current_event in output.iter_mut() {
+        match current_event.event {
+            EnumEclipseEvent::PENUMBRA_ENTRY => tmp_penumbra_entry = Some(current_event.time),
+            EnumEclipseEvent::UMBRA_ENTRY    => tmp_umbra_entry = Some(current_event.time),
+            EnumEclipseEvent::PENUMBRA_EXIT  => {
+                current_event.duration = tmp_penumbra_entry.take()
+                    .map(|t| current_event.time.signed_duration_since(t).num_milliseconds() as f64 / 1000.0);
+            },
+            EnumEclipseEvent::UMBRA_EXIT     => {
+                current_event.duration = tmp_umbra_entry.take()
+                    .map(|t| current_event.time.signed_duration_since(t).num_milliseconds() as f64 / 1000.0);
+            },
+        };
+    }
+
+    debug!("Found {} eclipse events between {} and {}", output.len(), in_start, in_stop);
+
+    Ok(output)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn sun_position_vallado() {
+        // Vallado, Example 5-1. 2 April 2006 00:00 UTC
+        let tmp_sun = sun_position(&Utc.ymd(2006, 4, 2).and_hms(0, 0, 0));
+
+        assert!( (tmp_sun[0] / ASTRONOMICAL_UNIT - 0.9771945).abs() < 1.0e-5 );
+        assert!( (tmp_sun[1] / ASTRONOMICAL_UNIT - 0.1924424).abs() < 1.0e-5 );
+        assert!( (tmp_sun[2] / ASTRONOMICAL_UNIT - 0.0834308).abs() < 1.0e-5 );
+    }
+
+    #[test]
+    fn shadow_functions() {
+        let tmp_sun = [ASTRONOMICAL_UNIT, 0.0, 0.0];
+
+        // Sunlight
+        assert!( penumbra_function(&[7000.0, 0.0, 0.0], &tmp_sun) > 0.0 );
+        assert!( penumbra_function(&[0.0, 7000.0, 0.0], &tmp_sun) > 0.0 );
+
+        // Umbra, behind the Earth
+        assert!( umbra_function(&[-7000.0, 0.0, 0.0], &tmp_sun) < 0.0 );
+
+        // Penumbra. Close to the limb of the Earth, seen from the Sun
+        let tmp_satellite = [-1000.0, WGS84_EQUATORIAL_RADIUS, 0.0];
+        assert!( penumbra_function(&tmp_satellite, &tmp_sun) < 0.0 );
+        assert!( umbra_function(&tmp_satellite, &tmp_sun) > 0.0 );
+    }
+
+    #[test]
+    fn eclipses_of_iss() {
+        let elements = sgp4::Elements::from_tle(
+            Some(String::from("ISS (ZARYA)")),
+            "1 25544U 98067A   20194.88612269 -.00002218  00000-0 -31515-4 0  9992".as_bytes(),
+            "2 25544  51.6461 221.2784 0001413  89.1723 280.4612 15.49507896236008".as_bytes(),
+        ).unwrap();
+
+        let tmp_start = Utc.ymd(2020, 7, 13).and_hms(0, 0, 0);
+        let tmp_stop = tmp_start + Duration::hours(6);
+
+        let events = eclipse_events(&elements, &tmp_start, &tmp_stop).unwrap();
+
+        // Around four orbits
+        assert!( events.len() >= 12 );
+
+        for current_event in events.iter() {
+            if let Some(d) = current_event.duration {
+                match current_event.event {
+                    // Umbra of the ISS lasts around 35 minutes at most
+                    EnumEclipseEvent::UMBRA_EXIT    => assert!( d > 600.0 && d < 2400.0, "Umbra duration {}", d ),
+                    EnumEclipseEvent::PENUMBRA_EXIT => assert!( d > 600.0 && d < 2500.0, "Penumbra duration {}", d ),
+                    _ => panic!("Only exit events have a duration"),
+                }
+            }
+        }
+
+        // The umbra is always inside the penumbra
+        for (i, current_event) in events.iter().enumerate() {
+            if current_event.event == EnumEclipseEvent::UMBRA_ENTRY && i > 0 {
+                assert_eq!( events[i - 1].event, EnumEclipseEvent::PENUMBRA_ENTRY );
+            }
+            if current_event.event == EnumEclipseEvent::UMBRA_EXIT && i + 1 < events.len() {
+                assert_eq!( events[i + 1].event, EnumEclipseEvent::PENUMBRA_EXIT );
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Earth Orientation Parameters (EOP)
+ * It reads an EOP file in CelesTrak CSV format (https://celestrak.org/SpaceData/EOP-All.csv)
+ * DATE,MJD,X,Y,UT1-UTC,LOD,DPSI,DEPS,DX,DY,DAT,DATA_TYPE
+ */
+use std::fs;
+use std::collections::HashMap;
+use std::cmp::Ordering;
+
+// Log
+use log::{debug};
+
+// Date & Time
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+use crate::time_scales::*;
+
+
+/**
+ * Calendar date of a Modified Julian Date. MJD 0 = 17 Nov 1858 00:00:00
+ */
+fn mjd_to_date(in_mjd: f64) -> NaiveDateTime
+{
+    NaiveDate::from_ymd(1858, 11, 17).and_hms(0, 0, 0) + Duration::seconds( (in_mjd * 86400.0).round() as i64 )
+}
+
+
+/**
+ * Earth orientation parameters of one day
+ */
+#[derive(Debug, Clone)]
+pub struct EopRecord {
+    // Modified Julian Date (UTC)
+    pub mjd:        f64,
+    // Polar motion. Arc seconds
+    pub x_pole:     f64,
+    pub y_pole:     f64,
+    // UT1 - UTC. Seconds
+    pub ut1_utc:    f64,
+    // Length of day. Seconds
+    pub lod:        f64,
+    // TAI - UTC. Seconds
+    pub tai_utc:    f64,
+}
+
+impl EopRecord {
+    /**
+     * Record used when there is no EOP data for a date
+     */
+    pub fn new(in_mjd: f64) -> Self
+    {
+        EopRecord {
+            mjd:        in_mjd,
+            x_pole:     0.0,
+            y_pole:     0.0,
+            ut1_utc:    0.0,
+            lod:        0.0,
+            // Leap seconds table
+            tai_utc:    tai_utc( &mjd_to_date(in_mjd) ),
+        }
+    }
+}
+
+
+/**
+ * List of EOP records sorted by date
+ */
+#[derive(Debug, Clone)]
+pub struct EopData {
+    records:        Vec<EopRecord>,
+}
+
+impl EopData {
+    pub fn new() -> Self
+    {
+        EopData {
+            records:    Vec::new(),
+        }
+    }
+
+    /**
+     * Read an EOP file in CelesTrak CSV format
+     * A file without records is an error. Otherwise, the transformations would silently use zero values
+     */
+    pub fn load(in_filename: &String) -> Result<Self, Box<dyn std::error::Error>>
+    {
+        debug!("Reading EOP file: {}", in_filename);
+
+        if in_filename.is_empty() == true {
+            return Err("EOP file name is empty".into());
+        }
+
+        let tmp_content = fs::read_to_string(in_filename.as_str())?;
+
+        let output = EopData::parse(tmp_content.as_str())?;
+
+        if output.records.is_empty() == true {
+            return Err( format!("EOP file does not contain any record: {}. Download https://celestrak.org/SpaceData/EOP-All.csv", 
+                                in_filename).into() );
+        }
+
+        debug!("Read {} EOP records. From MJD {} to MJD {}", output.records.len(),
+            output.records[0].mjd, output.records[output.records.len() - 1].mjd);
+
+        Ok(output)
+    }
+
+    /**
+     * Parse the content of an EOP file in CelesTrak CSV format
+     * Lines starting with '#' are ignored
+     */
+    pub fn parse(in_content: &str) -> Result<Self, Box<dyn std::error::Error>>
+    {
+        let mut output = EopData::new();
+        let mut columns : HashMap<String, usize> = HashMap::new();
+
+        for current_line in in_content.lines() {
+            let current_line = current_line.trim();
+
+            if current_line.is_empty() == true || current_line.starts_with('#') == true {
+                continue;
+            }
+
+            let fields : Vec<&str> = current_line.split(',').map(|f| f.trim()).collect();
+
+            // The first line is the header
+            if columns.is_empty() == true {
+                for (i, current_name) in fields.iter().enumerate() {
+                    columns.insert(current_name.to_uppercase(), i);
+                }
+
+                for current_name in ["MJD", "X", "Y", "UT1-UTC", "LOD"].iter() {
+                    if columns.contains_key(*current_name) == false {
+                        return Err( format!("Column not found in EOP file: {}", current_name).into() );
+                    }
+                }
+                continue;
+            }
+
+            let get_value = |in_name: &str, in_default: f64| -> f64 {
+                match columns.get(in_name) {
+                    Some(i) => match fields.get(*i) {
+                        Some(v) => v.parse::<f64>().unwrap_or(in_default),
+                        None    => in_default,
+                    },
+                    None => in_default,
+                }
+            };
+
+            let tmp_mjd = get_value("MJD", 0.0);
+
+            output.records.push( EopRecord {
+                mjd:        tmp_mjd,
+                x_pole:     get_value("X", 0.0),
+                y_pole:     get_value("Y", 0.0),
+                ut1_utc:    get_value("UT1-UTC", 0.0),
+                lod:        get_value("LOD", 0.0),
+                tai_utc:    get_value("DAT", tai_utc( &mjd_to_date(tmp_mjd) )),
+            });
+        }
+
+        output.records.sort_by(|a, b| a.mjd.partial_cmp(&b.mjd).unwrap_or(Ordering::Equal) );
+
+        Ok(output)
+    }
+
+    /**
+     * Return the EOP values of a date (MJD UTC)
+     * Values are linearly interpolated between days. TAI-UTC is not interpolated
+     * Outside of the file, the closest record is used. Without data, zero values are returned
+     */
+    pub fn get(&self, in_mjd: f64) -> EopRecord
+    {
+        if self.records.is_empty() == true {
+            return EopRecord::new(in_mjd);
+        }
+
+        // Index of the first record after the date
+        let tmp_index = match self.records.binary_search_by(|r| r.mjd.partial_cmp(&in_mjd).unwrap_or(Ordering::Equal)) {
+            Ok(i)  => i + 1,
+            Err(i) => i,
+        };
+
+        if tmp_index == 0 {
+            return self.records[0].clone();
+        }
+
+        if tmp_index >= self.records.len() {
+            return self.records[self.records.len() - 1].clone();
+        }
+
+        let previous = &self.records[tmp_index - 1];
+        let next = &self.records[tmp_index];
+
+        let tmp_fraction = (in_mjd - previous.mjd) / (next.mjd - previous.mjd);
+        let interpolate = |a: f64, b: f64| -> f64 { a + (b - a) * tmp_fraction };
+
+        EopRecord {
+            mjd:        in_mjd,
+            x_pole:     interpolate(previous.x_pole, next.x_pole),
+            y_pole:     interpolate(previous.y_pole, next.y_pole),
+            // Do not interpolate over a leap second
+            ut1_utc:    if previous.tai_utc == next.tai_utc { interpolate(previous.ut1_utc, next.ut1_utc) } else { previous.ut1_utc },
+            lod:        interpolate(previous.lod, next.lod),
+            tai_utc:    previous.tai_utc,
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EOP_CSV : &str = "# Test data. Values are not real
+DATE,MJD,X,Y,UT1-UTC,LOD,DPSI,DEPS,DX,DY,DAT,DATA_TYPE
+2016-12-31,57753,0.100000,0.300000,0.4000000,0.0010000,0.0,0.0,0.0,0.0,36,O
+2017-01-01,57754,0.110000,0.310000,-0.5900000,0.0010000,0.0,0.0,0.0,0.0,37,O
+2017-01-02,57755,0.120000,0.320000,-0.5910000,0.0012000,0.0,0.0,0.0,0.0,37,O
+";
+
+    #[test]
+    fn known_date() {
+        let tmp_data = EopData::parse(EOP_CSV).unwrap();
+
+        let tmp_record = tmp_data.get(57754.5);
+        assert!( (tmp_record.x_pole - 0.115).abs() < 1.0e-9 );
+        assert!( (tmp_record.y_pole - 0.315).abs() < 1.0e-9 );
+        assert!( (tmp_record.ut1_utc + 0.5905).abs() < 1.0e-9 );
+        assert_eq!( tmp_record.tai_utc, 37.0 );
+
+        // UT1-UTC is not interpolated over the leap second
+        let tmp_record = tmp_data.get(57753.5);
+        assert!( (tmp_record.ut1_utc - 0.4).abs() < 1.0e-9 );
+        assert_eq!( tmp_record.tai_utc, 36.0 );
+    }
+
+    #[test]
+    fn empty_file_is_rejected() {
+        let tmp_filename = std::env::temp_dir().join("eop_header_only.csv");
+        fs::write(&tmp_filename, "DATE,MJD,X,Y,UT1-UTC,LOD,DPSI,DEPS,DX,DY,DAT,DATA_TYPE\n").unwrap();
+
+        assert!( EopData::load( &tmp_filename.to_string_lossy().to_string() ).is_err() );
+
+        let _ = fs::remove_file(&tmp_filename);
+    }
+
+    #[test]
+    fn shipped_file() {
+        // The shipped file shall contain real data. Otherwise, the service does not start
+        let tmp_filename = format!("{}/config/EOP-All.csv", env!("CARGO_MANIFEST_DIR"));
+
+        let tmp_data = EopData::load(&tmp_filename).unwrap();
+
+        // Any date inside the file has a leap second value and polar motion
+        let tmp_record = tmp_data.get(tmp_data.records[tmp_data.records.len() / 2].mjd);
+        assert!( tmp_record.tai_utc >= 10.0 );
+        assert!( tmp_record.x_pole != 0.0 || tmp_record.y_pole != 0.0 );
+    }
+
+    #[test]
+    fn leap_seconds_without_data() {
+        // 01 Jan 2010
+        assert_eq!( EopData::new().get(55197.0).tai_utc, 34.0 );
+    }
+}
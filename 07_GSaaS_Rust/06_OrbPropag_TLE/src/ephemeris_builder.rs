@@ -0,0 +1,150 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Generate the output products of one object; ephemeris in the output reference frame,
+ * sub-satellite points, ground track and events
+ */
+
+// Log
+use log::{debug};
+
+// Date & Time
+use chrono::{DateTime, Utc};
+
+// Serialize/Deserialize; YAML, JSON
+use serde_json::Value;
+
+use crate::api_messages::*;
+use crate::propagation::*;
+use crate::frames::*;
+use crate::eop::*;
+use crate::geodetic::*;
+use crate::eclipse::*;
+use crate::time_scales::*;
+use crate::writers::parse_ephemeris_time;
+
+
+/**
+ * Output products of one object
+ */
+pub struct EphemerisProducts {
+    pub ephemeris:          Vec<SatelliteStateVector>,
+    pub ground_track:       Option<Value>,
+    pub events:             Option<Vec<OrbitEventStruct>>,
+}
+
+
+/**
+ * Propagate the elements and generate the products selected in the output options
+ * The properties are added to the GeoJSON ground track
+ */
+pub fn build_ephemeris(in_elements: &sgp4::Elements, in_start: &DateTime<Utc>, in_stop: &DateTime<Utc>, in_step_size: u16,
+    in_output: &OutputTleStruct, in_eop: &EopData, in_track_properties: Value) -> Result<EphemerisProducts, String>
+{
+    // Propagate from start time to stop time, relative to the TLE epoch
+    let ephemeris = propagate(in_elements, in_start, in_stop, in_step_size)?;
+
+    // SGP4 generates TEME state vectors
+    let tmp_reference_frame = EnumReferenceFrame::from_string(in_output.reference_frame.as_str())
+        .ok_or( format!("ERROR: Invalid output reference frame: {}", in_output.reference_frame) )?;
+
+    debug!("Output reference frame: {}", tmp_reference_frame.to_string());
+
+    let tmp_geodetic = in_output.geodetic.unwrap_or(false);
+    let tmp_ground_track = in_output.ground_track.unwrap_or(false);
+
+    let mut output = EphemerisProducts {
+        ephemeris:          Vec::with_capacity(ephemeris.len()),
+        ground_track:       None,
+        events:             None,
+    };
+
+    let mut tmp_track : Vec<GroundLocation> = Vec::new();
+
+    for current_point in ephemeris.iter() {
+        // Sub-satellite point. It is always computed from the Earth fixed position
+        let tmp_location = if tmp_geodetic == true || tmp_ground_track == true {
+            let tmp_itrf = convert_from_teme(current_point, &EnumReferenceFrame::ITRF, in_eop);
+            Some( GroundLocation::from_ecef(&tmp_itrf.position) )
+        } else {
+            None
+        };
+
+        let current_point = convert_from_teme(current_point, &tmp_reference_frame, in_eop);
+
+        // Add current ephemeris to the output list
+        output.ephemeris.push( SatelliteStateVector {
+            time:         current_point.time.to_rfc3339(),
+            position:     current_point.position,
+            velocity:     current_point.velocity,
+            latitude:     if tmp_geodetic == true { tmp_location.as_ref().map(|l| l.latitude) } else { None },
+            longitude:    if tmp_geodetic == true { tmp_location.as_ref().map(|l| l.longitude) } else { None },
+            altitude:     if tmp_geodetic == true { tmp_location.as_ref().map(|l| l.altitude) } else { None },
+        });
+
+        if let Some(l) = tmp_location {
+            tmp_track.push(l);
+        }
+    }
+
+    if tmp_ground_track == true {
+        output.ground_track = Some( ground_track_geojson(&tmp_track, in_track_properties) );
+    }
+
+    if in_output.eclipse_events.unwrap_or(false) == true {
+        let tmp_events = eclipse_events(in_elements, in_start, in_stop)?;
+
+        output.events = Some( tmp_events.iter().map(|e| OrbitEventStruct {
+            time:         e.time.to_rfc3339(),
+            event:        e.event.to_string(),
+            duration:     e.duration,
+        }).collect() );
+    }
+
+    Ok(output)
+}
+
+/**
+ * Remove position and velocity from a JSON list of ephemeris. Only the geodetic coordinates are kept
+ */
+pub fn remove_cartesian_state(in_ephemeris: &mut Value)
+{
+    if let Some(tmp_ephemeris) = in_ephemeris.as_array_mut() {
+        for current_point in tmp_ephemeris.iter_mut() {
+            if let Some(tmp_object) = current_point.as_object_mut() {
+                tmp_object.remove("position");
+                tmp_object.remove("velocity");
+            }
+        }
+    }
+}
+
+/**
+ * Render the times of the ephemeris and events in the requested epoch format
+ * The products are generated in UTC (RFC 3339). UTCGregorian is not modified
+ */
+pub fn apply_epoch_format(in_ephemeris: &mut [SatelliteStateVector], in_events: Option<&mut Vec<OrbitEventStruct>>,
+    in_format: &EpochFormat)
+{
+    if in_format.scale == EnumTimeScale::UTC && in_format.representation == EnumEpochRepresentation::GREGORIAN {
+        return;
+    }
+
+    for current_point in in_ephemeris.iter_mut() {
+        if let Some(t) = parse_ephemeris_time(current_point.time.as_str()) {
+            current_point.time = format_epoch(&t, in_format);
+        }
+    }
+
+    if let Some(tmp_events) = in_events {
+        for current_event in tmp_events.iter_mut() {
+            if let Some(t) = parse_ephemeris_time(current_event.time.as_str()) {
+                current_event.time = format_epoch(&t, in_format);
+            }
+        }
+    }
+}
@@ -0,0 +1,474 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Reference frames transformations
+ * SGP4 produces state vectors in TEME (True Equator, Mean Equinox). They are converted to:
+ *  - EME2000 (J2000). IAU-1976 precession and IAU-1980 nutation
+ *  - GCRF. EME2000 plus the frame bias
+ *  - ITRF (Earth fixed). GMST 1982, Earth rotation and polar motion
+ * See Vallado et al. "Revisiting Spacetrack Report #3", AIAA 2006-6753
+ */
+use std::f64::consts::PI;
+
+// Date & Time
+use chrono::{DateTime, Utc};
+
+use crate::eop::*;
+use crate::time_scales::tai_utc;
+use crate::propagation::EphemerisPoint;
+
+
+// Matrix 3x3
+pub type Matrix3 = [[f64; 3]; 3];
+
+pub const DEG_TO_RAD : f64 = PI / 180.0;
+pub const ARCSEC_TO_RAD : f64 = PI / (180.0 * 3600.0);
+
+// Julian date of J2000 epoch
+pub const JD_J2000 : f64 = 2451545.0;
+// Julian date of the Unix epoch (1 Jan 1970)
+pub const JD_UNIX_EPOCH : f64 = 2440587.5;
+// Offset between Julian Date and Modified Julian Date
+pub const MJD_OFFSET : f64 = 2400000.5;
+
+// Nominal Earth rotation rate. rad/s
+pub const EARTH_ROTATION_RATE : f64 = 7.29211514670698e-5;
+
+// TT - TAI. Seconds
+pub const TT_TAI : f64 = 32.184;
+
+
+/**
+ * Reference frames supported in the output
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnumReferenceFrame {
+    TEME,
+    EME2000,
+    GCRF,
+    ITRF,
+}
+
+impl EnumReferenceFrame {
+    pub fn to_string(&self) -> String {
+        match *self {
+            EnumReferenceFrame::TEME      => String::from("TEME"),
+            EnumReferenceFrame::EME2000   => String::from("EME2000"),
+            EnumReferenceFrame::GCRF      => String::from("GCRF"),
+            EnumReferenceFrame::ITRF      => String::from("ITRF"),
+        }
+    }
+
+    /**
+     * Return None if the name is not a known reference frame
+     * GMAT names are also accepted
+     */
+    pub fn from_string(in_frame: &str) -> Option<Self> {
+        match in_frame {
+            "TEME"                                      => Some(EnumReferenceFrame::TEME),
+            "EarthMJ2000Eq" | "EME2000" | "J2000"       => Some(EnumReferenceFrame::EME2000),
+            "EarthICRF" | "GCRF" | "ICRF"               => Some(EnumReferenceFrame::GCRF),
+            "EarthFixed" | "ITRF" | "ECEF"              => Some(EnumReferenceFrame::ITRF),
+            _                                           => None,
+        }
+    }
+}
+
+
+// IAU-1980 nutation series. Largest terms, truncation error below 0.02 arcsec
+// Multipliers of l, l', F, D, Omega. Coefficients in 0.0001 arcsec: A + B*T (psi), C + D*T (epsilon)
+const NUTATION_1980 : [([f64; 5], [f64; 4]); 18] = [
+    ([ 0.0,  0.0,  0.0,  0.0,  1.0], [-171996.0, -174.2, 92025.0,  8.9]),
+    ([ 0.0,  0.0,  2.0, -2.0,  2.0], [ -13187.0,   -1.6,  5736.0, -3.1]),
+    ([ 0.0,  0.0,  2.0,  0.0,  2.0], [  -2274.0,   -0.2,   977.0, -0.5]),
+    ([ 0.0,  0.0,  0.0,  0.0,  2.0], [   2062.0,    0.2,  -895.0,  0.5]),
+    ([ 0.0,  1.0,  0.0,  0.0,  0.0], [   1426.0,   -3.4,    54.0, -0.1]),
+    ([ 1.0,  0.0,  0.0,  0.0,  0.0], [    712.0,    0.1,    -7.0,  0.0]),
+    ([ 0.0,  1.0,  2.0, -2.0,  2.0], [   -517.0,    1.2,   224.0, -0.6]),
+    ([ 0.0,  0.0,  2.0,  0.0,  1.0], [   -386.0,   -0.4,   200.0,  0.0]),
+    ([ 1.0,  0.0,  2.0,  0.0,  2.0], [   -301.0,    0.0,   129.0, -0.1]),
+    ([ 0.0, -1.0,  2.0, -2.0,  2.0], [    217.0,   -0.5,   -95.0,  0.3]),
+    ([ 1.0,  0.0,  0.0, -2.0,  0.0], [   -158.0,    0.0,    -1.0,  0.0]),
+    ([ 0.0,  0.0,  2.0, -2.0,  1.0], [    129.0,    0.1,   -70.0,  0.0]),
+    ([-1.0,  0.0,  2.0,  0.0,  2.0], [    123.0,    0.0,   -53.0,  0.0]),
+    ([ 1.0,  0.0,  0.0,  0.0,  1.0], [     63.0,    0.1,   -33.0,  0.0]),
+    ([ 0.0,  0.0,  0.0,  2.0,  0.0], [     63.0,    0.0,    -2.0,  0.0]),
+    ([-1.0,  0.0,  2.0,  2.0,  2.0], [    -59.0,    0.0,    26.0,  0.0]),
+    ([-1.0,  0.0,  0.0,  0.0,  1.0], [    -58.0,   -0.1,    32.0,  0.0]),
+    ([ 1.0,  0.0,  2.0,  0.0,  1.0], [    -51.0,    0.0,    27.0,  0.0]),
+];
+
+
+//
+// ====================================================================
+// Vector and matrix operations
+// ====================================================================
+//
+
+/**
+ * Coordinate (passive) rotation around the X axis
+ */
+pub fn rot1(in_angle: f64) -> Matrix3
+{
+    let (s, c) = in_angle.sin_cos();
+
+    [[1.0, 0.0, 0.0],
+     [0.0,   c,   s],
+     [0.0,  -s,   c]]
+}
+
+/**
+ * Coordinate (passive) rotation around the Y axis
+ */
+pub fn rot2(in_angle: f64) -> Matrix3
+{
+    let (s, c) = in_angle.sin_cos();
+
+    [[  c, 0.0,  -s],
+     [0.0, 1.0, 0.0],
+     [  s, 0.0,   c]]
+}
+
+/**
+ * Coordinate (passive) rotation around the Z axis
+ */
+pub fn rot3(in_angle: f64) -> Matrix3
+{
+    let (s, c) = in_angle.sin_cos();
+
+    [[  c,   s, 0.0],
+     [ -s,   c, 0.0],
+     [0.0, 0.0, 1.0]]
+}
+
+pub fn mat_mul(a: &Matrix3, b: &Matrix3) -> Matrix3
+{
+    let mut output = [[0.0; 3]; 3];
+
+    for i in 0..3 {
+        for j in 0..3 {
+            output[i][j] = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j];
+        }
+    }
+
+    output
+}
+
+pub fn mat_vec(a: &Matrix3, v: &[f64; 3]) -> [f64; 3]
+{
+    [a[0][0] * v[0] + a[0][1] * v[1] + a[0][2] * v[2],
+     a[1][0] * v[0] + a[1][1] * v[1] + a[1][2] * v[2],
+     a[2][0] * v[0] + a[2][1] * v[1] + a[2][2] * v[2]]
+}
+
+pub fn transpose(a: &Matrix3) -> Matrix3
+{
+    [[a[0][0], a[1][0], a[2][0]],
+     [a[0][1], a[1][1], a[2][1]],
+     [a[0][2], a[1][2], a[2][2]]]
+}
+
+pub fn cross(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3]
+{
+    [a[1] * b[2] - a[2] * b[1],
+     a[2] * b[0] - a[0] * b[2],
+     a[0] * b[1] - a[1] * b[0]]
+}
+
+
+//
+// ====================================================================
+// Time
+// ====================================================================
+//
+
+/**
+ * Julian date of a UTC time
+ */
+pub fn julian_date(in_time: &DateTime<Utc>) -> f64
+{
+    let tmp_seconds = in_time.timestamp() as f64 + in_time.timestamp_subsec_nanos() as f64 * 1.0e-9;
+
+    JD_UNIX_EPOCH + tmp_seconds / 86400.0
+}
+
+/**
+ * Julian centuries since J2000
+ */
+fn julian_centuries(in_jd: f64) -> f64
+{
+    (in_jd - JD_J2000) / 36525.0
+}
+
+/**
+ * Greenwich Mean Sidereal Time (IAU-1982). Radians
+ * Input; Julian date in UT1
+ */
+pub fn gmst_1982(in_jd_ut1: f64) -> f64
+{
+    let t = julian_centuries(in_jd_ut1);
+
+    // Seconds
+    let tmp_gmst = 67310.54841 + (876600.0 * 3600.0 + 8640184.812866) * t + 0.093104 * t * t - 6.2e-6 * t * t * t;
+
+    let tmp_gmst = (tmp_gmst % 86400.0) / 240.0 * DEG_TO_RAD;
+
+    if tmp_gmst < 0.0 {
+        tmp_gmst + 2.0 * PI
+    } else {
+        tmp_gmst
+    }
+}
+
+
+//
+// ====================================================================
+// Precession, nutation
+// ====================================================================
+//
+
+/**
+ * Mean obliquity of the ecliptic (IAU-1980). Radians
+ * Input; Julian centuries TT
+ */
+fn mean_obliquity(t: f64) -> f64
+{
+    (84381.448 - 46.8150 * t - 0.00059 * t * t + 0.001813 * t * t * t) * ARCSEC_TO_RAD
+}
+
+/**
+ * Nutation in longitude and obliquity (IAU-1980). Radians
+ * Input; Julian centuries TT
+ */
+fn nutation_1980(t: f64) -> (f64, f64)
+{
+    let revolution = 360.0;
+
+    // Fundamental arguments. Degrees
+    let l       = 134.96298139 + (1325.0 * revolution + 198.8673981) * t + 0.0086972 * t * t + 1.78e-5 * t * t * t;
+    let l_prime = 357.52772333 + (99.0 * revolution + 359.0503400) * t - 0.0001603 * t * t - 3.3e-6 * t * t * t;
+    let f       = 93.27191028 + (1342.0 * revolution + 82.0175381) * t - 0.0036825 * t * t + 3.1e-6 * t * t * t;
+    let d       = 297.85036306 + (1236.0 * revolution + 307.1114800) * t - 0.0019142 * t * t + 5.3e-6 * t * t * t;
+    let omega   = 125.04452222 - (5.0 * revolution + 134.1362608) * t + 0.0020708 * t * t + 2.2e-6 * t * t * t;
+
+    let tmp_arguments = [l * DEG_TO_RAD, l_prime * DEG_TO_RAD, f * DEG_TO_RAD, d * DEG_TO_RAD, omega * DEG_TO_RAD];
+
+    let mut delta_psi = 0.0;
+    let mut delta_eps = 0.0;
+
+    for (multipliers, coefficients) in NUTATION_1980.iter() {
+        let tmp_angle : f64 = multipliers.iter().zip(tmp_arguments.iter()).map(|(m, a)| m * a).sum();
+
+        delta_psi += (coefficients[0] + coefficients[1] * t) * tmp_angle.sin();
+        delta_eps += (coefficients[2] + coefficients[3] * t) * tmp_angle.cos();
+    }
+
+    (delta_psi * 1.0e-4 * ARCSEC_TO_RAD, delta_eps * 1.0e-4 * ARCSEC_TO_RAD)
+}
+
+/**
+ * Precession matrix (IAU-1976). r_mod = P * r_j2000
+ * Input; Julian centuries TT
+ */
+fn precession_matrix(t: f64) -> Matrix3
+{
+    let zeta  = (2306.2181 * t + 0.30188 * t * t + 0.017998 * t * t * t) * ARCSEC_TO_RAD;
+    let theta = (2004.3109 * t - 0.42665 * t * t - 0.041833 * t * t * t) * ARCSEC_TO_RAD;
+    let z     = (2306.2181 * t + 1.09468 * t * t + 0.018203 * t * t * t) * ARCSEC_TO_RAD;
+
+    mat_mul( &rot3(-z), &mat_mul( &rot2(theta), &rot3(-zeta) ) )
+}
+
+/**
+ * Frame bias matrix. r_j2000 = B * r_gcrf
+ */
+fn frame_bias_matrix() -> Matrix3
+{
+    let d_alpha = -0.0146 * ARCSEC_TO_RAD;
+    let xi      = -0.016617 * ARCSEC_TO_RAD;
+    let eta     = -0.0068192 * ARCSEC_TO_RAD;
+
+    mat_mul( &rot1(-eta), &mat_mul( &rot2(xi), &rot3(d_alpha) ) )
+}
+
+/**
+ * Rotation matrix from TEME to EME2000 (J2000)
+ */
+fn teme_to_eme2000_matrix(in_jd_tt: f64) -> Matrix3
+{
+    let t = julian_centuries(in_jd_tt);
+
+    let (delta_psi, delta_eps) = nutation_1980(t);
+    let mean_eps = mean_obliquity(t);
+    let true_eps = mean_eps + delta_eps;
+
+    // Equation of the equinoxes
+    let eqeq = delta_psi * mean_eps.cos();
+
+    // r_tod = R3(-eqeq) * r_teme
+    let teme_to_tod = rot3(-eqeq);
+
+    // r_tod = N * r_mod
+    let nutation = mat_mul( &rot1(-true_eps), &mat_mul( &rot3(-delta_psi), &rot1(mean_eps) ) );
+
+    let precession = precession_matrix(t);
+
+    // r_j2000 = P' * N' * R3(-eqeq) * r_teme
+    mat_mul( &transpose(&precession), &mat_mul( &transpose(&nutation), &teme_to_tod ) )
+}
+
+
+//
+// ====================================================================
+// Transformations
+// ====================================================================
+//
+
+/**
+ * Convert a TEME state vector into EME2000 (J2000)
+ */
+pub fn teme_to_eme2000(in_point: &EphemerisPoint, in_eop: &EopRecord) -> EphemerisPoint
+{
+    let tmp_jd_tt = julian_date(&in_point.time) + (tai_utc(&in_point.time.naive_utc()) + TT_TAI) / 86400.0;
+
+    let tmp_matrix = teme_to_eme2000_matrix(tmp_jd_tt);
+
+    EphemerisPoint {
+        time:       in_point.time,
+        position:   mat_vec(&tmp_matrix, &in_point.position),
+        velocity:   mat_vec(&tmp_matrix, &in_point.velocity),
+    }
+}
+
+/**
+ * Convert a TEME state vector into GCRF
+ */
+pub fn teme_to_gcrf(in_point: &EphemerisPoint, in_eop: &EopRecord) -> EphemerisPoint
+{
+    let tmp_eme2000 = teme_to_eme2000(in_point, in_eop);
+
+    // r_gcrf = B' * r_j2000
+    let tmp_bias = transpose( &frame_bias_matrix() );
+
+    EphemerisPoint {
+        time:       in_point.time,
+        position:   mat_vec(&tmp_bias, &tmp_eme2000.position),
+        velocity:   mat_vec(&tmp_bias, &tmp_eme2000.velocity),
+    }
+}
+
+/**
+ * Convert a TEME state vector into ITRF (Earth fixed)
+ */
+pub fn teme_to_itrf(in_point: &EphemerisPoint, in_eop: &EopRecord) -> EphemerisPoint
+{
+    let tmp_jd_ut1 = julian_date(&in_point.time) + in_eop.ut1_utc / 86400.0;
+
+    // Pseudo Earth Fixed. r_pef = R3(gmst) * r_teme
+    let tmp_sidereal = rot3( gmst_1982(tmp_jd_ut1) );
+
+    let tmp_position_pef = mat_vec(&tmp_sidereal, &in_point.position);
+
+    let tmp_omega = [0.0, 0.0, EARTH_ROTATION_RATE * (1.0 - in_eop.lod / 86400.0)];
+    let tmp_omega_r = cross(&tmp_omega, &tmp_position_pef);
+    let tmp_velocity_pef = mat_vec(&tmp_sidereal, &in_point.velocity);
+    let tmp_velocity_pef = [tmp_velocity_pef[0] - tmp_omega_r[0],
+                            tmp_velocity_pef[1] - tmp_omega_r[1],
+                            tmp_velocity_pef[2] - tmp_omega_r[2]];
+
+    // Polar motion. r_pef = W * r_itrf
+    let x_pole = in_eop.x_pole * ARCSEC_TO_RAD;
+    let y_pole = in_eop.y_pole * ARCSEC_TO_RAD;
+    let tmp_polar_motion = transpose( &mat_mul( &rot1(y_pole), &rot2(x_pole) ) );
+
+    EphemerisPoint {
+        time:       in_point.time,
+        position:   mat_vec(&tmp_polar_motion, &tmp_position_pef),
+        velocity:   mat_vec(&tmp_polar_motion, &tmp_velocity_pef),
+    }
+}
+
+/**
+ * Convert a TEME state vector into the selected reference frame
+ */
+pub fn convert_from_teme(in_point: &EphemerisPoint, in_frame: &EnumReferenceFrame, in_eop_data: &EopData) -> EphemerisPoint
+{
+    let tmp_eop = in_eop_data.get( julian_date(&in_point.time) - MJD_OFFSET );
+
+    match *in_frame {
+        EnumReferenceFrame::TEME      => in_point.clone(),
+        EnumReferenceFrame::EME2000   => teme_to_eme2000(in_point, &tmp_eop),
+        EnumReferenceFrame::GCRF      => teme_to_gcrf(in_point, &tmp_eop),
+        EnumReferenceFrame::ITRF      => teme_to_itrf(in_point, &tmp_eop),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Duration};
+
+    // Vallado et al. "Revisiting Spacetrack Report #3", AIAA 2006-6753. Example of the Appendix C
+    fn vallado_example() -> (EphemerisPoint, EopRecord) {
+        let tmp_time = Utc.ymd(2004, 4, 6).and_hms(7, 51, 28) + Duration::microseconds(386009);
+
+        let tmp_point = EphemerisPoint {
+            time:       tmp_time,
+            position:   [5094.18016210, 6127.64465950, 6380.34453270],
+            velocity:   [-4.746131487, 0.785818041, 5.531931288],
+        };
+
+        let mut tmp_eop = EopRecord::new( julian_date(&tmp_time) - MJD_OFFSET );
+        tmp_eop.x_pole  = -0.140682;
+        tmp_eop.y_pole  = 0.333309;
+        tmp_eop.ut1_utc = -0.4399619;
+        tmp_eop.lod     = 0.0015563;
+        tmp_eop.tai_utc = 32.0;
+
+        (tmp_point, tmp_eop)
+    }
+
+    #[test]
+    fn teme_to_itrf_vallado() {
+        let (tmp_point, tmp_eop) = vallado_example();
+
+        let tmp_itrf = teme_to_itrf(&tmp_point, &tmp_eop);
+
+        let expected_position = [-1033.4793830, 7901.2952754, 6380.3565958];
+        let expected_velocity = [-3.225636520, -2.872451450, 5.531924446];
+
+        for i in 0..3 {
+            assert!( (tmp_itrf.position[i] - expected_position[i]).abs() < 1.0e-3, "{:?}", tmp_itrf.position );
+            assert!( (tmp_itrf.velocity[i] - expected_velocity[i]).abs() < 1.0e-6, "{:?}", tmp_itrf.velocity );
+        }
+    }
+
+    #[test]
+    fn teme_to_eme2000_vallado() {
+        let (tmp_point, tmp_eop) = vallado_example();
+
+        let tmp_eme2000 = teme_to_eme2000(&tmp_point, &tmp_eop);
+
+        // The nutation series is truncated. Accuracy of a few metres
+        let expected_position = [5102.5089579, 6123.0114007, 6378.1369282];
+        let expected_velocity = [-4.743220157, 0.790536497, 5.533755727];
+
+        for i in 0..3 {
+            assert!( (tmp_eme2000.position[i] - expected_position[i]).abs() < 5.0e-3, "{:?}", tmp_eme2000.position );
+            assert!( (tmp_eme2000.velocity[i] - expected_velocity[i]).abs() < 5.0e-6, "{:?}", tmp_eme2000.velocity );
+        }
+    }
+
+    #[test]
+    fn unknown_frames_are_rejected() {
+        assert_eq!( EnumReferenceFrame::from_string("EarthMJ2000Eq"), Some(EnumReferenceFrame::EME2000) );
+        assert_eq!( EnumReferenceFrame::from_string("ITRF"), Some(EnumReferenceFrame::ITRF) );
+        assert_eq!( EnumReferenceFrame::from_string("MarsFixed"), None );
+    }
+}
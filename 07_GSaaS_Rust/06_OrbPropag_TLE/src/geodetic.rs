@@ -0,0 +1,213 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Geodetic coordinates (WGS-84) and ground track
+ */
+
+// Serialize/Deserialize; YAML, JSON
+use serde_json::{json, Value};
+
+use crate::frames::*;
+
+
+// WGS-84 ellipsoid
+pub const WGS84_EQUATORIAL_RADIUS : f64 = 6378.137;
+pub const WGS84_FLATTENING : f64 = 1.0 / 298.257223563;
+
+// Maximum number of iterations of the ECEF to geodetic conversion
+const GEODETIC_MAX_ITERATIONS : usize = 10;
+
+
+/**
+ * Geodetic coordinates (WGS-84)
+ */
+#[derive(Debug, Clone)]
+pub struct GroundLocation {
+    // Degrees
+    pub latitude:       f64,
+    // Degrees. [-180, 180]
+    pub longitude:      f64,
+    // km
+    pub altitude:       f64,
+}
+
+impl GroundLocation {
+    pub fn new(in_latitude: f64, in_longitude: f64, in_altitude: f64) -> Self
+    {
+        GroundLocation {
+            latitude:       in_latitude,
+            longitude:      in_longitude,
+            altitude:       in_altitude,
+        }
+    }
+
+    /**
+     * Earth fixed position (ITRF). km
+     */
+    pub fn to_ecef(&self) -> [f64; 3]
+    {
+        let tmp_lat = self.latitude * DEG_TO_RAD;
+        let tmp_lon = self.longitude * DEG_TO_RAD;
+
+        let tmp_e2 = WGS84_FLATTENING * (2.0 - WGS84_FLATTENING);
+        let tmp_n = WGS84_EQUATORIAL_RADIUS / (1.0 - tmp_e2 * tmp_lat.sin().powi(2)).sqrt();
+
+        [
+            (tmp_n + self.altitude) * tmp_lat.cos() * tmp_lon.cos(),
+            (tmp_n + self.altitude) * tmp_lat.cos() * tmp_lon.sin(),
+            (tmp_n * (1.0 - tmp_e2) + self.altitude) * tmp_lat.sin(),
+        ]
+    }
+
+    /**
+     * Geodetic coordinates of an Earth fixed position (ITRF). km
+     * Iterative method. It converges to less than a millimeter in a few iterations
+     */
+    pub fn from_ecef(in_position: &[f64; 3]) -> Self
+    {
+        let tmp_e2 = WGS84_FLATTENING * (2.0 - WGS84_FLATTENING);
+        let tmp_p = (in_position[0] * in_position[0] + in_position[1] * in_position[1]).sqrt();
+
+        let tmp_lon = in_position[1].atan2(in_position[0]);
+        let mut tmp_lat = in_position[2].atan2(tmp_p * (1.0 - tmp_e2));
+        let mut tmp_alt = 0.0;
+
+        for _ in 0..GEODETIC_MAX_ITERATIONS {
+            let tmp_n = WGS84_EQUATORIAL_RADIUS / (1.0 - tmp_e2 * tmp_lat.sin().powi(2)).sqrt();
+
+            // Valid close to the poles too
+            tmp_alt = tmp_p * tmp_lat.cos() + (in_position[2] + tmp_e2 * tmp_n * tmp_lat.sin()) * tmp_lat.sin() - tmp_n;
+
+            let tmp_new_lat = in_position[2].atan2(tmp_p * (1.0 - tmp_e2 * tmp_n / (tmp_n + tmp_alt)));
+
+            if (tmp_new_lat - tmp_lat).abs() < 1.0e-12 {
+                tmp_lat = tmp_new_lat;
+                break;
+            }
+
+            tmp_lat = tmp_new_lat;
+        }
+
+        GroundLocation {
+            latitude:       tmp_lat / DEG_TO_RAD,
+            longitude:      tmp_lon / DEG_TO_RAD,
+            altitude:       tmp_alt,
+        }
+    }
+}
+
+
+/**
+ * Split the ground track in segments that do not cross the antimeridian
+ * Coordinates are [longitude, latitude], as expected by GeoJSON.
+ * The crossing point is added at the end of a segment (+-180) and at the start of the next one (-+180)
+ */
+pub fn split_ground_track(in_track: &[GroundLocation]) -> Vec<Vec<[f64; 2]>>
+{
+    let mut output : Vec<Vec<[f64; 2]>> = Vec::new();
+    let mut current_segment : Vec<[f64; 2]> = Vec::new();
+
+    for (i, current_location) in in_track.iter().enumerate() {
+        if i > 0 {
+            let previous = &in_track[i - 1];
+            let tmp_delta = current_location.longitude - previous.longitude;
+
+            if tmp_delta.abs() > 180.0 {
+                // Eastwards (+180 to -180) or westwards (-180 to +180)
+                let tmp_border = if tmp_delta < 0.0 { 180.0 } else { -180.0 };
+                let tmp_unwrapped = current_location.longitude + 2.0 * tmp_border;
+
+                let tmp_fraction = (tmp_border - previous.longitude) / (tmp_unwrapped - previous.longitude);
+                let tmp_latitude = previous.latitude + tmp_fraction * (current_location.latitude - previous.latitude);
+
+                current_segment.push( [tmp_border, tmp_latitude] );
+                output.push(current_segment);
+
+                current_segment = vec![ [-tmp_border, tmp_latitude] ];
+            }
+        }
+
+        current_segment.push( [current_location.longitude, current_location.latitude] );
+    }
+
+    if current_segment.is_empty() == false {
+        output.push(current_segment);
+    }
+
+    output
+}
+
+/**
+ * Return the ground track as a GeoJSON Feature (RFC 7946)
+ * The geometry is a LineString, or a MultiLineString if it crosses the antimeridian
+ */
+pub fn ground_track_geojson(in_track: &[GroundLocation], in_properties: Value) -> Value
+{
+    let tmp_segments = split_ground_track(in_track);
+
+    let tmp_geometry = if tmp_segments.len() == 1 {
+        json!({ "type": "LineString", "coordinates": tmp_segments[0] })
+    } else {
+        json!({ "type": "MultiLineString", "coordinates": tmp_segments })
+    };
+
+    json!({
+        "type":         "Feature",
+        "geometry":     tmp_geometry,
+        "properties":   in_properties,
+    })
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geodetic_to_ecef() {
+        // Vallado, Example 7-1. The example uses a slightly different eccentricity; a few meters
+        let tmp_location = GroundLocation::new(39.007, -104.883, 2.19456);
+        let tmp_ecef = tmp_location.to_ecef();
+
+        assert!( (tmp_ecef[0] - -1275.1219).abs() < 1.0e-2 );
+        assert!( (tmp_ecef[1] - -4797.9890).abs() < 1.0e-2 );
+        assert!( (tmp_ecef[2] -  3994.2975).abs() < 1.0e-2 );
+    }
+
+    #[test]
+    fn ecef_to_geodetic_round_trip() {
+        for (lat, lon, alt) in [(0.0, 0.0, 0.0), (39.007, -104.883, 2.19456), (-63.5, 171.2, 780.0), (89.99, 45.0, 35786.0)].iter() {
+            let tmp_location = GroundLocation::from_ecef( &GroundLocation::new(*lat, *lon, *alt).to_ecef() );
+
+            assert!( (tmp_location.latitude - lat).abs() < 1.0e-9 );
+            assert!( (tmp_location.longitude - lon).abs() < 1.0e-9 );
+            assert!( (tmp_location.altitude - alt).abs() < 1.0e-6 );
+        }
+    }
+
+    #[test]
+    fn ground_track_is_split_at_antimeridian() {
+        let tmp_track = vec![
+            GroundLocation::new(10.0, 170.0, 500.0),
+            GroundLocation::new(20.0, -170.0, 500.0),
+            GroundLocation::new(30.0, -150.0, 500.0),
+        ];
+
+        let tmp_segments = split_ground_track(&tmp_track);
+
+        assert_eq!(tmp_segments.len(), 2);
+        assert_eq!(tmp_segments[0], vec![ [170.0, 10.0], [180.0, 15.0] ]);
+        assert_eq!(tmp_segments[1], vec![ [-180.0, 15.0], [-170.0, 20.0], [-150.0, 30.0] ]);
+
+        let tmp_geojson = ground_track_geojson(&tmp_track, json!({}));
+        assert_eq!(tmp_geojson["geometry"]["type"], "MultiLineString");
+
+        let tmp_geojson = ground_track_geojson(&tmp_track[1..], json!({}));
+        assert_eq!(tmp_geojson["geometry"]["type"], "LineString");
+    }
+}
@@ -0,0 +1,289 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Interpolation of ephemeris at arbitrary epochs. Lagrange and Hermite polynomials
+ * using a window of points centered on the requested epoch
+ */
+
+// Date & Time
+use chrono::{DateTime, Utc};
+
+use crate::api_messages::*;
+use crate::propagation::*;
+use crate::writers::parse_ephemeris_time;
+
+
+// Default degree of the interpolation polynomial
+pub const DEFAULT_INTERPOLATION_ORDER : u16 = 7;
+
+// Maximum degree of the interpolation polynomial
+pub const MAX_INTERPOLATION_ORDER : u16 = 15;
+
+// Maximum number of epochs of a request
+pub const MAX_INTERPOLATION_EPOCHS : usize = 10000;
+
+
+/**
+ * Interpolation methods
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnumInterpolationMethod {
+    // Position and velocity are interpolated independently
+    LAGRANGE,
+    // Position and velocity are interpolated together. Velocity is the derivative of the position
+    HERMITE,
+}
+
+impl EnumInterpolationMethod {
+    pub fn to_string(&self) -> String {
+        match *self {
+            EnumInterpolationMethod::LAGRANGE   => String::from("LAGRANGE"),
+            EnumInterpolationMethod::HERMITE    => String::from("HERMITE"),
+        }
+    }
+
+    /**
+     * Return None if the name is not a known interpolation method
+     */
+    pub fn from_string(in_method: &str) -> Option<Self> {
+        match in_method.to_uppercase().as_str() {
+            "LAGRANGE"  => Some(EnumInterpolationMethod::LAGRANGE),
+            "HERMITE"   => Some(EnumInterpolationMethod::HERMITE),
+            _           => None,
+        }
+    }
+
+    /**
+     * Number of points used by a polynomial of the given degree
+     * Hermite uses two conditions per point (position and velocity)
+     */
+    pub fn number_points(&self, in_order: u16) -> usize {
+        match *self {
+            EnumInterpolationMethod::LAGRANGE   => in_order as usize + 1,
+            EnumInterpolationMethod::HERMITE    => ((in_order as usize + 2) / 2).max(2),
+        }
+    }
+}
+
+
+/**
+ * Convert a list of state vectors (RFC3339 times) into ephemeris points
+ */
+pub fn points_from_state_vectors(in_ephemeris: &[SatelliteStateVector]) -> Result<Vec<EphemerisPoint>, String>
+{
+    in_ephemeris.iter().map(|p| {
+        let tmp_time = parse_ephemeris_time(p.time.as_str())
+            .ok_or( format!("ERROR: Invalid ephemeris time: {}. It shall be in RFC 3339 format", p.time) )?;
+
+        Ok( EphemerisPoint {
+            time:       tmp_time,
+            position:   p.position,
+            velocity:   p.velocity,
+        })
+    }).collect()
+}
+
+/**
+ * Seconds between two times, keeping the microseconds
+ */
+fn seconds_between(in_from: &DateTime<Utc>, in_to: &DateTime<Utc>) -> f64
+{
+    let tmp_duration = in_to.signed_duration_since(*in_from);
+
+    match tmp_duration.num_microseconds() {
+        Some(us) => us as f64 / 1_000_000.0,
+        None     => tmp_duration.num_milliseconds() as f64 / 1000.0,
+    }
+}
+
+/**
+ * Lagrange polynomial through the points, evaluated at time t
+ */
+fn lagrange(in_times: &[f64], in_points: &[EphemerisPoint], in_t: f64) -> ([f64; 3], [f64; 3])
+{
+    let mut tmp_position = [0.0; 3];
+    let mut tmp_velocity = [0.0; 3];
+
+    for j in 0..in_times.len() {
+        let mut tmp_weight = 1.0;
+
+        for m in 0..in_times.len() {
+            if m != j {
+                tmp_weight *= (in_t - in_times[m]) / (in_times[j] - in_times[m]);
+            }
+        }
+
+        for k in 0..3 {
+            tmp_position[k] += tmp_weight * in_points[j].position[k];
+            tmp_velocity[k] += tmp_weight * in_points[j].velocity[k];
+        }
+    }
+
+    (tmp_position, tmp_velocity)
+}
+
+/**
+ * Hermite polynomial through the points (position and velocity), evaluated at time t
+ * Newton divided differences with every node repeated twice. The velocity is the derivative of the polynomial
+ */
+fn hermite(in_times: &[f64], in_points: &[EphemerisPoint], in_t: f64) -> ([f64; 3], [f64; 3])
+{
+    let tmp_size = 2 * in_times.len();
+    let tmp_nodes : Vec<f64> = (0..tmp_size).map(|i| in_times[i / 2]).collect();
+
+    let mut tmp_position = [0.0; 3];
+    let mut tmp_velocity = [0.0; 3];
+
+    for k in 0..3 {
+        let mut tmp_coefficients : Vec<f64> = (0..tmp_size).map(|i| in_points[i / 2].position[k]).collect();
+
+        for j in 1..tmp_size {
+            for i in (j..tmp_size).rev() {
+                if j == 1 && i % 2 == 1 {
+                    // Repeated node. The first divided difference is the derivative
+                    tmp_coefficients[i] = in_points[i / 2].velocity[k];
+                } else {
+                    tmp_coefficients[i] = (tmp_coefficients[i] - tmp_coefficients[i - 1]) / (tmp_nodes[i] - tmp_nodes[i - j]);
+                }
+            }
+        }
+
+        // Horner. Value and first derivative
+        let mut tmp_value = tmp_coefficients[tmp_size - 1];
+        let mut tmp_derivative = 0.0;
+
+        for i in (0..tmp_size - 1).rev() {
+            tmp_derivative = tmp_derivative * (in_t - tmp_nodes[i]) + tmp_value;
+            tmp_value = tmp_value * (in_t - tmp_nodes[i]) + tmp_coefficients[i];
+        }
+
+        tmp_position[k] = tmp_value;
+        tmp_velocity[k] = tmp_derivative;
+    }
+
+    (tmp_position, tmp_velocity)
+}
+
+/**
+ * Interpolate the ephemeris at every epoch
+ * The ephemeris shall be sorted by time. An epoch outside of the ephemeris span is an error
+ */
+pub fn interpolate(in_ephemeris: &[EphemerisPoint], in_epochs: &[DateTime<Utc>], in_method: &EnumInterpolationMethod,
+    in_order: u16) -> Result<Vec<EphemerisPoint>, String>
+{
+    if in_order == 0 || in_order > MAX_INTERPOLATION_ORDER {
+        return Err( format!("ERROR: Invalid interpolation order: {}. It shall be between 1 and {}", in_order, MAX_INTERPOLATION_ORDER) );
+    }
+
+    let tmp_number_points = in_method.number_points(in_order);
+
+    if in_ephemeris.len() < tmp_number_points {
+        return Err( format!("ERROR: Not enough ephemeris for a {} interpolation of order {}. Required: {}  Available: {}",
+                            in_method.to_string(), in_order, tmp_number_points, in_ephemeris.len()) );
+    }
+
+    for i in 1..in_ephemeris.len() {
+        if in_ephemeris[i].time <= in_ephemeris[i - 1].time {
+            return Err( format!("ERROR: The ephemeris times shall be strictly increasing: {}", in_ephemeris[i].time.to_rfc3339()) );
+        }
+    }
+
+    let tmp_first = &in_ephemeris[0].time;
+    let tmp_last = &in_ephemeris[in_ephemeris.len() - 1].time;
+
+    let mut output : Vec<EphemerisPoint> = Vec::with_capacity(in_epochs.len());
+
+    for current_epoch in in_epochs.iter() {
+        if current_epoch < tmp_first || current_epoch > tmp_last {
+            return Err( format!("ERROR: Epoch {} is outside of the ephemeris span [{}, {}]", current_epoch.to_rfc3339(),
+                                tmp_first.to_rfc3339(), tmp_last.to_rfc3339()) );
+        }
+
+        // Window centered on the epoch. It is moved inside at the borders of the ephemeris
+        let tmp_next = in_ephemeris.partition_point(|p| p.time <= *current_epoch);
+        let tmp_start = tmp_next.saturating_sub(tmp_number_points / 2).min(in_ephemeris.len() - tmp_number_points);
+
+        let tmp_window = &in_ephemeris[tmp_start..tmp_start + tmp_number_points];
+
+        // Times relative to the first point of the window
+        let tmp_times : Vec<f64> = tmp_window.iter().map(|p| seconds_between(&tmp_window[0].time, &p.time)).collect();
+        let tmp_t = seconds_between(&tmp_window[0].time, current_epoch);
+
+        let (tmp_position, tmp_velocity) = match *in_method {
+            EnumInterpolationMethod::LAGRANGE   => lagrange(&tmp_times, tmp_window, tmp_t),
+            EnumInterpolationMethod::HERMITE    => hermite(&tmp_times, tmp_window, tmp_t),
+        };
+
+        output.push( EphemerisPoint {
+            time:       *current_epoch,
+            position:   tmp_position,
+            velocity:   tmp_velocity,
+        });
+    }
+
+    Ok(output)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{Duration, TimeZone};
+
+    // Circular orbit. Radius in km, angular rate in rad/s
+    const RADIUS : f64 = 7000.0;
+    const RATE : f64 = 0.00108;
+
+    fn circular_orbit(in_start: &DateTime<Utc>, in_seconds: f64) -> EphemerisPoint
+    {
+        let tmp_angle = RATE * in_seconds;
+
+        EphemerisPoint {
+            time:       *in_start + Duration::microseconds((in_seconds * 1.0e6) as i64),
+            position:   [RADIUS * tmp_angle.cos(), RADIUS * tmp_angle.sin(), 0.0],
+            velocity:   [-RADIUS * RATE * tmp_angle.sin(), RADIUS * RATE * tmp_angle.cos(), 0.0],
+        }
+    }
+
+    #[test]
+    fn lagrange_and_hermite_on_circular_orbit() {
+        let tmp_start = Utc.ymd(2020, 7, 13).and_hms(0, 0, 0);
+        let tmp_ephemeris : Vec<EphemerisPoint> = (0..30).map(|i| circular_orbit(&tmp_start, 60.0 * i as f64)).collect();
+
+        // Between samples, close to both borders and at a sample
+        let tmp_seconds = [10.5, 95.25, 800.0, 1700.0, 1739.0];
+        let tmp_epochs : Vec<DateTime<Utc>> = tmp_seconds.iter().map(|s| circular_orbit(&tmp_start, *s).time).collect();
+
+        for current_method in [EnumInterpolationMethod::LAGRANGE, EnumInterpolationMethod::HERMITE].iter() {
+            let output = interpolate(&tmp_ephemeris, &tmp_epochs, current_method, 9).unwrap();
+
+            for (i, current_point) in output.iter().enumerate() {
+                let tmp_expected = circular_orbit(&tmp_start, tmp_seconds[i]);
+
+                for k in 0..3 {
+                    assert!( (current_point.position[k] - tmp_expected.position[k]).abs() < 1.0e-6 );
+                    assert!( (current_point.velocity[k] - tmp_expected.velocity[k]).abs() < 1.0e-8 );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn epochs_outside_of_the_span_are_rejected() {
+        let tmp_start = Utc.ymd(2020, 7, 13).and_hms(0, 0, 0);
+        let tmp_ephemeris : Vec<EphemerisPoint> = (0..10).map(|i| circular_orbit(&tmp_start, 60.0 * i as f64)).collect();
+
+        let tmp_after = tmp_start + Duration::seconds(541);
+        assert!( interpolate(&tmp_ephemeris, &[tmp_after], &EnumInterpolationMethod::LAGRANGE, 5).is_err() );
+
+        // Not enough points for the order
+        assert!( interpolate(&tmp_ephemeris, &[tmp_start], &EnumInterpolationMethod::LAGRANGE, 10).is_err() );
+        assert!( interpolate(&tmp_ephemeris, &[tmp_start], &EnumInterpolationMethod::HERMITE, 10).is_ok() );
+    }
+}
@@ -36,6 +36,7 @@ use actix_files as actixfs;
 // Important: It has to be included in the Root file
 #[macro_use]
 extern crate diesel;
+use diesel::sqlite::SqliteConnection;
 
 //#[macro_use]
 use lazy_static::lazy_static;
@@ -51,7 +52,60 @@ use config_tools::{config_log, read_config_json, ConfigVariables};
 
 mod api_messages;
 use api_messages::*;
-use sgp4::Prediction;
+
+mod propagation;
+use propagation::EphemerisPoint;
+
+mod writers;
+use writers::*;
+
+mod eop;
+use eop::*;
+
+mod frames;
+use frames::*;
+
+mod omm;
+use omm::*;
+
+mod geodetic;
+use geodetic::*;
+
+mod passes;
+use passes::*;
+
+mod eclipse;
+
+mod ephemeris_builder;
+use ephemeris_builder::*;
+
+mod tle;
+use tle::*;
+
+mod oem;
+use oem::*;
+
+mod interpolation;
+use interpolation::*;
+
+mod time_scales;
+use time_scales::*;
+
+mod batch;
+use batch::*;
+
+mod conjunction;
+use conjunction::*;
+
+mod streaming;
+use streaming::*;
+
+mod db;
+use db::{DbPool, EphemerisDbPool, establish_connection, create_module_tables};
+use db::antenna::AntennaDb;
+use db::ephemeris::EphemerisRunDb;
+use db::satellite::SatelliteDb;
+use db::tle::{TleDb, SatelliteTleDb};
 
 
 
@@ -146,204 +200,1952 @@ fn check_parameters(in_json_message: &RestRequest) -> Result<bool, HttpServiceEr
 }
 
 /**
- * Check the specific parameters of this operation
+ * Check the parameters shared by the single and the batch propagation operations
  * Return false - if there are no errors
  */
- fn check_operation_parameters(in_message: &OrbPropagationTleStruct, in_msg_id: String, 
+ fn check_propagation_parameters(in_epoch_format: &String, in_start_time: &String, in_stop_time: &String,
+    in_step_size: u16, in_output: &OutputTleStruct, in_msg_id: String, 
     out_start: &mut DateTime<Utc>, out_stop: &mut DateTime<Utc>,) -> Result<bool, HttpServiceError> 
  {
     // Check time format
-    if in_message.epoch_format != "UTCGregorian" &&
-       in_message.epoch_format != "UTCModJulian" {
-        let tmp_msg = format!("ERROR: Invalid epoch format: {}", in_message.epoch_format );
+    let tmp_epoch_format = match EpochFormat::from_string(in_epoch_format.as_str()) {
+        Some(f) => f,
+        None => {
+            let tmp_msg = format!("ERROR: Invalid epoch format: {}. Allowed values: UTCGregorian, UTCModJulian, TAIGregorian, TAIModJulian, TTGregorian, TTModJulian, GPSGregorian, GPSModJulian", in_epoch_format );
             
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+        }
+    };
+
+    // Check start and end time. Converted to UTC
+    *out_start = match parse_epoch(in_start_time.as_str(), &tmp_epoch_format) {
+        Ok(t) => t,
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to parse start time: {}", e);
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+        },
+    };
+
+    *out_stop = match parse_epoch(in_stop_time.as_str(), &tmp_epoch_format) {
+        Ok(t) => t,
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to parse stop time: {}", e);
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+        },
+    };
+
+    // Check if the dates are in reverse order
+    if out_stop < out_start {
+        // Error
+        let tmp_msg = format!("ERROR: Stop time is in the past. Smaller thant the start time");
+
         error!("{}", tmp_msg.as_str() );
         return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
     }
 
-    // Check start and end time 
-    let rfc3339 = match DateTime::parse_from_rfc3339(in_message.start_time.as_str()) {
+    // Check if they are not separated for more than 1 year
+    let interval_duration = out_stop.signed_duration_since(*out_start);
+
+    if interval_duration > chrono::Duration::days(365) {
+        // Error to long
+        let tmp_msg = format!("ERROR: The propagation period is to big; greater than 1 year");
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    // Step size in seconds
+    if in_step_size == 0 {
+        let tmp_msg = format!("ERROR: Invalid step size: {}. It shall be greater than 0 seconds", in_step_size);
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    if EnumReferenceFrame::from_string(in_output.reference_frame.as_str()).is_none() {
+        let tmp_msg = format!("ERROR: Invalid output reference frame: {}", in_output.reference_frame.as_str() );
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    if is_valid_output_format(in_output.output_format.as_str()) == false {
+        let tmp_msg = format!("ERROR: Invalid output ephemeris format: {}", in_output.output_format.as_str() );
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    // Not all the formats support all the epoch formats
+    if let Some(writer) = get_ephemeris_writer(in_output.output_format.as_str()) {
+        if let Err(tmp_msg) = writer.check_epoch_format(&tmp_epoch_format) {
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+        }
+    }
+
+    // Position and velocity can only be omitted in JSON
+    if in_output.cartesian.unwrap_or(true) == false {
+        if get_ephemeris_writer(in_output.output_format.as_str()).is_some() {
+            let tmp_msg = format!("ERROR: Position and velocity cannot be omitted in output format: {}", in_output.output_format.as_str() );
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+        }
+
+        if in_output.geodetic.unwrap_or(false) == false {
+            let tmp_msg = format!("ERROR: Either cartesian or geodetic output shall be selected");
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+        }
+    }
+
+    Ok(false)
+}
+
+/**
+ * Check the specific parameters of the propagation operation
+ * Return false - there are no errors
+ */
+ fn check_operation_parameters(in_message: &OrbPropagationTleStruct, in_msg_id: String, 
+    out_start: &mut DateTime<Utc>, out_stop: &mut DateTime<Utc>,) -> Result<bool, HttpServiceError> 
+ {
+    check_propagation_parameters(&in_message.epoch_format, &in_message.start_time, &in_message.stop_time,
+        in_message.step_size, &in_message.output, in_msg_id.clone(), out_start, out_stop)?;
+
+    // Orbital elements. Otherwise, the TLE catalog of the satellite is used
+    if in_message.input.tle.is_none() == true && in_message.input.omm.is_none() == true && in_message.satellite_id.is_empty() == true {
+        let tmp_msg = format!("ERROR: No orbital elements. Either a TLE, an OMM or a Satellite Id linked to the TLE catalog shall be provided");
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    // The ephemeris store is indexed by mission and satellite
+    if in_message.add_to_database == true && (in_message.mission_id.is_empty() == true || in_message.satellite_id.is_empty() == true) {
+        let tmp_msg = format!("ERROR: Mission Id and Satellite Id are required to add the ephemeris to the database");
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    Ok(false)
+}
+
+/**
+ * Check the specific parameters of the streaming propagation operation
+ * Only the products generated sample by sample can be streamed
+ * Return false - there are no errors
+ */
+ fn check_stream_parameters(in_message: &OrbPropagationTleStruct, in_msg_id: String, 
+    out_start: &mut DateTime<Utc>, out_stop: &mut DateTime<Utc>,) -> Result<bool, HttpServiceError> 
+ {
+    check_operation_parameters(in_message, in_msg_id.clone(), out_start, out_stop)?;
+
+    if EnumStreamFormat::from_output_format(in_message.output.output_format.as_str()).is_none() {
+        let tmp_msg = format!("ERROR: Invalid output ephemeris format: {}. Streaming only supports JSON and CCSDS-OEM", in_message.output.output_format.as_str() );
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    if in_message.add_to_database == true || in_message.output.ground_track.unwrap_or(false) == true 
+        || in_message.output.eclipse_events.unwrap_or(false) == true {
+        let tmp_msg = format!("ERROR: add_to_database, ground_track and eclipse_events are not supported in streaming mode. Use orb_propagation_sgp4_simple");
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    Ok(false)
+}
+
+/**
+ * Check the specific parameters of the batch propagation operation
+ * Return false - there are no errors
+ */
+ fn check_batch_parameters(in_message: &OrbPropagationTleBatchStruct, in_msg_id: String, 
+    out_start: &mut DateTime<Utc>, out_stop: &mut DateTime<Utc>,) -> Result<bool, HttpServiceError> 
+ {
+    check_propagation_parameters(&in_message.epoch_format, &in_message.start_time, &in_message.stop_time,
+        in_message.step_size, &in_message.output, in_msg_id.clone(), out_start, out_stop)?;
+
+    // One file per object is not supported
+    if get_ephemeris_writer(in_message.output.output_format.as_str()).is_some() {
+        let tmp_msg = format!("ERROR: Invalid output ephemeris format: {}. Batch propagation only supports JSON", in_message.output.output_format.as_str() );
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    if in_message.input.tle.is_none() == true && in_message.input.omm.is_none() == true && in_message.input.catalog.is_none() == true {
+        let tmp_msg = format!("ERROR: No orbital elements. A list of TLEs, a list of OMMs or a catalog shall be provided");
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    Ok(false)
+}
+
+/**
+ * Check the specific parameters of the interpolation operation
+ * Return false - there are no errors
+ */
+ fn check_interpolation_parameters(in_message: &EphemerisInterpolationStruct, in_msg_id: String, 
+    out_epochs: &mut Vec<DateTime<Utc>>) -> Result<bool, HttpServiceError> 
+ {
+    if in_message.epochs.is_empty() == true || in_message.epochs.len() > MAX_INTERPOLATION_EPOCHS {
+        let tmp_msg = format!("ERROR: Invalid number of epochs: {}. It shall be between 1 and {}", in_message.epochs.len(), MAX_INTERPOLATION_EPOCHS);
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    for current_epoch in in_message.epochs.iter() {
+        match DateTime::parse_from_rfc3339(current_epoch.as_str()) {
+            Ok(t) => out_epochs.push( t.with_timezone(&Utc) ),
+            Err(e) => {
+                let tmp_msg = format!("ERROR: Invalid epoch: {}. It shall be in RFC 3339 format: {}", current_epoch, e.to_string());
+
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+            }
+        };
+    }
+
+    if let Some(ref m) = in_message.method {
+        if EnumInterpolationMethod::from_string(m.as_str()).is_none() == true {
+            let tmp_msg = format!("ERROR: Invalid interpolation method: {}. Allowed values: LAGRANGE, HERMITE", m);
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+        }
+    }
+
+    if let Some(o) = in_message.order {
+        if o == 0 || o > MAX_INTERPOLATION_ORDER {
+            let tmp_msg = format!("ERROR: Invalid interpolation order: {}. It shall be between 1 and {}", o, MAX_INTERPOLATION_ORDER);
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+        }
+    }
+
+    // Only one source of ephemeris
+    let tmp_number_inputs = [in_message.input.stored.is_some(), in_message.input.ephemeris.is_some(), in_message.input.oem.is_some()]
+        .iter().filter(|i| **i == true).count();
+
+    if tmp_number_inputs != 1 {
+        let tmp_msg = format!("ERROR: Invalid input. Only one of stored, ephemeris or OEM shall be provided");
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    Ok(false)
+}
+
+/**
+ * Build the SGP4 elements from the input TLE or OMM
+ * If both are provided, the TLE is used
+ */
+fn get_elements(in_input: &InputTleStruct) -> Result<sgp4::Elements, String>
+{
+    if let Some(ref tle) = in_input.tle {
+        if in_input.omm.is_some() == true {
+            warn!("Both TLE and OMM provided. Using the TLE");
+        }
+
+        return elements_from_tle(tle);
+    }
+
+    match in_input.omm {
+        Some(ref omm) => elements_from_omm(omm),
+        None          => Err( String::from("ERROR: No orbital elements. Either a TLE or an OMM shall be provided") ),
+    }
+}
+
+/**
+ * Build the SGP4 elements from the input TLE or OMM. If none of them is provided,
+ * the TLE of the catalog whose epoch is the closest to the given time is used
+ */
+async fn get_request_elements(in_input: &InputTleStruct, in_satellite_id: &String, in_time: &DateTime<Utc>,
+    in_ephemeris_db_pool: &EphemerisDbPool, in_msg_id: String) -> Result<sgp4::Elements, HttpServiceError>
+{
+    if in_input.tle.is_some() == true || in_input.omm.is_some() == true {
+        return get_elements(in_input)
+            .map_err(|e| {
+                error!("{}", e.as_str() );
+                HttpServiceError::BadRequest(in_msg_id, e)
+            });
+    }
+
+    let new_conn = match in_ephemeris_db_pool.0.get() {
+        Ok(c) => c,
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to connect to the TLE catalog: {}", e.to_string());
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::InternalServerError(in_msg_id, tmp_msg));
+        },
+    };
+
+    let tmp_satellite_id = in_satellite_id.clone();
+    let tmp_time = *in_time;
+
+    let res = web::block(move || 
+        TleDb::by_satellite(&new_conn, &tmp_satellite_id, &tmp_time)
+    ).await;
+
+    let tle = match res {
         Ok(t) => t,
+        Err(err) => match err {
+            BlockingError::Error(tmp_msg) => {
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+            },
+            BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg_id, String::from("Cancelled operation")) ),
+        },
+    };
+
+    info!("TLE of the catalog. Satellite: {}  NORAD Id: {}  Epoch: {}", in_satellite_id, tle.norad_id, tle.epoch);
+
+    tle.elements()
+        .map_err(|e| {
+            error!("{}", e.as_str() );
+            HttpServiceError::InternalServerError(in_msg_id, e)
+        })
+}
+
+/**
+ * Build the SGP4 elements of an object of a conjunction screening
+ * Same rules as get_request_elements, but it is called from a blocking thread
+ */
+fn get_object_elements(conn: &SqliteConnection, in_object: &ConjunctionObjectStruct, in_time: &DateTime<Utc>) -> Result<sgp4::Elements, String>
+{
+    if in_object.input.tle.is_some() == true || in_object.input.omm.is_some() == true {
+        return get_elements(&in_object.input);
+    }
+
+    if in_object.satellite_id.is_empty() == true {
+        return Err( String::from("ERROR: No orbital elements. Either a TLE, an OMM or a Satellite Id linked to the TLE catalog shall be provided") );
+    }
+
+    TleDb::by_satellite(conn, &in_object.satellite_id, in_time)?.elements()
+}
+
+/**
+ * Read the location of an antenna of the Tools module
+ */
+async fn get_antenna_location(in_db_pool: &DbPool, in_antenna_id: &String, in_msg_id: String) -> Result<GroundLocation, HttpServiceError>
+{
+    let new_conn = match in_db_pool.get() {
+        Ok(c) => c,
         Err(e) => {
-            let tmp_msg = format!("ERROR: Unable to parse start time: {}", e.to_string());
+            let tmp_msg = format!("ERROR: Unable to connect to the database: {}", e.to_string());
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::InternalServerError(in_msg_id, tmp_msg));
+        },
+    };
+
+    let tmp_antenna_id = in_antenna_id.clone();
+
+    let res = web::block(move || AntennaDb::by_id(&new_conn, &tmp_antenna_id) ).await;
+
+    let antenna = match res {
+        Ok(Some(a)) => a,
+        Ok(None) => {
+            let tmp_msg = format!("ERROR: Antenna not found: {}", in_antenna_id);
 
             error!("{}", tmp_msg.as_str() );
             return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
         },
+        Err(err) => match err {
+            BlockingError::Error(e) => {
+                let tmp_msg = format!("ERROR: Unable to read the antenna: {}. Error: {}", in_antenna_id, e.to_string());
+
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::InternalServerError(in_msg_id, tmp_msg));
+            },
+            BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg_id, String::from("Cancelled operation")) ),
+        },
     };
 
-    // Convert to UTC
-    *out_start = rfc3339.with_timezone(&Utc);
+    debug!("Antenna: {}  Latitude: {}  Longitude: {}  Altitude: {}", antenna.id, antenna.latitude, antenna.longitude, antenna.altitude);
 
+    // Antenna altitude is in meters
+    Ok( GroundLocation::new(antenna.latitude, antenna.longitude, antenna.altitude / 1000.0) )
+}
 
-    let rfc3339 = match DateTime::parse_from_rfc3339(in_message.stop_time.as_str()) {
-        Ok(t) => t,
+/**
+ * Check the specific parameters of the pass prediction operation
+ * Return false - there are no errors
+ */
+fn check_pass_prediction_parameters(in_message: &PassPredictionStruct, in_msg_id: String, 
+    out_start: &mut DateTime<Utc>, out_stop: &mut DateTime<Utc>, out_elevation_mask: &mut f64) -> Result<bool, HttpServiceError> 
+{
+    if in_message.antenna_id.is_empty() == true {
+        let tmp_msg = format!("ERROR: Antenna Id not found");
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    // Check start and end time 
+    *out_start = match DateTime::parse_from_rfc3339(in_message.start_time.as_str()) {
+        Ok(t) => t.with_timezone(&Utc),
         Err(e) => {
-            let tmp_msg = format!("ERROR: Unable to parse stop time: {}", e.to_string());
+            let tmp_msg = format!("ERROR: Unable to parse start time: {}", e.to_string());
 
             error!("{}", tmp_msg.as_str() );
             return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
         },
     };
 
-    // Convert to UTC
-    *out_stop = rfc3339.with_timezone(&Utc);
+    *out_stop = match DateTime::parse_from_rfc3339(in_message.stop_time.as_str()) {
+        Ok(t) => t.with_timezone(&Utc),
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to parse stop time: {}", e.to_string());
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+        },
+    };
 
-    // Check if the dates are in reverse order
     if out_stop < out_start {
-        // Error
         let tmp_msg = format!("ERROR: Stop time is in the past. Smaller thant the start time");
 
         error!("{}", tmp_msg.as_str() );
         return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
     }
 
-    // Check if they are not separated for more than 1 year
-    let interval_duration = out_stop.signed_duration_since(*out_start);
+    // Passes are searched every few seconds. Limit the search window
+    if out_stop.signed_duration_since(*out_start) > chrono::Duration::days(31) {
+        let tmp_msg = format!("ERROR: The prediction period is to big; greater than 31 days");
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    if in_message.step_size == 0 {
+        let tmp_msg = format!("ERROR: Invalid step size: {}. It shall be greater than 0 seconds", in_message.step_size);
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    *out_elevation_mask = in_message.elevation_mask.unwrap_or(0.0);
+
+    if *out_elevation_mask < -90.0 || *out_elevation_mask >= 90.0 {
+        let tmp_msg = format!("ERROR: Invalid elevation mask: {}. It shall be between -90 and 90 degrees", out_elevation_mask);
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    // Orbital elements. Otherwise, the TLE catalog of the satellite is used
+    if in_message.input.tle.is_none() == true && in_message.input.omm.is_none() == true && in_message.satellite_id.is_empty() == true {
+        let tmp_msg = format!("ERROR: No orbital elements. Either a TLE, an OMM or a Satellite Id linked to the TLE catalog shall be provided");
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    Ok(false)
+}
+
+/**
+ * Check the specific parameters of the conjunction screening operation
+ * Return false - there are no errors
+ */
+fn check_conjunction_parameters(in_message: &ConjunctionScreeningStruct, in_msg_id: String, 
+    out_start: &mut DateTime<Utc>, out_stop: &mut DateTime<Utc>, out_step_size: &mut u16) -> Result<bool, HttpServiceError> 
+{
+    // Check start and end time 
+    *out_start = match DateTime::parse_from_rfc3339(in_message.start_time.as_str()) {
+        Ok(t) => t.with_timezone(&Utc),
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to parse start time: {}", e.to_string());
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+        },
+    };
+
+    *out_stop = match DateTime::parse_from_rfc3339(in_message.stop_time.as_str()) {
+        Ok(t) => t.with_timezone(&Utc),
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to parse stop time: {}", e.to_string());
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+        },
+    };
+
+    if out_stop <= out_start {
+        let tmp_msg = format!("ERROR: Stop time shall be greater than the start time");
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    // Every secondary is propagated over the whole window. Limit the screening window
+    if out_stop.signed_duration_since(*out_start) > chrono::Duration::days(MAX_SCREENING_DAYS) {
+        let tmp_msg = format!("ERROR: The screening period is to big; greater than {} days", MAX_SCREENING_DAYS);
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    *out_step_size = in_message.step_size.unwrap_or(DEFAULT_SCREENING_STEP);
+
+    if *out_step_size == 0 || *out_step_size > MAX_SCREENING_STEP {
+        let tmp_msg = format!("ERROR: Invalid step size: {}. It shall be between 1 and {} seconds", out_step_size, MAX_SCREENING_STEP);
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    if in_message.threshold.is_finite() == false || in_message.threshold <= 0.0 {
+        let tmp_msg = format!("ERROR: Invalid threshold: {}. It shall be greater than 0 km", in_message.threshold);
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    if in_message.secondaries.is_empty() == true || in_message.secondaries.len() > MAX_CONJUNCTION_SECONDARIES {
+        let tmp_msg = format!("ERROR: Invalid number of secondary objects: {}. It shall be between 1 and {}", 
+            in_message.secondaries.len(), MAX_CONJUNCTION_SECONDARIES);
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
+    }
+
+    Ok(false)
+}
+
+/**
+ * Return index.html
+ */
+// async fn index(req: HttpRequest) -> actix_web::Result<fs::NamedFile> {
+async fn index(in_request: HttpRequest) -> actix_web::Result<actixfs::NamedFile>  { // -> impl Responder
+    debug!("   *** Index");
+
+    // Record the HTTP access
+    //record_access(&in_request, &in_db);
+
+    Ok( actixfs::NamedFile::open("./WebContent/index.html")?.set_content_type(mime::TEXT_HTML_UTF_8).set_status_code(StatusCode::NOT_FOUND) )
+
+//     //let f = fs::Files::new("/", "WebContent").index_file("index.html");
+
+//     //let path: PathBuf = req.match_info().query("filename").parse().unwrap();
+//     //Ok( NamedFile::open( path )? )
+
+//     // response
+//     // Ok(HttpResponse::build( StatusCode::OK)
+//     //     .content_type("text/html; charset=utf-8")
+//     //     .body( HttpResponse::include_str!("../static/welcome.html")) )
+
+
+//     // return HttpResponse::build( StatusCode::OK)
+//     //     .content_type("text/html; charset=utf-8")
+//        // .body( include_str!("../static/welcome.html"));
+//     Ok ( fs::NamedFile::open("../WebContext/index.html")? )
+}
+
+/**
+    * Not Allowed Method handler
+    * Record the access in the database
+    */
+async fn not_allowed_method(in_payload: String, in_request: HttpRequest) -> HttpResponse 
+{
+    debug!("Not Allowed Method: {}", in_payload );
+
+    // Record the HTTP access
+    //record_access(&in_request, &in_db);
+
+    HttpResponse::MethodNotAllowed().finish()
+}
+    
+
+/**
+ * Return the descrption of the selected operation.actix_files
+ * It describes how to use the operation
+ */
+async fn api_usage(in_operation : web::Path<String>) -> HttpResponse 
+{
+    info!("   *** Get API Usage");
+
+    let usage_msg : String;
+
+    match in_operation.as_str() {
+
+        "SGP4_SIMPLE" |
+        "orb_propagation_sgp4_simple" => {
+            usage_msg = fs::read_to_string("doc/orb_propagation_sgp4_simple.html").expect("Unable to read 'doc/orb_propagation_sgp4_simple.html' file");
+        },
+
+        "SGP4_BATCH" |
+        "orb_propagation_sgp4_batch" => {
+            usage_msg = fs::read_to_string("doc/orb_propagation_sgp4_batch.txt").expect("Unable to read 'doc/orb_propagation_sgp4_batch.txt' file");
+        },
+
+        "SGP4_STREAM" |
+        "orb_propagation_sgp4_stream" => {
+            usage_msg = fs::read_to_string("doc/orb_propagation_sgp4_stream.txt").expect("Unable to read 'doc/orb_propagation_sgp4_stream.txt' file");
+        },
+
+        "EPHEMERIS_QUERY" |
+        "ephemeris_query" |
+        "EPHEMERIS_VERSIONS" |
+        "ephemeris_versions" => {
+            usage_msg = fs::read_to_string("doc/ephemeris_store.txt").expect("Unable to read 'doc/ephemeris_store.txt' file");
+        },
+
+        "INTERPOLATION" |
+        "ephemeris_interpolation" => {
+            usage_msg = fs::read_to_string("doc/ephemeris_interpolation.txt").expect("Unable to read 'doc/ephemeris_interpolation.txt' file");
+        },
+
+        "TLE_IMPORT" |
+        "tle_catalog_import" |
+        "TLE_LINK" |
+        "tle_satellite_link" |
+        "TLE_HISTORY" |
+        "tle_history" => {
+            usage_msg = fs::read_to_string("doc/tle_catalog.txt").expect("Unable to read 'doc/tle_catalog.txt' file");
+        },
+
+        "PASS_PREDICTION" |
+        "pass_prediction" => {
+            usage_msg = fs::read_to_string("doc/pass_prediction.txt").expect("Unable to read 'doc/pass_prediction.txt' file");
+        },
+
+        "DOPPLER_PREDICTION" |
+        "doppler_prediction" => {
+            usage_msg = fs::read_to_string("doc/doppler_prediction.txt").expect("Unable to read 'doc/doppler_prediction.txt' file");
+        },
+
+        "CONJUNCTION_SCREENING" |
+        "conjunction_screening" => {
+            usage_msg = fs::read_to_string("doc/conjunction_screening.txt").expect("Unable to read 'doc/conjunction_screening.txt' file");
+        },
+
+        _ => { 
+            usage_msg = format!("Unknown operation name: {}", in_operation);
+        }
+    };
+
+    return HttpResponse::Ok()
+                        .content_type("text/html")
+                        .body( usage_msg );
+ }
+
+ 
+/**
+ * Return the status of the tools module
+ * Status can be; None, Running, Stopped
+ */
+async fn get_status() -> HttpResponse 
+{
+    debug!("   *** Get Status");
+
+    let tmp_data = GLOBAL_DATA.read().unwrap();
+    // let tmp_status = tmp_data.fds_status.to_string();
+
+    let tmp_status = GetStatusResponseStruct{ status : tmp_data.service_status.to_string() };
+
+    return HttpResponse::Ok().content_type("application/json")
+                             .json( tmp_status );
+}
+
+/**
+ * Return the current version
+ */
+async fn get_version() -> HttpResponse 
+{
+    debug!("   *** Get Version");
+
+    let tmp_version = GetVersionResponseStruct{ version: ORB_PROPAG_TLE_VERSION.to_string() };
+
+    return HttpResponse::Ok().content_type("application/json")
+                             .json( tmp_version );
+}
+
+/**
+ * Check the user and password (hash), and if correct generate a JWT token
+ */
+async fn orb_propagation_tle(in_msg: web::Json<RestRequest>, 
+    in_api_version: web::Path<String>,
+    in_cfg: web::Data<ConfigVariables>,
+    in_eop: web::Data<EopData>,
+    in_ephemeris_db_pool: web::Data<EphemerisDbPool>) -> Result<HttpResponse, HttpServiceError>
+{
+    debug!("Orbit Propagation - SGP4 - TLE Input msg: {}", in_msg.to_string());
+
+    // Check minimum set of fields
+    if let Err(e) = check_parameters(&in_msg) {
+        return Err(e);
+    } 
+
+    if in_api_version.as_str() != "v1" {
+        let tmp_msg = format!("ERROR: Incorrect API version: {}. Only v1 is supported", in_api_version);
+            
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+    
+    // Decode JSON
+    let json_message = serde_json::from_value( in_msg.parameters.clone() );
+    let orb_propagation_tle_message : OrbPropagationTleStruct = match json_message {
+        Ok(msg) => msg,  
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to decode JSON DeregisterStruct: {}", e.to_string());
+            
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
+
+    // Check the specific parameters of the operation
+    let mut tle_start_time: DateTime<Utc> = Utc::now();
+    let mut tle_stop_time: DateTime<Utc> = Utc::now();
+
+    if let Err(e) = check_operation_parameters(&orb_propagation_tle_message, in_msg.msg_id.clone(),
+        &mut tle_start_time, &mut tle_stop_time) {
+        return Err(e);
+    } 
+
+    // All the ephemeris is kept in memory until the response is sent. Same limit as a batch request
+    let tmp_samples = batch_samples(1, &tle_start_time, &tle_stop_time, orb_propagation_tle_message.step_size);
+
+    if tmp_samples > MAX_BATCH_SAMPLES {
+        let tmp_msg = format!("ERROR: The propagation is too big: {} state vectors. It shall not be greater than {}. Reduce the propagation period, increase the step size or use the streaming endpoint /fdsaas/v1/orb_propagation_sgp4_stream",
+            tmp_samples, MAX_BATCH_SAMPLES);
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+
+    debug!("Start time: {}  Stop time: {}", tle_start_time, tle_stop_time);
+
+    let elements = get_request_elements(&orb_propagation_tle_message.input, &orb_propagation_tle_message.satellite_id,
+        &tle_start_time, &in_ephemeris_db_pool, in_msg.msg_id.clone()).await?;
+
+    let tmp_warnings = epoch_warnings(&elements, &tle_start_time, &tle_stop_time, in_cfg.tle_max_age_days);
+
+    for current_warning in tmp_warnings.iter() {
+        warn!("{}", current_warning);
+    }
+
+
+    // Propagate and convert to the output reference frame. Outside of the HTTP workers
+    let tmp_elements = elements.clone();
+    let tmp_step_size = orb_propagation_tle_message.step_size;
+    let tmp_output = orb_propagation_tle_message.output.clone();
+    let tmp_eop = in_eop.clone().into_inner();
+    let tmp_track_properties = json!({
+            "mission_id":       orb_propagation_tle_message.mission_id,
+            "satellite_id":     orb_propagation_tle_message.satellite_id,
+            "start_time":       tle_start_time.to_rfc3339(),
+            "stop_time":        tle_stop_time.to_rfc3339(),
+        });
+
+    let res = web::block(move || 
+        build_ephemeris(&tmp_elements, &tle_start_time, &tle_stop_time, tmp_step_size, &tmp_output, &tmp_eop, tmp_track_properties)
+    ).await;
+
+    let products = match res {
+        Ok(p) => p,
+        Err(err) => match err {
+            BlockingError::Error(tmp_msg) => {
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+            },
+            BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), String::from("Cancelled operation")) ),
+        },
+    };
+
+    // Store the ephemeris as a new version
+    let mut tmp_ephemeris_version : Option<i32> = None;
+
+    if orb_propagation_tle_message.add_to_database == true {
+        let new_conn = match in_ephemeris_db_pool.0.get() {
+            Ok(c) => c,
+            Err(e) => {
+                let tmp_msg = format!("ERROR: Unable to connect to the ephemeris database: {}", e.to_string());
+
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+            },
+        };
+
+        let tmp_mission_id = orb_propagation_tle_message.mission_id.clone();
+        let tmp_satellite_id = orb_propagation_tle_message.satellite_id.clone();
+        let tmp_reference_frame = orb_propagation_tle_message.output.reference_frame.clone();
+        let tmp_step_size = orb_propagation_tle_message.step_size;
+        let tmp_norad_id = elements.norad_id;
+        let tmp_ephemeris = products.ephemeris.clone();
+
+        let res = web::block(move || 
+            EphemerisRunDb::insert_db(&new_conn, &tmp_mission_id, &tmp_satellite_id, &tmp_reference_frame,
+                &tle_start_time, &tle_stop_time, tmp_step_size, Some(tmp_norad_id), &tmp_ephemeris)
+        ).await;
+
+        match res {
+            Ok(r) => {
+                info!("Ephemeris stored. Mission: {}  Satellite: {}  Version: {}", r.mission_id, r.satellite_id, r.version);
+                tmp_ephemeris_version = Some(r.version);
+            },
+            Err(err) => match err {
+                BlockingError::Error(tmp_msg) => {
+                    error!("{}", tmp_msg.as_str() );
+                    return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+                },
+                BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), String::from("Cancelled operation")) ),
+            },
+        };
+    }
+
+    // Create output structure
+    let output_data : OrbPropagationTleResponseStruct = OrbPropagationTleResponseStruct { 
+        mission_id:       orb_propagation_tle_message.mission_id, 
+        satellite_id:     orb_propagation_tle_message.satellite_id, 
+        reference_frame:  orb_propagation_tle_message.output.reference_frame.clone(), 
+        epoch_format:     orb_propagation_tle_message.epoch_format,  
+        ephemeris:        products.ephemeris,
+        ground_track:     products.ground_track,
+        events:           products.events,
+        ephemeris_version: tmp_ephemeris_version,
+        warnings:         tmp_warnings,
+    };
+
+    match get_ephemeris_writer(orb_propagation_tle_message.output.output_format.as_str()) {
+        Some(writer) => {
+            let tmp_object_name = match elements.object_name {
+                Some(ref n) => n.clone(),
+                None        => format!("SC_{}_{}", output_data.mission_id, output_data.satellite_id),
+            };
+
+            let output = writer.write(&output_data, tmp_object_name.as_str(), &orb_propagation_tle_message.output)
+                .map_err(|e| {
+                    error!("{}", e.as_str() );
+                    HttpServiceError::BadRequest(in_msg.msg_id.clone(), e)
+                })?;
+
+            Ok( HttpResponse::Ok().content_type(writer.content_type())
+                                  .header("Content-Disposition", 
+                                          format!("attachment; filename=\"{}_{}.{}\"", output_data.mission_id, 
+                                                  output_data.satellite_id, writer.file_extension()))
+                                  .body(output) )
+        },
+        None => {
+            // Checked in check_operation_parameters
+            let tmp_epoch_format = EpochFormat::from_string(output_data.epoch_format.as_str()).unwrap();
+
+            let mut output_data = output_data;
+            apply_epoch_format(&mut output_data.ephemeris, output_data.events.as_mut(), &tmp_epoch_format);
+
+            let mut tmp_value = json!(output_data);
+
+            // Only geodetic coordinates
+            if orb_propagation_tle_message.output.cartesian.unwrap_or(true) == false {
+                remove_cartesian_state(&mut tmp_value["ephemeris"]);
+            }
+
+            let output = RestResponse::new_value(String::from("orb_propagation_sgp4_simple_response"), in_msg.msg_id.clone(), 
+            tmp_value);
+
+            Ok( HttpResponse::Ok().content_type("application/json")
+                                  .json(output) )
+        }
+    }
+}
+
+/**
+ * Propagate a satellite orbit and stream the ephemeris while it is generated (chunked transfer encoding)
+ * Newline delimited JSON or CCSDS OEM. The propagation stops if the client disconnects
+ */
+async fn orb_propagation_tle_stream(in_msg: web::Json<RestRequest>, 
+    in_api_version: web::Path<String>,
+    in_cfg: web::Data<ConfigVariables>,
+    in_eop: web::Data<EopData>,
+    in_ephemeris_db_pool: web::Data<EphemerisDbPool>) -> Result<HttpResponse, HttpServiceError>
+{
+    debug!("Orbit Propagation - SGP4 - TLE Stream Input msg id: {}", in_msg.msg_id);
+
+    // Check minimum set of fields
+    if let Err(e) = check_parameters(&in_msg) {
+        return Err(e);
+    } 
+
+    if in_api_version.as_str() != "v1" {
+        let tmp_msg = format!("ERROR: Incorrect API version: {}. Only v1 is supported", in_api_version);
+            
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+    
+    // Decode JSON
+    let stream_message : OrbPropagationTleStruct = match serde_json::from_value( in_msg.parameters.clone() ) {
+        Ok(msg) => msg,  
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to decode JSON OrbPropagationTleStruct: {}", e.to_string());
+            
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
+
+    // Check the specific parameters of the operation
+    let mut tle_start_time: DateTime<Utc> = Utc::now();
+    let mut tle_stop_time: DateTime<Utc> = Utc::now();
+
+    if let Err(e) = check_stream_parameters(&stream_message, in_msg.msg_id.clone(),
+        &mut tle_start_time, &mut tle_stop_time) {
+        return Err(e);
+    } 
+
+    debug!("Start time: {}  Stop time: {}", tle_start_time, tle_stop_time);
+
+    let elements = get_request_elements(&stream_message.input, &stream_message.satellite_id,
+        &tle_start_time, &in_ephemeris_db_pool, in_msg.msg_id.clone()).await?;
+
+    let tmp_warnings = epoch_warnings(&elements, &tle_start_time, &tle_stop_time, in_cfg.tle_max_age_days);
+
+    for current_warning in tmp_warnings.iter() {
+        warn!("{}", current_warning);
+    }
+
+    // Checked in check_stream_parameters
+    let tmp_format = EnumStreamFormat::from_output_format(stream_message.output.output_format.as_str()).unwrap();
+
+    let tmp_object_name = match elements.object_name {
+        Some(ref n) => n.clone(),
+        None        => format!("SC_{}_{}", stream_message.mission_id, stream_message.satellite_id),
+    };
+
+    let stream = EphemerisStream::new(elements, in_eop.clone().into_inner(), tmp_format.clone(), in_msg.msg_id.as_str(),
+        &stream_message, tmp_object_name.as_str(), &tle_start_time, &tle_stop_time, &tmp_warnings)
+        .map_err(|e| {
+            error!("{}", e.as_str() );
+            HttpServiceError::InternalServerError(in_msg.msg_id.clone(), e)
+        })?;
+
+    info!("Streaming ephemeris. Mission: {}  Satellite: {}  Format: {:?}", stream_message.mission_id, stream_message.satellite_id, tmp_format);
+
+    Ok( HttpResponse::Ok().content_type(tmp_format.content_type())
+                          .header("Content-Disposition", 
+                                  format!("attachment; filename=\"{}_{}.{}\"", stream_message.mission_id, 
+                                          stream_message.satellite_id, tmp_format.file_extension()))
+                          .streaming(stream.into_body()) )
+}
+
+/**
+ * Propagate a list of satellites. Every object is propagated by a pool of workers
+ * Errors are reported per object
+ */
+async fn orb_propagation_tle_batch(in_msg: web::Json<RestRequest>, 
+    in_api_version: web::Path<String>,
+    in_cfg: web::Data<ConfigVariables>,
+    in_eop: web::Data<EopData>) -> Result<HttpResponse, HttpServiceError>
+{
+    debug!("Orbit Propagation - SGP4 - TLE Batch Input msg id: {}", in_msg.msg_id);
+
+    // Check minimum set of fields
+    if let Err(e) = check_parameters(&in_msg) {
+        return Err(e);
+    } 
+
+    if in_api_version.as_str() != "v1" {
+        let tmp_msg = format!("ERROR: Incorrect API version: {}. Only v1 is supported", in_api_version);
+            
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+    
+    // Decode JSON
+    let batch_message : OrbPropagationTleBatchStruct = match serde_json::from_value( in_msg.parameters.clone() ) {
+        Ok(msg) => msg,  
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to decode JSON OrbPropagationTleBatchStruct: {}", e.to_string());
+            
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
+
+    // Check the specific parameters of the operation
+    let mut tle_start_time: DateTime<Utc> = Utc::now();
+    let mut tle_stop_time: DateTime<Utc> = Utc::now();
+
+    if let Err(e) = check_batch_parameters(&batch_message, in_msg.msg_id.clone(),
+        &mut tle_start_time, &mut tle_stop_time) {
+        return Err(e);
+    } 
+
+    let objects = batch_objects(&batch_message.input);
+
+    if objects.is_empty() == true || objects.len() > MAX_BATCH_OBJECTS {
+        let tmp_msg = format!("ERROR: Invalid number of objects: {}. It shall be between 1 and {}", objects.len(), MAX_BATCH_OBJECTS);
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+
+    // All the ephemeris are kept in memory until the response is sent
+    let tmp_samples = batch_samples(objects.len(), &tle_start_time, &tle_stop_time, batch_message.step_size);
+
+    if tmp_samples > MAX_BATCH_SAMPLES {
+        let tmp_msg = format!("ERROR: The batch is too big: {} state vectors (objects x steps). It shall not be greater than {}. Reduce the number of objects, the propagation period or increase the step size",
+            tmp_samples, MAX_BATCH_SAMPLES);
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+
+    debug!("Batch of {} objects. Start time: {}  Stop time: {}", objects.len(), tle_start_time, tle_stop_time);
+
+    // Propagate outside of the HTTP workers
+    let tmp_mission_id = batch_message.mission_id.clone();
+    let tmp_step_size = batch_message.step_size;
+    let tmp_output = Arc::new(batch_message.output.clone());
+    let tmp_eop = in_eop.clone().into_inner();
+    let tmp_number_workers = in_cfg.batch_number_workers;
+    let tmp_max_age_days = in_cfg.tle_max_age_days;
+
+    let res = web::block(move || 
+        Ok::<Vec<BatchObjectResultStruct>, String>( propagate_batch(objects, tmp_mission_id, tle_start_time, tle_stop_time,
+            tmp_step_size, tmp_output, tmp_eop, tmp_number_workers, tmp_max_age_days) )
+    ).await;
+
+    let results = match res {
+        Ok(r) => r,
+        Err(err) => match err {
+            BlockingError::Error(tmp_msg) => return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg)),
+            BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), String::from("Cancelled operation")) ),
+        },
+    };
+
+    let mut results = results;
+
+    // Checked in check_batch_parameters
+    let tmp_epoch_format = EpochFormat::from_string(batch_message.epoch_format.as_str()).unwrap();
+
+    for current_result in results.iter_mut() {
+        if let Some(ref mut tmp_ephemeris) = current_result.ephemeris {
+            apply_epoch_format(tmp_ephemeris, current_result.events.as_mut(), &tmp_epoch_format);
+        }
+    }
+
+    let tmp_number_errors = results.iter().filter(|r| r.error.is_some()).count();
+
+    info!("Batch propagation completed. Objects: {}  Errors: {}", results.len(), tmp_number_errors);
+
+    let output_data = OrbPropagationTleBatchResponseStruct {
+        mission_id:       batch_message.mission_id,
+        reference_frame:  batch_message.output.reference_frame.clone(),
+        epoch_format:     batch_message.epoch_format,
+        number_objects:   results.len(),
+        number_errors:    tmp_number_errors,
+        objects:          results,
+    };
+
+    let mut tmp_value = json!(output_data);
+
+    // Only geodetic coordinates
+    if batch_message.output.cartesian.unwrap_or(true) == false {
+        if let Some(tmp_objects) = tmp_value["objects"].as_array_mut() {
+            for current_object in tmp_objects.iter_mut() {
+                remove_cartesian_state(&mut current_object["ephemeris"]);
+            }
+        }
+    }
+
+    let output = RestResponse::new_value(String::from("orb_propagation_sgp4_batch_response"), in_msg.msg_id.clone(), 
+        tmp_value);
+
+    Ok( HttpResponse::Ok().content_type("application/json")
+                          .json(output) )
+}
+
+/**
+ * Predict the passes of a satellite over an antenna of the Tools module
+ */
+async fn pass_prediction(in_msg: web::Json<RestRequest>, 
+    in_api_version: web::Path<String>,
+    in_cfg: web::Data<ConfigVariables>,
+    in_db_pool: web::Data<DbPool>,
+    in_ephemeris_db_pool: web::Data<EphemerisDbPool>,
+    in_eop: web::Data<EopData>) -> Result<HttpResponse, HttpServiceError>
+{
+    debug!("Pass prediction Input msg: {}", in_msg.to_string());
+
+    // Check minimum set of fields
+    if let Err(e) = check_parameters(&in_msg) {
+        return Err(e);
+    } 
+
+    if in_api_version.as_str() != "v1" {
+        let tmp_msg = format!("ERROR: Incorrect API version: {}. Only v1 is supported", in_api_version);
+            
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+    
+    // Decode JSON
+    let pass_prediction_message : PassPredictionStruct = match serde_json::from_value( in_msg.parameters.clone() ) {
+        Ok(msg) => msg,  
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to decode JSON PassPredictionStruct: {}", e.to_string());
+            
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
+
+    // Check the specific parameters of the operation
+    let mut tmp_start_time: DateTime<Utc> = Utc::now();
+    let mut tmp_stop_time: DateTime<Utc> = Utc::now();
+    let mut tmp_elevation_mask: f64 = 0.0;
+
+    if let Err(e) = check_pass_prediction_parameters(&pass_prediction_message, in_msg.msg_id.clone(),
+        &mut tmp_start_time, &mut tmp_stop_time, &mut tmp_elevation_mask) {
+        return Err(e);
+    } 
+
+    let elements = get_request_elements(&pass_prediction_message.input, &pass_prediction_message.satellite_id,
+        &tmp_start_time, &in_ephemeris_db_pool, in_msg.msg_id.clone()).await?;
+
+    let tmp_warnings = epoch_warnings(&elements, &tmp_start_time, &tmp_stop_time, in_cfg.tle_max_age_days);
+
+    for current_warning in tmp_warnings.iter() {
+        warn!("{}", current_warning);
+    }
+
+    let tmp_location = get_antenna_location(&in_db_pool, &pass_prediction_message.antenna_id, in_msg.msg_id.clone()).await?;
+
+    let passes = predict_passes(&elements, &tmp_location, &in_eop, &tmp_start_time, &tmp_stop_time,
+        pass_prediction_message.step_size, tmp_elevation_mask)
+        .map_err(|e| {
+            error!("{}", e.as_str() );
+            HttpServiceError::InternalServerError(in_msg.msg_id.clone(), e)
+        })?;
+
+    // Create output structure
+    let mut output_data = PassPredictionResponseStruct {
+        mission_id:       pass_prediction_message.mission_id,
+        satellite_id:     pass_prediction_message.satellite_id,
+        antenna_id:       pass_prediction_message.antenna_id,
+        elevation_mask:   tmp_elevation_mask,
+        passes:           Vec::with_capacity(passes.len()),
+        warnings:         tmp_warnings,
+    };
+
+    for current_pass in passes.iter() {
+        output_data.passes.push( PassStruct {
+            aos:              current_pass.aos.to_rfc3339(),
+            los:              current_pass.los.to_rfc3339(),
+            tca:              current_pass.tca.to_rfc3339(),
+            duration:         current_pass.los.signed_duration_since(current_pass.aos).num_milliseconds() as f64 / 1000.0,
+            aos_azimuth:      current_pass.aos_azimuth,
+            los_azimuth:      current_pass.los_azimuth,
+            max_elevation:    current_pass.max_elevation,
+            track:            current_pass.track.iter().map(|p| TrackPointStruct {
+                                  time:         p.time.to_rfc3339(),
+                                  azimuth:      p.look.azimuth,
+                                  elevation:    p.look.elevation,
+                                  range:        p.look.range,
+                              }).collect(),
+        });
+    }
+
+    let output = RestResponse::new_value(String::from("pass_prediction_response"), in_msg.msg_id.clone(), 
+        json!(output_data));
+
+    Ok( HttpResponse::Ok().content_type("application/json")
+                          .json(output) )
+}
+
+/**
+ * Predict the range, range rate and Doppler shifted frequency of a downlink carrier
+ * over the passes of a satellite on an antenna of the Tools module
+ */
+async fn doppler_prediction(in_msg: web::Json<RestRequest>, 
+    in_api_version: web::Path<String>,
+    in_cfg: web::Data<ConfigVariables>,
+    in_db_pool: web::Data<DbPool>,
+    in_ephemeris_db_pool: web::Data<EphemerisDbPool>,
+    in_eop: web::Data<EopData>) -> Result<HttpResponse, HttpServiceError>
+{
+    debug!("Doppler prediction Input msg: {}", in_msg.to_string());
+
+    // Check minimum set of fields
+    if let Err(e) = check_parameters(&in_msg) {
+        return Err(e);
+    } 
+
+    if in_api_version.as_str() != "v1" {
+        let tmp_msg = format!("ERROR: Incorrect API version: {}. Only v1 is supported", in_api_version);
+            
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+    
+    // Decode JSON
+    let doppler_message : DopplerPredictionStruct = match serde_json::from_value( in_msg.parameters.clone() ) {
+        Ok(msg) => msg,  
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to decode JSON DopplerPredictionStruct: {}", e.to_string());
+            
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
+
+    // Check the specific parameters of the operation
+    let mut tmp_start_time: DateTime<Utc> = Utc::now();
+    let mut tmp_stop_time: DateTime<Utc> = Utc::now();
+    let mut tmp_elevation_mask: f64 = 0.0;
+
+    if let Err(e) = check_pass_prediction_parameters(&doppler_message.pass, in_msg.msg_id.clone(),
+        &mut tmp_start_time, &mut tmp_stop_time, &mut tmp_elevation_mask) {
+        return Err(e);
+    } 
+
+    if doppler_message.frequency.is_finite() == false || doppler_message.frequency <= 0.0 {
+        let tmp_msg = format!("ERROR: Invalid frequency: {}. It shall be greater than 0 Hz", doppler_message.frequency);
+
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+
+    let elements = get_request_elements(&doppler_message.pass.input, &doppler_message.pass.satellite_id,
+        &tmp_start_time, &in_ephemeris_db_pool, in_msg.msg_id.clone()).await?;
+
+    let tmp_warnings = epoch_warnings(&elements, &tmp_start_time, &tmp_stop_time, in_cfg.tle_max_age_days);
+
+    for current_warning in tmp_warnings.iter() {
+        warn!("{}", current_warning);
+    }
+
+    let tmp_location = get_antenna_location(&in_db_pool, &doppler_message.pass.antenna_id, in_msg.msg_id.clone()).await?;
+
+    let passes = predict_passes(&elements, &tmp_location, &in_eop, &tmp_start_time, &tmp_stop_time,
+        doppler_message.pass.step_size, tmp_elevation_mask)
+        .map_err(|e| {
+            error!("{}", e.as_str() );
+            HttpServiceError::InternalServerError(in_msg.msg_id.clone(), e)
+        })?;
+
+    let tmp_frequency = doppler_message.frequency;
+
+    // Create output structure
+    let mut output_data = DopplerPredictionResponseStruct {
+        mission_id:       doppler_message.pass.mission_id,
+        satellite_id:     doppler_message.pass.satellite_id,
+        antenna_id:       doppler_message.pass.antenna_id,
+        elevation_mask:   tmp_elevation_mask,
+        frequency:        tmp_frequency,
+        passes:           Vec::with_capacity(passes.len()),
+        warnings:         tmp_warnings,
+    };
+
+    for current_pass in passes.iter() {
+        let tmp_track : Vec<DopplerPointStruct> = current_pass.track.iter().map(|p| {
+            let tmp_received = doppler_frequency(tmp_frequency, p.range_rate);
+
+            DopplerPointStruct {
+                time:           p.time.to_rfc3339(),
+                azimuth:        p.look.azimuth,
+                elevation:      p.look.elevation,
+                range:          p.look.range,
+                range_rate:     p.range_rate,
+                frequency:      tmp_received,
+                doppler_shift:  tmp_received - tmp_frequency,
+            }
+        }).collect();
+
+        output_data.passes.push( DopplerPassStruct {
+            aos:              current_pass.aos.to_rfc3339(),
+            los:              current_pass.los.to_rfc3339(),
+            tca:              current_pass.tca.to_rfc3339(),
+            duration:         current_pass.los.signed_duration_since(current_pass.aos).num_milliseconds() as f64 / 1000.0,
+            max_elevation:    current_pass.max_elevation,
+            min_frequency:    tmp_track.iter().map(|p| p.frequency).fold(f64::INFINITY, f64::min),
+            max_frequency:    tmp_track.iter().map(|p| p.frequency).fold(f64::NEG_INFINITY, f64::max),
+            track:            tmp_track,
+        });
+    }
+
+    let output = RestResponse::new_value(String::from("doppler_prediction_response"), in_msg.msg_id.clone(), 
+        json!(output_data));
+
+    Ok( HttpResponse::Ok().content_type("application/json")
+                          .json(output) )
+}
+
+/**
+ * Search the close approaches between a primary object and a list of secondary objects
+ * Errors of the secondary objects are reported per object
+ */
+async fn conjunction_screening(in_msg: web::Json<RestRequest>, 
+    in_api_version: web::Path<String>,
+    in_cfg: web::Data<ConfigVariables>,
+    in_ephemeris_db_pool: web::Data<EphemerisDbPool>,
+    in_eop: web::Data<EopData>) -> Result<HttpResponse, HttpServiceError>
+{
+    debug!("Conjunction screening Input msg id: {}", in_msg.msg_id);
+
+    // Check minimum set of fields
+    if let Err(e) = check_parameters(&in_msg) {
+        return Err(e);
+    } 
+
+    if in_api_version.as_str() != "v1" {
+        let tmp_msg = format!("ERROR: Incorrect API version: {}. Only v1 is supported", in_api_version);
+            
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+    
+    // Decode JSON
+    let conjunction_message : ConjunctionScreeningStruct = match serde_json::from_value( in_msg.parameters.clone() ) {
+        Ok(msg) => msg,  
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to decode JSON ConjunctionScreeningStruct: {}", e.to_string());
+            
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
+
+    // Check the specific parameters of the operation
+    let mut tmp_start_time: DateTime<Utc> = Utc::now();
+    let mut tmp_stop_time: DateTime<Utc> = Utc::now();
+    let mut tmp_step_size: u16 = DEFAULT_SCREENING_STEP;
+
+    if let Err(e) = check_conjunction_parameters(&conjunction_message, in_msg.msg_id.clone(),
+        &mut tmp_start_time, &mut tmp_stop_time, &mut tmp_step_size) {
+        return Err(e);
+    } 
+
+    debug!("Conjunction screening of {} objects. Start time: {}  Stop time: {}", 
+        conjunction_message.secondaries.len(), tmp_start_time, tmp_stop_time);
+
+    // The TLE catalog is used by the objects without orbital elements
+    let new_conn = match in_ephemeris_db_pool.0.get() {
+        Ok(c) => c,
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to connect to the TLE catalog: {}", e.to_string());
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
+
+    // Screen outside of the HTTP workers
+    let tmp_number_secondaries = conjunction_message.secondaries.len();
+    let tmp_primary = conjunction_message.primary;
+    let tmp_secondaries = conjunction_message.secondaries;
+    let tmp_threshold = conjunction_message.threshold;
+    let tmp_cdm = conjunction_message.cdm.unwrap_or(false);
+    let tmp_msg_id = in_msg.msg_id.clone();
+    let tmp_eop = in_eop.clone().into_inner();
+    let tmp_max_age_days = in_cfg.tle_max_age_days;
+
+    let res = web::block(move || {
+        let primary = get_object_elements(&new_conn, &tmp_primary, &tmp_start_time)?;
+
+        let screening = ConjunctionScreening::new(&primary, &tmp_start_time, &tmp_stop_time, tmp_step_size, tmp_threshold)?;
+
+        let mut tmp_warnings : Vec<String> = Vec::new();
+        let mut tmp_errors : Vec<ConjunctionErrorStruct> = Vec::new();
+        let mut tmp_events : Vec<(usize, ConjunctionEvent)> = Vec::new();
+        let mut tmp_elements : Vec<Option<sgp4::Elements>> = Vec::with_capacity(tmp_secondaries.len());
+
+        for current_warning in epoch_warnings(&primary, &tmp_start_time, &tmp_stop_time, tmp_max_age_days) {
+            tmp_warnings.push( format!("NORAD Id {}. {}", primary.norad_id, current_warning) );
+        }
+
+        for (i, current_object) in tmp_secondaries.iter().enumerate() {
+            let tmp_result = get_object_elements(&new_conn, current_object, &tmp_start_time)
+                .and_then(|e| screening.screen(&e).map(|events| (e, events)) );
+
+            match tmp_result {
+                Ok((elements, events)) => {
+                    for current_warning in epoch_warnings(&elements, &tmp_start_time, &tmp_stop_time, tmp_max_age_days) {
+                        tmp_warnings.push( format!("NORAD Id {}. {}", elements.norad_id, current_warning) );
+                    }
+
+                    tmp_events.extend( events.into_iter().map(|e| (i, e)) );
+                    tmp_elements.push( Some(elements) );
+                },
+                Err(e) => {
+                    warn!("Secondary object {}. {}", i, e);
+
+                    tmp_errors.push( ConjunctionErrorStruct { secondary_index: i, error: e } );
+                    tmp_elements.push(None);
+                },
+            }
+        }
+
+        tmp_events.sort_by(|a, b| a.1.tca.cmp(&b.1.tca) );
+
+        let mut tmp_conjunctions : Vec<ConjunctionEventStruct> = Vec::with_capacity(tmp_events.len());
+
+        for (n, (i, current_event)) in tmp_events.iter().enumerate() {
+            // Only objects without errors have events
+            let tmp_secondary = tmp_elements[*i].as_ref().unwrap();
+
+            tmp_conjunctions.push( ConjunctionEventStruct {
+                secondary_index:        *i,
+                secondary_satellite_id: if tmp_secondaries[*i].satellite_id.is_empty() == true { None } 
+                                        else { Some(tmp_secondaries[*i].satellite_id.clone()) },
+                primary_norad_id:       primary.norad_id,
+                secondary_norad_id:     tmp_secondary.norad_id,
+                secondary_name:         tmp_secondary.object_name.clone(),
+                tca:                    current_event.tca.to_rfc3339(),
+                miss_distance:          current_event.miss_distance,
+                relative_speed:         current_event.relative_speed,
+                relative_position_rtn:  current_event.relative_position_rtn,
+                relative_velocity_rtn:  current_event.relative_velocity_rtn,
+                cdm:                    if tmp_cdm == true {
+                                            Some( conjunction_cdm(current_event, format!("{}_{:04}", tmp_msg_id, n + 1).as_str(),
+                                                &primary, tmp_secondary, &tmp_eop) )
+                                        } else {
+                                            None
+                                        },
+            });
+        }
+
+        Ok::<_, String>( (tmp_conjunctions, tmp_errors, tmp_warnings) )
+    }).await;
+
+    let (conjunctions, errors, warnings) = match res {
+        Ok(r) => r,
+        Err(err) => match err {
+            BlockingError::Error(tmp_msg) => {
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+            },
+            BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), String::from("Cancelled operation")) ),
+        },
+    };
+
+    for current_warning in warnings.iter() {
+        warn!("{}", current_warning);
+    }
+
+    info!("Conjunction screening completed. Secondaries: {}  Conjunctions: {}  Errors: {}", 
+        tmp_number_secondaries, conjunctions.len(), errors.len());
+
+    // Create output structure
+    let output_data = ConjunctionScreeningResponseStruct {
+        mission_id:           conjunction_message.mission_id,
+        start_time:           tmp_start_time.to_rfc3339(),
+        stop_time:            tmp_stop_time.to_rfc3339(),
+        step_size:            tmp_step_size,
+        threshold:            tmp_threshold,
+        number_secondaries:   tmp_number_secondaries,
+        number_errors:        errors.len(),
+        conjunctions:         conjunctions,
+        errors:               errors,
+        warnings:             warnings,
+    };
+
+    let output = RestResponse::new_value(String::from("conjunction_screening_response"), in_msg.msg_id.clone(), 
+        json!(output_data));
+
+    Ok( HttpResponse::Ok().content_type("application/json")
+                          .json(output) )
+}
+
+/**
+ * Read a stored ephemeris between two epochs
+ */
+async fn ephemeris_query(in_msg: web::Json<RestRequest>, 
+    in_api_version: web::Path<String>,
+    in_ephemeris_db_pool: web::Data<EphemerisDbPool>) -> Result<HttpResponse, HttpServiceError>
+{
+    debug!("Ephemeris query Input msg: {}", in_msg.to_string());
+
+    // Check minimum set of fields
+    if let Err(e) = check_parameters(&in_msg) {
+        return Err(e);
+    } 
+
+    if in_api_version.as_str() != "v1" {
+        let tmp_msg = format!("ERROR: Incorrect API version: {}. Only v1 is supported", in_api_version);
+            
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+    
+    // Decode JSON
+    let query_message : EphemerisQueryStruct = match serde_json::from_value( in_msg.parameters.clone() ) {
+        Ok(msg) => msg,  
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to decode JSON EphemerisQueryStruct: {}", e.to_string());
+            
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
+
+    // Optional time range
+    let mut tmp_times : Vec<Option<DateTime<Utc>>> = Vec::new();
+
+    for current_time in [&query_message.start_time, &query_message.stop_time].iter() {
+        match current_time {
+            Some(t) => match DateTime::parse_from_rfc3339(t.as_str()) {
+                Ok(v) => tmp_times.push( Some(v.with_timezone(&Utc)) ),
+                Err(e) => {
+                    let tmp_msg = format!("ERROR: Invalid time: {}. It shall be in RFC 3339 format: {}", t, e.to_string());
+
+                    error!("{}", tmp_msg.as_str() );
+                    return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+                }
+            },
+            None => tmp_times.push(None),
+        };
+    }
+
+    let tmp_start_time = tmp_times[0];
+    let tmp_stop_time = tmp_times[1];
+
+    if let (Some(start), Some(stop)) = (tmp_start_time, tmp_stop_time) {
+        if start > stop {
+            let tmp_msg = format!("ERROR: Start time is after stop time");
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+        }
+    }
+
+    let new_conn = match in_ephemeris_db_pool.0.get() {
+        Ok(c) => c,
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to connect to the ephemeris database: {}", e.to_string());
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
+
+    let tmp_mission_id = query_message.mission_id.clone();
+    let tmp_satellite_id = query_message.satellite_id.clone();
+    let tmp_version = query_message.version;
+
+    let res = web::block(move || {
+        let run = EphemerisRunDb::by_version(&new_conn, &tmp_mission_id, &tmp_satellite_id, tmp_version)
+            .ok_or( format!("ERROR: Ephemeris not found. Mission: {}  Satellite: {}  Version: {}", tmp_mission_id, tmp_satellite_id,
+                            tmp_version.map(|v| v.to_string()).unwrap_or(String::from("last"))) )?;
+
+        let ephemeris = run.ephemeris(&new_conn, tmp_start_time.as_ref(), tmp_stop_time.as_ref())
+            .map_err(|e| format!("ERROR: Unable to read the ephemeris: {}", e.to_string()) )?;
+
+        Ok::<(EphemerisRunDb, Vec<SatelliteStateVector>), String>( (run, ephemeris) )
+    }).await;
+
+    let (run, ephemeris) = match res {
+        Ok(r) => r,
+        Err(err) => match err {
+            BlockingError::Error(tmp_msg) => {
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+            },
+            BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), String::from("Cancelled operation")) ),
+        },
+    };
+
+    let output_data = EphemerisQueryResponseStruct {
+        mission_id:       run.mission_id,
+        satellite_id:     run.satellite_id,
+        version:          run.version,
+        reference_frame:  run.reference_frame,
+        norad_id:         run.norad_id,
+        created:          run.created,
+        ephemeris:        ephemeris,
+    };
+
+    let output = RestResponse::new_value(String::from("ephemeris_query_response"), in_msg.msg_id.clone(), 
+        json!(output_data));
+
+    Ok( HttpResponse::Ok().content_type("application/json")
+                          .json(output) )
+}
+
+/**
+ * List the stored versions of the ephemeris of a satellite
+ */
+async fn ephemeris_versions(in_msg: web::Json<RestRequest>, 
+    in_api_version: web::Path<String>,
+    in_ephemeris_db_pool: web::Data<EphemerisDbPool>) -> Result<HttpResponse, HttpServiceError>
+{
+    debug!("Ephemeris versions Input msg: {}", in_msg.to_string());
+
+    // Check minimum set of fields
+    if let Err(e) = check_parameters(&in_msg) {
+        return Err(e);
+    } 
+
+    if in_api_version.as_str() != "v1" {
+        let tmp_msg = format!("ERROR: Incorrect API version: {}. Only v1 is supported", in_api_version);
+            
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+    
+    // Decode JSON
+    let versions_message : EphemerisVersionsStruct = match serde_json::from_value( in_msg.parameters.clone() ) {
+        Ok(msg) => msg,  
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to decode JSON EphemerisVersionsStruct: {}", e.to_string());
+            
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
+
+    let new_conn = match in_ephemeris_db_pool.0.get() {
+        Ok(c) => c,
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to connect to the ephemeris database: {}", e.to_string());
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
+
+    let tmp_mission_id = versions_message.mission_id.clone();
+    let tmp_satellite_id = versions_message.satellite_id.clone();
+
+    let res = web::block(move || 
+        EphemerisRunDb::list(&new_conn, &tmp_mission_id, &tmp_satellite_id)
+            .map_err(|e| format!("ERROR: Unable to read the ephemeris versions: {}", e.to_string()) )
+    ).await;
+
+    let runs = match res {
+        Ok(r) => r,
+        Err(err) => match err {
+            BlockingError::Error(tmp_msg) => {
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+            },
+            BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), String::from("Cancelled operation")) ),
+        },
+    };
+
+    let output_data = EphemerisVersionsResponseStruct {
+        mission_id:       versions_message.mission_id,
+        satellite_id:     versions_message.satellite_id,
+        versions:         runs.into_iter().map(|r| EphemerisVersionStruct {
+                              version:          r.version,
+                              reference_frame:  r.reference_frame,
+                              start_time:       r.start_time,
+                              stop_time:        r.stop_time,
+                              step_size:        r.step_size,
+                              number_points:    r.number_points,
+                              norad_id:         r.norad_id,
+                              created:          r.created,
+                          }).collect(),
+    };
+
+    let output = RestResponse::new_value(String::from("ephemeris_versions_response"), in_msg.msg_id.clone(), 
+        json!(output_data));
+
+    Ok( HttpResponse::Ok().content_type("application/json")
+                          .json(output) )
+}
+
+/**
+ * Interpolate a stored or uploaded ephemeris at a list of epochs
+ */
+async fn ephemeris_interpolation(in_msg: web::Json<RestRequest>, 
+    in_api_version: web::Path<String>,
+    in_ephemeris_db_pool: web::Data<EphemerisDbPool>) -> Result<HttpResponse, HttpServiceError>
+{
+    debug!("Ephemeris interpolation Input msg id: {}", in_msg.msg_id);
+
+    // Check minimum set of fields
+    if let Err(e) = check_parameters(&in_msg) {
+        return Err(e);
+    } 
+
+    if in_api_version.as_str() != "v1" {
+        let tmp_msg = format!("ERROR: Incorrect API version: {}. Only v1 is supported", in_api_version);
+            
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+    
+    // Decode JSON
+    let interpolation_message : EphemerisInterpolationStruct = match serde_json::from_value( in_msg.parameters.clone() ) {
+        Ok(msg) => msg,  
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to decode JSON EphemerisInterpolationStruct: {}", e.to_string());
+            
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
 
-    if interval_duration > chrono::Duration::days(365) {
-        // Error to long
-        let tmp_msg = format!("ERROR: The propagation period is to big; greater than 1 year");
+    // Check the specific parameters of the operation
+    let mut tmp_epochs : Vec<DateTime<Utc>> = Vec::new();
 
-        error!("{}", tmp_msg.as_str() );
-        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
-    }
+    if let Err(e) = check_interpolation_parameters(&interpolation_message, in_msg.msg_id.clone(), &mut tmp_epochs) {
+        return Err(e);
+    } 
 
-    if in_message.output.reference_frame != "EarthMJ2000Eq" {
-        let tmp_msg = format!("ERROR: Invalid output reference frame: {}", in_message.output.reference_frame.as_str() );
+    // Method and order. The request overrides the ones of the OEM
+    let mut tmp_method = EnumInterpolationMethod::LAGRANGE;
+    let mut tmp_order = DEFAULT_INTERPOLATION_ORDER;
 
-        error!("{}", tmp_msg.as_str() );
-        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
-    }
+    let mut tmp_reference_frame : Option<String> = interpolation_message.input.reference_frame.clone();
+    let mut tmp_ephemeris : Vec<EphemerisPoint> = Vec::new();
 
-    if in_message.output.output_format != "json" && in_message.output.output_format != "JSON" &&
-    in_message.output.output_format != "ccsds-oem" && in_message.output.output_format != "CCSDS-OEM"    {
-        let tmp_msg = format!("ERROR: Invalid output ephemeris format: {}", in_message.output.output_format.as_str() );
+    if let Some(ref tmp_oem) = interpolation_message.input.oem {
+        let oem = parse_ccsds_oem(tmp_oem.as_str())
+            .map_err(|e| {
+                error!("{}", e.as_str() );
+                HttpServiceError::BadRequest(in_msg.msg_id.clone(), e)
+            })?;
 
-        error!("{}", tmp_msg.as_str() );
-        return Err(HttpServiceError::BadRequest(in_msg_id, tmp_msg));
-    }
+        if let Some(m) = oem.interpolation.as_ref().and_then(|m| EnumInterpolationMethod::from_string(m.as_str())) {
+            tmp_method = m;
+        }
 
-    Ok(false)
-}
+        if let Some(o) = oem.interpolation_degree {
+            tmp_order = o;
+        }
 
-/**
- * Return index.html
- */
-// async fn index(req: HttpRequest) -> actix_web::Result<fs::NamedFile> {
-async fn index(in_request: HttpRequest) -> actix_web::Result<actixfs::NamedFile>  { // -> impl Responder
-    debug!("   *** Index");
+        tmp_reference_frame = oem.reference_frame;
+        tmp_ephemeris = oem.points;
+    }
 
-    // Record the HTTP access
-    //record_access(&in_request, &in_db);
+    if let Some(ref m) = interpolation_message.method {
+        tmp_method = EnumInterpolationMethod::from_string(m.as_str()).unwrap_or(tmp_method);
+    }
 
-    Ok( actixfs::NamedFile::open("./WebContent/index.html")?.set_content_type(mime::TEXT_HTML_UTF_8).set_status_code(StatusCode::NOT_FOUND) )
+    if let Some(o) = interpolation_message.order {
+        tmp_order = o;
+    }
 
-//     //let f = fs::Files::new("/", "WebContent").index_file("index.html");
+    if let Some(ref tmp_state_vectors) = interpolation_message.input.ephemeris {
+        tmp_ephemeris = points_from_state_vectors(tmp_state_vectors)
+            .map_err(|e| {
+                error!("{}", e.as_str() );
+                HttpServiceError::BadRequest(in_msg.msg_id.clone(), e)
+            })?;
+    }
 
-//     //let path: PathBuf = req.match_info().query("filename").parse().unwrap();
-//     //Ok( NamedFile::open( path )? )
+    if let Some(ref tmp_stored) = interpolation_message.input.stored {
+        let new_conn = match in_ephemeris_db_pool.0.get() {
+            Ok(c) => c,
+            Err(e) => {
+                let tmp_msg = format!("ERROR: Unable to connect to the ephemeris database: {}", e.to_string());
+
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+            },
+        };
+
+        let tmp_mission_id = tmp_stored.mission_id.clone();
+        let tmp_satellite_id = tmp_stored.satellite_id.clone();
+        let tmp_version = tmp_stored.version;
+        let tmp_number_points = tmp_method.number_points(tmp_order) as i64;
+        let tmp_first_epoch = *tmp_epochs.iter().min().unwrap();
+        let tmp_last_epoch = *tmp_epochs.iter().max().unwrap();
+
+        let res = web::block(move || {
+            let run = EphemerisRunDb::by_version(&new_conn, &tmp_mission_id, &tmp_satellite_id, tmp_version)
+                .ok_or( format!("ERROR: Ephemeris not found. Mission: {}  Satellite: {}  Version: {}", tmp_mission_id, tmp_satellite_id,
+                                tmp_version.map(|v| v.to_string()).unwrap_or(String::from("last"))) )?;
+
+            // Only the points around the requested epochs
+            let tmp_margin = Duration::seconds( (tmp_number_points + 1) * run.step_size as i64 );
+
+            let ephemeris = run.ephemeris(&new_conn, Some(&(tmp_first_epoch - tmp_margin)), Some(&(tmp_last_epoch + tmp_margin)))
+                .map_err(|e| format!("ERROR: Unable to read the ephemeris: {}", e.to_string()) )?;
+
+            Ok::<(String, Vec<SatelliteStateVector>), String>( (run.reference_frame, ephemeris) )
+        }).await;
+
+        let (tmp_frame, tmp_state_vectors) = match res {
+            Ok(r) => r,
+            Err(err) => match err {
+                BlockingError::Error(tmp_msg) => {
+                    error!("{}", tmp_msg.as_str() );
+                    return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+                },
+                BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), String::from("Cancelled operation")) ),
+            },
+        };
+
+        tmp_reference_frame = Some(tmp_frame);
+        tmp_ephemeris = points_from_state_vectors(&tmp_state_vectors)
+            .map_err(|e| {
+                error!("{}", e.as_str() );
+                HttpServiceError::InternalServerError(in_msg.msg_id.clone(), e)
+            })?;
+    }
 
-//     // response
-//     // Ok(HttpResponse::build( StatusCode::OK)
-//     //     .content_type("text/html; charset=utf-8")
-//     //     .body( HttpResponse::include_str!("../static/welcome.html")) )
+    debug!("Interpolation. Method: {}  Order: {}  Ephemeris: {}  Epochs: {}", tmp_method.to_string(), tmp_order,
+        tmp_ephemeris.len(), tmp_epochs.len());
+
+    let output_points = interpolate(&tmp_ephemeris, &tmp_epochs, &tmp_method, tmp_order)
+        .map_err(|e| {
+            error!("{}", e.as_str() );
+            HttpServiceError::BadRequest(in_msg.msg_id.clone(), e)
+        })?;
+
+    let output_data = EphemerisInterpolationResponseStruct {
+        method:           tmp_method.to_string(),
+        order:            tmp_order,
+        reference_frame:  tmp_reference_frame,
+        ephemeris:        output_points.iter().map(|p| SatelliteStateVector {
+                              time:         p.time.to_rfc3339(),
+                              position:     p.position,
+                              velocity:     p.velocity,
+                              latitude:     None,
+                              longitude:    None,
+                              altitude:     None,
+                          }).collect(),
+    };
 
+    let output = RestResponse::new_value(String::from("ephemeris_interpolation_response"), in_msg.msg_id.clone(), 
+        json!(output_data));
 
-//     // return HttpResponse::build( StatusCode::OK)
-//     //     .content_type("text/html; charset=utf-8")
-//        // .body( include_str!("../static/welcome.html"));
-//     Ok ( fs::NamedFile::open("../WebContext/index.html")? )
+    Ok( HttpResponse::Ok().content_type("application/json")
+                          .json(output) )
 }
 
 /**
-    * Not Allowed Method handler
-    * Record the access in the database
-    */
-async fn not_allowed_method(in_payload: String, in_request: HttpRequest) -> HttpResponse 
+ * Import a TLE catalog. Every valid TLE is stored with its NORAD id and epoch
+ * Invalid entries are reported and do not stop the import
+ */
+async fn tle_catalog_import(in_msg: web::Json<RestRequest>, 
+    in_api_version: web::Path<String>,
+    in_ephemeris_db_pool: web::Data<EphemerisDbPool>) -> Result<HttpResponse, HttpServiceError>
 {
-    debug!("Not Allowed Method: {}", in_payload );
+    debug!("TLE catalog import Input msg id: {}", in_msg.msg_id);
 
-    // Record the HTTP access
-    //record_access(&in_request, &in_db);
+    // Check minimum set of fields
+    if let Err(e) = check_parameters(&in_msg) {
+        return Err(e);
+    } 
 
-    HttpResponse::MethodNotAllowed().finish()
-}
+    if in_api_version.as_str() != "v1" {
+        let tmp_msg = format!("ERROR: Incorrect API version: {}. Only v1 is supported", in_api_version);
+            
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
     
+    // Decode JSON
+    let import_message : TleCatalogImportStruct = match serde_json::from_value( in_msg.parameters.clone() ) {
+        Ok(msg) => msg,  
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to decode JSON TleCatalogImportStruct: {}", e.to_string());
+            
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
 
-/**
- * Return the descrption of the selected operation.actix_files
- * It describes how to use the operation
- */
-async fn api_usage(in_operation : web::Path<String>) -> HttpResponse 
-{
-    info!("   *** Get API Usage");
+    let entries = parse_tle_catalog(import_message.catalog.as_str());
 
-    let usage_msg : String;
+    if entries.is_empty() == true {
+        let tmp_msg = format!("ERROR: The catalog does not contain any TLE");
 
-    match in_operation.as_str() {
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
 
-        "SGP4_SIMPLE" |
-        "orb_propagation_sgp4_simple" => {
-            usage_msg = fs::read_to_string("doc/orb_propagation_sgp4_simple.html").expect("Unable to read 'doc/orb_propagation_sgp4_simple.html' file");
-        },
+    let mut tmp_tles : Vec<TleDb> = Vec::with_capacity(entries.len());
+    let mut tmp_errors : Vec<TleCatalogErrorStruct> = Vec::new();
 
-        _ => { 
-            usage_msg = format!("Unknown operation name: {}", in_operation);
-        }
-    };
+    for current_entry in entries.iter() {
+        let tmp_tle = current_entry.tle.as_ref()
+            .map_err(|e| e.clone() )
+            .and_then(|t| elements_from_tle(t).map(|e| TleDb::new(t, &e)) );
 
-    return HttpResponse::Ok()
-                        .content_type("text/html")
-                        .body( usage_msg );
- }
+        match tmp_tle {
+            Ok(t) => tmp_tles.push(t),
+            Err(e) => tmp_errors.push( TleCatalogErrorStruct { line_number: current_entry.line_number, error: e } ),
+        };
+    }
 
- 
-/**
- * Return the status of the tools module
- * Status can be; None, Running, Stopped
- */
-async fn get_status() -> HttpResponse 
-{
-    debug!("   *** Get Status");
+    let new_conn = match in_ephemeris_db_pool.0.get() {
+        Ok(c) => c,
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to connect to the TLE catalog: {}", e.to_string());
 
-    let tmp_data = GLOBAL_DATA.read().unwrap();
-    // let tmp_status = tmp_data.fds_status.to_string();
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
 
-    let tmp_status = GetStatusResponseStruct{ status : tmp_data.service_status.to_string() };
+    let tmp_number_valid = tmp_tles.len();
+
+    let res = web::block(move || 
+        TleDb::insert_db(&new_conn, &tmp_tles)
+            .map_err(|e| format!("ERROR: Unable to store the TLE catalog: {}", e.to_string()) )
+    ).await;
+
+    let tmp_number_inserted = match res {
+        Ok(n) => n,
+        Err(err) => match err {
+            BlockingError::Error(tmp_msg) => {
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+            },
+            BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), String::from("Cancelled operation")) ),
+        },
+    };
 
-    return HttpResponse::Ok().content_type("application/json")
-                             .json( tmp_status );
-}
+    info!("TLE catalog imported. TLEs: {}  Inserted: {}  Errors: {}", entries.len(), tmp_number_inserted, tmp_errors.len());
 
-/**
- * Return the current version
- */
-async fn get_version() -> HttpResponse 
-{
-    debug!("   *** Get Version");
+    let output_data = TleCatalogImportResponseStruct {
+        number_tles:        entries.len(),
+        number_inserted:    tmp_number_inserted,
+        number_duplicates:  tmp_number_valid - tmp_number_inserted,
+        number_errors:      tmp_errors.len(),
+        errors:             tmp_errors,
+    };
 
-    let tmp_version = GetVersionResponseStruct{ version: ORB_PROPAG_TLE_VERSION.to_string() };
+    let output = RestResponse::new_value(String::from("tle_catalog_import_response"), in_msg.msg_id.clone(), 
+        json!(output_data));
 
-    return HttpResponse::Ok().content_type("application/json")
-                             .json( tmp_version );
+    Ok( HttpResponse::Ok().content_type("application/json")
+                          .json(output) )
 }
 
 /**
- * Check the user and password (hash), and if correct generate a JWT token
+ * Link a satellite of the Tools module with a NORAD id of the TLE catalog
  */
-async fn orb_propagation_tle(in_msg: web::Json<RestRequest>, 
+async fn tle_satellite_link(in_msg: web::Json<RestRequest>, 
     in_api_version: web::Path<String>,
-    in_cfg: web::Data<ConfigVariables>) -> Result<HttpResponse, HttpServiceError>
+    in_db_pool: web::Data<DbPool>,
+    in_ephemeris_db_pool: web::Data<EphemerisDbPool>) -> Result<HttpResponse, HttpServiceError>
 {
-    debug!("Orbit Propagation - SGP4 - TLE Input msg: {}", in_msg.to_string());
+    debug!("TLE satellite link Input msg: {}", in_msg.to_string());
 
     // Check minimum set of fields
     if let Err(e) = check_parameters(&in_msg) {
         return Err(e);
     } 
 
-    if in_api_version != "v1" {
+    if in_api_version.as_str() != "v1" {
         let tmp_msg = format!("ERROR: Incorrect API version: {}. Only v1 is supported", in_api_version);
             
         error!("{}", tmp_msg.as_str() );
@@ -351,127 +2153,208 @@ async fn orb_propagation_tle(in_msg: web::Json<RestRequest>,
     }
     
     // Decode JSON
-    let json_message = serde_json::from_value( in_msg.parameters.clone() );
-    let orb_propagation_tle_message : OrbPropagationTleStruct = match json_message {
+    let link_message : TleSatelliteLinkStruct = match serde_json::from_value( in_msg.parameters.clone() ) {
         Ok(msg) => msg,  
         Err(e) => {
-            let tmp_msg = format!("ERROR: Unable to decode JSON DeregisterStruct: {}", e.to_string());
+            let tmp_msg = format!("ERROR: Unable to decode JSON TleSatelliteLinkStruct: {}", e.to_string());
             
             error!("{}", tmp_msg.as_str() );
-            return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+            return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
         },
     };
 
-    // Check the specific parameters of the operation
-    let mut tle_start_time: DateTime<Utc> = Utc::now();
-    let mut tle_stop_time: DateTime<Utc> = Utc::now();
-
-    if let Err(e) = check_operation_parameters(&orb_propagation_tle_message, in_msg.msg_id.clone(),
-        &mut tle_start_time, &mut tle_stop_time) {
-        return Err(e);
-    } 
+    // Any catalog number. Not only the 5 digits of the TLE format (Alpha-5, OMM)
+    if link_message.norad_id == 0 {
+        let tmp_msg = format!("ERROR: Invalid NORAD Id: {}. It shall be between 1 and {}", link_message.norad_id, u32::MAX);
 
-    debug!("Start time: {}  Stop time: {}", tle_start_time, tle_stop_time);
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
 
-    let elements = sgp4::Elements::from_tle(
-        orb_propagation_tle_message.input.tle.name.clone(),
-        orb_propagation_tle_message.input.tle.line1.as_bytes(),
-        orb_propagation_tle_message.input.tle.line2.as_bytes(),
-    ).map_err(|e| 
-        HttpServiceError::InternalServerError(in_msg.msg_id.clone(), e.to_string()) 
-    );
+    // The satellite shall exist in the Tools module
+    let new_conn = match in_db_pool.get() {
+        Ok(c) => c,
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to connect to the database: {}", e.to_string());
 
-    let elements = match elements {
-        Ok(el) => el,
-        Err(e) => return Err(e),
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+        },
     };
 
+    let tmp_satellite_id = link_message.satellite_id.clone();
 
-    let constants = sgp4::Constants::from_elements(&elements)
-        .map_err(|e| 
-            HttpServiceError::InternalServerError(in_msg.msg_id.clone(), e.to_string())
-        );
+    let res = web::block(move || 
+        SatelliteDb::by_id(&new_conn, &tmp_satellite_id).ok_or( format!("ERROR: Satellite not found: {}", tmp_satellite_id) )
+    ).await;
 
-    let constants = match constants {
-        Ok(c) => c,
-        Err(e) => return Err(e),
+    let satellite = match res {
+        Ok(s) => s,
+        Err(err) => match err {
+            BlockingError::Error(tmp_msg) => {
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+            },
+            BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), String::from("Cancelled operation")) ),
+        },
     };
-    
-    // Create output structure
-    let mut output_data : OrbPropagationTleResponseStruct = OrbPropagationTleResponseStruct { 
-        mission_id:       orb_propagation_tle_message.mission_id, 
-        satellite_id:     orb_propagation_tle_message.satellite_id, 
-        reference_frame:  orb_propagation_tle_message.output.reference_frame, 
-        epoch_format:     orb_propagation_tle_message.epoch_format,  
-        ephemeris:        Vec::new(),
+
+    let new_conn = match in_ephemeris_db_pool.0.get() {
+        Ok(c) => c,
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to connect to the TLE catalog: {}", e.to_string());
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+        },
     };
-    
-    let mut satellite_state_vector : SatelliteStateVector = SatelliteStateVector 
-    {
-        time:             Utc::now().to_rfc3339(),
-        position:         [0.0, 0.0, 0.0],
-        velocity:         [0.0, 0.0, 0.0],
+
+    let tmp_satellite_id = satellite.id.clone();
+    let tmp_norad_id = link_message.norad_id;
+
+    let res = web::block(move || 
+        SatelliteTleDb::insert_db(&new_conn, &tmp_satellite_id, tmp_norad_id as i64)
+            .map_err(|e| format!("ERROR: Unable to link the satellite: {}", e.to_string()) )
+    ).await;
+
+    if let Err(err) = res {
+        match err {
+            BlockingError::Error(tmp_msg) => {
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
+            },
+            BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), String::from("Cancelled operation")) ),
+        };
+    }
+
+    info!("Satellite linked to the TLE catalog. Satellite: {}  NORAD Id: {}", satellite.id, link_message.norad_id);
+
+    let output_data = TleSatelliteLinkResponseStruct {
+        mission_id:       satellite.mission_id,
+        satellite_id:     satellite.id,
+        norad_id:         link_message.norad_id,
     };
-    
-    // Propagate from start time to stop time
-    let start_in_min = tle_start_time.timestamp() as f64 / 60.0 as f64;
-    let stop_in_min = tle_stop_time.timestamp() as f64 / 60.0 as f64;
 
-    println!(" start time: {}   stop time: {}", start_in_min, stop_in_min);
+    let output = RestResponse::new_value(String::from("tle_satellite_link_response"), in_msg.msg_id.clone(), 
+        json!(output_data));
 
-    let mut i = start_in_min;
-    while i < stop_in_min {
-        
-        // Number of minutes since epoch
-        let prediction = constants.propagate(i)
-            .map_err(|e| 
-                HttpServiceError::InternalServerError(in_msg.msg_id.clone(), e.to_string())
-            );
-    
-        if let Err(e) = prediction  {
-            return Err(e);
-        }
+    Ok( HttpResponse::Ok().content_type("application/json")
+                          .json(output) )
+}
+
+/**
+ * List the stored TLEs of a satellite or of a NORAD id between two epochs
+ */
+async fn tle_history(in_msg: web::Json<RestRequest>, 
+    in_api_version: web::Path<String>,
+    in_ephemeris_db_pool: web::Data<EphemerisDbPool>) -> Result<HttpResponse, HttpServiceError>
+{
+    debug!("TLE history Input msg: {}", in_msg.to_string());
 
-        let prediction = prediction.unwrap();
+    // Check minimum set of fields
+    if let Err(e) = check_parameters(&in_msg) {
+        return Err(e);
+    } 
 
-        let tmp_timestamp = DateTime::<Utc>::from_utc(chrono::NaiveDateTime::from_timestamp((i as i64) * 60, 0), Utc );
-        
-        satellite_state_vector.time = tmp_timestamp.to_rfc3339();
-        
-        satellite_state_vector.position[0] = prediction.position[0];
-        satellite_state_vector.position[1] = prediction.position[1];
-        satellite_state_vector.position[2] = prediction.position[2];
-        
-        satellite_state_vector.velocity[0] = prediction.velocity[0];
-        satellite_state_vector.velocity[1] = prediction.velocity[1];
-        satellite_state_vector.velocity[2] = prediction.velocity[2];
+    if in_api_version.as_str() != "v1" {
+        let tmp_msg = format!("ERROR: Incorrect API version: {}. Only v1 is supported", in_api_version);
+            
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
+    
+    // Decode JSON
+    let history_message : TleHistoryStruct = match serde_json::from_value( in_msg.parameters.clone() ) {
+        Ok(msg) => msg,  
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to decode JSON TleHistoryStruct: {}", e.to_string());
+            
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+        },
+    };
 
-        // Add current ephemeris to the output list
-        output_data.ephemeris.push(satellite_state_vector.clone());
-        
-        debug!("t = {} min", i);
-        debug!("    r = {:?} km", prediction.position);
-        debug!("    ṙ = {:?} km.s⁻¹", prediction.velocity);
+    if history_message.satellite_id.is_none() == true && history_message.norad_id.is_none() == true {
+        let tmp_msg = format!("ERROR: Either a Satellite Id or a NORAD Id shall be provided");
 
-// TODO: Use requested step
+        error!("{}", tmp_msg.as_str() );
+        return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+    }
 
-        // Next minute
-        i += 1.0;
+    // Optional time range
+    let mut tmp_times : Vec<Option<DateTime<Utc>>> = Vec::new();
+
+    for current_time in [&history_message.start_time, &history_message.stop_time].iter() {
+        match current_time {
+            Some(t) => match DateTime::parse_from_rfc3339(t.as_str()) {
+                Ok(v) => tmp_times.push( Some(v.with_timezone(&Utc)) ),
+                Err(e) => {
+                    let tmp_msg = format!("ERROR: Invalid time: {}. It shall be in RFC 3339 format: {}", t, e.to_string());
+
+                    error!("{}", tmp_msg.as_str() );
+                    return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+                }
+            },
+            None => tmp_times.push(None),
+        };
     }
 
-    match orb_propagation_tle_message.output.output_format.as_str() {
-        "json" | "JSON" => {
+    let tmp_start_time = tmp_times[0];
+    let tmp_stop_time = tmp_times[1];
+
+    let new_conn = match in_ephemeris_db_pool.0.get() {
+        Ok(c) => c,
+        Err(e) => {
+            let tmp_msg = format!("ERROR: Unable to connect to the TLE catalog: {}", e.to_string());
+
+            error!("{}", tmp_msg.as_str() );
+            return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), tmp_msg));
         },
-        "ccsds-oem" | "CCSDS-OEM" => {
-            
+    };
+
+    let tmp_satellite_id = history_message.satellite_id.clone();
+    let tmp_norad_id = history_message.norad_id;
+
+    let res = web::block(move || {
+        // The NORAD id of the request has priority over the link of the satellite
+        let norad_id = match (tmp_norad_id, tmp_satellite_id) {
+            (Some(n), _)    => n as i64,
+            (None, Some(s)) => SatelliteTleDb::by_satellite(&new_conn, &s)
+                                   .ok_or( format!("ERROR: Satellite not linked to the TLE catalog: {}", s) )?
+                                   .norad_id,
+            (None, None)    => return Err( String::from("ERROR: Either a Satellite Id or a NORAD Id shall be provided") ),
+        };
+
+        let tles = TleDb::history(&new_conn, norad_id, tmp_start_time.as_ref(), tmp_stop_time.as_ref())
+            .map_err(|e| format!("ERROR: Unable to read the TLE catalog: {}", e.to_string()) )?;
+
+        Ok::<(i64, Vec<TleDb>), String>( (norad_id, tles) )
+    }).await;
+
+    let (norad_id, tles) = match res {
+        Ok(r) => r,
+        Err(err) => match err {
+            BlockingError::Error(tmp_msg) => {
+                error!("{}", tmp_msg.as_str() );
+                return Err(HttpServiceError::BadRequest(in_msg.msg_id.clone(), tmp_msg));
+            },
+            BlockingError::Canceled => return Err(HttpServiceError::InternalServerError(in_msg.msg_id.clone(), String::from("Cancelled operation")) ),
         },
-        _ => {
-            
-        }
-    }; 
-    
-    let output = RestResponse::new_value(String::from("orb_propagation_sgp4_simple_response"), in_msg.msg_id.clone(), 
-    json!(output_data));
+    };
+
+    let output_data = TleHistoryResponseStruct {
+        satellite_id:     history_message.satellite_id,
+        norad_id:         norad_id as u32,
+        tles:             tles.into_iter().map(|t| TleHistoryEntryStruct {
+                              epoch:    t.epoch,
+                              name:     t.name,
+                              line1:    t.line1,
+                              line2:    t.line2,
+                          }).collect(),
+    };
+
+    let output = RestResponse::new_value(String::from("tle_history_response"), in_msg.msg_id.clone(), 
+        json!(output_data));
 
     Ok( HttpResponse::Ok().content_type("application/json")
                           .json(output) )
@@ -573,6 +2456,42 @@ async fn main() -> std::io::Result<()>
    
     info!("Listening HTTP IP Address: {}", http_address);
 
+    // Earth Orientation Parameters. Used by the reference frames transformations
+    let eop_data = match EopData::load(&config_variables.eop_filename) {
+        Ok(d) => d,
+        Err(e) => {
+            error!("Unable to read the EOP file: {}. Error: {}", config_variables.eop_filename, e);
+            return Err( std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()) );
+        }
+    };
+
+    // Tools database. Ground stations and antennas
+    let conn_pool = match establish_connection(&config_variables.tools_database_filename) {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Unable to open the Tools database: {}. Error: {}", config_variables.tools_database_filename, e);
+            return Ok(());
+        }
+    };
+
+    // Ephemeris database. Stored propagation runs and TLE catalog
+    let ephemeris_conn_pool = match establish_connection(&config_variables.ephemeris_database_filename) {
+        Ok(p) => EphemerisDbPool(p),
+        Err(e) => {
+            error!("Unable to open the ephemeris database: {}. Error: {}", config_variables.ephemeris_database_filename, e);
+            return Ok(());
+        }
+    };
+
+    let tmp_tables = ephemeris_conn_pool.0.get()
+        .map_err(|e| e.to_string())
+        .and_then(|c| create_module_tables(&c).map_err(|e| e.to_string()) );
+
+    if let Err(e) = tmp_tables {
+        error!("Unable to create the tables of the ephemeris database: {}", e);
+        return Ok(());
+    }
+
 
     // Data shared between all threads
     {
@@ -594,6 +2513,13 @@ async fn main() -> std::io::Result<()>
 
         .data( config_variables.clone() )
 
+        .data( eop_data.clone() )
+
+        // Pass data to the handler. It makes a copy
+        .data( conn_pool.clone() )
+
+        .data( ephemeris_conn_pool.clone() )
+
         // Stopping the server
         .data( tx.clone() )
 
@@ -614,6 +2540,33 @@ async fn main() -> std::io::Result<()>
                 // MODULE SPECIFIC
                 .route("/{version}/orb_propagation_sgp4_simple", web::get().to(orb_propagation_tle))
                 .route("/{version}/OP/SGP4_SIMPLE", web::get().to(orb_propagation_tle))
+                .route("/{version}/orb_propagation_sgp4_batch", web::get().to(orb_propagation_tle_batch))
+                .route("/{version}/OP/SGP4_BATCH", web::get().to(orb_propagation_tle_batch))
+                .route("/{version}/orb_propagation_sgp4_stream", web::get().to(orb_propagation_tle_stream))
+                .route("/{version}/OP/SGP4_STREAM", web::get().to(orb_propagation_tle_stream))
+                .route("/{version}/pass_prediction", web::get().to(pass_prediction))
+                .route("/{version}/OP/PASS_PREDICTION", web::get().to(pass_prediction))
+                .route("/{version}/doppler_prediction", web::get().to(doppler_prediction))
+                .route("/{version}/OP/DOPPLER_PREDICTION", web::get().to(doppler_prediction))
+                .route("/{version}/conjunction_screening", web::get().to(conjunction_screening))
+                .route("/{version}/OP/CONJUNCTION_SCREENING", web::get().to(conjunction_screening))
+                .route("/{version}/ephemeris_query", web::get().to(ephemeris_query))
+                .route("/{version}/OP/EPHEMERIS_QUERY", web::get().to(ephemeris_query))
+                .route("/{version}/ephemeris_versions", web::get().to(ephemeris_versions))
+                .route("/{version}/OP/EPHEMERIS_VERSIONS", web::get().to(ephemeris_versions))
+                .route("/{version}/ephemeris_interpolation", web::get().to(ephemeris_interpolation))
+                .route("/{version}/OP/INTERPOLATION", web::get().to(ephemeris_interpolation))
+
+                // A catalog is bigger than the rest of the requests
+                .service(
+                    web::resource(["/{version}/tle_catalog_import", "/{version}/OP/TLE_IMPORT"])
+                        .app_data(web::JsonConfig::default().limit( MAX_SIZE_TLE_CATALOG ))
+                        .route(web::get().to(tle_catalog_import))
+                )
+                .route("/{version}/tle_satellite_link", web::get().to(tle_satellite_link))
+                .route("/{version}/OP/TLE_LINK", web::get().to(tle_satellite_link))
+                .route("/{version}/tle_history", web::get().to(tle_history))
+                .route("/{version}/OP/TLE_HISTORY", web::get().to(tle_history))
         )
         
         // Root URL
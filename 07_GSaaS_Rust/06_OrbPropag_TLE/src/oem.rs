@@ -0,0 +1,201 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * CCSDS Orbit Ephemeris Message (OEM) reader. Text format (KVN)
+ * See CCSDS 502.0-B-2 Orbit Data Messages
+ */
+
+// Date & Time
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::propagation::*;
+
+
+/**
+ * Ephemeris and metadata of an OEM. All the segments are merged
+ */
+#[derive(Debug)]
+pub struct OemEphemeris {
+    pub object_name:            Option<String>,
+    pub reference_frame:        Option<String>,
+    pub interpolation:          Option<String>,
+    pub interpolation_degree:   Option<u16>,
+    pub points:                 Vec<EphemerisPoint>,
+}
+
+
+/**
+ * Parse a CCSDS epoch. Calendar (YYYY-MM-DDThh:mm:ss.ddd) or day of year (YYYY-DDDThh:mm:ss.ddd) format
+ */
+pub fn parse_ccsds_epoch(in_text: &str) -> Option<DateTime<Utc>>
+{
+    let tmp_text = in_text.trim().trim_end_matches('Z');
+
+    for current_format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%jT%H:%M:%S%.f"].iter() {
+        if let Ok(t) = NaiveDateTime::parse_from_str(tmp_text, current_format) {
+            return Some( DateTime::<Utc>::from_utc(t, Utc) );
+        }
+    }
+
+    None
+}
+
+/**
+ * Read the ephemeris of an OEM in KVN format
+ * Only UTC and Earth centered ephemeris are supported. Covariance blocks are ignored
+ */
+pub fn parse_ccsds_oem(in_text: &str) -> Result<OemEphemeris, String>
+{
+    let mut output = OemEphemeris {
+        object_name:            None,
+        reference_frame:        None,
+        interpolation:          None,
+        interpolation_degree:   None,
+        points:                 Vec::new(),
+    };
+
+    let mut tmp_in_metadata = false;
+    let mut tmp_in_covariance = false;
+
+    for (i, current_line) in in_text.lines().enumerate() {
+        let current_line = current_line.trim();
+
+        if current_line.is_empty() == true || current_line.starts_with("COMMENT") == true {
+            continue;
+        }
+
+        match current_line {
+            "META_START"       => { tmp_in_metadata = true; continue; },
+            "META_STOP"        => { tmp_in_metadata = false; continue; },
+            "COVARIANCE_START" => { tmp_in_covariance = true; continue; },
+            "COVARIANCE_STOP"  => { tmp_in_covariance = false; continue; },
+            _                  => {},
+        };
+
+        if tmp_in_covariance == true {
+            continue;
+        }
+
+        // Header and metadata. "KEYWORD = value"
+        if let Some(tmp_position) = current_line.find('=') {
+            let tmp_key = current_line[..tmp_position].trim().to_uppercase();
+            let tmp_value = current_line[tmp_position + 1..].trim();
+
+            if tmp_in_metadata == false {
+                continue;
+            }
+
+            match tmp_key.as_str() {
+                "OBJECT_NAME" => output.object_name = Some( String::from(tmp_value) ),
+                "CENTER_NAME" => {
+                    if tmp_value.to_uppercase() != "EARTH" {
+                        return Err( format!("ERROR: Unsupported OEM center: {}. Only EARTH is supported", tmp_value) );
+                    }
+                },
+                "TIME_SYSTEM" => {
+                    if tmp_value.to_uppercase() != "UTC" {
+                        return Err( format!("ERROR: Unsupported OEM time system: {}. Only UTC is supported", tmp_value) );
+                    }
+                },
+                "REF_FRAME" => {
+                    // All the segments shall use the same reference frame
+                    if let Some(ref f) = output.reference_frame {
+                        if f != tmp_value {
+                            return Err( format!("ERROR: OEM segments with different reference frames: {} and {}", f, tmp_value) );
+                        }
+                    }
+
+                    output.reference_frame = Some( String::from(tmp_value) );
+                },
+                "INTERPOLATION" => output.interpolation = Some( tmp_value.to_uppercase() ),
+                "INTERPOLATION_DEGREE" => {
+                    output.interpolation_degree = Some( tmp_value.parse::<u16>()
+                        .map_err(|_| format!("ERROR: Invalid OEM interpolation degree: {}", tmp_value) )? );
+                },
+                _ => {},
+            };
+
+            continue;
+        }
+
+        // Data line. Epoch, position, velocity and optionally acceleration
+        let tmp_fields : Vec<&str> = current_line.split_whitespace().collect();
+
+        if tmp_fields.len() != 7 && tmp_fields.len() != 10 {
+            return Err( format!("ERROR: Invalid OEM data line {}: {}", i + 1, current_line) );
+        }
+
+        let tmp_time = parse_ccsds_epoch(tmp_fields[0])
+            .ok_or( format!("ERROR: Invalid OEM epoch at line {}: {}", i + 1, tmp_fields[0]) )?;
+
+        let mut tmp_values = [0.0; 6];
+
+        for (j, current_value) in tmp_fields[1..7].iter().enumerate() {
+            tmp_values[j] = current_value.parse::<f64>()
+                .map_err(|_| format!("ERROR: Invalid OEM value at line {}: {}", i + 1, current_value) )?;
+        }
+
+        output.points.push( EphemerisPoint {
+            time:       tmp_time,
+            position:   [tmp_values[0], tmp_values[1], tmp_values[2]],
+            velocity:   [tmp_values[3], tmp_values[4], tmp_values[5]],
+        });
+    }
+
+    if output.points.is_empty() == true {
+        return Err( String::from("ERROR: The OEM does not contain any ephemeris") );
+    }
+
+    Ok(output)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    const OEM_KVN : &str = "CCSDS_OEM_VERS = 2.0
+CREATION_DATE  = 2020-07-13T00:00:00.000
+ORIGINATOR     = INCOMPLETE WORLDS GSAAS
+
+META_START
+OBJECT_NAME          = ISS (ZARYA)
+OBJECT_ID            = sat1
+CENTER_NAME          = EARTH
+REF_FRAME            = EME2000
+TIME_SYSTEM          = UTC
+START_TIME           = 2020-07-13T00:00:00.000
+STOP_TIME            = 2020-07-13T00:01:00.000
+INTERPOLATION        = LAGRANGE
+INTERPOLATION_DEGREE = 5
+META_STOP
+
+COMMENT Position in km, velocity in km/s
+2020-07-13T00:00:00.000   6.7e3   0.0   0.0   0.0   7.5   0.0
+2020-195T00:01:00.000   6.6e3   4.5e2   0.0   -0.5   7.4   0.0
+";
+
+    #[test]
+    fn read_oem_kvn() {
+        let oem = parse_ccsds_oem(OEM_KVN).unwrap();
+
+        assert_eq!(oem.object_name, Some(String::from("ISS (ZARYA)")));
+        assert_eq!(oem.reference_frame, Some(String::from("EME2000")));
+        assert_eq!(oem.interpolation, Some(String::from("LAGRANGE")));
+        assert_eq!(oem.interpolation_degree, Some(5));
+
+        assert_eq!(oem.points.len(), 2);
+        assert_eq!(oem.points[1].time, Utc.ymd(2020, 7, 13).and_hms(0, 1, 0));
+        assert_eq!(oem.points[1].position[1], 450.0);
+        assert_eq!(oem.points[1].velocity[0], -0.5);
+
+        assert!( parse_ccsds_oem(OEM_KVN.replace("TIME_SYSTEM          = UTC", "TIME_SYSTEM = TAI").as_str()).is_err() );
+    }
+}
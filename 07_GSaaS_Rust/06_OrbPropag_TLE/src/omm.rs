@@ -0,0 +1,294 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * CCSDS Orbit Mean-elements Message (OMM) reader
+ * It accepts the three encodings of the OMM; KVN (text), XML and JSON (CelesTrak and Space-Track)
+ * See CCSDS 502.0-B-2 Orbit Data Messages
+ */
+use std::collections::BTreeMap;
+
+// Serialize/Deserialize; YAML, JSON
+use serde_json::{Map, Number, Value};
+
+
+// Keywords with text values
+const OMM_TEXT_KEYS : [&str; 4] = ["OBJECT_NAME", "OBJECT_ID", "EPOCH", "CLASSIFICATION_TYPE"];
+
+// Keywords with real values
+const OMM_REAL_KEYS : [&str; 9] = ["MEAN_MOTION", "ECCENTRICITY", "INCLINATION", "RA_OF_ASC_NODE", "ARG_OF_PERICENTER",
+                                   "MEAN_ANOMALY", "BSTAR", "MEAN_MOTION_DOT", "MEAN_MOTION_DDOT"];
+
+// Keywords with integer values
+const OMM_INTEGER_KEYS : [&str; 4] = ["EPHEMERIS_TYPE", "NORAD_CAT_ID", "ELEMENT_SET_NO", "REV_AT_EPOCH"];
+
+// Mandatory keywords for building the SGP4 elements
+const OMM_MANDATORY_KEYS : [&str; 9] = ["EPOCH", "MEAN_MOTION", "ECCENTRICITY", "INCLINATION", "RA_OF_ASC_NODE",
+                                        "ARG_OF_PERICENTER", "MEAN_ANOMALY", "NORAD_CAT_ID", "BSTAR"];
+
+
+/**
+ * Read the keywords of an OMM in KVN format. "KEYWORD = value [units]"
+ */
+fn parse_kvn(in_text: &str) -> BTreeMap<String, String>
+{
+    let mut output = BTreeMap::new();
+
+    for current_line in in_text.lines() {
+        let current_line = current_line.trim();
+
+        if current_line.is_empty() == true || current_line.starts_with("COMMENT") == true {
+            continue;
+        }
+
+        if let Some(tmp_position) = current_line.find('=') {
+            let tmp_key = current_line[..tmp_position].trim().to_uppercase();
+            let mut tmp_value = current_line[tmp_position + 1..].trim();
+
+            // Remove the units
+            if let Some(tmp_units) = tmp_value.find('[') {
+                tmp_value = tmp_value[..tmp_units].trim();
+            }
+
+            output.insert(tmp_key, String::from(tmp_value));
+        }
+    }
+
+    output
+}
+
+/**
+ * Return the value of an XML tag. Attributes (i.e. units) are ignored
+ */
+fn xml_tag_value(in_text: &str, in_tag: &str) -> Option<String>
+{
+    let tmp_open = format!("<{}", in_tag);
+    let tmp_close = format!("</{}>", in_tag);
+
+    let mut tmp_search_from = 0;
+
+    while let Some(tmp_start) = in_text[tmp_search_from..].find(tmp_open.as_str()) {
+        let tmp_start = tmp_search_from + tmp_start + tmp_open.len();
+
+        // Skip tags that only start with the same name. I.e. MEAN_MOTION and MEAN_MOTION_DOT
+        match in_text[tmp_start..].chars().next() {
+            Some('>') | Some(' ') | Some('\t') | Some('\n') | Some('\r') => {
+                let tmp_value_start = tmp_start + in_text[tmp_start..].find('>')? + 1;
+                let tmp_value_end = tmp_value_start + in_text[tmp_value_start..].find(tmp_close.as_str())?;
+
+                return Some( String::from( in_text[tmp_value_start..tmp_value_end].trim() ) );
+            },
+            _ => {
+                tmp_search_from = tmp_start;
+            }
+        };
+    }
+
+    None
+}
+
+/**
+ * Read the keywords of an OMM in XML format
+ */
+fn parse_xml(in_text: &str) -> BTreeMap<String, String>
+{
+    let mut output = BTreeMap::new();
+
+    for current_key in OMM_TEXT_KEYS.iter().chain(OMM_REAL_KEYS.iter()).chain(OMM_INTEGER_KEYS.iter()).chain(["MEAN_ELEMENT_THEORY"].iter()) {
+        if let Some(tmp_value) = xml_tag_value(in_text, current_key) {
+            output.insert(String::from(*current_key), tmp_value);
+        }
+    }
+
+    output
+}
+
+/**
+ * Read the keywords of an OMM in JSON format
+ * Space-Track encodes all the values as strings, CelesTrak uses numbers
+ */
+fn parse_json(in_object: &Map<String, Value>) -> BTreeMap<String, String>
+{
+    let mut output = BTreeMap::new();
+
+    for (current_key, current_value) in in_object.iter() {
+        let tmp_value = match current_value {
+            Value::String(s) => s.clone(),
+            Value::Null      => continue,
+            v                => v.to_string(),
+        };
+
+        output.insert(current_key.to_uppercase(), tmp_value);
+    }
+
+    output
+}
+
+/**
+ * Convert the OMM keywords into the JSON object expected by the SGP4 library (CelesTrak format)
+ */
+fn keywords_to_json(in_keywords: &BTreeMap<String, String>) -> Result<Value, String>
+{
+    for current_key in OMM_MANDATORY_KEYS.iter() {
+        if in_keywords.contains_key(*current_key) == false {
+            return Err( format!("ERROR: Mandatory OMM keyword not found: {}", current_key) );
+        }
+    }
+
+    // Only SGP4 mean elements can be propagated
+    if let Some(tmp_theory) = in_keywords.get("MEAN_ELEMENT_THEORY") {
+        if tmp_theory != "SGP4" && tmp_theory != "SGP/SGP4" {
+            return Err( format!("ERROR: Invalid OMM mean element theory: {}. Only SGP4 is supported", tmp_theory) );
+        }
+    }
+
+    let mut output = Map::new();
+
+    for current_key in OMM_TEXT_KEYS.iter() {
+        let tmp_value = match in_keywords.get(*current_key) {
+            Some(v) if *current_key == "EPOCH" => Value::String( String::from( v.trim_end_matches('Z') ) ),
+            Some(v)                            => Value::String( v.clone() ),
+            None if *current_key == "CLASSIFICATION_TYPE" => Value::String( String::from("U") ),
+            None                               => Value::Null,
+        };
+
+        output.insert(String::from(*current_key), tmp_value);
+    }
+
+    for current_key in OMM_REAL_KEYS.iter() {
+        let tmp_value = match in_keywords.get(*current_key) {
+            Some(v) => v.parse::<f64>().map_err(|_| format!("ERROR: Invalid OMM real value. {}: {}", current_key, v))?,
+            None    => 0.0,
+        };
+
+        let tmp_number = Number::from_f64(tmp_value)
+            .ok_or(format!("ERROR: Invalid OMM real value. {}: {}", current_key, tmp_value))?;
+
+        output.insert(String::from(*current_key), Value::Number(tmp_number));
+    }
+
+    for current_key in OMM_INTEGER_KEYS.iter() {
+        let tmp_value = match in_keywords.get(*current_key) {
+            Some(v) => v.parse::<u64>().map_err(|_| format!("ERROR: Invalid OMM integer value. {}: {}", current_key, v))?,
+            None if *current_key == "ELEMENT_SET_NO" => 999,
+            None    => 0,
+        };
+
+        output.insert(String::from(*current_key), Value::Number( Number::from(tmp_value) ));
+    }
+
+    Ok( Value::Object(output) )
+}
+
+/**
+ * Build the SGP4 elements from an OMM
+ * The input can be a JSON object or a string containing a KVN or XML document
+ */
+pub fn elements_from_omm(in_omm: &Value) -> Result<sgp4::Elements, String>
+{
+    let tmp_keywords = match in_omm {
+        Value::Object(o) => parse_json(o),
+        Value::String(s) => {
+            if s.trim_start().starts_with('<') == true {
+                parse_xml(s.as_str())
+            } else {
+                parse_kvn(s.as_str())
+            }
+        },
+        _ => return Err( String::from("ERROR: Invalid OMM. It shall be a JSON object or a KVN/XML document") ),
+    };
+
+    let tmp_json = keywords_to_json(&tmp_keywords)?;
+
+    serde_json::from_value::<sgp4::Elements>(tmp_json)
+        .map_err(|e| format!("ERROR: Unable to build the SGP4 elements from the OMM: {}", e.to_string()) )
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const OMM_KVN : &str = "CCSDS_OMM_VERS = 2.0
+CREATION_DATE = 2020-07-12T06:00:00
+ORIGINATOR = CELESTRAK
+COMMENT Example
+OBJECT_NAME = ISS (ZARYA)
+OBJECT_ID = 1998-067A
+CENTER_NAME = EARTH
+REF_FRAME = TEME
+TIME_SYSTEM = UTC
+MEAN_ELEMENT_THEORY = SGP4
+EPOCH = 2020-07-12T01:19:07.402656
+MEAN_MOTION = 15.49560532 [rev/day]
+ECCENTRICITY = .0001771
+INCLINATION = 51.6435 [deg]
+RA_OF_ASC_NODE = 225.4004 [deg]
+ARG_OF_PERICENTER = 44.9625 [deg]
+MEAN_ANOMALY = 5.1087 [deg]
+EPHEMERIS_TYPE = 0
+CLASSIFICATION_TYPE = U
+NORAD_CAT_ID = 25544
+ELEMENT_SET_NO = 999
+REV_AT_EPOCH = 23587
+BSTAR = .0049645
+MEAN_MOTION_DOT = .00289036
+MEAN_MOTION_DDOT = 0";
+
+    const OMM_XML : &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<ndm><omm id=\"CCSDS_OMM_VERS\" version=\"2.0\"><body><segment>
+<metadata><OBJECT_NAME>ISS (ZARYA)</OBJECT_NAME><OBJECT_ID>1998-067A</OBJECT_ID><MEAN_ELEMENT_THEORY>SGP4</MEAN_ELEMENT_THEORY></metadata>
+<data><meanElements><EPOCH>2020-07-12T01:19:07.402656</EPOCH><MEAN_MOTION units=\"rev/day\">15.49560532</MEAN_MOTION>
+<ECCENTRICITY>.0001771</ECCENTRICITY><INCLINATION>51.6435</INCLINATION><RA_OF_ASC_NODE>225.4004</RA_OF_ASC_NODE>
+<ARG_OF_PERICENTER>44.9625</ARG_OF_PERICENTER><MEAN_ANOMALY>5.1087</MEAN_ANOMALY></meanElements>
+<tleParameters><EPHEMERIS_TYPE>0</EPHEMERIS_TYPE><CLASSIFICATION_TYPE>U</CLASSIFICATION_TYPE><NORAD_CAT_ID>25544</NORAD_CAT_ID>
+<ELEMENT_SET_NO>999</ELEMENT_SET_NO><REV_AT_EPOCH>23587</REV_AT_EPOCH><BSTAR>.0049645</BSTAR>
+<MEAN_MOTION_DOT>.00289036</MEAN_MOTION_DOT><MEAN_MOTION_DDOT>0</MEAN_MOTION_DDOT></tleParameters></data>
+</segment></body></omm></ndm>";
+
+    #[test]
+    fn kvn_xml_and_json_give_the_same_elements() {
+        let tmp_json = json!({
+            "OBJECT_NAME": "ISS (ZARYA)", "OBJECT_ID": "1998-067A", "EPOCH": "2020-07-12T01:19:07.402656",
+            "MEAN_MOTION": 15.49560532, "ECCENTRICITY": 0.0001771, "INCLINATION": 51.6435, "RA_OF_ASC_NODE": 225.4004,
+            "ARG_OF_PERICENTER": 44.9625, "MEAN_ANOMALY": 5.1087, "EPHEMERIS_TYPE": 0, "CLASSIFICATION_TYPE": "U",
+            "NORAD_CAT_ID": 25544, "ELEMENT_SET_NO": 999, "REV_AT_EPOCH": 23587, "BSTAR": 0.0049645,
+            "MEAN_MOTION_DOT": 0.00289036, "MEAN_MOTION_DDOT": 0
+        });
+
+        let from_json = elements_from_omm(&tmp_json).unwrap();
+        let from_kvn = elements_from_omm(&Value::String(String::from(OMM_KVN))).unwrap();
+        let from_xml = elements_from_omm(&Value::String(String::from(OMM_XML))).unwrap();
+
+        for current in [&from_kvn, &from_xml].iter() {
+            assert_eq!(current.norad_id, from_json.norad_id);
+            assert_eq!(current.datetime, from_json.datetime);
+            assert_eq!(current.object_name, from_json.object_name);
+            assert_eq!(current.mean_motion, from_json.mean_motion);
+            assert_eq!(current.eccentricity, from_json.eccentricity);
+            assert_eq!(current.drag_term, from_json.drag_term);
+        }
+    }
+
+    #[test]
+    fn large_catalog_numbers_and_string_values() {
+        // Space-Track encoding. Catalog numbers beyond 99999 cannot be represented in a TLE
+        let tmp_json = json!({
+            "OBJECT_NAME": "TEST", "EPOCH": "2020-07-12T01:19:07.402656Z", "MEAN_MOTION": "15.49560532",
+            "ECCENTRICITY": "0.0001771", "INCLINATION": "51.6435", "RA_OF_ASC_NODE": "225.4004",
+            "ARG_OF_PERICENTER": "44.9625", "MEAN_ANOMALY": "5.1087", "NORAD_CAT_ID": "270001", "BSTAR": "0.0049645"
+        });
+
+        let tmp_elements = elements_from_omm(&tmp_json).unwrap();
+        assert_eq!(tmp_elements.norad_id, 270001);
+
+        let mut tmp_missing = tmp_json.clone();
+        tmp_missing.as_object_mut().unwrap().remove("EPOCH");
+        assert!( elements_from_omm(&tmp_missing).is_err() );
+    }
+}
@@ -0,0 +1,432 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Ground station pass prediction. It computes the visibility windows (AOS, LOS, TCA)
+ * of a satellite from an antenna and the azimuth, elevation, range and range rate track of every pass
+ */
+
+// Log
+use log::{debug};
+
+// Date & Time
+use chrono::{DateTime, Utc, Duration};
+
+use crate::propagation::*;
+use crate::frames::*;
+use crate::eop::*;
+use crate::geodetic::*;
+
+
+// Maximum step used for searching the passes. Seconds
+// A LEO pass above the horizon lasts several minutes
+const PASS_SEARCH_STEP : f64 = 30.0;
+
+// Accuracy of the AOS, LOS and TCA times. Seconds
+const PASS_TIME_TOLERANCE : f64 = 0.01;
+
+// km/s
+pub const SPEED_OF_LIGHT : f64 = 299792.458;
+
+
+/**
+ * Topocentric coordinates of the satellite
+ */
+#[derive(Debug, Clone)]
+pub struct LookAngles {
+    // Degrees. From North, clockwise. [0, 360)
+    pub azimuth:        f64,
+    // Degrees
+    pub elevation:      f64,
+    // km
+    pub range:          f64,
+}
+
+/**
+ * Compute the azimuth, elevation and range of a satellite from a ground location
+ * The satellite position shall be in the Earth fixed frame (ITRF). km
+ */
+pub fn look_angles(in_location: &GroundLocation, in_itrf_position: &[f64; 3]) -> LookAngles
+{
+    let tmp_site = in_location.to_ecef();
+    let rho = [in_itrf_position[0] - tmp_site[0], in_itrf_position[1] - tmp_site[1], in_itrf_position[2] - tmp_site[2]];
+
+    let tmp_lat = in_location.latitude * DEG_TO_RAD;
+    let tmp_lon = in_location.longitude * DEG_TO_RAD;
+
+    // South, East, Zenith
+    let tmp_south = tmp_lat.sin() * tmp_lon.cos() * rho[0] + tmp_lat.sin() * tmp_lon.sin() * rho[1] - tmp_lat.cos() * rho[2];
+    let tmp_east = -tmp_lon.sin() * rho[0] + tmp_lon.cos() * rho[1];
+    let tmp_zenith = tmp_lat.cos() * tmp_lon.cos() * rho[0] + tmp_lat.cos() * tmp_lon.sin() * rho[1] + tmp_lat.sin() * rho[2];
+
+    let tmp_range = (rho[0] * rho[0] + rho[1] * rho[1] + rho[2] * rho[2]).sqrt();
+
+    let mut tmp_azimuth = tmp_east.atan2(-tmp_south) / DEG_TO_RAD;
+    if tmp_azimuth < 0.0 {
+        tmp_azimuth += 360.0;
+    }
+
+    LookAngles {
+        azimuth:        tmp_azimuth,
+        elevation:      (tmp_zenith / tmp_range).asin() / DEG_TO_RAD,
+        range:          tmp_range,
+    }
+}
+
+/**
+ * Derivative of the range between a ground location and a satellite. km/s
+ * Positive while the satellite moves away. The state vector shall be in the Earth fixed frame (ITRF)
+ */
+pub fn range_rate(in_location: &GroundLocation, in_itrf_point: &EphemerisPoint) -> f64
+{
+    let tmp_site = in_location.to_ecef();
+    let rho = [in_itrf_point.position[0] - tmp_site[0], in_itrf_point.position[1] - tmp_site[1], in_itrf_point.position[2] - tmp_site[2]];
+
+    let tmp_range = (rho[0] * rho[0] + rho[1] * rho[1] + rho[2] * rho[2]).sqrt();
+
+    (rho[0] * in_itrf_point.velocity[0] + rho[1] * in_itrf_point.velocity[1] + rho[2] * in_itrf_point.velocity[2]) / tmp_range
+}
+
+/**
+ * Frequency received on ground from a carrier transmitted by the satellite. Hz
+ * The ground location is static in the Earth fixed frame, so the range rate is the speed of the source
+ */
+pub fn doppler_frequency(in_frequency: f64, in_range_rate: f64) -> f64
+{
+    in_frequency * SPEED_OF_LIGHT / (SPEED_OF_LIGHT + in_range_rate)
+}
+
+
+/**
+ * One sample of the track of a pass
+ */
+#[derive(Debug, Clone)]
+pub struct TrackPoint {
+    pub time:           DateTime<Utc>,
+    pub look:           LookAngles,
+    // km/s
+    pub range_rate:     f64,
+}
+
+/**
+ * One pass of the satellite over the antenna
+ * If the satellite is visible at the start (stop) time of the window, AOS (LOS) is the start (stop) time
+ */
+#[derive(Debug, Clone)]
+pub struct Pass {
+    // Acquisition of signal
+    pub aos:            DateTime<Utc>,
+    // Loss of signal
+    pub los:            DateTime<Utc>,
+    // Time of closest approach (maximum elevation)
+    pub tca:            DateTime<Utc>,
+
+    pub aos_azimuth:    f64,
+    pub los_azimuth:    f64,
+    pub max_elevation:  f64,
+
+    pub track:          Vec<TrackPoint>,
+}
+
+
+/**
+ * Compute the look angles at any time of the search window
+ * Times are seconds since the start of the window
+ */
+struct PassPredictor<'a> {
+    elements:       &'a sgp4::Elements,
+    constants:      sgp4::Constants,
+    location:       &'a GroundLocation,
+    eop_data:       &'a EopData,
+    start:          DateTime<Utc>,
+}
+
+impl<'a> PassPredictor<'a> {
+    fn time_at(&self, in_seconds: f64) -> DateTime<Utc>
+    {
+        self.start + Duration::microseconds( (in_seconds * 1.0e6).round() as i64 )
+    }
+
+    fn itrf_at(&self, in_seconds: f64) -> Result<EphemerisPoint, String>
+    {
+        let tmp_point = propagate_at(self.elements, &self.constants, &self.time_at(in_seconds))?;
+
+        Ok( convert_from_teme(&tmp_point, &EnumReferenceFrame::ITRF, self.eop_data) )
+    }
+
+    fn look_at(&self, in_seconds: f64) -> Result<LookAngles, String>
+    {
+        Ok( look_angles(self.location, &self.itrf_at(in_seconds)?.position) )
+    }
+
+    fn track_at(&self, in_seconds: f64) -> Result<TrackPoint, String>
+    {
+        let tmp_itrf = self.itrf_at(in_seconds)?;
+
+        Ok( TrackPoint {
+            time:           tmp_itrf.time,
+            look:           look_angles(self.location, &tmp_itrf.position),
+            range_rate:     range_rate(self.location, &tmp_itrf),
+        })
+    }
+
+    /**
+     * Find the time where the elevation crosses the mask (bisection)
+     * The elevation at the lower bound is below the mask if in_rising is true, above otherwise
+     */
+    fn find_crossing(&self, in_lower: f64, in_upper: f64, in_mask: f64, in_rising: bool) -> Result<f64, String>
+    {
+        let mut tmp_lower = in_lower;
+        let mut tmp_upper = in_upper;
+
+        while tmp_upper - tmp_lower > PASS_TIME_TOLERANCE {
+            let tmp_middle = 0.5 * (tmp_lower + tmp_upper);
+            let tmp_visible = self.look_at(tmp_middle)?.elevation >= in_mask;
+
+            if tmp_visible == in_rising {
+                tmp_upper = tmp_middle;
+            } else {
+                tmp_lower = tmp_middle;
+            }
+        }
+
+        Ok( if in_rising == true { tmp_upper } else { tmp_lower } )
+    }
+
+    /**
+     * Find the time of maximum elevation (golden section search)
+     */
+    fn find_maximum(&self, in_lower: f64, in_upper: f64) -> Result<f64, String>
+    {
+        let tmp_ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+
+        let mut a = in_lower;
+        let mut b = in_upper;
+        let mut c = b - tmp_ratio * (b - a);
+        let mut d = a + tmp_ratio * (b - a);
+        let mut elevation_c = self.look_at(c)?.elevation;
+        let mut elevation_d = self.look_at(d)?.elevation;
+
+        while b - a > PASS_TIME_TOLERANCE {
+            if elevation_c > elevation_d {
+                b = d;
+                d = c;
+                elevation_d = elevation_c;
+                c = b - tmp_ratio * (b - a);
+                elevation_c = self.look_at(c)?.elevation;
+            } else {
+                a = c;
+                c = d;
+                elevation_c = elevation_d;
+                d = a + tmp_ratio * (b - a);
+                elevation_d = self.look_at(d)?.elevation;
+            }
+        }
+
+        Ok( 0.5 * (a + b) )
+    }
+}
+
+
+/**
+ * Predict the passes of a satellite over a ground location between start and stop time
+ * The elevation mask is in degrees. The track of every pass is sampled every step size seconds,
+ * always including AOS and LOS
+ */
+pub fn predict_passes(in_elements: &sgp4::Elements, in_location: &GroundLocation, in_eop_data: &EopData,
+    in_start: &DateTime<Utc>, in_stop: &DateTime<Utc>, in_step_size: u16, in_elevation_mask: f64) -> Result<Vec<Pass>, String>
+{
+    if in_step_size == 0 {
+        return Err( String::from("ERROR: Step size shall be greater than 0 seconds") );
+    }
+
+    if in_stop < in_start {
+        return Err( String::from("ERROR: Stop time is smaller than the start time") );
+    }
+
+    let predictor = PassPredictor {
+        elements:       in_elements,
+        constants:      sgp4::Constants::from_elements(in_elements)
+                            .map_err(|e| format!("ERROR: Unable to initialize SGP4: {}", e.to_string()) )?,
+        location:       in_location,
+        eop_data:       in_eop_data,
+        start:          *in_start,
+    };
+
+    let tmp_duration = in_stop.signed_duration_since(*in_start).num_milliseconds() as f64 / 1000.0;
+    let tmp_search_step = PASS_SEARCH_STEP.min(in_step_size as f64);
+
+    // Coarse search of the visibility windows. List of (AOS, LOS) in seconds since start
+    let mut tmp_windows : Vec<(f64, f64)> = Vec::new();
+    let mut tmp_aos : Option<f64> = None;
+
+    let mut tmp_previous_time = 0.0;
+    let mut tmp_previous_visible = false;
+    let mut current_time = 0.0;
+
+    loop {
+        let tmp_visible = predictor.look_at(current_time)?.elevation >= in_elevation_mask;
+
+        if current_time == 0.0 {
+            if tmp_visible == true {
+                tmp_aos = Some(0.0);
+            }
+        } else if tmp_visible == true && tmp_previous_visible == false {
+            tmp_aos = Some( predictor.find_crossing(tmp_previous_time, current_time, in_elevation_mask, true)? );
+        } else if tmp_visible == false && tmp_previous_visible == true {
+            let tmp_los = predictor.find_crossing(tmp_previous_time, current_time, in_elevation_mask, false)?;
+
+            if let Some(aos) = tmp_aos.take() {
+                tmp_windows.push( (aos, tmp_los) );
+            }
+        }
+
+        tmp_previous_time = current_time;
+        tmp_previous_visible = tmp_visible;
+
+        if current_time >= tmp_duration {
+            break;
+        }
+
+        current_time = (current_time + tmp_search_step).min(tmp_duration);
+    }
+
+    // The satellite is still visible at the stop time
+    if let Some(aos) = tmp_aos {
+        tmp_windows.push( (aos, tmp_duration) );
+    }
+
+    debug!("Found {} passes between {} and {}", tmp_windows.len(), in_start, in_stop);
+
+    let mut output : Vec<Pass> = Vec::with_capacity(tmp_windows.len());
+
+    for (current_aos, current_los) in tmp_windows.iter() {
+        let tmp_tca = predictor.find_maximum(*current_aos, *current_los)?;
+
+        let tmp_aos_time = predictor.time_at(*current_aos);
+        let tmp_los_time = predictor.time_at(*current_los);
+
+        let mut tmp_track : Vec<TrackPoint> = Vec::new();
+
+        for current_sample in sample_times(&tmp_aos_time, &tmp_los_time, in_step_size)?.iter() {
+            let tmp_seconds = current_sample.signed_duration_since(*in_start).num_microseconds().unwrap_or(0) as f64 / 1.0e6;
+
+            let mut tmp_point = predictor.track_at(tmp_seconds)?;
+            tmp_point.time = *current_sample;
+
+            tmp_track.push(tmp_point);
+        }
+
+        output.push( Pass {
+            aos:            tmp_aos_time,
+            los:            tmp_los_time,
+            tca:            predictor.time_at(tmp_tca),
+            aos_azimuth:    tmp_track.first().map(|p| p.look.azimuth).unwrap_or(0.0),
+            los_azimuth:    tmp_track.last().map(|p| p.look.azimuth).unwrap_or(0.0),
+            max_elevation:  predictor.look_at(tmp_tca)?.elevation,
+            track:          tmp_track,
+        });
+    }
+
+    Ok(output)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn look_angles_of_known_directions() {
+        let tmp_location = GroundLocation::new(40.0, -3.0, 0.0);
+        let tmp_site = tmp_location.to_ecef();
+
+        // Straight up along the ellipsoid normal
+        let tmp_lat = 40.0 * DEG_TO_RAD;
+        let tmp_lon = -3.0 * DEG_TO_RAD;
+        let tmp_up = [tmp_lat.cos() * tmp_lon.cos(), tmp_lat.cos() * tmp_lon.sin(), tmp_lat.sin()];
+        let tmp_zenith = [tmp_site[0] + 500.0 * tmp_up[0], tmp_site[1] + 500.0 * tmp_up[1], tmp_site[2] + 500.0 * tmp_up[2]];
+
+        let tmp_look = look_angles(&tmp_location, &tmp_zenith);
+        assert!( (tmp_look.elevation - 90.0).abs() < 1.0e-6 );
+        assert!( (tmp_look.range - 500.0).abs() < 1.0e-6 );
+
+        // Towards the East, on the horizon
+        let tmp_east = [-tmp_lon.sin(), tmp_lon.cos(), 0.0];
+        let tmp_horizon = [tmp_site[0] + 100.0 * tmp_east[0], tmp_site[1] + 100.0 * tmp_east[1], tmp_site[2] + 100.0 * tmp_east[2]];
+
+        let tmp_look = look_angles(&tmp_location, &tmp_horizon);
+        assert!( (tmp_look.azimuth - 90.0).abs() < 1.0e-6 );
+        assert!( tmp_look.elevation.abs() < 1.0e-6 );
+    }
+
+    #[test]
+    fn doppler_shift_of_a_moving_source() {
+        // 7 km/s at 2.2 GHz is around 51 kHz
+        assert!( (doppler_frequency(2.2e9, -7.0) - 2.2e9 - 51370.1).abs() < 0.1 );
+        assert!( (doppler_frequency(2.2e9, 7.0) - 2.2e9 + 51367.7).abs() < 0.1 );
+        assert_eq!( doppler_frequency(2.2e9, 0.0), 2.2e9 );
+    }
+
+    #[test]
+    fn passes_of_iss() {
+        let elements = sgp4::Elements::from_tle(
+            Some(String::from("ISS (ZARYA)")),
+            "1 25544U 98067A   20194.88612269 -.00002218  00000-0 -31515-4 0  9992".as_bytes(),
+            "2 25544  51.6461 221.2784 0001413  89.1723 280.4612 15.49507896236008".as_bytes(),
+        ).unwrap();
+
+        let tmp_location = GroundLocation::new(40.4527, -4.3676, 0.8);
+        let tmp_start = Utc.ymd(2020, 7, 13).and_hms(0, 0, 0);
+        let tmp_stop = tmp_start + Duration::days(1);
+        let tmp_mask = 10.0;
+
+        let passes = predict_passes(&elements, &tmp_location, &EopData::new(), &tmp_start, &tmp_stop, 20, tmp_mask).unwrap();
+
+        // The ISS is visible from mid latitudes several times per day
+        assert!( passes.is_empty() == false );
+
+        for current_pass in passes.iter() {
+            assert!( current_pass.aos <= current_pass.tca && current_pass.tca <= current_pass.los );
+            assert!( current_pass.max_elevation >= tmp_mask && current_pass.max_elevation <= 90.0 );
+
+            assert_eq!( current_pass.track.first().unwrap().time, current_pass.aos );
+            assert_eq!( current_pass.track.last().unwrap().time, current_pass.los );
+
+            // Elevation at AOS and LOS is the mask, unless the pass is cut by the window
+            if current_pass.aos > tmp_start {
+                assert!( (current_pass.track.first().unwrap().look.elevation - tmp_mask).abs() < 0.01 );
+            }
+            if current_pass.los < tmp_stop {
+                assert!( (current_pass.track.last().unwrap().look.elevation - tmp_mask).abs() < 0.01 );
+            }
+
+            for current_point in current_pass.track.iter() {
+                assert!( current_point.look.elevation >= tmp_mask - 0.01 );
+                assert!( current_point.look.elevation <= current_pass.max_elevation + 1.0e-6 );
+
+                // Approaching before TCA, moving away after it
+                if (current_point.time - current_pass.tca).num_seconds().abs() > 60 {
+                    assert_eq!( current_point.range_rate > 0.0, current_point.time > current_pass.tca );
+                }
+            }
+        }
+
+        // Range rate is the derivative of the range. Track of the first pass every second
+        let tmp_track = &predict_passes(&elements, &tmp_location, &EopData::new(), &passes[0].aos, &passes[0].los, 1, tmp_mask)
+            .unwrap()[0].track;
+
+        for tmp_points in tmp_track.windows(2) {
+            let tmp_step = (tmp_points[1].time - tmp_points[0].time).num_microseconds().unwrap() as f64 / 1.0e6;
+            let tmp_derivative = (tmp_points[1].look.range - tmp_points[0].look.range) / tmp_step;
+            let tmp_mean = 0.5 * (tmp_points[0].range_rate + tmp_points[1].range_rate);
+
+            assert!( (tmp_derivative - tmp_mean).abs() < 0.001, "Range rate {} vs {}", tmp_mean, tmp_derivative );
+        }
+    }
+}
@@ -0,0 +1,213 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Propagation engine. It propagates a set of SGP4 elements between
+ * a start and a stop time using a fixed step size
+ */
+
+// Log
+use log::{debug};
+
+// Date & Time
+use chrono::{DateTime, Utc, Duration};
+
+
+/**
+ * One propagated sample. Position in km and velocity in km/s
+ * Values are expressed in the SGP4 native frame (TEME)
+ */
+#[derive(Debug, Clone)]
+pub struct EphemerisPoint {
+    pub time:                  DateTime<Utc>,
+    pub position:              [f64; 3],
+    pub velocity:              [f64; 3],
+}
+
+
+/**
+ * Return the epoch of the elements as a UTC date
+ */
+pub fn elements_epoch(in_elements: &sgp4::Elements) -> DateTime<Utc>
+{
+    DateTime::<Utc>::from_utc(in_elements.datetime, Utc)
+}
+
+/**
+ * Return the number of minutes between the epoch of the elements and the input time
+ * It is the time argument expected by SGP4
+ */
+pub fn minutes_since_epoch(in_elements: &sgp4::Elements, in_time: &DateTime<Utc>) -> f64
+{
+    let tmp_duration = in_time.signed_duration_since( elements_epoch(in_elements) );
+
+    // Use microseconds for keeping sub-second steps. It cannot overflow for intervals shorter than 290,000 years
+    match tmp_duration.num_microseconds() {
+        Some(us) => us as f64 / 60_000_000.0,
+        None     => tmp_duration.num_milliseconds() as f64 / 60_000.0,
+    }
+}
+
+/**
+ * Propagate the orbit at a single time
+ */
+pub fn propagate_at(in_elements: &sgp4::Elements, in_constants: &sgp4::Constants,
+    in_time: &DateTime<Utc>) -> Result<EphemerisPoint, String>
+{
+    let tmp_minutes = minutes_since_epoch(in_elements, in_time);
+
+    let prediction = in_constants.propagate(tmp_minutes)
+        .map_err(|e| format!("ERROR: SGP4 propagation failed at {}: {}", in_time.to_rfc3339(), e.to_string()) )?;
+
+    Ok( EphemerisPoint {
+        time:         *in_time,
+        position:     prediction.position,
+        velocity:     prediction.velocity,
+    })
+}
+
+/**
+ * Return the list of sample times from start to stop time, every step size seconds
+ * The stop time is always the last sample, even if it is not a multiple of the step
+ */
+pub fn sample_times(in_start: &DateTime<Utc>, in_stop: &DateTime<Utc>, in_step_size: u16) -> Result<Vec<DateTime<Utc>>, String>
+{
+    if in_step_size == 0 {
+        return Err( String::from("ERROR: Step size shall be greater than 0 seconds") );
+    }
+
+    if in_stop < in_start {
+        return Err( String::from("ERROR: Stop time is smaller than the start time") );
+    }
+
+    let tmp_step = Duration::seconds(in_step_size as i64);
+    let mut output : Vec<DateTime<Utc>> = Vec::new();
+
+    let mut current_time = *in_start;
+    while current_time < *in_stop {
+        output.push(current_time);
+
+        current_time = current_time + tmp_step;
+    }
+
+    // Always include the exact stop time
+    output.push(*in_stop);
+
+    Ok(output)
+}
+
+/**
+ * Propagate the orbit from start to stop time
+ * Step size in seconds
+ */
+pub fn propagate(in_elements: &sgp4::Elements, in_start: &DateTime<Utc>, in_stop: &DateTime<Utc>,
+    in_step_size: u16) -> Result<Vec<EphemerisPoint>, String>
+{
+    let constants = sgp4::Constants::from_elements(in_elements)
+        .map_err(|e| format!("ERROR: Unable to initialize SGP4: {}", e.to_string()) )?;
+
+    let tmp_times = sample_times(in_start, in_stop, in_step_size)?;
+
+    debug!("Propagating {} samples. Start: {}  Stop: {}  Step: {} s", tmp_times.len(), in_start, in_stop, in_step_size);
+
+    let mut output : Vec<EphemerisPoint> = Vec::with_capacity(tmp_times.len());
+
+    for current_time in tmp_times.iter() {
+        output.push( propagate_at(in_elements, &constants, current_time)? );
+    }
+
+    Ok(output)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // SGP4 verification vectors. Vallado et al. "Revisiting Spacetrack Report #3", AIAA 2006-6753
+    const TLE_00005_LINE1 : &str = "1 00005U 58002B   00179.78495062  .00000023  00000-0  28098-4 0  4753";
+    const TLE_00005_LINE2 : &str = "2 00005  34.2682 348.7242 1859667 331.7664  19.3264 10.82419157413667";
+
+    const TLE_06251_LINE1 : &str = "1 06251U 62025E   06176.82412014  .00008885  00000-0  12808-3 0  3985";
+    const TLE_06251_LINE2 : &str = "2 06251  58.0579  54.0425 0030035 139.1568 221.1854 15.56387291  6774";
+
+    fn assert_state(in_point: &EphemerisPoint, in_position: [f64; 3], in_velocity: [f64; 3]) {
+        for i in 0..3 {
+            assert!( (in_point.position[i] - in_position[i]).abs() < 1.0e-3,
+                "position[{}] = {} expected {}", i, in_point.position[i], in_position[i] );
+            assert!( (in_point.velocity[i] - in_velocity[i]).abs() < 1.0e-6,
+                "velocity[{}] = {} expected {}", i, in_point.velocity[i], in_velocity[i] );
+        }
+    }
+
+    #[test]
+    fn verification_vectors_00005() {
+        let elements = sgp4::Elements::from_tle(None, TLE_00005_LINE1.as_bytes(), TLE_00005_LINE2.as_bytes()).unwrap();
+        let start = elements_epoch(&elements);
+        let stop = start + Duration::minutes(720);
+
+        // Every 360 minutes
+        let ephemeris = propagate(&elements, &start, &stop, 21600).unwrap();
+
+        assert_eq!(ephemeris.len(), 3);
+        assert_state(&ephemeris[0], [7022.46529266, -1400.08296755, 0.03995155],
+                                    [1.893841015, 6.405893759, 4.534807250]);
+        assert_state(&ephemeris[1], [-7154.03120202, -3783.17682504, -3536.19412294],
+                                    [4.741887409, -4.151817765, -2.093935425]);
+        assert_state(&ephemeris[2], [-7134.59340119, 6531.68641334, 3260.27186483],
+                                    [-4.113793027, -2.911922039, -2.557327851]);
+    }
+
+    #[test]
+    fn verification_vectors_06251() {
+        let elements = sgp4::Elements::from_tle(None, TLE_06251_LINE1.as_bytes(), TLE_06251_LINE2.as_bytes()).unwrap();
+        let start = elements_epoch(&elements);
+        let stop = start + Duration::minutes(120);
+
+        let ephemeris = propagate(&elements, &start, &stop, 7200).unwrap();
+
+        assert_eq!(ephemeris.len(), 2);
+        assert_state(&ephemeris[0], [3988.31022699, 5498.96657235, 0.90055879],
+                                    [-3.290032738, 2.357652820, 6.496623475]);
+        assert_state(&ephemeris[1], [-3935.69800083, 409.10980837, 5471.33577327],
+                                    [-3.374784183, -6.635211043, -1.942056221]);
+    }
+
+    #[test]
+    fn minutes_are_relative_to_tle_epoch() {
+        let elements = sgp4::Elements::from_tle(None, TLE_00005_LINE1.as_bytes(), TLE_00005_LINE2.as_bytes()).unwrap();
+        let epoch = elements_epoch(&elements);
+
+        assert_eq!(minutes_since_epoch(&elements, &epoch), 0.0);
+        assert!( (minutes_since_epoch(&elements, &(epoch + Duration::seconds(90))) - 1.5).abs() < 1.0e-9 );
+        assert!( (minutes_since_epoch(&elements, &(epoch - Duration::hours(2))) + 120.0).abs() < 1.0e-9 );
+    }
+
+    #[test]
+    fn sample_times_include_stop_time() {
+        let start = Utc.ymd(2020, 5, 15).and_hms(11, 30, 0);
+
+        // Sub-minute step
+        let times = sample_times(&start, &(start + Duration::seconds(95)), 10).unwrap();
+        assert_eq!(times.len(), 11);
+        assert_eq!(times[1], start + Duration::seconds(10));
+        assert_eq!(*times.last().unwrap(), start + Duration::seconds(95));
+
+        // Multi-hour step
+        let times = sample_times(&start, &(start + Duration::hours(24)), 7200).unwrap();
+        assert_eq!(times.len(), 13);
+        assert_eq!(*times.last().unwrap(), start + Duration::hours(24));
+
+        // Same start and stop time
+        let times = sample_times(&start, &start, 30).unwrap();
+        assert_eq!(times.len(), 1);
+
+        assert!( sample_times(&start, &start, 0).is_err() );
+        assert!( sample_times(&(start + Duration::seconds(1)), &start, 30).is_err() );
+    }
+}
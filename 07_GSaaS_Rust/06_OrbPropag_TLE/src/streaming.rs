@@ -0,0 +1,421 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Streaming of long ephemeris. The samples are propagated in chunks while the HTTP response
+ * is being sent, so the memory does not depend on the propagation window.
+ * Every chunk is propagated in the thread pool of the blocking operations, not in the HTTP workers.
+ * The next chunk is only propagated when the previous one has been sent.
+ * If the client disconnects, the stream is dropped and the propagation stops
+ */
+use std::sync::Arc;
+
+// Log
+use log::{debug, error, info, warn};
+
+// Date & Time
+use chrono::{DateTime, Utc, Duration};
+
+// Serialize/Deserialize; YAML, JSON
+use serde_json::{json, Value};
+
+// Actix Web Server
+use actix_web::web::{self, Bytes};
+use actix_web::error::BlockingError;
+use futures::stream::{self, LocalBoxStream, StreamExt};
+
+use crate::api_messages::*;
+use crate::propagation::*;
+use crate::frames::*;
+use crate::eop::*;
+use crate::geodetic::*;
+use crate::time_scales::*;
+use crate::writers::ccsds_oem::{ccsds_oem_header, ccsds_time_scale, write_ccsds_oem_line, CCSDS_OEM_CONTENT_TYPE};
+
+
+// Number of samples of every chunk of the response
+pub const STREAM_CHUNK_SAMPLES : u64 = 500;
+
+// Newline delimited JSON
+pub const NDJSON_CONTENT_TYPE : &str = "application/x-ndjson";
+
+
+/**
+ * Format of the streamed ephemeris
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnumStreamFormat {
+    // One JSON object per line. The first line is the header
+    NDJSON,
+    // CCSDS OEM (KVN). The header is sent with the first chunk
+    CCSDS_OEM,
+}
+
+impl EnumStreamFormat {
+    /**
+     * Return None if the output format cannot be streamed
+     */
+    pub fn from_output_format(in_output_format: &str) -> Option<Self> {
+        match in_output_format {
+            "json" | "JSON"           => Some(EnumStreamFormat::NDJSON),
+            "ccsds-oem" | "CCSDS-OEM" => Some(EnumStreamFormat::CCSDS_OEM),
+            _                         => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match *self {
+            EnumStreamFormat::NDJSON     => NDJSON_CONTENT_TYPE,
+            EnumStreamFormat::CCSDS_OEM  => CCSDS_OEM_CONTENT_TYPE,
+        }
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        match *self {
+            EnumStreamFormat::NDJSON     => "ndjson",
+            EnumStreamFormat::CCSDS_OEM  => "oem",
+        }
+    }
+}
+
+
+/**
+ * Ephemeris of one object, propagated chunk by chunk
+ */
+pub struct EphemerisStream {
+    elements:           sgp4::Elements,
+    constants:          sgp4::Constants,
+    eop_data:           Arc<EopData>,
+
+    format:             EnumStreamFormat,
+    reference_frame:    EnumReferenceFrame,
+    epoch_format:       EpochFormat,
+    cartesian:          bool,
+    geodetic:           bool,
+
+    start:              DateTime<Utc>,
+    stop:               DateTime<Utc>,
+    step_size:          u16,
+
+    // Header of the document. Sent before the first sample
+    header:             Option<String>,
+    // Index of the next sample
+    next_sample:        u64,
+    finished:           bool,
+}
+
+impl EphemerisStream {
+    /**
+     * The header contains the identifiers of the request. The warnings are only sent in NDJSON
+     */
+    pub fn new(in_elements: sgp4::Elements, in_eop_data: Arc<EopData>, in_format: EnumStreamFormat, in_msg_id: &str,
+        in_message: &OrbPropagationTleStruct, in_object_name: &str, in_start: &DateTime<Utc>, in_stop: &DateTime<Utc>,
+        in_warnings: &[String]) -> Result<Self, String>
+    {
+        let tmp_reference_frame = EnumReferenceFrame::from_string(in_message.output.reference_frame.as_str())
+            .ok_or( format!("ERROR: Invalid output reference frame: {}", in_message.output.reference_frame) )?;
+
+        let tmp_epoch_format = EpochFormat::from_string(in_message.epoch_format.as_str())
+            .ok_or( format!("ERROR: Invalid epoch format: {}", in_message.epoch_format) )?;
+
+        if in_message.step_size == 0 || in_stop < in_start {
+            return Err( String::from("ERROR: Invalid propagation window") );
+        }
+
+        let tmp_header = match in_format {
+            EnumStreamFormat::NDJSON => {
+                format!("{}\n", json!({
+                    "msg_id":           in_msg_id,
+                    "msg_code":         "orb_propagation_sgp4_stream_response",
+                    "mission_id":       in_message.mission_id,
+                    "satellite_id":     in_message.satellite_id,
+                    "reference_frame":  in_message.output.reference_frame,
+                    "epoch_format":     in_message.epoch_format,
+                    "start_time":       in_start.to_rfc3339(),
+                    "stop_time":        in_stop.to_rfc3339(),
+                    "step_size":        in_message.step_size,
+                    "warnings":         in_warnings,
+                }))
+            },
+            EnumStreamFormat::CCSDS_OEM => {
+                ccsds_oem_header(in_message.mission_id.as_str(), in_message.satellite_id.as_str(),
+                    in_message.output.reference_frame.as_str(), in_object_name, in_start.to_rfc3339().as_str(),
+                    in_stop.to_rfc3339().as_str(), in_message.output.interpolation_order,
+                    &ccsds_time_scale(&tmp_epoch_format)?)
+            },
+        };
+
+        Ok( EphemerisStream {
+            constants:          sgp4::Constants::from_elements(&in_elements)
+                                    .map_err(|e| format!("ERROR: Unable to initialize SGP4: {}", e.to_string()) )?,
+            elements:           in_elements,
+            eop_data:           in_eop_data,
+            format:             in_format,
+            reference_frame:    tmp_reference_frame,
+            epoch_format:       tmp_epoch_format,
+            cartesian:          in_message.output.cartesian.unwrap_or(true),
+            geodetic:           in_message.output.geodetic.unwrap_or(false),
+            start:              *in_start,
+            stop:               *in_stop,
+            step_size:          in_message.step_size,
+            header:             Some(tmp_header),
+            next_sample:        0,
+            finished:           false,
+        })
+    }
+
+    /**
+     * Time of a sample. The stop time is always the last sample, as in sample_times
+     * Return None after the stop time
+     */
+    fn sample_time(&self, in_index: u64) -> Option<DateTime<Utc>>
+    {
+        let tmp_time = self.start + Duration::seconds( in_index as i64 * self.step_size as i64 );
+
+        if tmp_time < self.stop {
+            return Some(tmp_time);
+        }
+
+        // First sample not before the stop time
+        if in_index == 0 || self.start + Duration::seconds( (in_index - 1) as i64 * self.step_size as i64 ) < self.stop {
+            Some(self.stop)
+        } else {
+            None
+        }
+    }
+
+    /**
+     * Propagate one sample and convert it into the output reference frame
+     */
+    fn state_vector(&self, in_time: &DateTime<Utc>) -> Result<SatelliteStateVector, String>
+    {
+        let tmp_point = propagate_at(&self.elements, &self.constants, in_time)?;
+
+        let tmp_location = if self.geodetic == true {
+            let tmp_itrf = convert_from_teme(&tmp_point, &EnumReferenceFrame::ITRF, &self.eop_data);
+            Some( GroundLocation::from_ecef(&tmp_itrf.position) )
+        } else {
+            None
+        };
+
+        let tmp_point = convert_from_teme(&tmp_point, &self.reference_frame, &self.eop_data);
+
+        Ok( SatelliteStateVector {
+            time:         tmp_point.time.to_rfc3339(),
+            position:     tmp_point.position,
+            velocity:     tmp_point.velocity,
+            latitude:     tmp_location.as_ref().map(|l| l.latitude),
+            longitude:    tmp_location.as_ref().map(|l| l.longitude),
+            altitude:     tmp_location.as_ref().map(|l| l.altitude),
+        })
+    }
+
+    /**
+     * Add one sample to the chunk
+     */
+    fn write_sample(&self, out_chunk: &mut String, in_point: &SatelliteStateVector, in_time: &DateTime<Utc>)
+    {
+        match self.format {
+            EnumStreamFormat::NDJSON => {
+                let mut tmp_value = json!(in_point);
+                tmp_value["time"] = Value::String( format_epoch(in_time, &self.epoch_format) );
+
+                // Only geodetic coordinates
+                if self.cartesian == false {
+                    if let Some(tmp_object) = tmp_value.as_object_mut() {
+                        tmp_object.remove("position");
+                        tmp_object.remove("velocity");
+                    }
+                }
+
+                out_chunk.push_str( tmp_value.to_string().as_str() );
+                out_chunk.push('\n');
+            },
+            EnumStreamFormat::CCSDS_OEM => write_ccsds_oem_line(out_chunk, in_point, &self.epoch_format.scale),
+        }
+    }
+
+    /**
+     * Report an error in the middle of the stream. The HTTP status has already been sent
+     */
+    fn write_error(&self, out_chunk: &mut String, in_error: &str)
+    {
+        match self.format {
+            EnumStreamFormat::NDJSON     => {
+                out_chunk.push_str( json!({ "error": in_error }).to_string().as_str() );
+                out_chunk.push('\n');
+            },
+            EnumStreamFormat::CCSDS_OEM  => {
+                out_chunk.push_str( format!("COMMENT {}\n", in_error).as_str() );
+            },
+        }
+    }
+
+    /**
+     * Propagate the next chunk of samples. None at the end of the ephemeris
+     */
+    fn next_chunk(&mut self) -> Option<String>
+    {
+        if let Some(tmp_header) = self.header.take() {
+            return Some(tmp_header);
+        }
+
+        if self.finished == true {
+            return None;
+        }
+
+        let mut output = String::new();
+
+        for _ in 0..STREAM_CHUNK_SAMPLES {
+            let tmp_time = match self.sample_time(self.next_sample) {
+                Some(t) => t,
+                None    => {
+                    self.finished = true;
+                    break;
+                },
+            };
+
+            match self.state_vector(&tmp_time) {
+                Ok(p) => self.write_sample(&mut output, &p, &tmp_time),
+                Err(e) => {
+                    warn!("Ephemeris stream stopped after {} samples. {}", self.next_sample, e);
+
+                    self.write_error(&mut output, e.as_str());
+                    self.finished = true;
+                    break;
+                },
+            };
+
+            self.next_sample += 1;
+        }
+
+        if self.finished == true {
+            info!("Ephemeris stream completed. Samples: {}", self.next_sample);
+        }
+
+        if output.is_empty() == true {
+            None
+        } else {
+            Some(output)
+        }
+    }
+
+    /**
+     * Body of the HTTP response. The SGP4 propagation is CPU bound, so every chunk is generated by web::block
+     * The state of the stream is moved to the blocking thread and returned with the chunk
+     */
+    pub fn into_body(self) -> LocalBoxStream<'static, Result<Bytes, actix_web::Error>>
+    {
+        stream::unfold(Some(self), |in_state| async move {
+            let tmp_stream = in_state?;
+
+            let res = web::block(move || {
+                let mut tmp_stream = tmp_stream;
+                let tmp_chunk = tmp_stream.next_chunk();
+
+                Ok::<(Option<String>, EphemerisStream), String>( (tmp_chunk, tmp_stream) )
+            }).await;
+
+            match res {
+                Ok((Some(c), s)) => Some( (Ok( Bytes::from(c) ), Some(s)) ),
+                Ok((None, _))    => None,
+                Err(err) => {
+                    let tmp_msg = match err {
+                        BlockingError::Error(e) => e,
+                        BlockingError::Canceled => String::from("Cancelled operation"),
+                    };
+
+                    error!("Ephemeris stream stopped. {}", tmp_msg);
+                    Some( (Err( actix_web::error::ErrorInternalServerError(tmp_msg) ), None) )
+                },
+            }
+        }).boxed_local()
+    }
+}
+
+impl Drop for EphemerisStream {
+    fn drop(&mut self)
+    {
+        if self.finished == false {
+            info!("Ephemeris stream cancelled by the client. Samples sent: {}", self.next_sample);
+        } else {
+            debug!("Ephemeris stream closed");
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+    use futures::executor::block_on_stream;
+
+    fn request(in_output_format: &str, in_step_size: u16) -> OrbPropagationTleStruct
+    {
+        serde_json::from_value( json!({
+            "mission_id":       "mis1",
+            "satellite_id":     "sat1",
+            "add_to_database":  false,
+            "epoch_format":     "UTCGregorian",
+            "start_time":       "2020-07-13T00:00:00Z",
+            "stop_time":        "2020-07-13T01:00:00Z",
+            "step_size":        in_step_size,
+            "initial_position": [0.0, 0.0, 0.0],
+            "initial_velocity": [0.0, 0.0, 0.0],
+            "input":            {},
+            "output":           { "reference_frame": "EME2000", "interpolation_order": 7, "output_format": in_output_format },
+        })).unwrap()
+    }
+
+    #[test]
+    fn streamed_ephemeris_matches_the_propagation() {
+        let elements = sgp4::Elements::from_tle(
+            Some(String::from("ISS (ZARYA)")),
+            "1 25544U 98067A   20194.88612269 -.00002218  00000-0 -31515-4 0  9992".as_bytes(),
+            "2 25544  51.6461 221.2784 0001413  89.1723 280.4612 15.49507896236008".as_bytes(),
+        ).unwrap();
+
+        let tmp_start = Utc.ymd(2020, 7, 13).and_hms(0, 0, 0);
+        // Not a multiple of the step size
+        let tmp_stop = tmp_start + Duration::seconds(7 * STREAM_CHUNK_SAMPLES as i64 + 3);
+        let tmp_eop = Arc::new(EopData::new());
+
+        let stream = EphemerisStream::new(elements.clone(), tmp_eop.clone(), EnumStreamFormat::NDJSON, "001",
+            &request("json", 3), "ISS", &tmp_start, &tmp_stop, &[]).unwrap();
+
+        let chunks : Vec<Bytes> = block_on_stream(stream.into_body()).map(|c| c.unwrap()).collect();
+
+        // Header and three chunks
+        assert_eq!( chunks.len(), 4 );
+
+        let tmp_text : String = chunks.iter().map(|c| String::from_utf8(c.to_vec()).unwrap()).collect();
+        let tmp_lines : Vec<Value> = tmp_text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+
+        assert_eq!( tmp_lines[0]["msg_code"], "orb_propagation_sgp4_stream_response" );
+
+        let tmp_expected = propagate(&elements, &tmp_start, &tmp_stop, 3).unwrap();
+        assert_eq!( tmp_lines.len() - 1, tmp_expected.len() );
+        assert_eq!( tmp_lines.last().unwrap()["time"], tmp_stop.to_rfc3339() );
+
+        for (current_line, current_point) in tmp_lines[1..].iter().zip(tmp_expected.iter()) {
+            let tmp_point = convert_from_teme(current_point, &EnumReferenceFrame::EME2000, &tmp_eop);
+
+            assert_eq!( current_line["time"], current_point.time.to_rfc3339() );
+            assert!( (current_line["position"][0].as_f64().unwrap() - tmp_point.position[0]).abs() < 1.0e-9 );
+        }
+
+        // OEM. Only the first chunk is read; the rest is not propagated
+        let mut stream = EphemerisStream::new(elements, tmp_eop, EnumStreamFormat::CCSDS_OEM, "001",
+            &request("ccsds-oem", 3), "ISS", &tmp_start, &tmp_stop, &[]).unwrap();
+
+        assert!( stream.next_chunk().unwrap().starts_with("CCSDS_OEM_VERS") );
+        assert_eq!( stream.next_chunk().unwrap().lines().count() as u64, STREAM_CHUNK_SAMPLES );
+        assert_eq!( stream.next_sample, STREAM_CHUNK_SAMPLES );
+    }
+}
@@ -0,0 +1,293 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Time scales (UTC, TAI, TT, GPS) and epoch formats (Gregorian and Modified Julian)
+ * The epoch formats follow the GMAT naming. I.e. UTCGregorian, TAIModJulian
+ */
+
+// Date & Time
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+
+
+// TT - TAI. Seconds
+pub const TT_TAI_OFFSET : f64 = 32.184;
+
+// TAI - GPS. Seconds
+pub const TAI_GPS_OFFSET : f64 = 19.0;
+
+// TAI - UTC (seconds) since the given date. IERS Bulletin C
+// UTC dates before 1972 use the first value
+const LEAP_SECONDS : [(i32, u32, u32, f64); 28] = [
+    (1972,  1, 1, 10.0), (1972,  7, 1, 11.0), (1973,  1, 1, 12.0), (1974,  1, 1, 13.0),
+    (1975,  1, 1, 14.0), (1976,  1, 1, 15.0), (1977,  1, 1, 16.0), (1978,  1, 1, 17.0),
+    (1979,  1, 1, 18.0), (1980,  1, 1, 19.0), (1981,  7, 1, 20.0), (1982,  7, 1, 21.0),
+    (1983,  7, 1, 22.0), (1985,  7, 1, 23.0), (1988,  1, 1, 24.0), (1990,  1, 1, 25.0),
+    (1991,  1, 1, 26.0), (1992,  7, 1, 27.0), (1993,  7, 1, 28.0), (1994,  7, 1, 29.0),
+    (1996,  1, 1, 30.0), (1997,  7, 1, 31.0), (1999,  1, 1, 32.0), (2006,  1, 1, 33.0),
+    (2009,  1, 1, 34.0), (2012,  7, 1, 35.0), (2015,  7, 1, 36.0), (2017,  1, 1, 37.0),
+];
+
+
+/**
+ * Time scales
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnumTimeScale {
+    UTC,
+    TAI,
+    TT,
+    GPS,
+}
+
+impl EnumTimeScale {
+    pub fn to_string(&self) -> String {
+        match *self {
+            EnumTimeScale::UTC   => String::from("UTC"),
+            EnumTimeScale::TAI   => String::from("TAI"),
+            EnumTimeScale::TT    => String::from("TT"),
+            EnumTimeScale::GPS   => String::from("GPS"),
+        }
+    }
+
+    /**
+     * Return None if the name is not a known time scale
+     */
+    pub fn from_string(in_scale: &str) -> Option<Self> {
+        match in_scale {
+            "UTC"   => Some(EnumTimeScale::UTC),
+            "TAI"   => Some(EnumTimeScale::TAI),
+            "TT"    => Some(EnumTimeScale::TT),
+            "GPS"   => Some(EnumTimeScale::GPS),
+            _       => None,
+        }
+    }
+}
+
+/**
+ * Representation of an epoch
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnumEpochRepresentation {
+    // Calendar date. I.e. "2000-01-01T11:59:28.000" or "01 Jan 2000 11:59:28.000"
+    GREGORIAN,
+    // Days since 05 Jan 1941 12:00:00.000. I.e. "21544.99962962963"
+    MOD_JULIAN,
+}
+
+/**
+ * Epoch format; a time scale and a representation. I.e. TAIModJulian
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochFormat {
+    pub scale:              EnumTimeScale,
+    pub representation:     EnumEpochRepresentation,
+}
+
+impl EpochFormat {
+    pub fn to_string(&self) -> String {
+        match self.representation {
+            EnumEpochRepresentation::GREGORIAN  => format!("{}Gregorian", self.scale.to_string()),
+            EnumEpochRepresentation::MOD_JULIAN => format!("{}ModJulian", self.scale.to_string()),
+        }
+    }
+
+    /**
+     * Return None if the name is not a known epoch format
+     */
+    pub fn from_string(in_format: &str) -> Option<Self> {
+        let (tmp_scale, tmp_representation) = if in_format.ends_with("Gregorian") == true {
+            (&in_format[..in_format.len() - "Gregorian".len()], EnumEpochRepresentation::GREGORIAN)
+        } else if in_format.ends_with("ModJulian") == true {
+            (&in_format[..in_format.len() - "ModJulian".len()], EnumEpochRepresentation::MOD_JULIAN)
+        } else {
+            return None;
+        };
+
+        Some( EpochFormat {
+            scale:              EnumTimeScale::from_string(tmp_scale)?,
+            representation:     tmp_representation,
+        })
+    }
+}
+
+
+/**
+ * Convert seconds into a duration, keeping the microseconds
+ */
+fn duration_seconds(in_seconds: f64) -> Duration
+{
+    Duration::microseconds( (in_seconds * 1.0e6).round() as i64 )
+}
+
+/**
+ * TAI - UTC at a UTC date. Seconds
+ */
+pub fn tai_utc(in_time: &NaiveDateTime) -> f64
+{
+    let mut output = LEAP_SECONDS[0].3;
+
+    for (year, month, day, value) in LEAP_SECONDS.iter() {
+        if *in_time >= NaiveDate::from_ymd(*year, *month, *day).and_hms(0, 0, 0) {
+            output = *value;
+        } else {
+            break;
+        }
+    }
+
+    output
+}
+
+/**
+ * Offset between a time scale and UTC. Seconds
+ */
+fn scale_utc_offset(in_scale: &EnumTimeScale, in_tai_utc: f64) -> f64
+{
+    match *in_scale {
+        EnumTimeScale::UTC   => 0.0,
+        EnumTimeScale::TAI   => in_tai_utc,
+        EnumTimeScale::TT    => in_tai_utc + TT_TAI_OFFSET,
+        EnumTimeScale::GPS   => in_tai_utc - TAI_GPS_OFFSET,
+    }
+}
+
+/**
+ * Convert a UTC time into the calendar date of another time scale
+ */
+pub fn utc_to_scale(in_time: &DateTime<Utc>, in_scale: &EnumTimeScale) -> NaiveDateTime
+{
+    let tmp_offset = scale_utc_offset(in_scale, tai_utc(&in_time.naive_utc()));
+
+    in_time.naive_utc() + duration_seconds(tmp_offset)
+}
+
+/**
+ * Convert the calendar date of a time scale into UTC
+ * The leap second is computed again with the first approximation, for dates close to a leap second
+ */
+pub fn scale_to_utc(in_time: &NaiveDateTime, in_scale: &EnumTimeScale) -> DateTime<Utc>
+{
+    let tmp_utc = *in_time - duration_seconds( scale_utc_offset(in_scale, tai_utc(in_time)) );
+    let tmp_utc = *in_time - duration_seconds( scale_utc_offset(in_scale, tai_utc(&tmp_utc)) );
+
+    DateTime::<Utc>::from_utc(tmp_utc, Utc)
+}
+
+/**
+ * Reference epoch of the Modified Julian Date used by GMAT; MJD = JD - 2430000.0
+ */
+fn gmat_mjd_epoch() -> NaiveDateTime
+{
+    NaiveDate::from_ymd(1941, 1, 5).and_hms(12, 0, 0)
+}
+
+/**
+ * Parse an epoch in the given format and return it in UTC
+ */
+pub fn parse_epoch(in_text: &str, in_format: &EpochFormat) -> Result<DateTime<Utc>, String>
+{
+    let tmp_text = in_text.trim();
+
+    let tmp_time = match in_format.representation {
+        EnumEpochRepresentation::GREGORIAN => {
+            // RFC 3339 with time zone is only valid in UTC
+            if in_format.scale == EnumTimeScale::UTC {
+                if let Ok(t) = DateTime::parse_from_rfc3339(tmp_text) {
+                    return Ok( t.with_timezone(&Utc) );
+                }
+            }
+
+            let tmp_text = tmp_text.trim_end_matches('Z');
+
+            ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%d %b %Y %H:%M:%S%.f"].iter()
+                .find_map(|f| NaiveDateTime::parse_from_str(tmp_text, f).ok())
+                .ok_or( format!("ERROR: Invalid {} epoch: {}", in_format.to_string(), in_text) )?
+        },
+        EnumEpochRepresentation::MOD_JULIAN => {
+            let tmp_days = tmp_text.parse::<f64>()
+                .map_err(|_| format!("ERROR: Invalid {} epoch: {}", in_format.to_string(), in_text) )?;
+
+            if tmp_days.is_finite() == false {
+                return Err( format!("ERROR: Invalid {} epoch: {}", in_format.to_string(), in_text) );
+            }
+
+            gmat_mjd_epoch().checked_add_signed( duration_seconds(tmp_days * 86400.0) )
+                .ok_or( format!("ERROR: {} epoch out of range: {}", in_format.to_string(), in_text) )?
+        },
+    };
+
+    Ok( scale_to_utc(&tmp_time, &in_format.scale) )
+}
+
+/**
+ * Render a UTC epoch in the given format
+ * UTCGregorian keeps the RFC 3339 format used by the rest of the service
+ */
+pub fn format_epoch(in_time: &DateTime<Utc>, in_format: &EpochFormat) -> String
+{
+    let tmp_time = utc_to_scale(in_time, &in_format.scale);
+
+    match in_format.representation {
+        EnumEpochRepresentation::GREGORIAN => {
+            if in_format.scale == EnumTimeScale::UTC {
+                in_time.to_rfc3339()
+            } else {
+                tmp_time.format("%Y-%m-%dT%H:%M:%S%.6f").to_string()
+            }
+        },
+        EnumEpochRepresentation::MOD_JULIAN => {
+            let tmp_duration = tmp_time.signed_duration_since( gmat_mjd_epoch() );
+            let tmp_seconds = match tmp_duration.num_microseconds() {
+                Some(us) => us as f64 / 1.0e6,
+                None     => tmp_duration.num_milliseconds() as f64 / 1.0e3,
+            };
+
+            format!("{:.11}", tmp_seconds / 86400.0)
+        },
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn leap_seconds() {
+        assert_eq!( tai_utc(&NaiveDate::from_ymd(2016, 12, 31).and_hms(23, 59, 59)), 36.0 );
+        assert_eq!( tai_utc(&NaiveDate::from_ymd(2017, 1, 1).and_hms(0, 0, 0)), 37.0 );
+        assert_eq!( tai_utc(&NaiveDate::from_ymd(1960, 1, 1).and_hms(0, 0, 0)), 10.0 );
+
+        // J2000 in TT is 11:58:55.816 UTC
+        let tmp_j2000 = Utc.ymd(2000, 1, 1).and_hms_milli(11, 58, 55, 816);
+        assert_eq!( utc_to_scale(&tmp_j2000, &EnumTimeScale::TT), NaiveDate::from_ymd(2000, 1, 1).and_hms(12, 0, 0) );
+        assert_eq!( scale_to_utc(&NaiveDate::from_ymd(2000, 1, 1).and_hms(12, 0, 0), &EnumTimeScale::TT), tmp_j2000 );
+    }
+
+    #[test]
+    fn epoch_formats() {
+        let tmp_format = EpochFormat::from_string("TTModJulian").unwrap();
+        assert_eq!(tmp_format.scale, EnumTimeScale::TT);
+        assert_eq!(tmp_format.to_string(), "TTModJulian");
+        assert!( EpochFormat::from_string("UTCJulian").is_none() );
+
+        // J2000 (TT) is JD 2451545.0
+        let tmp_utc = parse_epoch("21545.0", &tmp_format).unwrap();
+        assert_eq!( tmp_utc, Utc.ymd(2000, 1, 1).and_hms_milli(11, 58, 55, 816) );
+        assert_eq!( format_epoch(&tmp_utc, &tmp_format), "21545.00000000000" );
+
+        let tmp_format = EpochFormat::from_string("TAIGregorian").unwrap();
+        let tmp_utc = parse_epoch("15 Jun 2020 12:00:37.000", &tmp_format).unwrap();
+        assert_eq!( tmp_utc, Utc.ymd(2020, 6, 15).and_hms(12, 0, 0) );
+        assert_eq!( format_epoch(&tmp_utc, &tmp_format), "2020-06-15T12:00:37.000000" );
+
+        let tmp_format = EpochFormat::from_string("UTCGregorian").unwrap();
+        assert_eq!( parse_epoch("2020-06-15T14:00:00+02:00", &tmp_format).unwrap(), Utc.ymd(2020, 6, 15).and_hms(12, 0, 0) );
+    }
+}
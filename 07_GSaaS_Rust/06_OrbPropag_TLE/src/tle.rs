@@ -0,0 +1,473 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Two Line Elements catalogs. Text files in 2-line or 3-line format (name, line 1, line 2)
+ * as distributed by CelesTrak and Space-Track
+ */
+
+// Date & Time
+use chrono::{DateTime, Utc};
+
+use crate::api_messages::*;
+
+
+// Maximum size of a catalog import request. Bytes
+// A full catalog of CelesTrak is around 3 MB
+pub const MAX_SIZE_TLE_CATALOG : usize = 8 * 1024 * 1024;
+
+
+/**
+ * One entry of a catalog. Either the TLE or the description of the error
+ * The line number (starting at 1) helps to locate the error
+ */
+pub struct CatalogEntry {
+    pub line_number:    usize,
+    pub tle:            Result<TleStruct, String>,
+}
+
+
+/**
+ * Split a catalog in TLEs. Empty lines are ignored
+ * The name line is optional. A leading "0 " (Space-Track 3LE format) is removed from the name
+ */
+pub fn parse_tle_catalog(in_text: &str) -> Vec<CatalogEntry>
+{
+    let mut output : Vec<CatalogEntry> = Vec::new();
+
+    let mut tmp_name : Option<String> = None;
+    let mut tmp_line1 : Option<(usize, String)> = None;
+
+    for (i, current_line) in in_text.lines().enumerate() {
+        let tmp_line_number = i + 1;
+        let current_line = current_line.trim_end();
+
+        if current_line.trim().is_empty() == true {
+            continue;
+        }
+
+        if current_line.starts_with("1 ") == true && current_line.len() >= 64 {
+            if let Some((n, _)) = tmp_line1.take() {
+                output.push( CatalogEntry { line_number: n, tle: Err( String::from("ERROR: TLE line 1 without line 2") ) } );
+            }
+
+            tmp_line1 = Some( (tmp_line_number, String::from(current_line)) );
+        } else if current_line.starts_with("2 ") == true && current_line.len() >= 64 {
+            match tmp_line1.take() {
+                Some((n, line1)) => {
+                    output.push( CatalogEntry {
+                        line_number:    n,
+                        tle:            Ok( TleStruct {
+                                            name:   tmp_name.take(),
+                                            line1:  line1,
+                                            line2:  String::from(current_line),
+                                        }),
+                    });
+                },
+                None => {
+                    output.push( CatalogEntry { line_number: tmp_line_number, tle: Err( String::from("ERROR: TLE line 2 without line 1") ) } );
+                }
+            };
+
+            tmp_name = None;
+        } else {
+            // Name of the next object
+            if let Some((n, _)) = tmp_line1.take() {
+                output.push( CatalogEntry { line_number: n, tle: Err( String::from("ERROR: TLE line 1 without line 2") ) } );
+            }
+
+            let tmp_value = current_line.trim();
+            let tmp_value = if tmp_value.starts_with("0 ") == true { tmp_value[2..].trim() } else { tmp_value };
+
+            tmp_name = Some( String::from(tmp_value) );
+        }
+    }
+
+    if let Some((n, _)) = tmp_line1.take() {
+        output.push( CatalogEntry { line_number: n, tle: Err( String::from("ERROR: TLE line 1 without line 2") ) } );
+    }
+
+    output
+}
+
+
+/**
+ * Sum of the digits of a TLE line. A minus sign counts as 1. Letters count as 0
+ */
+fn checksum(in_bytes: &[u8]) -> u32
+{
+    in_bytes.iter().map(|c| match *c {
+        b'0'..=b'9' => (*c - b'0') as u32,
+        b'-'        => 1,
+        _           => 0,
+    }).sum()
+}
+
+/**
+ * Check the checksum of a TLE line. Column 69 is the sum modulo 10 of the
+ * digits of the first 68 columns. A minus sign counts as 1
+ */
+pub fn verify_checksum(in_line: &str) -> Result<(), String>
+{
+    let tmp_bytes = in_line.as_bytes();
+
+    if tmp_bytes.len() < 69 {
+        return Err( format!("too short: {} characters. It shall be 69", tmp_bytes.len()) );
+    }
+
+    let tmp_sum = checksum(&tmp_bytes[..68]);
+
+    match (tmp_bytes[68] as char).to_digit(10) {
+        Some(d) if d == tmp_sum % 10 => Ok(()),
+        _ => Err( format!("invalid checksum. Expected: {}  Found: '{}'", tmp_sum % 10, tmp_bytes[68] as char) ),
+    }
+}
+
+/**
+ * Columns of a TLE line. First and last column start at 1, both included
+ */
+fn columns(in_line: &str, in_first: usize, in_last: usize) -> &str
+{
+    in_line.get(in_first - 1..in_last).unwrap_or("")
+}
+
+/**
+ * Real number. I.e. " 51.6461", "-.00002218"
+ */
+fn check_real(in_line: &str, in_first: usize, in_last: usize, in_name: &str, in_min: f64, in_max: f64) -> Result<f64, String>
+{
+    let tmp_value = columns(in_line, in_first, in_last);
+
+    match tmp_value.trim().parse::<f64>() {
+        Ok(v) if v >= in_min && v <= in_max => Ok(v),
+        Ok(_) => Err( format!("columns {}-{} ({}): '{}' out of range [{}, {}]", in_first, in_last, in_name, tmp_value, in_min, in_max) ),
+        Err(_) => Err( format!("columns {}-{} ({}): '{}' is not a number", in_first, in_last, in_name, tmp_value) ),
+    }
+}
+
+/**
+ * Integer, right aligned. Leading spaces are allowed
+ */
+fn check_integer(in_line: &str, in_first: usize, in_last: usize, in_name: &str) -> Result<(), String>
+{
+    let tmp_value = columns(in_line, in_first, in_last);
+    let tmp_digits = tmp_value.trim_start();
+
+    if tmp_digits.is_empty() == true || tmp_digits.bytes().all(|c| c.is_ascii_digit()) == false {
+        return Err( format!("columns {}-{} ({}): '{}' is not an integer", in_first, in_last, in_name, tmp_value) );
+    }
+
+    Ok(())
+}
+
+/**
+ * Alpha-5 catalog number. The first digit is replaced by a letter to go beyond 99999; A = 10, ..., Z = 33
+ * I and O are not used. I.e. "A0000" is 100000 and "Z9999" is 339999
+ * Return None if the value is not an Alpha-5 number
+ */
+pub fn alpha5_catalog_number(in_value: &str) -> Option<u64>
+{
+    let tmp_bytes = in_value.as_bytes();
+
+    if tmp_bytes.len() != 5 || tmp_bytes[0].is_ascii_uppercase() == false || tmp_bytes[0] == b'I' || tmp_bytes[0] == b'O'
+        || tmp_bytes[1..].iter().all(|c| c.is_ascii_digit()) == false {
+        return None;
+    }
+
+    // Skip I and O
+    let mut tmp_first = (tmp_bytes[0] - b'A') as u64 + 10;
+
+    if tmp_bytes[0] > b'I' {
+        tmp_first -= 1;
+    }
+
+    if tmp_bytes[0] > b'O' {
+        tmp_first -= 1;
+    }
+
+    Some( tmp_first * 10000 + in_value[1..].parse::<u64>().ok()? )
+}
+
+/**
+ * Catalog number; columns 3-7. Either an integer or an Alpha-5 number
+ */
+fn check_catalog_number(in_line: &str) -> Result<(), String>
+{
+    if alpha5_catalog_number(columns(in_line, 3, 7)).is_some() == true {
+        return Ok(());
+    }
+
+    check_integer(in_line, 3, 7, "catalog number")
+        .map_err(|_| format!("columns 3-7 (catalog number): '{}' is neither an integer nor an Alpha-5 number", columns(in_line, 3, 7)) )
+}
+
+/**
+ * Replace the Alpha-5 catalog number of a line by 00000 and recompute the checksum
+ * SGP4 only parses numeric catalog numbers
+ */
+fn numeric_catalog_line(in_line: &str) -> String
+{
+    let tmp_line = format!("{}00000{}", columns(in_line, 1, 2), columns(in_line, 8, 68));
+
+    format!("{}{}", tmp_line, checksum(tmp_line.as_bytes()) % 10)
+}
+
+/**
+ * Number with an assumed decimal point and an exponent. I.e. " 28098-4" is 0.28098e-4
+ */
+fn check_exponential(in_line: &str, in_first: usize, in_last: usize, in_name: &str) -> Result<(), String>
+{
+    let tmp_value = columns(in_line, in_first, in_last).as_bytes();
+
+    let tmp_valid = tmp_value.len() == 8
+        && (tmp_value[0] == b' ' || tmp_value[0] == b'+' || tmp_value[0] == b'-')
+        && tmp_value[1..6].iter().all(|c| c.is_ascii_digit())
+        && (tmp_value[6] == b'+' || tmp_value[6] == b'-')
+        && tmp_value[7].is_ascii_digit();
+
+    if tmp_valid == false {
+        return Err( format!("columns {}-{} ({}): '{}' shall be in format [+-]NNNNN[+-]N", in_first, in_last, in_name,
+                            String::from_utf8_lossy(tmp_value)) );
+    }
+
+    Ok(())
+}
+
+/**
+ * Check the format of a TLE: line lengths, the format of every field, the checksums
+ * and that both lines belong to the same object
+ * All the problems are reported in the error
+ */
+pub fn validate_tle(in_tle: &TleStruct) -> Result<(), String>
+{
+    let mut tmp_errors : Vec<String> = Vec::new();
+
+    for (i, current_line) in [&in_tle.line1, &in_tle.line2].iter().enumerate() {
+        let tmp_number = i + 1;
+        let current_line = current_line.trim_end();
+
+        if current_line.is_ascii() == false {
+            tmp_errors.push( format!("Line {}: only ASCII characters are allowed", tmp_number) );
+            continue;
+        }
+
+        if current_line.len() != 69 {
+            tmp_errors.push( format!("Line {}: invalid length: {} characters. It shall be 69", tmp_number, current_line.len()) );
+            continue;
+        }
+
+        if columns(current_line, 1, 2) != format!("{} ", tmp_number) {
+            tmp_errors.push( format!("Line {}: it shall start with '{} '", tmp_number, tmp_number) );
+        }
+
+        let mut tmp_results : Vec<Result<(), String>> = vec![
+            check_catalog_number(current_line),
+            verify_checksum(current_line).map_err(|e| format!("column 69: {}", e) ),
+        ];
+
+        if tmp_number == 1 {
+            if "UCS".contains(columns(current_line, 8, 8)) == false {
+                tmp_results.push( Err( format!("column 8 (classification): '{}' shall be U, C or S", columns(current_line, 8, 8)) ) );
+            }
+
+            tmp_results.push( check_integer(current_line, 19, 20, "epoch year") );
+            tmp_results.push( check_real(current_line, 21, 32, "epoch day", 1.0, 367.0).map(|_| ()) );
+            tmp_results.push( check_real(current_line, 34, 43, "first derivative of the mean motion", -1.0, 1.0).map(|_| ()) );
+            tmp_results.push( check_exponential(current_line, 45, 52, "second derivative of the mean motion") );
+            tmp_results.push( check_exponential(current_line, 54, 61, "BSTAR") );
+        } else {
+            tmp_results.push( check_real(current_line, 9, 16, "inclination", 0.0, 180.0).map(|_| ()) );
+            tmp_results.push( check_real(current_line, 18, 25, "right ascension of the ascending node", 0.0, 360.0).map(|_| ()) );
+            tmp_results.push( check_integer(current_line, 27, 33, "eccentricity") );
+            tmp_results.push( check_real(current_line, 35, 42, "argument of perigee", 0.0, 360.0).map(|_| ()) );
+            tmp_results.push( check_real(current_line, 44, 51, "mean anomaly", 0.0, 360.0).map(|_| ()) );
+            tmp_results.push( check_real(current_line, 53, 63, "mean motion", 0.0, 20.0).map(|_| ()) );
+        }
+
+        for current_result in tmp_results.into_iter() {
+            if let Err(e) = current_result {
+                tmp_errors.push( format!("Line {} {}", tmp_number, e) );
+            }
+        }
+    }
+
+    // Only if both lines have a valid catalog number
+    let tmp_norad_id1 = columns(in_tle.line1.as_str(), 3, 7).trim();
+    let tmp_norad_id2 = columns(in_tle.line2.as_str(), 3, 7).trim();
+
+    if tmp_errors.is_empty() == true && tmp_norad_id1 != tmp_norad_id2 {
+        tmp_errors.push( format!("The lines belong to different objects: {} and {}", tmp_norad_id1, tmp_norad_id2) );
+    }
+
+    if tmp_errors.is_empty() == false {
+        return Err( format!("ERROR: Invalid TLE. {}", tmp_errors.join(". ")) );
+    }
+
+    Ok(())
+}
+
+/**
+ * Validate a TLE and build the SGP4 elements
+ */
+pub fn elements_from_tle(in_tle: &TleStruct) -> Result<sgp4::Elements, String>
+{
+    validate_tle(in_tle)?;
+
+    // Both lines have the same catalog number
+    let tmp_alpha5 = alpha5_catalog_number(columns(in_tle.line1.as_str(), 3, 7));
+
+    let (tmp_line1, tmp_line2) = match tmp_alpha5 {
+        Some(_) => ( numeric_catalog_line(in_tle.line1.trim_end()), numeric_catalog_line(in_tle.line2.trim_end()) ),
+        None    => ( in_tle.line1.clone(), in_tle.line2.clone() ),
+    };
+
+    let mut output = sgp4::Elements::from_tle(in_tle.name.clone(), tmp_line1.as_bytes(), tmp_line2.as_bytes())
+        .map_err(|e| format!("ERROR: Invalid TLE: {}", e.to_string()) )?;
+
+    if let Some(n) = tmp_alpha5 {
+        output.norad_id = n;
+    }
+
+    Ok(output)
+}
+
+/**
+ * Warn if the propagation window is far from the epoch of the orbital elements
+ * SGP4 errors grow with the time from the epoch
+ */
+pub fn epoch_warnings(in_elements: &sgp4::Elements, in_start: &DateTime<Utc>, in_stop: &DateTime<Utc>,
+    in_max_days: f64) -> Vec<String>
+{
+    let mut output : Vec<String> = Vec::new();
+
+    let tmp_epoch = DateTime::<Utc>::from_utc(in_elements.datetime, Utc);
+
+    let tmp_days = [in_start, in_stop].iter()
+        .map(|t| t.signed_duration_since(tmp_epoch).num_seconds().abs() as f64 / 86400.0)
+        .fold(0.0, f64::max);
+
+    if tmp_days > in_max_days {
+        output.push( format!("WARNING: The propagation window is {:.1} days from the epoch of the orbital elements ({}). Maximum recommended: {} days",
+                             tmp_days, tmp_epoch.to_rfc3339(), in_max_days) );
+    }
+
+    output
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TLE_00005_LINE1 : &str = "1 00005U 58002B   00179.78495062  .00000023  00000-0  28098-4 0  4753";
+    const TLE_00005_LINE2 : &str = "2 00005  34.2682 348.7242 1859667 331.7664  19.3264 10.82419157413667";
+
+    const TLE_06251_LINE1 : &str = "1 06251U 62025E   06176.82412014  .00008885  00000-0  12808-3 0  3985";
+    const TLE_06251_LINE2 : &str = "2 06251  58.0579  54.0425 0030035 139.1568 221.1854 15.56387291  6774";
+
+    #[test]
+    fn catalog_with_and_without_names() {
+        let tmp_catalog = format!("0 VANGUARD 1\n{}\n{}\n\n{}\n{}\n", TLE_00005_LINE1, TLE_00005_LINE2, TLE_06251_LINE1, TLE_06251_LINE2);
+
+        let entries = parse_tle_catalog(tmp_catalog.as_str());
+        assert_eq!(entries.len(), 2);
+
+        let first = entries[0].tle.as_ref().unwrap();
+        assert_eq!(entries[0].line_number, 2);
+        assert_eq!(first.name, Some(String::from("VANGUARD 1")));
+        assert_eq!(first.line2, TLE_00005_LINE2);
+
+        let second = entries[1].tle.as_ref().unwrap();
+        assert_eq!(entries[1].line_number, 5);
+        assert_eq!(second.name, None);
+    }
+
+    #[test]
+    fn incomplete_entries_are_reported() {
+        let tmp_catalog = format!("SAT A\n{}\nSAT B\n{}\n{}\n{}\n", TLE_00005_LINE1, TLE_06251_LINE1, TLE_06251_LINE2, TLE_00005_LINE2);
+
+        let entries = parse_tle_catalog(tmp_catalog.as_str());
+        assert_eq!(entries.len(), 3);
+
+        assert!( entries[0].tle.is_err() );
+        assert_eq!( entries[1].tle.as_ref().unwrap().name, Some(String::from("SAT B")) );
+        assert!( entries[2].tle.is_err() );
+        assert_eq!( entries[2].line_number, 6 );
+    }
+
+    #[test]
+    fn checksums_and_formats_are_verified() {
+        let tmp_tle = TleStruct { name: None, line1: String::from(TLE_00005_LINE1), line2: String::from(TLE_00005_LINE2) };
+        assert!( validate_tle(&tmp_tle).is_ok() );
+
+        // One digit changed
+        let tmp_tle = TleStruct { name: None, line1: TLE_00005_LINE1.replace("28098-4", "28099-4"), line2: String::from(TLE_00005_LINE2) };
+        let tmp_error = validate_tle(&tmp_tle).unwrap_err();
+        assert!( tmp_error.contains("Line 1 column 69") );
+
+        // Lines of different objects
+        let tmp_tle = TleStruct { name: None, line1: String::from(TLE_00005_LINE1), line2: String::from(TLE_06251_LINE2) };
+        assert!( validate_tle(&tmp_tle).unwrap_err().contains("different objects") );
+
+        // Inclination out of range. The checksum is kept valid
+        let tmp_tle = TleStruct { name: None, line1: String::from(TLE_00005_LINE1), line2: TLE_00005_LINE2.replace(" 34.2682", "181.0582") };
+        let tmp_error = validate_tle(&tmp_tle).unwrap_err();
+        assert!( tmp_error.contains("columns 9-16 (inclination)") );
+        assert!( tmp_error.contains("column 69") == false );
+
+        let tmp_tle = TleStruct { name: None, line1: String::from(&TLE_00005_LINE1[..60]), line2: String::from(TLE_00005_LINE2) };
+        assert!( validate_tle(&tmp_tle).unwrap_err().contains("Line 1: invalid length") );
+    }
+
+    #[test]
+    fn alpha5_catalog_numbers() {
+        assert_eq!( alpha5_catalog_number("A0000"), Some(100000) );
+        assert_eq!( alpha5_catalog_number("H9999"), Some(179999) );
+        assert_eq!( alpha5_catalog_number("J0000"), Some(180000) );
+        assert_eq!( alpha5_catalog_number("P0000"), Some(230000) );
+        assert_eq!( alpha5_catalog_number("Z9999"), Some(339999) );
+        assert_eq!( alpha5_catalog_number("I0000"), None );
+        assert_eq!( alpha5_catalog_number("O0000"), None );
+        assert_eq!( alpha5_catalog_number("06251"), None );
+        assert_eq!( alpha5_catalog_number("a6251"), None );
+
+        // A letter counts as 0 in the checksum. So, the lines are still valid
+        let tmp_tle = TleStruct {
+            name:   None,
+            line1:  TLE_06251_LINE1.replacen("06251", "A6251", 1),
+            line2:  TLE_06251_LINE2.replacen("06251", "A6251", 1),
+        };
+
+        let tmp_elements = elements_from_tle(&tmp_tle).unwrap();
+        let tmp_numeric = sgp4::Elements::from_tle(None, TLE_06251_LINE1.as_bytes(), TLE_06251_LINE2.as_bytes()).unwrap();
+
+        assert_eq!( tmp_elements.norad_id, 106251 );
+        assert_eq!( tmp_elements.datetime, tmp_numeric.datetime );
+        assert_eq!( tmp_elements.mean_motion, tmp_numeric.mean_motion );
+
+        for current_letter in ["I", "O", "a"].iter() {
+            let tmp_tle = TleStruct {
+                name:   None,
+                line1:  TLE_06251_LINE1.replacen("06251", format!("{}6251", current_letter).as_str(), 1),
+                line2:  TLE_06251_LINE2.replacen("06251", format!("{}6251", current_letter).as_str(), 1),
+            };
+
+            assert!( validate_tle(&tmp_tle).unwrap_err().contains("Alpha-5") );
+        }
+    }
+
+    #[test]
+    fn stale_elements_are_reported() {
+        let tmp_elements = sgp4::Elements::from_tle(None, TLE_06251_LINE1.as_bytes(), TLE_06251_LINE2.as_bytes()).unwrap();
+        let tmp_epoch = DateTime::<Utc>::from_utc(tmp_elements.datetime, Utc);
+
+        let tmp_stop = tmp_epoch + chrono::Duration::days(1);
+        assert!( epoch_warnings(&tmp_elements, &tmp_epoch, &tmp_stop, 7.0).is_empty() );
+
+        let tmp_stop = tmp_epoch + chrono::Duration::days(10);
+        assert_eq!( epoch_warnings(&tmp_elements, &tmp_epoch, &tmp_stop, 7.0).len(), 1 );
+    }
+}
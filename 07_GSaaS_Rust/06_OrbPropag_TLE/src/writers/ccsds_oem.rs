@@ -0,0 +1,244 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * CCSDS Orbit Ephemeris Message (OEM) writer. Text format (KVN)
+ * See CCSDS 502.0-B-2 Orbit Data Messages
+ */
+use std::fmt::Write;
+
+// Date & Time
+use chrono::{Utc};
+
+use crate::api_messages::*;
+use crate::time_scales::*;
+use super::{EphemerisWriter, parse_ephemeris_time, response_epoch_format};
+
+
+pub const CCSDS_OEM_VERSION : &str = "2.0";
+pub const CCSDS_OEM_ORIGINATOR : &str = "INCOMPLETE WORLDS GSAAS";
+
+// Returned content type of an OEM document
+pub const CCSDS_OEM_CONTENT_TYPE : &str = "text/plain; charset=utf-8";
+
+
+pub struct CcsdsOemWriter {
+}
+
+impl EphemerisWriter for CcsdsOemWriter {
+    fn content_type(&self) -> &'static str {
+        CCSDS_OEM_CONTENT_TYPE
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "oem"
+    }
+
+    fn check_epoch_format(&self, in_format: &EpochFormat) -> Result<(), String> {
+        ccsds_time_scale(in_format).map(|_| ())
+    }
+
+    fn write(&self, in_data: &OrbPropagationTleResponseStruct, in_object_name: &str,
+        in_output: &OutputTleStruct) -> Result<String, String>
+    {
+        generate_ccsds_oem(in_data, in_object_name, in_output.interpolation_order)
+    }
+}
+
+
+/**
+ * Convert the name of a reference frame (GMAT naming) into the CCSDS name
+ */
+pub fn ccsds_reference_frame(in_reference_frame: &str) -> String
+{
+    match in_reference_frame {
+        "EarthMJ2000Eq" | "EME2000" | "J2000"  => String::from("EME2000"),
+        "EarthICRF" | "GCRF"                   => String::from("GCRF"),
+        "EarthFixed" | "ITRF"                  => String::from("ITRF"),
+        "TEME"                                 => String::from("TEME"),
+        _                                      => in_reference_frame.to_uppercase(),
+    }
+}
+
+/**
+ * Return the time system (TIME_SYSTEM) of an epoch format
+ * The epochs of an OEM are calendar dates. So, the Modified Julian formats are not supported
+ */
+pub fn ccsds_time_scale(in_format: &EpochFormat) -> Result<EnumTimeScale, String>
+{
+    match in_format.representation {
+        EnumEpochRepresentation::GREGORIAN  => Ok( in_format.scale.clone() ),
+        EnumEpochRepresentation::MOD_JULIAN => Err( format!("ERROR: Epoch format {} is not supported by CCSDS OEM. Use {}Gregorian",
+                                                            in_format.to_string(), in_format.scale.to_string()) ),
+    }
+}
+
+/**
+ * Convert the time of an ephemeris (UTC) into the CCSDS format in the given time system; YYYY-MM-DDThh:mm:ss.sss
+ * If it cannot be parsed, it is returned as it is
+ */
+fn ccsds_time(in_time: &str, in_time_scale: &EnumTimeScale) -> String
+{
+    match parse_ephemeris_time(in_time) {
+        Some(t) => utc_to_scale(&t, in_time_scale).format("%Y-%m-%dT%H:%M:%S%.3f").to_string(),
+        None    => String::from(in_time),
+    }
+}
+
+/**
+ * Generate the header, the metadata and the first comment of a CCSDS OEM document
+ * Start and stop time in RFC 3339 format. They are written in the time system
+ */
+pub fn ccsds_oem_header(in_mission_id: &str, in_satellite_id: &str, in_reference_frame: &str, in_object_name: &str,
+    in_start_time: &str, in_stop_time: &str, in_interpolation_order: u16, in_time_scale: &EnumTimeScale) -> String
+{
+    let mut output = String::new();
+
+    let tmp_start_time = ccsds_time(in_start_time, in_time_scale);
+    let tmp_stop_time = ccsds_time(in_stop_time, in_time_scale);
+
+    // Header
+    writeln!(output, "CCSDS_OEM_VERS = {}", CCSDS_OEM_VERSION).unwrap();
+    writeln!(output, "CREATION_DATE  = {}", Utc::now().format("%Y-%m-%dT%H:%M:%S%.3f")).unwrap();
+    writeln!(output, "ORIGINATOR     = {}", CCSDS_OEM_ORIGINATOR).unwrap();
+    writeln!(output).unwrap();
+
+    // Metadata
+    writeln!(output, "META_START").unwrap();
+    writeln!(output, "OBJECT_NAME          = {}", in_object_name).unwrap();
+    writeln!(output, "OBJECT_ID            = {}", in_satellite_id).unwrap();
+    writeln!(output, "CENTER_NAME          = EARTH").unwrap();
+    writeln!(output, "REF_FRAME            = {}", ccsds_reference_frame(in_reference_frame)).unwrap();
+    writeln!(output, "TIME_SYSTEM          = {}", in_time_scale.to_string()).unwrap();
+    writeln!(output, "START_TIME           = {}", tmp_start_time).unwrap();
+    writeln!(output, "USEABLE_START_TIME   = {}", tmp_start_time).unwrap();
+    writeln!(output, "USEABLE_STOP_TIME    = {}", tmp_stop_time).unwrap();
+    writeln!(output, "STOP_TIME            = {}", tmp_stop_time).unwrap();
+
+    if in_interpolation_order > 0 {
+        writeln!(output, "INTERPOLATION        = LAGRANGE").unwrap();
+        writeln!(output, "INTERPOLATION_DEGREE = {}", in_interpolation_order).unwrap();
+    }
+    writeln!(output, "META_STOP").unwrap();
+    writeln!(output).unwrap();
+
+    // Data lines
+    writeln!(output, "COMMENT Mission: {}. Position in km, velocity in km/s", in_mission_id).unwrap();
+
+    output
+}
+
+/**
+ * Add one data line of a CCSDS OEM document. The time of the point is in UTC (RFC 3339)
+ */
+pub fn write_ccsds_oem_line(out_text: &mut String, in_point: &SatelliteStateVector, in_time_scale: &EnumTimeScale)
+{
+    writeln!(out_text, "{}   {:.15e}   {:.15e}   {:.15e}   {:.15e}   {:.15e}   {:.15e}",
+        ccsds_time(in_point.time.as_str(), in_time_scale),
+        in_point.position[0], in_point.position[1], in_point.position[2],
+        in_point.velocity[0], in_point.velocity[1], in_point.velocity[2]).unwrap();
+}
+
+/**
+ * Generate a CCSDS OEM document with the list of ephemeris
+ * Positions in km and velocities in km/s. The time system is the one of the epoch format
+ */
+pub fn generate_ccsds_oem(in_data: &OrbPropagationTleResponseStruct, in_object_name: &str,
+    in_interpolation_order: u16) -> Result<String, String>
+{
+    let tmp_time_scale = ccsds_time_scale( &response_epoch_format(in_data)? )?;
+
+    let tmp_start_time = match in_data.ephemeris.first() {
+        Some(e) => e.time.as_str(),
+        None    => "",
+    };
+
+    let tmp_stop_time = match in_data.ephemeris.last() {
+        Some(e) => e.time.as_str(),
+        None    => "",
+    };
+
+    let mut output = ccsds_oem_header(in_data.mission_id.as_str(), in_data.satellite_id.as_str(), in_data.reference_frame.as_str(),
+        in_object_name, tmp_start_time, tmp_stop_time, in_interpolation_order, &tmp_time_scale);
+
+    for current_point in in_data.ephemeris.iter() {
+        write_ccsds_oem_line(&mut output, current_point, &tmp_time_scale);
+    }
+
+    Ok(output)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writers::{test_output, test_response};
+
+    // The creation date changes in every document
+    fn without_creation_date(in_text: &str) -> String
+    {
+        in_text.lines().map(|l| {
+            if l.starts_with("CREATION_DATE") == true { "CREATION_DATE  = -" } else { l }
+        }).collect::<Vec<&str>>().join("\n") + "\n"
+    }
+
+    #[test]
+    fn oem_document() {
+        let tmp_text = CcsdsOemWriter{}.write(&test_response("UTCGregorian"), "ISS", &test_output("ccsds-oem", serde_json::Value::Null)).unwrap();
+
+        let expected = [
+            "CCSDS_OEM_VERS = 2.0",
+            "CREATION_DATE  = -",
+            "ORIGINATOR     = INCOMPLETE WORLDS GSAAS",
+            "",
+            "META_START",
+            "OBJECT_NAME          = ISS",
+            "OBJECT_ID            = sat1",
+            "CENTER_NAME          = EARTH",
+            "REF_FRAME            = EME2000",
+            "TIME_SYSTEM          = UTC",
+            "START_TIME           = 2020-07-13T00:00:00.000",
+            "USEABLE_START_TIME   = 2020-07-13T00:00:00.000",
+            "USEABLE_STOP_TIME    = 2020-07-13T00:01:00.000",
+            "STOP_TIME            = 2020-07-13T00:01:00.000",
+            "INTERPOLATION        = LAGRANGE",
+            "INTERPOLATION_DEGREE = 7",
+            "META_STOP",
+            "",
+            "COMMENT Mission: mis1. Position in km, velocity in km/s",
+            "2020-07-13T00:00:00.000   7.000000000000000e3   -1.500000000000000e0   2.500000000000000e-1   5.000000000000000e-1   7.500000000000000e0   -1.250000000000000e-1",
+            "2020-07-13T00:01:00.000   6.999500000000000e3   4.500000000000000e2   1.000000000000000e0   -6.250000000000000e-2   7.250000000000000e0   3.750000000000000e-1",
+        ].join("\n") + "\n";
+
+        assert!(tmp_text.lines().nth(1).unwrap().starts_with("CREATION_DATE  = 20"));
+        assert_eq!(without_creation_date(tmp_text.as_str()), expected);
+    }
+
+    #[test]
+    fn oem_time_system() {
+        // TAI - UTC = 37 s in 2020
+        let tmp_text = generate_ccsds_oem(&test_response("TAIGregorian"), "ISS", 0).unwrap();
+
+        assert!(tmp_text.contains("TIME_SYSTEM          = TAI\n"));
+        assert!(tmp_text.contains("START_TIME           = 2020-07-13T00:00:37.000\n"));
+        assert!(tmp_text.contains("STOP_TIME            = 2020-07-13T00:01:37.000\n"));
+        assert!(tmp_text.contains("\n2020-07-13T00:00:37.000   7.000000000000000e3"));
+        assert!(tmp_text.contains("INTERPOLATION") == false);
+
+        // GPS = TAI - 19 s
+        let tmp_text = generate_ccsds_oem(&test_response("GPSGregorian"), "ISS", 0).unwrap();
+
+        assert!(tmp_text.contains("TIME_SYSTEM          = GPS\n"));
+        assert!(tmp_text.contains("\n2020-07-13T00:01:18.000   6.999500000000000e3"));
+    }
+
+    #[test]
+    fn oem_rejects_mod_julian() {
+        assert!(generate_ccsds_oem(&test_response("UTCModJulian"), "ISS", 7).is_err());
+        assert!(CcsdsOemWriter{}.check_epoch_format( &EpochFormat::from_string("TTModJulian").unwrap() ).is_err());
+        assert!(CcsdsOemWriter{}.check_epoch_format( &EpochFormat::from_string("TTGregorian").unwrap() ).is_ok());
+    }
+}
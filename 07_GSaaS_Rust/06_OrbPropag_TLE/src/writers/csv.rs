@@ -0,0 +1,164 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * CSV ephemeris writer. Columns, units and separator are configurable
+ */
+use std::fmt::Write;
+
+use crate::api_messages::*;
+use crate::time_scales::*;
+use super::{EphemerisWriter, parse_ephemeris_time, response_epoch_format};
+
+
+pub const CSV_CONTENT_TYPE : &str = "text/csv; charset=utf-8";
+
+// Default list of columns
+const CSV_DEFAULT_COLUMNS : [&str; 7] = ["time", "x", "y", "z", "vx", "vy", "vz"];
+
+
+pub struct CsvWriter {
+}
+
+impl EphemerisWriter for CsvWriter {
+    fn content_type(&self) -> &'static str {
+        CSV_CONTENT_TYPE
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "csv"
+    }
+
+    // All the epoch formats are supported
+    fn check_epoch_format(&self, _in_format: &EpochFormat) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn write(&self, in_data: &OrbPropagationTleResponseStruct, _in_object_name: &str,
+        in_output: &OutputTleStruct) -> Result<String, String>
+    {
+        let tmp_epoch_format = response_epoch_format(in_data)?;
+
+        let tmp_options = match in_output.csv {
+            Some(ref o) => o.clone(),
+            None        => CsvOutputStruct {
+                columns:    None,
+                units:      None,
+                separator:  None,
+                header:     None,
+            },
+        };
+
+        let tmp_columns : Vec<String> = match tmp_options.columns {
+            Some(ref c) if c.is_empty() == false => c.clone(),
+            _ => CSV_DEFAULT_COLUMNS.iter().map(|c| String::from(*c)).collect(),
+        };
+
+        // Scale factor from km to the output units
+        let (tmp_scale, tmp_distance_units, tmp_velocity_units) = match tmp_options.units.as_deref() {
+            None | Some("km") => (1.0, "km", "km/s"),
+            Some("m")         => (1000.0, "m", "m/s"),
+            Some(u)           => return Err( format!("ERROR: Invalid CSV units: {}. Allowed values; km, m", u) ),
+        };
+
+        let tmp_separator = match tmp_options.separator {
+            Some(ref s) if s.is_empty() == false => s.clone(),
+            _ => String::from(","),
+        };
+
+        // Check the name of the columns before generating anything
+        for current_column in tmp_columns.iter() {
+            if CSV_DEFAULT_COLUMNS.contains(&current_column.as_str()) == false {
+                return Err( format!("ERROR: Invalid CSV column: {}. Allowed values; {}", current_column, CSV_DEFAULT_COLUMNS.join(", ")) );
+            }
+        }
+
+        let mut output = String::new();
+
+        if tmp_options.header.unwrap_or(true) == true {
+            let tmp_header : Vec<String> = tmp_columns.iter().map(|c| {
+                match c.as_str() {
+                    "time"             => format!("time ({})", tmp_epoch_format.to_string()),
+                    "x" | "y" | "z"    => format!("{} ({})", c, tmp_distance_units),
+                    _                  => format!("{} ({})", c, tmp_velocity_units),
+                }
+            }).collect();
+
+            writeln!(output, "{}", tmp_header.join(tmp_separator.as_str())).unwrap();
+        }
+
+        for current_point in in_data.ephemeris.iter() {
+            let tmp_values : Vec<String> = tmp_columns.iter().map(|c| {
+                match c.as_str() {
+                    "time" => match parse_ephemeris_time(current_point.time.as_str()) {
+                        Some(t) => format_epoch(&t, &tmp_epoch_format),
+                        None    => current_point.time.clone(),
+                    },
+                    "x"    => format!("{:.6}", current_point.position[0] * tmp_scale),
+                    "y"    => format!("{:.6}", current_point.position[1] * tmp_scale),
+                    "z"    => format!("{:.6}", current_point.position[2] * tmp_scale),
+                    "vx"   => format!("{:.9}", current_point.velocity[0] * tmp_scale),
+                    "vy"   => format!("{:.9}", current_point.velocity[1] * tmp_scale),
+                    _      => format!("{:.9}", current_point.velocity[2] * tmp_scale),
+                }
+            }).collect();
+
+            writeln!(output, "{}", tmp_values.join(tmp_separator.as_str())).unwrap();
+        }
+
+        Ok(output)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::writers::{test_output, test_response};
+
+    #[test]
+    fn csv_default_options() {
+        let tmp_text = CsvWriter{}.write(&test_response("UTCGregorian"), "ISS", &test_output("csv", json!(null))).unwrap();
+
+        let expected = [
+            "time (UTCGregorian),x (km),y (km),z (km),vx (km/s),vy (km/s),vz (km/s)",
+            "2020-07-13T00:00:00+00:00,7000.000000,-1.500000,0.250000,0.500000000,7.500000000,-0.125000000",
+            "2020-07-13T00:01:00+00:00,6999.500000,450.000000,1.000000,-0.062500000,7.250000000,0.375000000",
+        ].join("\n") + "\n";
+
+        assert_eq!(tmp_text, expected);
+    }
+
+    #[test]
+    fn csv_options() {
+        let tmp_output = test_output("csv", json!({ "columns": ["time", "x", "vz"], "units": "m", "separator": ";" }));
+        let tmp_text = CsvWriter{}.write(&test_response("TAIGregorian"), "ISS", &tmp_output).unwrap();
+
+        let expected = [
+            "time (TAIGregorian);x (m);vz (m/s)",
+            "2020-07-13T00:00:37.000000;7000000.000000;-125.000000000",
+            "2020-07-13T00:01:37.000000;6999500.000000;375.000000000",
+        ].join("\n") + "\n";
+
+        assert_eq!(tmp_text, expected);
+
+        // Without header
+        let tmp_output = test_output("csv", json!({ "columns": ["time"], "header": false }));
+        let tmp_text = CsvWriter{}.write(&test_response("UTCModJulian"), "ISS", &tmp_output).unwrap();
+
+        assert_eq!(tmp_text, "29043.50000000000\n29043.50069444444\n");
+    }
+
+    #[test]
+    fn csv_wrong_options() {
+        let tmp_output = test_output("csv", json!({ "columns": ["time", "w"] }));
+        assert!(CsvWriter{}.write(&test_response("UTCGregorian"), "ISS", &tmp_output).is_err());
+
+        let tmp_output = test_output("csv", json!({ "units": "ft" }));
+        assert!(CsvWriter{}.write(&test_response("UTCGregorian"), "ISS", &tmp_output).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * Ephemeris writers. Each output format (other than JSON) is generated
+ * by a writer selected by the 'output_format' field of the request
+ */
+
+// List of 'modules' = files that compose the 'writers' module
+pub mod ccsds_oem;
+pub mod csv;
+pub mod stk;
+
+// Date & Time
+use chrono::{DateTime, Utc};
+
+use crate::api_messages::*;
+use crate::time_scales::*;
+
+use ccsds_oem::CcsdsOemWriter;
+use csv::CsvWriter;
+use stk::StkWriter;
+
+
+/**
+ * Common interface of all the ephemeris writers
+ */
+pub trait EphemerisWriter {
+    // Content type of the HTTP response
+    fn content_type(&self) -> &'static str;
+
+    // Extension of the generated file. Without dot
+    fn file_extension(&self) -> &'static str;
+
+    // Check if the epoch format can be used in the document
+    fn check_epoch_format(&self, in_format: &EpochFormat) -> Result<(), String>;
+
+    // Generate the ephemeris document
+    fn write(&self, in_data: &OrbPropagationTleResponseStruct, in_object_name: &str,
+        in_output: &OutputTleStruct) -> Result<String, String>;
+}
+
+
+/**
+ * Return the writer associated to an output format
+ * None, if the format is unknown or it is JSON (default output)
+ */
+pub fn get_ephemeris_writer(in_output_format: &str) -> Option<Box<dyn EphemerisWriter>>
+{
+    match in_output_format {
+        "ccsds-oem" | "CCSDS-OEM"         => Some( Box::new(CcsdsOemWriter {}) ),
+        "csv" | "CSV"                     => Some( Box::new(CsvWriter {}) ),
+        "stk" | "STK" | "stk-e" | "STK-E" => Some( Box::new(StkWriter {}) ),
+        _                                 => None,
+    }
+}
+
+/**
+ * Return true if the output format is supported; JSON or any of the writers
+ */
+pub fn is_valid_output_format(in_output_format: &str) -> bool
+{
+    match in_output_format {
+        "json" | "JSON" => true,
+        _               => get_ephemeris_writer(in_output_format).is_some(),
+    }
+}
+
+/**
+ * Return the epoch format of the times of the document
+ */
+pub fn response_epoch_format(in_data: &OrbPropagationTleResponseStruct) -> Result<EpochFormat, String>
+{
+    EpochFormat::from_string(in_data.epoch_format.as_str())
+        .ok_or( format!("ERROR: Invalid epoch format: {}", in_data.epoch_format) )
+}
+
+/**
+ * Parse the time of an ephemeris point (RFC3339)
+ */
+pub fn parse_ephemeris_time(in_time: &str) -> Option<DateTime<Utc>>
+{
+    match DateTime::parse_from_rfc3339(in_time) {
+        Ok(t)  => Some( t.with_timezone(&Utc) ),
+        Err(_) => None,
+    }
+}
+
+
+/**
+ * Ephemeris shared by the tests of the writers. Two points, one minute apart
+ * The values are exact in binary, so the text is the same in every platform
+ */
+#[cfg(test)]
+pub fn test_response(in_epoch_format: &str) -> OrbPropagationTleResponseStruct
+{
+    let point = |t: &str, p: [f64; 3], v: [f64; 3]| SatelliteStateVector {
+        time:       String::from(t),
+        position:   p,
+        velocity:   v,
+        latitude:   None,
+        longitude:  None,
+        altitude:   None,
+    };
+
+    OrbPropagationTleResponseStruct {
+        mission_id:         String::from("mis1"),
+        satellite_id:       String::from("sat1"),
+        reference_frame:    String::from("EME2000"),
+        epoch_format:       String::from(in_epoch_format),
+        ephemeris:          vec![
+            point("2020-07-13T00:00:00+00:00", [7000.0, -1.5, 0.25], [0.5, 7.5, -0.125]),
+            point("2020-07-13T00:01:00+00:00", [6999.5, 450.0, 1.0], [-0.0625, 7.25, 0.375]),
+        ],
+        ground_track:       None,
+        events:             None,
+        ephemeris_version:  None,
+        warnings:           Vec::new(),
+    }
+}
+
+#[cfg(test)]
+pub fn test_output(in_output_format: &str, in_csv: serde_json::Value) -> OutputTleStruct
+{
+    serde_json::from_value( serde_json::json!({
+        "reference_frame":      "EME2000",
+        "interpolation_order":  7,
+        "output_format":        in_output_format,
+        "csv":                  in_csv,
+    })).unwrap()
+}
@@ -0,0 +1,157 @@
+/**
+ * (c) Incomplete Worlds 2021
+ * Alberto Fernandez (ajfg)
+ *
+ * GS as a Service
+ * Orbit Propagation - SGP4 - TLE
+ *
+ * STK ephemeris writer (.e file). Format EphemerisTimePosVel
+ * Times are seconds since the scenario epoch (first ephemeris)
+ */
+use std::fmt::Write;
+
+use crate::api_messages::*;
+use crate::time_scales::*;
+use super::{EphemerisWriter, parse_ephemeris_time, response_epoch_format};
+
+
+pub const STK_CONTENT_TYPE : &str = "text/plain; charset=utf-8";
+pub const STK_VERSION : &str = "stk.v.11.0";
+
+
+/**
+ * Convert the name of a reference frame (GMAT naming) into the STK coordinate system
+ */
+pub fn stk_coordinate_system(in_reference_frame: &str) -> String
+{
+    match in_reference_frame {
+        "EarthMJ2000Eq" | "EME2000" | "J2000"  => String::from("J2000"),
+        "EarthICRF" | "GCRF"                   => String::from("ICRF"),
+        "EarthFixed" | "ITRF"                  => String::from("Fixed"),
+        "TEME"                                 => String::from("TEMEOfDate"),
+        _                                      => String::from(in_reference_frame),
+    }
+}
+
+
+pub struct StkWriter {
+}
+
+impl EphemerisWriter for StkWriter {
+    fn content_type(&self) -> &'static str {
+        STK_CONTENT_TYPE
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "e"
+    }
+
+    // The scenario epoch of STK is always UTC Gregorian (UTCG). The times are seconds since the epoch
+    fn check_epoch_format(&self, in_format: &EpochFormat) -> Result<(), String> {
+        if in_format.scale != EnumTimeScale::UTC || in_format.representation != EnumEpochRepresentation::GREGORIAN {
+            return Err( format!("ERROR: Epoch format {} is not supported by STK. Use UTCGregorian", in_format.to_string()) );
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, in_data: &OrbPropagationTleResponseStruct, in_object_name: &str,
+        in_output: &OutputTleStruct) -> Result<String, String>
+    {
+        self.check_epoch_format( &response_epoch_format(in_data)? )?;
+
+        // Convert the times. The first one is the scenario epoch
+        let mut tmp_times = Vec::with_capacity(in_data.ephemeris.len());
+
+        for current_point in in_data.ephemeris.iter() {
+            match parse_ephemeris_time(current_point.time.as_str()) {
+                Some(t) => tmp_times.push(t),
+                None    => return Err( format!("ERROR: Invalid ephemeris time: {}", current_point.time) ),
+            };
+        }
+
+        let tmp_epoch = match tmp_times.first() {
+            Some(t) => *t,
+            None    => return Err( String::from("ERROR: The ephemeris is empty") ),
+        };
+
+        let mut output = String::new();
+
+        writeln!(output, "{}", STK_VERSION).unwrap();
+        writeln!(output, "# {} - Mission: {} Satellite: {}", in_object_name, in_data.mission_id, in_data.satellite_id).unwrap();
+        writeln!(output).unwrap();
+        writeln!(output, "BEGIN Ephemeris").unwrap();
+        writeln!(output).unwrap();
+        writeln!(output, "NumberOfEphemerisPoints {}", in_data.ephemeris.len()).unwrap();
+        writeln!(output, "ScenarioEpoch {}", tmp_epoch.format("%d %b %Y %H:%M:%S%.6f")).unwrap();
+
+        if in_output.interpolation_order > 0 {
+            writeln!(output, "InterpolationMethod Lagrange").unwrap();
+            writeln!(output, "InterpolationOrder {}", in_output.interpolation_order).unwrap();
+        }
+
+        writeln!(output, "CentralBody Earth").unwrap();
+        writeln!(output, "CoordinateSystem {}", stk_coordinate_system(in_data.reference_frame.as_str())).unwrap();
+        writeln!(output, "DistanceUnit Kilometers").unwrap();
+        writeln!(output).unwrap();
+        writeln!(output, "EphemerisTimePosVel").unwrap();
+        writeln!(output).unwrap();
+
+        for (current_point, current_time) in in_data.ephemeris.iter().zip(tmp_times.iter()) {
+            let tmp_seconds = current_time.signed_duration_since(tmp_epoch).num_microseconds().unwrap_or(0) as f64 / 1.0e6;
+
+            writeln!(output, "{:>18.6} {:>18.9} {:>18.9} {:>18.9} {:>15.9} {:>15.9} {:>15.9}",
+                tmp_seconds,
+                current_point.position[0], current_point.position[1], current_point.position[2],
+                current_point.velocity[0], current_point.velocity[1], current_point.velocity[2]).unwrap();
+        }
+
+        writeln!(output).unwrap();
+        writeln!(output, "END Ephemeris").unwrap();
+
+        Ok(output)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::writers::{test_output, test_response};
+
+    #[test]
+    fn stk_document() {
+        let tmp_text = StkWriter{}.write(&test_response("UTCGregorian"), "ISS", &test_output("stk", json!(null))).unwrap();
+
+        let expected = [
+            "stk.v.11.0",
+            "# ISS - Mission: mis1 Satellite: sat1",
+            "",
+            "BEGIN Ephemeris",
+            "",
+            "NumberOfEphemerisPoints 2",
+            "ScenarioEpoch 13 Jul 2020 00:00:00.000000",
+            "InterpolationMethod Lagrange",
+            "InterpolationOrder 7",
+            "CentralBody Earth",
+            "CoordinateSystem J2000",
+            "DistanceUnit Kilometers",
+            "",
+            "EphemerisTimePosVel",
+            "",
+            "          0.000000     7000.000000000       -1.500000000        0.250000000     0.500000000     7.500000000    -0.125000000",
+            "         60.000000     6999.500000000      450.000000000        1.000000000    -0.062500000     7.250000000     0.375000000",
+            "",
+            "END Ephemeris",
+        ].join("\n") + "\n";
+
+        assert_eq!(tmp_text, expected);
+    }
+
+    #[test]
+    fn stk_only_utc_gregorian() {
+        assert!(StkWriter{}.write(&test_response("TAIGregorian"), "ISS", &test_output("stk", json!(null))).is_err());
+        assert!(StkWriter{}.write(&test_response("UTCModJulian"), "ISS", &test_output("stk", json!(null))).is_err());
+    }
+}
@@ -1,3 +1,12 @@
 #!/bin/bash
 
+# Earth Orientation Parameters. Download them if the file does not contain any record
+EOP_FILENAME=config/EOP-All.csv
+EOP_URL=https://celestrak.org/SpaceData/EOP-All.csv
+
+if [ $(grep -v '^#' $EOP_FILENAME | grep -c ',') -le 1 ]; then
+    echo "Downloading EOP file: $EOP_URL"
+    curl -sSf -o $EOP_FILENAME.tmp $EOP_URL && mv $EOP_FILENAME.tmp $EOP_FILENAME
+fi
+
 cargo run config/orb_propagation_tle.json